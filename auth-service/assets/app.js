@@ -32,6 +32,18 @@ signupLoginLink.addEventListener("click", (e) => {
 
 // -----------------------------------------------------
 
+// Set when an OAuth client sent the user here to log in before authorizing it
+const returnTo = new URLSearchParams(window.location.search).get("return_to");
+
+function loggedIn() {
+    // Only authorization requests are resumed, so the parameter can't send the user to another site
+    if (returnTo !== null && returnTo.startsWith("/oauth/authorize?")) {
+        window.location.assign(returnTo);
+    } else {
+        alert("You have successfully logged in.");
+    }
+}
+
 const loginForm = document.getElementById("login-form");
 const loginButton = document.getElementById("login-form-submit");
 const loginErrAlter = document.getElementById("login-err-alert");
@@ -49,24 +61,24 @@ loginButton.addEventListener("click", (e) => {
         },
         body: JSON.stringify({ email, password }),
     }).then(response => {
-        if (response.status === 206) {
-            TwoFAForm.email.value = email;
+        if (response.ok) {
             response.json().then(data => {
-                TwoFAForm.login_attempt_id.value = data.loginAttemptId;
+                loginForm.email.value = "";
+                loginForm.password.value = "";
+                loginErrAlter.style.display = "none";
+
+                // An empty loginAttemptId means the account has no second factor
+                if (data.loginAttemptId) {
+                    TwoFAForm.email.value = email;
+                    TwoFAForm.login_attempt_id.value = data.loginAttemptId;
+
+                    loginSection.style.display = "none";
+                    twoFASection.style.display = "block";
+                    signupSection.style.display = "none";
+                } else {
+                    loggedIn();
+                }
             });
-
-            loginForm.email.value = "";
-            loginForm.password.value = "";
-
-            loginSection.style.display = "none";
-            twoFASection.style.display = "block";
-            signupSection.style.display = "none";
-            loginErrAlter.style.display = "none";
-        } else if (response.status === 200) {
-            loginForm.email.value = "";
-            loginForm.password.value = "";
-            loginErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -145,10 +157,10 @@ TwoFAButton.addEventListener("click", (e) => {
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
+            loggedIn();
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Accounts created before email verification existed keep working
+UPDATE users SET verified = TRUE;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS secret_hash;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS allowed_audiences;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS client_type;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS client_type TEXT NOT NULL DEFAULT 'public'
+    CHECK (client_type IN ('public', 'machine'));
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS allowed_audiences TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS secret_hash TEXT;
@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE totp_secrets ADD COLUMN IF NOT EXISTS last_used_step BIGINT;
@@ -0,0 +1,8 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS jwt_signing_keys(
+   kid TEXT NOT NULL PRIMARY KEY,
+   algorithm TEXT NOT NULL,
+   encrypted_private_key BYTEA NOT NULL,
+   activates_at TIMESTAMPTZ NOT NULL,
+   retire_at TIMESTAMPTZ
+);
@@ -1,27 +1,81 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use crate::{domain::{BannedTokenStore, TwoFACodeStore, UserStore}, services::mock_email_client::MockEmailClient};
+use crate::services::email_templates::EmailTemplates;
+use crate::domain::{ApiKeyStore, AuthorizationGrantStore, BannedTokenStore, EmailClient, EmailOutboxStore, EmailVerificationTokenStore, LoginAttemptStore, MagicLinkStore, OAuthClientStore, PasswordResetTokenStore, PhoneVerificationCodeStore, RateLimitStore, RecoveryCodeStore, RefreshTokenStore, SigningKeyStore, SmsClient, TotpSecretStore, TwoFACodeStore, UserStore, WebauthnChallengeStore, WebauthnCredentialStore};
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>; 
-pub type EmailClientType = MockEmailClient;
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore + Send + Sync>>;
+pub type EmailVerificationTokenStoreType = Arc<RwLock<dyn EmailVerificationTokenStore + Send + Sync>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type TotpSecretStoreType = Arc<RwLock<dyn TotpSecretStore + Send + Sync>>;
+pub type WebauthnCredentialStoreType = Arc<RwLock<dyn WebauthnCredentialStore + Send + Sync>>;
+pub type WebauthnChallengeStoreType = Arc<RwLock<dyn WebauthnChallengeStore + Send + Sync>>;
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore + Send + Sync>>;
+pub type MagicLinkStoreType = Arc<RwLock<dyn MagicLinkStore + Send + Sync>>;
+pub type LoginAttemptStoreType = Arc<RwLock<dyn LoginAttemptStore + Send + Sync>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore + Send + Sync>>;
+pub type EmailOutboxStoreType = Arc<RwLock<dyn EmailOutboxStore + Send + Sync>>;
+pub type PhoneVerificationCodeStoreType = Arc<RwLock<dyn PhoneVerificationCodeStore + Send + Sync>>;
+pub type OAuthClientStoreType = Arc<RwLock<dyn OAuthClientStore + Send + Sync>>;
+pub type AuthorizationGrantStoreType = Arc<RwLock<dyn AuthorizationGrantStore + Send + Sync>>;
+pub type ApiKeyStoreType = Arc<RwLock<dyn ApiKeyStore + Send + Sync>>;
+pub type SigningKeyStoreType = Arc<RwLock<dyn SigningKeyStore + Send + Sync>>;
+pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type SmsClientType = Arc<dyn SmsClient + Send + Sync>;
+pub type EmailTemplatesType = Arc<EmailTemplates>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
-    pub email_client: EmailClientType
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub totp_secret_store: TotpSecretStoreType,
+    pub webauthn_credential_store: WebauthnCredentialStoreType,
+    pub webauthn_challenge_store: WebauthnChallengeStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub magic_link_store: MagicLinkStoreType,
+    pub login_attempt_store: LoginAttemptStoreType,
+    pub rate_limit_store: RateLimitStoreType,
+    pub email_outbox_store: EmailOutboxStoreType,
+    pub email_templates: EmailTemplatesType,
+    pub phone_verification_code_store: PhoneVerificationCodeStoreType,
+    pub sms_client: SmsClientType,
+    pub oauth_client_store: OAuthClientStoreType,
+    pub authorization_grant_store: AuthorizationGrantStoreType,
+    pub api_key_store: ApiKeyStoreType,
+    pub signing_key_store: SigningKeyStoreType
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType, 
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
-        email_client: EmailClientType
+        password_reset_token_store: PasswordResetTokenStoreType,
+        email_verification_token_store: EmailVerificationTokenStoreType,
+        refresh_token_store: RefreshTokenStoreType,
+        totp_secret_store: TotpSecretStoreType,
+        webauthn_credential_store: WebauthnCredentialStoreType,
+        webauthn_challenge_store: WebauthnChallengeStoreType,
+        recovery_code_store: RecoveryCodeStoreType,
+        magic_link_store: MagicLinkStoreType,
+        login_attempt_store: LoginAttemptStoreType,
+        rate_limit_store: RateLimitStoreType,
+        email_outbox_store: EmailOutboxStoreType,
+        email_templates: EmailTemplatesType,
+        phone_verification_code_store: PhoneVerificationCodeStoreType,
+        sms_client: SmsClientType,
+        oauth_client_store: OAuthClientStoreType,
+        authorization_grant_store: AuthorizationGrantStoreType,
+        api_key_store: ApiKeyStoreType,
+        signing_key_store: SigningKeyStoreType
     ) -> Self {
-        Self { user_store, banned_token_store, two_fa_code_store, email_client }
+        Self { user_store, banned_token_store, two_fa_code_store, password_reset_token_store, email_verification_token_store, refresh_token_store, totp_secret_store, webauthn_credential_store, webauthn_challenge_store, recovery_code_store, magic_link_store, login_attempt_store, rate_limit_store, email_outbox_store, email_templates, phone_verification_code_store, sms_client, oauth_client_store, authorization_grant_store, api_key_store, signing_key_store }
     }
 }
\ No newline at end of file
@@ -0,0 +1,148 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use super::{Email, Scopes};
+
+// Every API key starts with this, so leaked keys are easy to recognize in logs and by secret scanners
+pub const API_KEY_PREFIX: &str = "ask_";
+const API_KEY_ID_LENGTH: usize = 16;
+
+// Public part of an API key, shown in listings and used to look the key up
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ApiKeyId(String);
+
+impl ApiKeyId {
+    pub fn parse(id: String) -> Result<Self> {
+        if id.len() != API_KEY_ID_LENGTH || !id.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)) {
+            return Err(eyre!("Invalid API key id"));
+        }
+        Ok(Self(id))
+    }
+}
+
+impl Default for ApiKeyId {
+    fn default() -> Self {
+        let bytes: [u8; API_KEY_ID_LENGTH / 2] = rand::rng().random();
+        ApiKeyId(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+impl AsRef<str> for ApiKeyId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// The full key as handed to the user, `ask_<id>_<secret>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiKeyToken {
+    id: ApiKeyId,
+    secret: String,
+}
+
+impl ApiKeyToken {
+    pub fn parse(token: &str) -> Result<Self> {
+        let rest = token.strip_prefix(API_KEY_PREFIX).ok_or(eyre!("Not an API key"))?;
+        let (id, secret) = rest.split_at_checked(API_KEY_ID_LENGTH).ok_or(eyre!("Invalid API key"))?;
+        let secret = secret.strip_prefix('_').ok_or(eyre!("Invalid API key"))?;
+        let bytes = URL_SAFE_NO_PAD.decode(secret).wrap_err("Invalid API key secret")?;
+        if bytes.len() != 32 {
+            return Err(eyre!("Invalid API key secret length"));
+        }
+        Ok(Self { id: ApiKeyId::parse(id.to_owned())?, secret: secret.to_owned() })
+    }
+
+    pub fn id(&self) -> &ApiKeyId {
+        &self.id
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    // Keys carry 256 random bits, which a fast hash protects as well as a password hash would
+    pub fn secret_hash(&self) -> String {
+        Sha256::digest(self.secret.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl Default for ApiKeyToken {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        Self { id: ApiKeyId::default(), secret: URL_SAFE_NO_PAD.encode(bytes) }
+    }
+}
+
+impl std::fmt::Display for ApiKeyToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}_{}", API_KEY_PREFIX, self.id.as_ref(), self.secret)
+    }
+}
+
+// A long lived credential a user created for scripts, standing in for their session
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiKey {
+    pub id: ApiKeyId,
+    pub email: Email,
+    pub name: String,
+    pub scopes: Scopes,
+    pub created_at: i64,
+    // Keys without an expiry stay valid until they are revoked
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+}
+
+impl ApiKey {
+    // What the key starts with, enough for users to tell their keys apart
+    pub fn prefix(&self) -> String {
+        format!("{}{}", API_KEY_PREFIX, self.id.as_ref())
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_token_round_trip() {
+        let token = ApiKeyToken::default();
+        let key = token.to_string();
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert_eq!(ApiKeyToken::parse(&key).unwrap(), token);
+        assert_eq!(token.secret_hash().len(), 64);
+
+        for invalid in [
+            "",
+            "ask_",
+            &key[API_KEY_PREFIX.len()..],
+            &key[..key.len() - 1],
+            &key.replacen('_', "-", 2),
+            &format!("ask_0123456789ABCDEF_{}", token.secret()),
+        ] {
+            assert!(ApiKeyToken::parse(invalid).is_err(), "Failed for input: {}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_api_key_expiry() {
+        let key = ApiKey {
+            id: ApiKeyId::default(),
+            email: Email::parse("test@example.com".to_owned()).unwrap(),
+            name: "Scripts".to_owned(),
+            scopes: Scopes::default(),
+            created_at: 0,
+            expires_at: Some(100),
+            last_used_at: None,
+        };
+        assert!(!key.is_expired(99));
+        assert!(key.is_expired(100));
+        assert!(!ApiKey { expires_at: None, ..key.clone() }.is_expired(i64::MAX));
+        assert_eq!(key.prefix(), format!("ask_{}", key.id.as_ref()));
+    }
+}
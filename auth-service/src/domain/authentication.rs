@@ -0,0 +1,80 @@
+use chrono::Utc;
+
+// How a user proved who they are, named after the RFC 8176 `amr` values
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AuthMethod {
+    Password,
+    // A magic link, which has no registered value of its own
+    EmailLink,
+    // An emailed code, an authenticator app code or a recovery code
+    OneTimeCode,
+    Sms,
+    SecurityKey,
+}
+
+impl AuthMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthMethod::Password => "pwd",
+            AuthMethod::EmailLink => "email",
+            AuthMethod::OneTimeCode => "otp",
+            AuthMethod::Sms => "sms",
+            AuthMethod::SecurityKey => "hwk",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "pwd" => Ok(AuthMethod::Password),
+            "email" => Ok(AuthMethod::EmailLink),
+            "otp" => Ok(AuthMethod::OneTimeCode),
+            "sms" => Ok(AuthMethod::Sms),
+            "hwk" => Ok(AuthMethod::SecurityKey),
+            _ => Err(format!("Invalid authentication method: {}", s)),
+        }
+    }
+}
+
+// When and how the user of a session logged in, kept across refreshes for the OIDC `auth_time` and `amr` claims
+#[derive(Clone, Debug, PartialEq)]
+pub struct Authentication {
+    // Unix timestamp
+    pub auth_time: i64,
+    pub methods: Vec<AuthMethod>,
+}
+
+impl Authentication {
+    pub fn now(methods: Vec<AuthMethod>) -> Self {
+        Self { auth_time: Utc::now().timestamp(), methods }
+    }
+
+    // Reads back the `amr` values written by `amr`, skipping the ones that only summarize others
+    pub fn from_amr(auth_time: i64, amr: &[String]) -> Self {
+        let methods = amr.iter().filter_map(|value| AuthMethod::parse(value).ok()).collect();
+        Self { auth_time, methods }
+    }
+
+    pub fn amr(&self) -> Vec<String> {
+        let mut amr: Vec<String> = self.methods.iter().map(|method| method.as_str().to_owned()).collect();
+        if self.methods.len() > 1 {
+            amr.push("mfa".to_owned());
+        }
+        amr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amr_round_trip_and_mfa() {
+        let single = Authentication { auth_time: 10, methods: vec![AuthMethod::Password] };
+        assert_eq!(single.amr(), vec!["pwd"]);
+
+        let two_factor = Authentication { auth_time: 10, methods: vec![AuthMethod::EmailLink, AuthMethod::Sms] };
+        let amr = two_factor.amr();
+        assert_eq!(amr, vec!["email", "sms", "mfa"]);
+        assert_eq!(Authentication::from_amr(10, &amr), two_factor);
+    }
+}
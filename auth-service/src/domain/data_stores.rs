@@ -2,6 +2,13 @@ use rand::Rng;
 use uuid::Uuid;
 use thiserror::Error;
 use color_eyre::eyre::{eyre, Context, Report, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+use jsonwebtoken::Algorithm;
+use totp_rs::{Algorithm as TotpAlgorithm, Secret, TOTP};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::utils::constants::TOTP_ISSUER;
 
 use super::*;
 
@@ -10,6 +17,19 @@ pub trait UserStore{
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &str) -> Result<&User, UserStoreError>;
     async fn validate_user(&self, email: &str, password: &str) -> Result<(), UserStoreError>;
+    async fn update_password(&mut self, email: &str, password: Password) -> Result<(), UserStoreError>;
+    async fn mark_email_verified(&mut self, email: &str) -> Result<(), UserStoreError>;
+    async fn delete_user(&mut self, email: &str) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(&mut self, email: &str, requires_2fa: bool) -> Result<(), UserStoreError>;
+    // Replaces every role the user has
+    async fn set_roles(&mut self, email: &str, roles: Vec<Role>) -> Result<(), UserStoreError>;
+    async fn set_disabled(&mut self, email: &str, disabled: bool) -> Result<(), UserStoreError>;
+    // A new number (or none) has to be verified again and moves 2FA codes back to email
+    async fn set_phone_number(&mut self, email: &str, phone_number: Option<PhoneNumber>) -> Result<(), UserStoreError>;
+    async fn mark_phone_verified(&mut self, email: &str) -> Result<(), UserStoreError>;
+    async fn set_two_fa_channel(&mut self, email: &str, channel: TwoFAChannel) -> Result<(), UserStoreError>;
+    // Returns a page of users whose email starts with `email_prefix`, ordered by email, and how many match in total
+    async fn list_users(&self, email_prefix: &str, offset: u64, limit: u64) -> Result<(Vec<User>, u64), UserStoreError>;
 }
 
 #[derive(Debug, PartialEq)]
@@ -17,6 +37,8 @@ pub enum UserStoreError {
     UserAlreadyExists,
     UserNotFound,
     InvalidCredentials,
+    // The role isn't in the roles catalog
+    UnknownRole,
     UnexpectedError
 }
 
@@ -24,6 +46,9 @@ pub enum UserStoreError {
 pub trait BannedTokenStore {
     async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError>;
     async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError>;
+    // Bans every token issued to `email` before the `issued_before` unix timestamp
+    async fn revoke_user_tokens(&mut self, email: &str, issued_before: i64) -> Result<(), BannedTokenStoreError>;
+    async fn get_user_revocation(&self, email: &str) -> Result<Option<i64>, BannedTokenStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -46,17 +71,21 @@ impl PartialEq for BannedTokenStoreError {
 
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
+    // `first_factor` is how the user got this far, so the session can list both factors once the code checks out
     async fn add_code(
         &mut self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
+        first_factor: AuthMethod,
     ) -> Result<(), TwoFACodeStoreError>;
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
     async fn get_code(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    ) -> Result<(LoginAttemptId, TwoFACode, AuthMethod), TwoFACodeStoreError>;
+    // Counts a verification attempt against the pending code and returns how many were made since it was added
+    async fn record_attempt(&mut self, email: &Email) -> Result<u32, TwoFACodeStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -77,55 +106,1197 @@ impl PartialEq for TwoFACodeStoreError {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct LoginAttemptId(String);
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    async fn remove_token(&mut self, email: &Email) -> Result<(), PasswordResetTokenStoreError>;
+    async fn get_token(
+        &self,
+        email: &Email,
+    ) -> Result<PasswordResetToken, PasswordResetTokenStoreError>;
+}
 
-impl LoginAttemptId {
-    pub fn parse(id: String) -> Result<Self> {
-        let parsed_id = uuid::Uuid::parse_str(&id).wrap_err("Invalid login attempt id")?;
-        Ok(Self(parsed_id.to_string()))
+#[derive(Debug, Error)]
+pub enum PasswordResetTokenStoreError {
+    #[error("Password reset token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for PasswordResetTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
     }
 }
 
-impl Default for LoginAttemptId {
-    fn default() -> Self {
-        let uuid = Uuid::new_v4();
-        LoginAttemptId(uuid.to_string())
+#[async_trait::async_trait]
+pub trait EmailVerificationTokenStore {
+    // `issued_at` is a unix timestamp, kept so resends can be rate limited
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+        issued_at: i64,
+    ) -> Result<(), EmailVerificationTokenStoreError>;
+    async fn remove_token(&mut self, email: &Email) -> Result<(), EmailVerificationTokenStoreError>;
+    async fn get_token(
+        &self,
+        email: &Email,
+    ) -> Result<(EmailVerificationToken, i64), EmailVerificationTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailVerificationTokenStoreError {
+    #[error("Email verification token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for EmailVerificationTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
     }
 }
 
-impl AsRef<str> for LoginAttemptId {
-    fn as_ref(&self) -> &str {
-        &self.0
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    // Makes `token` the only valid token of its family, replacing the one it was rotated from.
+    // The family remembers how its user logged in, which every token issued from it reports.
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: RefreshToken,
+        authentication: Authentication,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn get_token(
+        &self,
+        family_id: &str,
+    ) -> Result<(Email, RefreshToken, Authentication), RefreshTokenStoreError>;
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError>;
+    async fn revoke_all_families(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token family not found")]
+    FamilyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::FamilyNotFound, Self::FamilyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
     }
 }
 
+#[async_trait::async_trait]
+pub trait TotpSecretStore {
+    // Stores an unconfirmed secret for `email`, replacing any enrollment that was never confirmed
+    async fn add_secret(&mut self, email: &Email, secret: TotpSecret) -> Result<(), TotpSecretStoreError>;
+    // Returns the secret and whether its enrollment has been confirmed
+    async fn get_secret(&self, email: &Email) -> Result<(TotpSecret, bool), TotpSecretStoreError>;
+    async fn confirm_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError>;
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError>;
+    // Records the time step of an accepted code, failing for any step at or before the last one recorded
+    // so every code works only once (RFC 6238 section 5.2)
+    async fn use_time_step(&mut self, email: &Email, time_step: u64) -> Result<(), TotpSecretStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum TotpSecretStoreError {
+    #[error("TOTP secret not found")]
+    SecretNotFound,
+    #[error("TOTP code already used")]
+    TimeStepAlreadyUsed,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct TwoFACode(String);
+impl PartialEq for TotpSecretStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SecretNotFound, Self::SecretNotFound)
+                | (Self::TimeStepAlreadyUsed, Self::TimeStepAlreadyUsed)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
 
-impl TwoFACode {
-    pub fn parse(code: String) -> Result<Self> {
-        let code_as_u32 = code.parse::<u32>().wrap_err("Invalid 2FA code")?;
+#[async_trait::async_trait]
+pub trait WebauthnCredentialStore {
+    async fn add_credential(&mut self, credential: WebauthnCredential) -> Result<(), WebauthnCredentialStoreError>;
+    async fn get_credential(&self, credential_id: &str) -> Result<WebauthnCredential, WebauthnCredentialStoreError>;
+    async fn get_credentials(&self, email: &Email) -> Result<Vec<WebauthnCredential>, WebauthnCredentialStoreError>;
+    // Only ever moves the counter forward, failing with `SignCountNotIncreased` otherwise so concurrent
+    // logins with a cloned authenticator can't both succeed
+    async fn update_sign_count(&mut self, credential_id: &str, sign_count: u32) -> Result<(), WebauthnCredentialStoreError>;
+}
 
-        if (100_000..=999_999).contains(&code_as_u32) {
-            Ok(Self(code))
-        } else {
-            Err(eyre!("Invalid 2FA code")) 
-        }
+#[derive(Debug, Error)]
+pub enum WebauthnCredentialStoreError {
+    #[error("Credential already exists")]
+    CredentialAlreadyExists,
+    #[error("Credential not found")]
+    CredentialNotFound,
+    #[error("Sign count did not increase")]
+    SignCountNotIncreased,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for WebauthnCredentialStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CredentialAlreadyExists, Self::CredentialAlreadyExists)
+                | (Self::CredentialNotFound, Self::CredentialNotFound)
+                | (Self::SignCountNotIncreased, Self::SignCountNotIncreased)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
     }
 }
 
-impl Default for TwoFACode {
-    fn default() -> Self {
-        let mut rng = rand::rng();
-        let six_digit_number = rng.random_range(100000..1000000);
-        TwoFACode(six_digit_number.to_string())
+#[async_trait::async_trait]
+pub trait WebauthnChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: WebauthnChallenge,
+        ceremony: WebauthnCeremony,
+    ) -> Result<(), WebauthnChallengeStoreError>;
+    // Challenges are single use, so reading one also removes it
+    async fn take_challenge(
+        &mut self,
+        challenge: &WebauthnChallenge,
+    ) -> Result<WebauthnCeremony, WebauthnChallengeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum WebauthnChallengeStoreError {
+    #[error("Challenge not found")]
+    ChallengeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for WebauthnChallengeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ChallengeNotFound, Self::ChallengeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
     }
 }
 
-impl AsRef<str> for TwoFACode {
-    fn as_ref(&self) -> &str {
-        &self.0
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore {
+    // Replaces the whole set of `email`, so previously issued codes stop working
+    async fn set_codes(&mut self, email: &Email, codes: Vec<RecoveryCode>) -> Result<(), RecoveryCodeStoreError>;
+    // Codes are single use, so a matching code is removed from the set
+    async fn use_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), RecoveryCodeStoreError>;
+    // How many unused codes `email` has left
+    async fn count_codes(&self, email: &Email) -> Result<usize, RecoveryCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeStoreError {
+    #[error("Recovery code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait MagicLinkStore {
+    async fn add_link(&mut self, link_id: MagicLinkId, nonce: MagicLinkNonce) -> Result<(), MagicLinkStoreError>;
+    // Links are single use, so reading one also removes it
+    async fn take_link(&mut self, link_id: &MagicLinkId) -> Result<MagicLinkNonce, MagicLinkStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum MagicLinkStoreError {
+    #[error("Magic link not found")]
+    LinkNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for MagicLinkStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::LinkNotFound, Self::LinkNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait OAuthClientStore {
+    // Machine clients come with a secret, which is only kept hashed
+    async fn add_client(&mut self, client: OAuthClient, secret: Option<ClientSecret>) -> Result<(), OAuthClientStoreError>;
+    async fn get_client(&self, client_id: &OAuthClientId) -> Result<OAuthClient, OAuthClientStoreError>;
+    // Every registered client, oldest first
+    async fn list_clients(&self) -> Result<Vec<OAuthClient>, OAuthClientStoreError>;
+    async fn delete_client(&mut self, client_id: &OAuthClientId) -> Result<(), OAuthClientStoreError>;
+    async fn verify_client_secret(&self, client_id: &OAuthClientId, secret: &ClientSecret) -> Result<(), OAuthClientStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OAuthClientStoreError {
+    #[error("OAuth client not found")]
+    ClientNotFound,
+    #[error("Invalid client secret")]
+    InvalidSecret,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for OAuthClientStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ClientNotFound, Self::ClientNotFound)
+                | (Self::InvalidSecret, Self::InvalidSecret)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ApiKeyStore {
+    // Only a hash of the token's secret is kept, so the key can't be shown again
+    async fn add_key(&mut self, key: ApiKey, token: &ApiKeyToken) -> Result<(), ApiKeyStoreError>;
+    // Keys of `email`, newest first
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError>;
+    async fn delete_key(&mut self, email: &Email, id: &ApiKeyId) -> Result<(), ApiKeyStoreError>;
+    // Revokes every key of `email`, for when their password changes or an admin signs them out
+    async fn delete_all_keys(&mut self, email: &Email) -> Result<(), ApiKeyStoreError>;
+    // Finds the unexpired key `token` belongs to and records that it was just used
+    async fn use_key(&mut self, token: &ApiKeyToken) -> Result<ApiKey, ApiKeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyStoreError {
+    #[error("API key not found")]
+    KeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for ApiKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::KeyNotFound, Self::KeyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait AuthorizationGrantStore {
+    // Holds the request shown on the consent page until the user answers it
+    async fn add_pending_consent(&mut self, consent_id: ConsentId, grant: AuthorizationGrant) -> Result<(), AuthorizationGrantStoreError>;
+    // Consents and codes are single use, so reading one also removes it
+    async fn take_pending_consent(&mut self, consent_id: &ConsentId) -> Result<AuthorizationGrant, AuthorizationGrantStoreError>;
+    async fn add_code(&mut self, code: AuthorizationCode, grant: AuthorizationGrant) -> Result<(), AuthorizationGrantStoreError>;
+    async fn take_code(&mut self, code: &AuthorizationCode) -> Result<AuthorizationGrant, AuthorizationGrantStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuthorizationGrantStoreError {
+    #[error("Authorization grant not found")]
+    GrantNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for AuthorizationGrantStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::GrantNotFound, Self::GrantNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PhoneVerificationCodeStore {
+    // Replaces any code still pending for `email`, remembering which number it was sent to
+    async fn add_code(
+        &mut self,
+        email: Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), PhoneVerificationCodeStoreError>;
+    async fn remove_code(&mut self, email: &Email) -> Result<(), PhoneVerificationCodeStoreError>;
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(PhoneNumber, TwoFACode), PhoneVerificationCodeStoreError>;
+    // Counts a verification attempt against the pending code and returns how many were made since it was added
+    async fn record_attempt(&mut self, email: &Email) -> Result<u32, PhoneVerificationCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PhoneVerificationCodeStoreError {
+    #[error("Phone verification code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for PhoneVerificationCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait LoginAttemptStore {
+    // Counts a wrong password or 2FA code and returns how many happened within the current failure window
+    async fn record_failure(&mut self, email: &Email) -> Result<u32, LoginAttemptStoreError>;
+    async fn clear_failures(&mut self, email: &Email) -> Result<(), LoginAttemptStoreError>;
+    // Locks the account for `duration_seconds`, starting a fresh failure window once it ends
+    async fn lock(&mut self, email: &Email, duration_seconds: u64) -> Result<(), LoginAttemptStoreError>;
+    // How many times the account was locked within the escalation window
+    async fn lock_count(&self, email: &Email) -> Result<u32, LoginAttemptStoreError>;
+    // Seconds until the current lock ends, if the account is locked
+    async fn lock_remaining(&self, email: &Email) -> Result<Option<u64>, LoginAttemptStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum LoginAttemptStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for LoginAttemptStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait RateLimitStore {
+    // Takes a token from the bucket stored under `key`, refilling it for the time that passed since it was last used
+    async fn take_token(&mut self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, RateLimitStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
     }
-}
\ No newline at end of file
+}
+
+#[async_trait::async_trait]
+pub trait EmailOutboxStore {
+    // Queues `content` for delivery and returns its id. From here on delivery is at least once: a worker that dies
+    // between sending and `mark_sent` sends the email again once its lease runs out. Emails are never sent after
+    // `expires_at`, a unix timestamp, which is when the code or link inside stops working.
+    async fn enqueue(&mut self, recipient: &Email, content: &EmailContent, expires_at: Option<i64>) -> Result<OutboxEmailId, EmailOutboxStoreError>;
+    // Hands out up to `limit` pending emails that are due, counting the attempt and hiding them
+    // from other workers for `lease_seconds` so a crashed worker's emails are picked up again
+    async fn claim_due(&mut self, limit: u32, lease_seconds: u64) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError>;
+    // Delivered emails are removed, their bodies hold codes and tokens that shouldn't linger
+    async fn mark_sent(&mut self, id: &OutboxEmailId) -> Result<(), EmailOutboxStoreError>;
+    // Schedules another attempt in `retry_in_seconds`, or dead-letters the email when there is none
+    async fn mark_failed(&mut self, id: &OutboxEmailId, error: &str, retry_in_seconds: Option<u64>) -> Result<(), EmailOutboxStoreError>;
+    // Returns a page of emails with `status`, newest first, and how many there are in total
+    async fn list(&self, status: OutboxStatus, offset: u64, limit: u64) -> Result<(Vec<OutboxEmail>, u64), EmailOutboxStoreError>;
+    // Makes the email due right away with a fresh set of attempts
+    async fn replay(&mut self, id: &OutboxEmailId) -> Result<OutboxEmail, EmailOutboxStoreError>;
+    // Removes every email to `recipient`, pending or dead, once their account is gone
+    async fn purge_recipient(&mut self, recipient: &Email) -> Result<(), EmailOutboxStoreError>;
+    // Removes dead emails queued before `created_before`, a unix timestamp, and returns how many there were
+    async fn purge_dead(&mut self, created_before: i64) -> Result<u64, EmailOutboxStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailOutboxStoreError {
+    #[error("Email not found")]
+    EmailNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for EmailOutboxStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::EmailNotFound, Self::EmailNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Shared by every replica, so they all sign and verify with the same keys and survive restarts
+#[async_trait::async_trait]
+pub trait SigningKeyStore {
+    // Keys that haven't retired at `now`, oldest activation first
+    async fn get_keys(&self, now: i64) -> Result<Vec<StoredSigningKey>, SigningKeyStoreError>;
+    // Seeds an empty store and leaves one that already has keys alone, so the configured keys only
+    // count until the first rotation
+    async fn add_initial_keys(&mut self, keys: Vec<StoredSigningKey>) -> Result<(), SigningKeyStoreError>;
+    // Adds the key to rotate to, retiring every key that had no retirement yet at `retire_at`
+    async fn add_next_key(&mut self, key: StoredSigningKey, retire_at: i64) -> Result<(), SigningKeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SigningKeyStoreError {
+    #[error("Signing key already exists")]
+    KeyAlreadyExists,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report)
+}
+
+impl PartialEq for SigningKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::KeyAlreadyExists, Self::KeyAlreadyExists)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginAttemptId(String);
+
+impl LoginAttemptId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = uuid::Uuid::parse_str(&id).wrap_err("Invalid login attempt id")?;
+        Ok(Self(parsed_id.to_string()))
+    }
+}
+
+impl Default for LoginAttemptId {
+    fn default() -> Self {
+        let uuid = Uuid::new_v4();
+        LoginAttemptId(uuid.to_string())
+    }
+}
+
+impl AsRef<str> for LoginAttemptId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TwoFACode(String);
+
+impl TwoFACode {
+    pub fn parse(code: String) -> Result<Self> {
+        // Authenticator apps zero-pad their codes, so a leading zero is valid
+        if code.len() == 6 && code.bytes().all(|b| b.is_ascii_digit()) {
+            Ok(Self(code))
+        } else {
+            Err(eyre!("Invalid 2FA code"))
+        }
+    }
+}
+
+impl Default for TwoFACode {
+    fn default() -> Self {
+        let mut rng = rand::rng();
+        let six_digit_number = rng.random_range(100000..1000000);
+        TwoFACode(six_digit_number.to_string())
+    }
+}
+
+impl AsRef<str> for TwoFACode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasswordResetToken(String);
+
+impl PasswordResetToken {
+    pub fn parse(token: String) -> Result<Self> {
+        let parsed_token = uuid::Uuid::parse_str(&token).wrap_err("Invalid password reset token")?;
+        Ok(Self(parsed_token.to_string()))
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        PasswordResetToken(Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for PasswordResetToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmailVerificationToken(String);
+
+impl EmailVerificationToken {
+    pub fn parse(token: String) -> Result<Self> {
+        let parsed_token = uuid::Uuid::parse_str(&token).wrap_err("Invalid email verification token")?;
+        Ok(Self(parsed_token.to_string()))
+    }
+}
+
+impl Default for EmailVerificationToken {
+    fn default() -> Self {
+        EmailVerificationToken(Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for EmailVerificationToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// A refresh token is `<family id>.<secret>`. Every rotation keeps the family id and draws a new
+// secret, which lets the store tell a reused (already rotated) token apart from an unknown one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefreshToken(String);
+
+impl RefreshToken {
+    pub fn parse(token: String) -> Result<Self> {
+        let (family_id, secret) = token.split_once('.').ok_or(eyre!("Invalid refresh token"))?;
+        let family_id = uuid::Uuid::parse_str(family_id).wrap_err("Invalid refresh token family")?;
+        let secret = uuid::Uuid::parse_str(secret).wrap_err("Invalid refresh token secret")?;
+        Ok(Self(format!("{}.{}", family_id, secret)))
+    }
+
+    pub fn family_id(&self) -> &str {
+        self.0.split_once('.').map(|(family_id, _)| family_id).unwrap_or_default()
+    }
+
+    // Returns the next token of the same family
+    pub fn rotate(&self) -> Self {
+        Self(format!("{}.{}", self.family_id(), Uuid::new_v4()))
+    }
+}
+
+impl Default for RefreshToken {
+    // Starts a new token family
+    fn default() -> Self {
+        RefreshToken(format!("{}.{}", Uuid::new_v4(), Uuid::new_v4()))
+    }
+}
+
+impl AsRef<str> for RefreshToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Base32 encoded RFC 6238 shared secret of an authenticator app
+#[derive(Clone, Debug, PartialEq)]
+pub struct TotpSecret(String);
+
+impl TotpSecret {
+    pub fn parse(secret: String) -> Result<Self> {
+        let bytes = Secret::Encoded(secret.clone()).to_bytes().map_err(|e| eyre!("Invalid TOTP secret: {:?}", e))?;
+        // RFC 4226 requires shared secrets of at least 128 bits
+        if bytes.len() < 16 {
+            return Err(eyre!("TOTP secret is too short"));
+        }
+        Ok(Self(secret))
+    }
+
+    // Authenticator apps show 6 digit codes that change every 30 seconds. One step of skew
+    // is accepted on either side to absorb clock drift.
+    fn totp(&self, email: &Email) -> Result<TOTP> {
+        let bytes = Secret::Encoded(self.0.clone()).to_bytes().map_err(|e| eyre!("Invalid TOTP secret: {:?}", e))?;
+        TOTP::new(TotpAlgorithm::SHA1, 6, 1, 30, bytes, Some(TOTP_ISSUER.to_owned()), email.as_ref().to_owned())
+            .wrap_err("failed to build TOTP")
+    }
+
+    pub fn otpauth_uri(&self, email: &Email) -> Result<String> {
+        Ok(self.totp(email)?.get_url())
+    }
+
+    // Returns the time step the code belongs to, which has to be recorded with the store before the code
+    // is accepted
+    pub fn verify(&self, email: &Email, code: &str) -> Result<Option<u64>> {
+        let totp = self.totp(email)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).wrap_err("failed to read system time")?.as_secs();
+        let current_step = now / totp.step;
+        let skew = u64::from(totp.skew);
+        Ok((current_step.saturating_sub(skew)..=current_step + skew)
+            .find(|time_step| constant_time_eq(&totp.generate(time_step * totp.step), code)))
+    }
+
+    pub fn generate_current(&self, email: &Email) -> Result<String> {
+        self.totp(email)?.generate_current().wrap_err("failed to read system time")
+    }
+
+    pub fn generate_for_time_step(&self, email: &Email, time_step: u64) -> Result<String> {
+        let totp = self.totp(email)?;
+        Ok(totp.generate(time_step * totp.step))
+    }
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl Default for TotpSecret {
+    fn default() -> Self {
+        match Secret::generate_secret().to_encoded() {
+            Secret::Encoded(secret) => TotpSecret(secret),
+            Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+        }
+    }
+}
+
+impl AsRef<str> for TotpSecret {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebauthnCredential {
+    // Base64url encoded credential id chosen by the authenticator
+    pub credential_id: String,
+    pub email: Email,
+    // Uncompressed SEC1 encoding of the credential's P-256 public key
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+    pub name: String,
+    pub created_at: i64,
+}
+
+// What a WebAuthn challenge was issued for, so it can't be replayed in another ceremony
+#[derive(Clone, Debug, PartialEq)]
+pub enum WebauthnCeremony {
+    Registration(Email),
+    SecondFactor(Email, LoginAttemptId),
+    // The email is only known when the user typed it in before picking a passkey
+    Passwordless(Option<Email>),
+}
+
+// Base64url encoded random challenge the authenticator has to sign
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebauthnChallenge(String);
+
+impl WebauthnChallenge {
+    pub fn parse(challenge: String) -> Result<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(&challenge).wrap_err("Invalid WebAuthn challenge")?;
+        if bytes.len() != 32 {
+            return Err(eyre!("Invalid WebAuthn challenge length"));
+        }
+        Ok(Self(challenge))
+    }
+}
+
+impl Default for WebauthnChallenge {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        WebauthnChallenge(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for WebauthnChallenge {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Identifies a pending magic link, carried as the `jti` of its signed token
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MagicLinkId(String);
+
+impl MagicLinkId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = uuid::Uuid::parse_str(&id).wrap_err("Invalid magic link id")?;
+        Ok(Self(parsed_id.to_string()))
+    }
+}
+
+impl Default for MagicLinkId {
+    fn default() -> Self {
+        MagicLinkId(Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for MagicLinkId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Base64url encoded random value kept in a cookie of the browser that asked for a magic link
+#[derive(Clone, Debug, PartialEq)]
+pub struct MagicLinkNonce(String);
+
+impl MagicLinkNonce {
+    pub fn parse(nonce: String) -> Result<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(&nonce).wrap_err("Invalid magic link nonce")?;
+        if bytes.len() != 32 {
+            return Err(eyre!("Invalid magic link nonce length"));
+        }
+        Ok(Self(nonce))
+    }
+}
+
+impl Default for MagicLinkNonce {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        MagicLinkNonce(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for MagicLinkNonce {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Base64url encoded random value identifying a request waiting on the consent page
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ConsentId(String);
+
+impl ConsentId {
+    pub fn parse(id: String) -> Result<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(&id).wrap_err("Invalid consent id")?;
+        if bytes.len() != 32 {
+            return Err(eyre!("Invalid consent id length"));
+        }
+        Ok(Self(id))
+    }
+}
+
+impl Default for ConsentId {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        ConsentId(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for ConsentId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Base64url encoded random value handed to the client's redirect URI and exchanged at /oauth/token
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AuthorizationCode(String);
+
+impl AuthorizationCode {
+    pub fn parse(code: String) -> Result<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(&code).wrap_err("Invalid authorization code")?;
+        if bytes.len() != 32 {
+            return Err(eyre!("Invalid authorization code length"));
+        }
+        Ok(Self(code))
+    }
+}
+
+impl Default for AuthorizationCode {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        AuthorizationCode(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for AuthorizationCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Base64url encoded random secret of a machine OAuth client, shown once when the client is registered
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientSecret(String);
+
+impl ClientSecret {
+    pub fn parse(secret: String) -> Result<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(&secret).wrap_err("Invalid client secret")?;
+        if bytes.len() != 32 {
+            return Err(eyre!("Invalid client secret length"));
+        }
+        Ok(Self(secret))
+    }
+
+    // Checked on every unauthenticated token request, and 256 random bits need no slow hash anyway
+    pub fn secret_hash(&self) -> String {
+        Sha256::digest(self.0.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl Default for ClientSecret {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        ClientSecret(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for ClientSecret {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Lowercase letters and digits without the easily confused 0/o, 1/l/i
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+// Single use backup code shown as `xxxxx-xxxxx`, for when the usual second factor is lost
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveryCode(String);
+
+impl RecoveryCode {
+    // Users copy these by hand, so case, spaces and the dash are not significant
+    pub fn parse(code: String) -> Result<Self> {
+        let normalized: String = code
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        if normalized.len() != RECOVERY_CODE_LENGTH || !normalized.bytes().all(|b| RECOVERY_CODE_ALPHABET.contains(&b)) {
+            return Err(eyre!("Invalid recovery code"));
+        }
+        Ok(Self(format!("{}-{}", &normalized[..5], &normalized[5..])))
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::rng();
+        let code: String = (0..RECOVERY_CODE_LENGTH)
+            .map(|_| RECOVERY_CODE_ALPHABET[rng.random_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+            .collect();
+        RecoveryCode(format!("{}-{}", &code[..5], &code[5..]))
+    }
+}
+
+impl AsRef<str> for RecoveryCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Token bucket holding up to `capacity` requests, gaining one back every `refill_interval_ms`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitPolicy {
+    pub capacity: u32,
+    pub refill_interval_ms: u64,
+}
+
+impl RateLimitPolicy {
+    pub const fn per_minute(requests: u32) -> Self {
+        Self { capacity: requests, refill_interval_ms: 60_000 / requests as u64 }
+    }
+
+    // How long an untouched bucket takes to fill up again, after which it can be forgotten
+    pub fn full_refill_ms(&self) -> u64 {
+        self.capacity as u64 * self.refill_interval_ms
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    // Seconds until the next token, only meaningful when the request was refused
+    pub retry_after_seconds: u64,
+    // Seconds until the bucket is full again
+    pub reset_seconds: u64,
+}
+
+// Holds the refill time banked in milliseconds, a request spending one `refill_interval_ms` of it.
+// Whole milliseconds keep the arithmetic exact where fractional tokens would drift.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitBucket {
+    pub credit_ms: u64,
+    pub updated_at_ms: i64,
+}
+
+impl RateLimitBucket {
+    pub fn full(policy: &RateLimitPolicy, now_ms: i64) -> Self {
+        Self { credit_ms: policy.full_refill_ms(), updated_at_ms: now_ms }
+    }
+
+    pub fn take(&mut self, policy: &RateLimitPolicy, now_ms: i64) -> RateLimitDecision {
+        let interval = policy.refill_interval_ms;
+        let elapsed = (now_ms - self.updated_at_ms).max(0) as u64;
+        self.credit_ms = self.credit_ms.saturating_add(elapsed).min(policy.full_refill_ms());
+        self.updated_at_ms = now_ms;
+
+        let allowed = self.credit_ms >= interval;
+        if allowed {
+            self.credit_ms -= interval;
+        }
+
+        RateLimitDecision {
+            allowed,
+            limit: policy.capacity,
+            remaining: (self.credit_ms / interval) as u32,
+            retry_after_seconds: if allowed { 0 } else { (interval - self.credit_ms).div_ceil(1000) },
+            reset_seconds: (policy.full_refill_ms() - self.credit_ms).div_ceil(1000),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct OutboxEmailId(String);
+
+impl OutboxEmailId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = uuid::Uuid::parse_str(&id).wrap_err("Invalid outbox email id")?;
+        Ok(Self(parsed_id.to_string()))
+    }
+}
+
+impl Default for OutboxEmailId {
+    fn default() -> Self {
+        OutboxEmailId(Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for OutboxEmailId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Emails stay pending while they have attempts left, then sit in the dead letter state until replayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutboxStatus {
+    Pending,
+    Dead,
+}
+
+impl OutboxStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Dead => "dead",
+        }
+    }
+
+    pub fn parse(status: &str) -> Result<Self> {
+        match status {
+            "pending" => Ok(OutboxStatus::Pending),
+            "dead" => Ok(OutboxStatus::Dead),
+            _ => Err(eyre!("Invalid outbox status {}", status)),
+        }
+    }
+}
+
+// A JWT signing key as kept in the signing key store
+#[derive(Clone, PartialEq)]
+pub struct StoredSigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    // PKCS#8 PEM, or the shared secret for HS256
+    pub private_key: Vec<u8>,
+    // Unix timestamps. The newest key that has activated signs, the rest only verify until they retire.
+    pub activates_at: i64,
+    pub retire_at: Option<i64>,
+}
+
+// Leaves the private key out of logs
+impl std::fmt::Debug for StoredSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoredSigningKey")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .field("activates_at", &self.activates_at)
+            .field("retire_at", &self.retire_at)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEmail {
+    pub id: OutboxEmailId,
+    pub recipient: Email,
+    pub content: EmailContent,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    // Unix timestamps
+    pub created_at: i64,
+    pub next_attempt_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_bucket_refuses_when_empty_and_refills() {
+        let policy = RateLimitPolicy::per_minute(2);
+        let mut bucket = RateLimitBucket::full(&policy, 0);
+
+        let first = bucket.take(&policy, 0);
+        assert!(first.allowed);
+        assert_eq!((first.limit, first.remaining, first.reset_seconds), (2, 1, 30));
+        assert!(bucket.take(&policy, 0).allowed);
+
+        let refused = bucket.take(&policy, 10_000);
+        assert!(!refused.allowed);
+        assert_eq!((refused.remaining, refused.retry_after_seconds), (0, 20));
+
+        assert!(bucket.take(&policy, 30_000).allowed);
+        // Idle time never fills the bucket past its capacity
+        assert_eq!(bucket.take(&policy, 10_000_000).remaining, 1);
+    }
+
+    #[test]
+    fn test_refresh_token_rotation_keeps_family() {
+        let token = RefreshToken::default();
+        let rotated = token.rotate();
+        assert_ne!(token, rotated);
+        assert_eq!(token.family_id(), rotated.family_id());
+        assert_ne!(token.family_id(), RefreshToken::default().family_id());
+    }
+
+    #[test]
+    fn test_two_fa_code_parse() {
+        assert!(TwoFACode::parse("123456".to_owned()).is_ok());
+        assert!(TwoFACode::parse("012345".to_owned()).is_ok());
+        for code in ["12345", "1234567", "12345a", "+12345", " 12345", ""] {
+            assert!(TwoFACode::parse(code.to_owned()).is_err(), "Failed for input: {}", code);
+        }
+    }
+
+    #[test]
+    fn test_totp_secret_verifies_current_code() {
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        let secret = TotpSecret::default();
+        assert_eq!(TotpSecret::parse(secret.as_ref().to_owned()).unwrap(), secret);
+
+        let code = secret.generate_current(&email).unwrap();
+        let time_step = secret.verify(&email, &code).unwrap().expect("Current code should verify");
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!(time_step.abs_diff(now / 30) <= 1);
+        assert_eq!(secret.generate_for_time_step(&email, time_step).unwrap(), code);
+        assert_eq!(TotpSecret::default().verify(&email, &code).unwrap(), None);
+
+        let uri = secret.otpauth_uri(&email).unwrap();
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret.as_ref())));
+    }
+
+    #[test]
+    fn test_totp_secret_parse_rejects_invalid_secrets() {
+        assert!(TotpSecret::parse("not base32!".to_owned()).is_err());
+        assert!(TotpSecret::parse("JBSWY3DPEHPK3PXP".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_webauthn_challenge_parse() {
+        let challenge = WebauthnChallenge::default();
+        assert_eq!(WebauthnChallenge::parse(challenge.as_ref().to_owned()).unwrap(), challenge);
+        assert_ne!(challenge, WebauthnChallenge::default());
+        assert!(WebauthnChallenge::parse("not base64url!".to_owned()).is_err());
+        assert!(WebauthnChallenge::parse(URL_SAFE_NO_PAD.encode([0u8; 16])).is_err());
+    }
+
+    #[test]
+    fn test_magic_link_nonce_parse() {
+        let nonce = MagicLinkNonce::default();
+        assert_eq!(MagicLinkNonce::parse(nonce.as_ref().to_owned()).unwrap(), nonce);
+        assert_ne!(nonce, MagicLinkNonce::default());
+        assert!(MagicLinkNonce::parse("not base64url!".to_owned()).is_err());
+        assert!(MagicLinkNonce::parse(URL_SAFE_NO_PAD.encode([0u8; 16])).is_err());
+    }
+
+    #[test]
+    fn test_client_secret_parse_and_hash() {
+        let secret = ClientSecret::default();
+        assert_eq!(ClientSecret::parse(secret.as_ref().to_owned()).unwrap(), secret);
+        assert!(ClientSecret::parse(URL_SAFE_NO_PAD.encode([0u8; 16])).is_err());
+        assert_eq!(secret.secret_hash().len(), 64);
+        assert_ne!(secret.secret_hash(), ClientSecret::default().secret_hash());
+    }
+
+    #[test]
+    fn test_recovery_code_parse() {
+        let code = RecoveryCode::default();
+        assert_eq!(RecoveryCode::parse(code.as_ref().to_owned()).unwrap(), code);
+        assert_eq!(RecoveryCode::parse(code.as_ref().to_uppercase().replace('-', " ")).unwrap(), code);
+        assert_ne!(code, RecoveryCode::default());
+        assert!(RecoveryCode::parse("abcde-fghj".to_owned()).is_err());
+        assert!(RecoveryCode::parse("abcde-fghi0".to_owned()).is_err());
+        assert!(RecoveryCode::parse("123456".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_outbox_email_id_and_status_parse() {
+        let id = OutboxEmailId::default();
+        assert_eq!(OutboxEmailId::parse(id.as_ref().to_owned()).unwrap(), id);
+        assert!(OutboxEmailId::parse("not-a-uuid".to_owned()).is_err());
+
+        for status in [OutboxStatus::Pending, OutboxStatus::Dead] {
+            assert_eq!(OutboxStatus::parse(status.as_str()).unwrap(), status);
+        }
+        assert!(OutboxStatus::parse("sent").is_err());
+    }
+
+    #[test]
+    fn test_refresh_token_parse() {
+        let token = RefreshToken::default();
+        assert_eq!(RefreshToken::parse(token.as_ref().to_owned()).unwrap(), token);
+        assert!(RefreshToken::parse("not-a-token".to_owned()).is_err());
+        assert!(RefreshToken::parse(format!("{}.not-a-uuid", Uuid::new_v4())).is_err());
+    }
+}
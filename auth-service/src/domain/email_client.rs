@@ -1,13 +1,20 @@
 use super::Email;
 use color_eyre::Result;
 
+// A rendered email, sent as a multipart message so clients can pick the plain text or HTML part
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailContent {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
 // This trait represents the interface all concrete email clients should implement
 #[async_trait::async_trait]
 pub trait EmailClient {
     async fn send_email(
         &self,
         recipient: &Email,
-        subject: &str,
-        content: &str,
+        content: &EmailContent,
     ) -> Result<(), String>;
-}
\ No newline at end of file
+}
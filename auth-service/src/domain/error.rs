@@ -14,6 +14,108 @@ pub enum AuthAPIError {
     #[error("Missing token")]
     MissingToken,
     #[error("Invalid token")]
-    InvalidToken
+    InvalidToken,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("Too many requests")]
+    TooManyRequests,
+    #[error("TOTP already enabled")]
+    TotpAlreadyEnabled,
+    #[error("Signing key already in use")]
+    SigningKeyAlreadyInUse,
+    #[error("Unsupported signing algorithm")]
+    UnsupportedSigningAlgorithm,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Account disabled")]
+    AccountDisabled,
+    #[error("User not found")]
+    UserNotFound,
+    // Carries the seconds left until the account unlocks
+    #[error("Account locked")]
+    AccountLocked(u64),
+    #[error("Too many incorrect 2FA codes")]
+    TwoFAAttemptsExceeded,
+    #[error("Outbox email not found")]
+    OutboxEmailNotFound,
+    #[error("Phone number not verified")]
+    PhoneNumberNotVerified,
+    #[error("Too many incorrect phone verification codes")]
+    PhoneVerificationAttemptsExceeded,
+    #[error("OAuth client not found")]
+    OAuthClientNotFound,
+    #[error("API key not found")]
+    ApiKeyNotFound,
+    #[error("Unknown role")]
+    UnknownRole,
+    #[error("Admins can't disable, delete or demote themselves")]
+    AdminSelfLockout
+}
+
+// Errors of the OAuth 2.0 endpoints, which clients expect in the RFC 6749 section 5.2 format rather than ours
+#[derive(Debug, Error)]
+pub enum OAuthError {
+    #[error("Invalid request: {0}")]
+    InvalidRequest(&'static str),
+    #[error("Invalid client")]
+    InvalidClient,
+    #[error("Invalid grant: {0}")]
+    InvalidGrant(&'static str),
+    #[error("Unauthorized client")]
+    UnauthorizedClient,
+    #[error("Unsupported grant type")]
+    UnsupportedGrantType,
+    #[error("Unsupported response type")]
+    UnsupportedResponseType,
+    #[error("Invalid scope")]
+    InvalidScope,
+    #[error("Invalid target")]
+    InvalidTarget,
+    #[error("Access denied")]
+    AccessDenied,
+    // Bearer token errors of protected resources such as /userinfo (RFC 6750 section 3.1)
+    #[error("Invalid access token")]
+    InvalidToken,
+    #[error("Insufficient scope")]
+    InsufficientScope,
+    #[error("Server error")]
+    ServerError(#[source] Report)
+}
+
+impl OAuthError {
+    // The `error` code from the OAuth 2.0 error registry
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest(_) => "invalid_request",
+            OAuthError::InvalidClient => "invalid_client",
+            OAuthError::InvalidGrant(_) => "invalid_grant",
+            OAuthError::UnauthorizedClient => "unauthorized_client",
+            OAuthError::UnsupportedGrantType => "unsupported_grant_type",
+            OAuthError::UnsupportedResponseType => "unsupported_response_type",
+            OAuthError::InvalidScope => "invalid_scope",
+            // Resource indicators (RFC 8707 section 2)
+            OAuthError::InvalidTarget => "invalid_target",
+            OAuthError::AccessDenied => "access_denied",
+            OAuthError::InvalidToken => "invalid_token",
+            OAuthError::InsufficientScope => "insufficient_scope",
+            OAuthError::ServerError(_) => "server_error",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest(description) | OAuthError::InvalidGrant(description) => description,
+            OAuthError::InvalidClient => "Unknown client",
+            OAuthError::UnauthorizedClient => "The client is not allowed to make this request",
+            OAuthError::UnsupportedGrantType => "Only the authorization_code and client_credentials grant types are supported",
+            OAuthError::UnsupportedResponseType => "Only the code response type is supported",
+            OAuthError::InvalidScope => "The requested scope is not allowed for this client",
+            OAuthError::InvalidTarget => "The requested audience is missing or not allowed for this client",
+            OAuthError::AccessDenied => "The user denied the request",
+            OAuthError::InvalidToken => "The access token is missing, expired or revoked",
+            OAuthError::InsufficientScope => "The access token was not granted the openid scope",
+            OAuthError::ServerError(_) => "Unexpected error",
+        }
+    }
 }
 
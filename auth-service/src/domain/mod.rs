@@ -1,13 +1,27 @@
 mod user;
+mod api_key;
+mod authentication;
 mod error;
 pub mod data_stores;
 mod email;
+mod oauth;
+mod phone_number;
 mod password;
+mod role;
 mod email_client;
+mod sms_client;
 
-pub use user::User;
-pub use error::{AuthAPIError};
-pub use data_stores::{UserStore, UserStoreError, BannedTokenStore, BannedTokenStoreError, TwoFACodeStore, TwoFACodeStoreError};
+pub use user::{TwoFAChannel, User};
+pub use api_key::{ApiKey, ApiKeyId, ApiKeyToken, API_KEY_PREFIX};
+pub use authentication::{AuthMethod, Authentication};
+pub use error::{AuthAPIError, OAuthError};
+pub use data_stores::{UserStore, UserStoreError, ApiKeyStore, ApiKeyStoreError, BannedTokenStore, BannedTokenStoreError, EmailOutboxStore, EmailOutboxStoreError, TwoFACodeStore, TwoFACodeStoreError, AuthorizationGrantStore, AuthorizationGrantStoreError, MagicLinkStore, MagicLinkStoreError, OAuthClientStore, OAuthClientStoreError, PhoneVerificationCodeStore, PhoneVerificationCodeStoreError, LoginAttemptStore, LoginAttemptStoreError, RateLimitStore, RateLimitStoreError, PasswordResetTokenStore, PasswordResetTokenStoreError, RecoveryCodeStore, RecoveryCodeStoreError, EmailVerificationTokenStore, EmailVerificationTokenStoreError, RefreshTokenStore, RefreshTokenStoreError, SigningKeyStore, SigningKeyStoreError, TotpSecretStore, TotpSecretStoreError, WebauthnChallengeStore, WebauthnChallengeStoreError, WebauthnCredentialStore, WebauthnCredentialStoreError};
 pub use email::Email;
 pub use password::Password;
-pub use email_client::*;
\ No newline at end of file
+pub use oauth::{
+    Audience, AuthorizationGrant, CodeChallenge, OAuthClient, OAuthClientId, OAuthClientType, RedirectUri, Scopes, OPENID_SCOPE,
+};
+pub use phone_number::PhoneNumber;
+pub use role::{Role, ADMIN_ROLE};
+pub use email_client::*;
+pub use sms_client::*;
\ No newline at end of file
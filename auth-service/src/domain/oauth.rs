@@ -0,0 +1,279 @@
+use std::{collections::BTreeSet, fmt};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use regex::Regex;
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{Authentication, Email};
+
+// Public identifier of a registered OAuth client
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct OAuthClientId(String);
+
+impl OAuthClientId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = Uuid::parse_str(&id).wrap_err("Invalid OAuth client id")?;
+        Ok(Self(parsed_id.to_string()))
+    }
+}
+
+impl Default for OAuthClientId {
+    fn default() -> Self {
+        OAuthClientId(Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for OAuthClientId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Redirect URIs are compared as exact strings, so they are kept the way they were registered
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedirectUri(String);
+
+impl RedirectUri {
+    pub fn parse(uri: String) -> Result<Self> {
+        let url = Url::parse(&uri).wrap_err("Invalid redirect URI")?;
+        if url.fragment().is_some() {
+            return Err(eyre!("Redirect URI must not contain a fragment"));
+        }
+        match url.scheme() {
+            "https" => {},
+            // Native apps receive the redirect on a loopback port (RFC 8252 section 7.3)
+            "http" if matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")) => {},
+            // Private-use schemes of mobile apps are reverse domain names (RFC 8252 section 7.1)
+            scheme if scheme.contains('.') => {},
+            _ => return Err(eyre!("Redirect URI must use https, a loopback address or a private-use scheme")),
+        }
+        Ok(Self(uri))
+    }
+}
+
+impl AsRef<str> for RedirectUri {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Scope that turns an authorization request into an OpenID Connect one, which gets an ID token back
+pub const OPENID_SCOPE: &str = "openid";
+
+// A set of scope tokens (RFC 6749 section 3.3), kept sorted so it prints the same way every time
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scopes(BTreeSet<String>);
+
+impl Scopes {
+    // Parses a space separated `scope` parameter
+    pub fn parse(scope: &str) -> Result<Self> {
+        Self::from_tokens(scope.split(' ').filter(|token| !token.is_empty()).map(str::to_owned))
+    }
+
+    pub fn from_tokens(tokens: impl IntoIterator<Item = String>) -> Result<Self> {
+        let token_regex = Regex::new(r"^[\x21\x23-\x5B\x5D-\x7E]{1,64}$").unwrap();
+        let mut scopes = BTreeSet::new();
+        for token in tokens {
+            if !token_regex.is_match(&token) {
+                return Err(eyre!("Invalid scope: {}", token));
+            }
+            scopes.insert(token);
+        }
+        Ok(Self(scopes))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, scope: &str) -> bool {
+        self.0.contains(scope)
+    }
+
+    pub fn is_subset(&self, other: &Scopes) -> bool {
+        self.0.is_subset(&other.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+impl fmt::Display for Scopes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.iter().collect::<Vec<_>>().join(" "))
+    }
+}
+
+// BASE64URL(SHA256(code_verifier)) sent to /oauth/authorize, only the S256 method of PKCE is supported (RFC 7636)
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeChallenge(String);
+
+impl CodeChallenge {
+    pub fn parse(challenge: String) -> Result<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(&challenge).wrap_err("Invalid code challenge")?;
+        if bytes.len() != 32 {
+            return Err(eyre!("Invalid code challenge length"));
+        }
+        Ok(Self(challenge))
+    }
+
+    // Checks the code verifier the client presents to /oauth/token against the challenge
+    pub fn verify(&self, code_verifier: &str) -> bool {
+        let verifier_regex = Regex::new(r"^[A-Za-z0-9._~-]{43,128}$").unwrap();
+        verifier_regex.is_match(code_verifier)
+            && URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes())) == self.0
+    }
+}
+
+impl AsRef<str> for CodeChallenge {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// An application allowed to send users to /oauth/authorize
+#[derive(Clone, Debug, PartialEq)]
+pub struct OAuthClient {
+    pub client_id: OAuthClientId,
+    // Shown to the user on the consent page
+    pub name: String,
+    pub client_type: OAuthClientType,
+    // Only public clients have redirect URIs, only machine clients have audiences
+    pub redirect_uris: Vec<RedirectUri>,
+    pub allowed_scopes: Scopes,
+    pub allowed_audiences: Vec<Audience>,
+    pub created_at: i64,
+}
+
+impl OAuthClient {
+    pub fn has_redirect_uri(&self, uri: &str) -> bool {
+        self.redirect_uris.iter().any(|redirect_uri| redirect_uri.as_ref() == uri)
+    }
+
+    pub fn allows_audience(&self, audience: &str) -> bool {
+        self.allowed_audiences.iter().any(|allowed| allowed.as_ref() == audience)
+    }
+}
+
+// Public clients act for a user through the authorization code flow and prove who they are with PKCE.
+// Machine clients are backend services acting for themselves through the client credentials grant,
+// authenticating with a secret (RFC 6749 section 4.4).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OAuthClientType {
+    Public,
+    Machine,
+}
+
+impl OAuthClientType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OAuthClientType::Public => "public",
+            OAuthClientType::Machine => "machine",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "public" => Ok(OAuthClientType::Public),
+            "machine" => Ok(OAuthClientType::Machine),
+            _ => Err(eyre!("Invalid OAuth client type: {}", s)),
+        }
+    }
+}
+
+// Name of a service that accepts client credentials access tokens, used as their `aud` claim
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Audience(String);
+
+impl Audience {
+    pub fn parse(audience: String) -> Result<Self> {
+        let audience_regex = Regex::new(r"^[\x21-\x7E]{1,255}$").unwrap();
+        if !audience_regex.is_match(&audience) {
+            return Err(eyre!("Invalid audience: {}", audience));
+        }
+        Ok(Self(audience))
+    }
+}
+
+impl AsRef<str> for Audience {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// What a user allowed a client to do, carried from the consent page to the authorization code and on to the token
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthorizationGrant {
+    pub client_id: OAuthClientId,
+    pub redirect_uri: RedirectUri,
+    pub email: Email,
+    pub scopes: Scopes,
+    pub code_challenge: CodeChallenge,
+    // Opaque value the client gets back on the redirect
+    pub state: Option<String>,
+    // OpenID Connect value echoed in the ID token, binding it to the client's session
+    pub nonce: Option<String>,
+    // The user's login at the time of the request, reported in the ID token
+    pub authentication: Authentication,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_uri_parse() {
+        for uri in [
+            "https://app.example.com/callback",
+            "http://127.0.0.1:51234/callback",
+            "http://localhost/callback",
+            "com.example.app:/oauth2redirect",
+        ] {
+            assert!(RedirectUri::parse(uri.to_owned()).is_ok(), "Failed for input: {}", uri);
+        }
+        for uri in [
+            "/callback",
+            "http://app.example.com/callback",
+            "https://app.example.com/callback#fragment",
+            "javascript:alert(1)",
+        ] {
+            assert!(RedirectUri::parse(uri.to_owned()).is_err(), "Failed for input: {}", uri);
+        }
+    }
+
+    #[test]
+    fn test_scopes_parse_and_compare() {
+        let requested = Scopes::parse("profile  email profile").unwrap();
+        assert_eq!(requested.to_string(), "email profile");
+
+        let allowed = Scopes::parse("email openid profile").unwrap();
+        assert!(requested.is_subset(&allowed));
+        assert!(!allowed.is_subset(&requested));
+        assert!(Scopes::parse("").unwrap().is_empty());
+        assert!(Scopes::parse("email \"quoted\"").is_err());
+    }
+
+    #[test]
+    fn test_audience_parse() {
+        assert!(Audience::parse("app-service".to_owned()).is_ok());
+        assert!(Audience::parse("https://api.example.com".to_owned()).is_ok());
+        assert!(Audience::parse(String::new()).is_err());
+        assert!(Audience::parse("app service".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_code_challenge_verify() {
+        // Example from RFC 7636 appendix B
+        let challenge = CodeChallenge::parse("E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".to_owned()).unwrap();
+        assert!(challenge.verify("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"));
+        assert!(!challenge.verify("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXl"));
+        assert!(!challenge.verify("short"));
+
+        assert!(CodeChallenge::parse("not base64!".to_owned()).is_err());
+        assert!(CodeChallenge::parse("c2hvcnQ".to_owned()).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use regex::Regex;
+
+// A phone number in E.164 form, the format SMS providers expect
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PhoneNumber(String);
+
+impl PhoneNumber {
+    pub fn parse(s: String) -> Result<Self, String> {
+        // Spaces and the usual punctuation are only formatting, so they are dropped before validating
+        let normalized: String = s.chars().filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')')).collect();
+        let phone_regex = Regex::new(r"^\+[1-9][0-9]{7,14}$").unwrap();
+        if phone_regex.is_match(&normalized) {
+            Ok(PhoneNumber(normalized))
+        } else {
+            Err(format!("Invalid phone number: {}", s))
+        }
+    }
+}
+
+impl AsRef<str> for PhoneNumber {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_phone_numbers() {
+        assert_eq!(PhoneNumber::parse("+14155552671".to_owned()).unwrap().as_ref(), "+14155552671");
+        assert_eq!(PhoneNumber::parse("+44 20 7946 0958".to_owned()).unwrap().as_ref(), "+442079460958");
+        assert_eq!(PhoneNumber::parse("+1 (415) 555-2671".to_owned()).unwrap().as_ref(), "+14155552671");
+    }
+
+    #[test]
+    fn test_invalid_phone_numbers() {
+        for phone_number in ["", "14155552671", "+0155552671", "+1415", "+1234567890123456", "+1415555267a"] {
+            assert!(PhoneNumber::parse(phone_number.to_owned()).is_err(), "Failed for input: {}", phone_number);
+        }
+    }
+}
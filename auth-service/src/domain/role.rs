@@ -0,0 +1,52 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+// Grants every permission on the admin API
+pub const ADMIN_ROLE: &str = "admin";
+
+lazy_static! {
+    // Role names end up in every JWT, so keep them short and unambiguous
+    static ref ROLE_REGEX: Regex = Regex::new(r"^[a-z][a-z0-9_-]{0,31}$").unwrap();
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Role(String);
+
+impl Role {
+    pub fn parse(s: String) -> Result<Self, String> {
+        if ROLE_REGEX.is_match(&s) {
+            Ok(Role(s))
+        } else {
+            Err(format!("Invalid role: {}", s))
+        }
+    }
+
+    pub fn admin() -> Self {
+        Role(ADMIN_ROLE.to_owned())
+    }
+}
+
+impl AsRef<str> for Role {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_roles() {
+        for role in ["admin", "support", "billing-read", "team_lead2"] {
+            assert_eq!(Role::parse(role.to_owned()).unwrap().as_ref(), role);
+        }
+    }
+
+    #[test]
+    fn test_invalid_roles() {
+        for role in ["", "Admin", "2fa", "has space", "a".repeat(33).as_str()] {
+            assert!(Role::parse(role.to_owned()).is_err(), "Failed for input: {}", role);
+        }
+    }
+}
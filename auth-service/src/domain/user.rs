@@ -1,10 +1,18 @@
-use crate::domain::{Email, Password};
+use crate::domain::{Email, Password, PhoneNumber, Role};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct User{
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    pub verified: bool,
+    // Disabled accounts can't log in, an admin has to enable them again
+    pub disabled: bool,
+    pub roles: Vec<Role>,
+    pub phone_number: Option<PhoneNumber>,
+    // Only a verified phone number can receive 2FA codes
+    pub phone_verified: bool,
+    pub two_fa_channel: TwoFAChannel,
 }
 
 impl User {
@@ -13,6 +21,51 @@ impl User {
             email,
             password,
             requires_2fa,
+            verified: false,
+            disabled: false,
+            roles: Vec::new(),
+            phone_number: None,
+            phone_verified: false,
+            two_fa_channel: TwoFAChannel::Email,
         }
     }
-}
\ No newline at end of file
+}
+
+// Where login 2FA codes are sent, unless an authenticator app is set up
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TwoFAChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl TwoFAChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TwoFAChannel::Email => "email",
+            TwoFAChannel::Sms => "sms",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "email" => Ok(TwoFAChannel::Email),
+            "sms" => Ok(TwoFAChannel::Sms),
+            _ => Err(format!("Invalid 2FA channel: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_fa_channel_parse() {
+        for channel in [TwoFAChannel::Email, TwoFAChannel::Sms] {
+            assert_eq!(TwoFAChannel::parse(channel.as_str()), Ok(channel));
+        }
+        assert!(TwoFAChannel::parse("SMS").is_err());
+        assert!(TwoFAChannel::parse("totp").is_err());
+    }
+}
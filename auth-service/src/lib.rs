@@ -1,9 +1,12 @@
 use std::error::Error;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use axum::{
-    http::{StatusCode, Method},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    http::{header, HeaderValue, StatusCode, Method},
+    middleware,
     response::{IntoResponse, Response},
-    routing::post, 
+    routing::{delete, get, post}, 
     serve::Serve, 
     Router    
 };
@@ -11,10 +14,10 @@ use redis::{RedisResult, Client};
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use tokio::net::TcpListener;
 use app_state::AppState;
-use domain::AuthAPIError;
+use domain::{AuthAPIError, OAuthError};
 use serde::{Deserialize, Serialize};
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
-use utils::tracing::{make_span_with_request_id, on_request, on_response};
+use utils::{rate_limit::rate_limit, tracing::{make_span_with_request_id, on_request, on_response}};
 
 pub mod routes;
 pub mod domain;
@@ -30,13 +33,34 @@ pub struct ErrorResponse {
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
+        let retry_after = match self {
+            AuthAPIError::AccountLocked(seconds) => Some(seconds),
+            _ => None,
+        };
         let (status, error_message) = match self {
             AuthAPIError::IncorrectCredentials => (StatusCode::UNAUTHORIZED, "Incorrect credentials"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
             AuthAPIError::UnexpectedError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error"),
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing token"),
-            AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token")
+            AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP already enabled"),
+            AuthAPIError::SigningKeyAlreadyInUse => (StatusCode::CONFLICT, "Signing key already in use"),
+            AuthAPIError::UnsupportedSigningAlgorithm => (StatusCode::BAD_REQUEST, "Unsupported signing algorithm, use EdDSA or RS256"),
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            AuthAPIError::AccountDisabled => (StatusCode::FORBIDDEN, "Account disabled"),
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::AccountLocked(_) => (StatusCode::LOCKED, "Account locked"),
+            AuthAPIError::TwoFAAttemptsExceeded => (StatusCode::UNAUTHORIZED, "Too many incorrect 2FA codes, log in again"),
+            AuthAPIError::OutboxEmailNotFound => (StatusCode::NOT_FOUND, "Outbox email not found"),
+            AuthAPIError::PhoneNumberNotVerified => (StatusCode::CONFLICT, "Phone number not verified"),
+            AuthAPIError::PhoneVerificationAttemptsExceeded => (StatusCode::UNAUTHORIZED, "Too many incorrect codes, request a new one"),
+            AuthAPIError::OAuthClientNotFound => (StatusCode::NOT_FOUND, "OAuth client not found"),
+            AuthAPIError::ApiKeyNotFound => (StatusCode::NOT_FOUND, "API key not found"),
+            AuthAPIError::UnknownRole => (StatusCode::BAD_REQUEST, "Unknown role"),
+            AuthAPIError::AdminSelfLockout => (StatusCode::CONFLICT, "Admins can't disable, delete or demote themselves")
         };
 
         let body = serde_json::to_string(&ErrorResponse {
@@ -44,7 +68,55 @@ impl IntoResponse for AuthAPIError {
         })
         .unwrap_or_else(|_| "{\"error\": \"Failed to serialize error message\"}".to_string());
 
-        (status, [("Content-Type", "application/json")], body).into_response()
+        let mut response = (status, [("Content-Type", "application/json")], body).into_response();
+        if let Some(seconds) = retry_after {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OAuthErrorResponse {
+    pub error: String,
+    pub error_description: String,
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        log_error_chain(&self);
+        let status = match self {
+            OAuthError::InvalidClient | OAuthError::InvalidToken => StatusCode::UNAUTHORIZED,
+            OAuthError::InsufficientScope => StatusCode::FORBIDDEN,
+            OAuthError::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        // Resource endpoints also name the error in the challenge header (RFC 6750 section 3)
+        let challenge = match self {
+            OAuthError::InvalidToken | OAuthError::InsufficientScope => {
+                Some(format!("Bearer error=\"{}\"", self.code()))
+            }
+            // Machine clients authenticate to the token endpoint with HTTP Basic (RFC 6749 section 5.2)
+            OAuthError::InvalidClient => Some("Basic realm=\"oauth\"".to_owned()),
+            _ => None,
+        };
+
+        let body = serde_json::to_string(&OAuthErrorResponse {
+            error: self.code().to_string(),
+            error_description: self.description().to_string(),
+        })
+        .unwrap_or_else(|_| "{\"error\": \"server_error\"}".to_string());
+
+        let mut response = (
+            status,
+            [(header::CONTENT_TYPE, "application/json"), (header::CACHE_CONTROL, "no-store")],
+            body,
+        )
+            .into_response();
+        if let Some(challenge) = challenge.and_then(|challenge| HeaderValue::from_str(&challenge).ok()) {
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, challenge);
+        }
+        response
     }
 }
 
@@ -64,7 +136,7 @@ fn log_error_chain(e: &(dyn Error + 'static)) {
 
 // this struct encapsulates our application-related logic
 pub struct Application {
-    server: Serve<TcpListener, Router, Router>,
+    server: Serve<TcpListener, IntoMakeServiceWithConnectInfo<Router, SocketAddr>, middleware::AddExtension<Router, ConnectInfo<SocketAddr>>>,
     // address is exposed as a public field
     // so that tests can access it
     pub address: String,
@@ -77,18 +149,63 @@ impl Application {
             // "http://[YOUR_DROPLET_IP]:8000".parse()?,
         ];
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
             .allow_credentials(true)
             .allow_origin(allow_origins);
 
         let router = Router::new()
             .route("/signup", post(routes::signup))
             .route("/login", post(routes::login))
+            .route("/login/magic-link", post(routes::request_magic_link))
+            .route("/login/magic-link/callback", get(routes::magic_link_callback))
             .route("/logout", post(routes::logout))
             .route("/verify-2fa", post(routes::verify_2fa))
+            .route("/2fa/totp/enroll", post(routes::enroll_totp))
+            .route("/2fa/totp/confirm", post(routes::confirm_totp))
+            .route("/2fa/recovery-codes", post(routes::regenerate_recovery_codes))
+            .route("/webauthn/register/start", post(routes::webauthn_register_start))
+            .route("/webauthn/register/finish", post(routes::webauthn_register_finish))
+            .route("/webauthn/login/start", post(routes::webauthn_login_start))
+            .route("/webauthn/login/finish", post(routes::webauthn_login_finish))
             .route("/verify-token", post(routes::verify_token))
+            .route("/.well-known/jwks.json", get(routes::jwks))
+            .route("/.well-known/openid-configuration", get(routes::openid_configuration))
+            .route("/admin/signing-keys/rotate", post(routes::rotate_signing_key))
+            .route("/admin/users", get(routes::list_users))
+            .route(
+                "/admin/users/{email}",
+                get(routes::get_user).patch(routes::update_user).delete(routes::delete_user),
+            )
+            .route("/admin/users/{email}/logout", post(routes::force_logout))
+            .route("/admin/email-outbox", get(routes::list_outbox_emails))
+            .route("/admin/email-outbox/{id}/replay", post(routes::replay_outbox_email))
+            .route("/admin/oauth/clients", post(routes::create_oauth_client).get(routes::list_oauth_clients))
+            .route(
+                "/admin/oauth/clients/{client_id}",
+                get(routes::get_oauth_client).delete(routes::delete_oauth_client),
+            )
+            .route("/oauth/authorize", get(routes::authorize))
+            .route("/oauth/authorize/consent", post(routes::authorize_consent))
+            .route("/oauth/token", post(routes::token))
+            .route("/oauth/introspect", post(routes::introspect))
+            .route("/oauth/revoke", post(routes::revoke))
+            .route("/userinfo", get(routes::userinfo).post(routes::userinfo))
+            .route("/refresh", post(routes::refresh))
+            .route("/password-reset/request", post(routes::password_reset_request))
+            .route("/password-reset/confirm", post(routes::password_reset_confirm))
+            .route("/verify-email", post(routes::verify_email))
+            .route("/verify-email/resend", post(routes::resend_verification_email))
+            .route("/change-password", post(routes::change_password))
+            .route("/account", delete(routes::delete_account))
+            .route("/account/export", get(routes::export_account))
+            .route("/account/phone", post(routes::set_phone_number).delete(routes::remove_phone_number))
+            .route("/account/phone/verify", post(routes::verify_phone_number))
+            .route("/account/2fa-channel", post(routes::set_two_fa_channel))
+            .route("/account/api-keys", post(routes::create_api_key).get(routes::list_api_keys))
+            .route("/account/api-keys/{id}", delete(routes::revoke_api_key))
             .fallback_service(ServeDir::new("assets"))
-            .with_state(app_state)
+            .with_state(app_state.clone())
+            .layer(middleware::from_fn_with_state(app_state, rate_limit))
             .layer(cors)
             .layer(
                 TraceLayer::new_for_http()
@@ -99,7 +216,8 @@ impl Application {
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Rate limits are keyed by the client address
+        let server = axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>());
 
         Ok(Self { server, address })
     }
@@ -1,10 +1,10 @@
 use auth_service::{
     Application, app_state::{self}, get_postgres_pool, get_redis_client, services::{
-        RedisBannedTokenStore, RedisTwoFACodeStore, data_stores::PostgresUserStore, mock_email_client::MockEmailClient
-    }, utils::{constants::{DATABASE_URL, REDIS_HOST_NAME, prod}, tracing::init_tracing}
+        RedisAuthorizationGrantStore, RedisBannedTokenStore, RedisEmailVerificationTokenStore, RedisLoginAttemptStore, RedisMagicLinkStore, RedisPasswordResetTokenStore, RedisPhoneVerificationCodeStore, RedisRateLimitStore, RedisRefreshTokenStore, RedisTwoFACodeStore, RedisWebauthnChallengeStore, data_stores::{PostgresApiKeyStore, PostgresEmailOutboxStore, PostgresOAuthClientStore, PostgresRecoveryCodeStore, PostgresSigningKeyStore, PostgresTotpSecretStore, PostgresUserStore, PostgresWebauthnCredentialStore}, email_outbox_worker::EmailOutboxWorker, email_templates::EmailTemplates, http_sms_client::HttpSmsClient, mock_email_client::MockEmailClient, mock_sms_client::MockSmsClient, signing_key_refresher::SigningKeyRefresher, smtp_email_client::SmtpEmailClient
+    }, utils::{constants::{DATABASE_URL, EMAIL_OUTBOX_POLL_INTERVAL_MS, EMAIL_TEMPLATES_DIR, JWT_KEY_RING_REFRESH_SECONDS, REDIS_HOST_NAME, SMS_CONFIG, SMTP_CONFIG, prod}, tracing::init_tracing}
 };
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
 #[tokio::main]
@@ -16,15 +16,58 @@ async fn main() {
     let pg_pool = configure_postgresql().await;
     let redis_conn = configure_redis();
     let arc_redis_conn = Arc::new(RwLock::new(redis_conn));
-    let user_store = PostgresUserStore::new(pg_pool);
+    let user_store = PostgresUserStore::new(pg_pool.clone());
+    let totp_secret_store = PostgresTotpSecretStore::new(pg_pool.clone());
+    let webauthn_credential_store = PostgresWebauthnCredentialStore::new(pg_pool.clone());
+    let recovery_code_store = PostgresRecoveryCodeStore::new(pg_pool.clone());
+    let oauth_client_store = PostgresOAuthClientStore::new(pg_pool.clone());
+    let api_key_store = PostgresApiKeyStore::new(pg_pool.clone());
+    let signing_key_store = PostgresSigningKeyStore::new(pg_pool.clone());
+    let email_outbox_store = PostgresEmailOutboxStore::new(pg_pool);
     let banned_token_store = RedisBannedTokenStore::new(arc_redis_conn.clone());
-    let two_fa_code_store = RedisTwoFACodeStore::new(arc_redis_conn);
-    let email_client = MockEmailClient::default();
+    let two_fa_code_store = RedisTwoFACodeStore::new(arc_redis_conn.clone());
+    let password_reset_token_store = RedisPasswordResetTokenStore::new(arc_redis_conn.clone());
+    let email_verification_token_store = RedisEmailVerificationTokenStore::new(arc_redis_conn.clone());
+    let refresh_token_store = RedisRefreshTokenStore::new(arc_redis_conn.clone());
+    let webauthn_challenge_store = RedisWebauthnChallengeStore::new(arc_redis_conn.clone());
+    let magic_link_store = RedisMagicLinkStore::new(arc_redis_conn.clone());
+    let login_attempt_store = RedisLoginAttemptStore::new(arc_redis_conn.clone());
+    let phone_verification_code_store = RedisPhoneVerificationCodeStore::new(arc_redis_conn.clone());
+    let authorization_grant_store = RedisAuthorizationGrantStore::new(arc_redis_conn.clone());
+    let rate_limit_store = RedisRateLimitStore::new(arc_redis_conn);
+    let email_client = configure_email_client();
+    let email_templates = configure_email_templates();
+    let sms_client = configure_sms_client();
 
     let arc_user_store = Arc::new(RwLock::new(user_store));
     let arc_banned_token_store = Arc::new(RwLock::new(banned_token_store));
     let arc_two_fa_code_store = Arc::new(RwLock::new(two_fa_code_store));
-    let app_state = Arc::new(app_state::AppState::new(arc_user_store, arc_banned_token_store, arc_two_fa_code_store, email_client));
+    let arc_password_reset_token_store = Arc::new(RwLock::new(password_reset_token_store));
+    let arc_email_verification_token_store = Arc::new(RwLock::new(email_verification_token_store));
+    let arc_refresh_token_store = Arc::new(RwLock::new(refresh_token_store));
+    let arc_totp_secret_store = Arc::new(RwLock::new(totp_secret_store));
+    let arc_webauthn_credential_store = Arc::new(RwLock::new(webauthn_credential_store));
+    let arc_webauthn_challenge_store = Arc::new(RwLock::new(webauthn_challenge_store));
+    let arc_recovery_code_store = Arc::new(RwLock::new(recovery_code_store));
+    let arc_magic_link_store = Arc::new(RwLock::new(magic_link_store));
+    let arc_login_attempt_store = Arc::new(RwLock::new(login_attempt_store));
+    let arc_rate_limit_store = Arc::new(RwLock::new(rate_limit_store));
+    let arc_email_outbox_store = Arc::new(RwLock::new(email_outbox_store));
+    let arc_phone_verification_code_store = Arc::new(RwLock::new(phone_verification_code_store));
+    let arc_oauth_client_store = Arc::new(RwLock::new(oauth_client_store));
+    let arc_api_key_store = Arc::new(RwLock::new(api_key_store));
+    let arc_signing_key_store = Arc::new(RwLock::new(signing_key_store));
+    let arc_authorization_grant_store = Arc::new(RwLock::new(authorization_grant_store));
+
+    let email_outbox_worker = EmailOutboxWorker::new(arc_email_outbox_store.clone(), email_client);
+    tokio::spawn(email_outbox_worker.run(Duration::from_millis(EMAIL_OUTBOX_POLL_INTERVAL_MS)));
+
+    // Every replica signs with the keys in the store, so a replica that can't load them must not start
+    let signing_key_refresher = SigningKeyRefresher::new(arc_signing_key_store.clone());
+    signing_key_refresher.initialize().await.expect("Failed to load JWT signing keys");
+    tokio::spawn(signing_key_refresher.run(Duration::from_secs(JWT_KEY_RING_REFRESH_SECONDS)));
+
+    let app_state = Arc::new(app_state::AppState::new(arc_user_store, arc_banned_token_store, arc_two_fa_code_store, arc_password_reset_token_store, arc_email_verification_token_store, arc_refresh_token_store, arc_totp_secret_store, arc_webauthn_credential_store, arc_webauthn_challenge_store, arc_recovery_code_store, arc_magic_link_store, arc_login_attempt_store, arc_rate_limit_store, arc_email_outbox_store, email_templates, arc_phone_verification_code_store, sms_client, arc_oauth_client_store, arc_authorization_grant_store, arc_api_key_store, arc_signing_key_store));
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build application");
@@ -55,4 +98,39 @@ fn configure_redis() -> redis::Connection {
         .expect("Failed to get Redis client")
         .get_connection()
         .expect("Failed to get Redis connection")
-}
\ No newline at end of file
+}
+
+fn configure_email_client() -> app_state::EmailClientType {
+    match SMTP_CONFIG.clone() {
+        Some(config) => {
+            println!("Sending emails through SMTP server {}:{}", config.host, config.port);
+            Arc::new(SmtpEmailClient::new(config).expect("Failed to configure SMTP email client"))
+        }
+        None => {
+            println!("EMAIL_CLIENT is not smtp, emails will only be logged");
+            Arc::new(MockEmailClient::default())
+        }
+    }
+}
+
+fn configure_sms_client() -> app_state::SmsClientType {
+    match SMS_CONFIG.clone() {
+        Some(config) => {
+            println!("Sending text messages through SMS provider {}", config.url);
+            Arc::new(HttpSmsClient::new(config).expect("Failed to configure HTTP SMS client"))
+        }
+        None => {
+            println!("SMS_CLIENT is not http, text messages will only be logged");
+            Arc::new(MockSmsClient::default())
+        }
+    }
+}
+
+// Broken templates stop the service from starting rather than failing the first time an email goes out
+fn configure_email_templates() -> app_state::EmailTemplatesType {
+    println!("Loading email templates from {}", EMAIL_TEMPLATES_DIR.as_str());
+    match EmailTemplates::load(EMAIL_TEMPLATES_DIR.as_str()) {
+        Ok(templates) => Arc::new(templates),
+        Err(e) => panic!("{}", e),
+    }
+}
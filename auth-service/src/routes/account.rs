@@ -0,0 +1,252 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, Password, TotpSecretStoreError, UserStoreError as ErrorUser};
+use crate::utils::{auth::authenticate, client_context::ClientContext, constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME}};
+
+use super::api_keys::ApiKeySummary;
+use super::login::record_failed_login;
+
+#[tracing::instrument(name = "Delete account", skip_all)]
+pub async fn delete_account(
+    State(state): State<Arc<AppState>>,
+    client: ClientContext,
+    jar: CookieJar,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (token, claims) = match authenticate(&jar, state.banned_token_store.clone()).await {
+        Ok(res) => res,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let password = match Password::parse(request.password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    // Locked accounts are refused before the password is even checked
+    match state.login_attempt_store.read().await.lock_remaining(&email).await {
+        Ok(Some(seconds)) => return (jar, Err(AuthAPIError::AccountLocked(seconds))),
+        Ok(None) => {},
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    // A valid session is not enough to delete an account, the user must re-enter their password
+    let mut user_store = state.user_store.write().await;
+    match user_store.validate_user(email.as_ref(), password.as_ref()).await {
+        Ok(_) => {},
+        Err(ErrorUser::InvalidCredentials) => {
+            drop(user_store);
+            return (jar, Err(record_failed_login(&email, &state, &client).await));
+        }
+        Err(ErrorUser::UserNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e)))),
+    }
+
+    if let Err(e) = user_store.delete_user(email.as_ref()).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))));
+    }
+    drop(user_store);
+
+    if let Err(e) = purge_pending_tokens(&state, &email).await {
+        return (jar, Err(e));
+    }
+
+    let mut banned_token_store = state.banned_token_store.write().await;
+    if let Err(e) = banned_token_store.add_token(token).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    if let Err(e) = banned_token_store.revoke_user_tokens(email.as_ref(), Utc::now().timestamp()).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    drop(banned_token_store);
+
+    let jar = jar.remove(JWT_COOKIE_NAME).remove(REFRESH_COOKIE_NAME);
+
+    (jar, Ok(StatusCode::NO_CONTENT))
+}
+
+#[tracing::instrument(name = "Export account", skip_all)]
+pub async fn export_account(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = match state.user_store.read().await.get_user(email.as_ref()).await {
+        Ok(user) => user.clone(),
+        Err(ErrorUser::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+    };
+
+    // Secrets (password hash, pending codes and tokens) are never exported, only whether they exist
+    let pending_two_fa_code = state.two_fa_code_store.read().await.get_code(&email).await.is_ok();
+    let pending_password_reset = state.password_reset_token_store.read().await.get_token(&email).await.is_ok();
+    let pending_email_verification = state.email_verification_token_store.read().await.get_token(&email).await.is_ok();
+    let passkeys = state
+        .webauthn_credential_store
+        .read()
+        .await
+        .get_credentials(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|credential| ExportedPasskey {
+            credential_id: credential.credential_id,
+            name: credential.name,
+            sign_count: credential.sign_count,
+            created_at: credential.created_at,
+        })
+        .collect();
+    let api_keys = state
+        .api_key_store
+        .read()
+        .await
+        .list_keys(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .iter()
+        .map(ApiKeySummary::from)
+        .collect();
+    let remaining_recovery_codes = state
+        .recovery_code_store
+        .read()
+        .await
+        .count_codes(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let totp_enrollment = match state.totp_secret_store.read().await.get_secret(&email).await {
+        Ok((_, true)) => "confirmed",
+        Ok((_, false)) => "pending",
+        Err(TotpSecretStoreError::SecretNotFound) => "none",
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let response = AccountExport {
+        email: user.email.as_ref().to_owned(),
+        requires_2fa: user.requires_2fa,
+        verified: user.verified,
+        roles: user.roles.iter().map(|role| role.as_ref().to_owned()).collect(),
+        disabled: user.disabled,
+        phone_number: user.phone_number.as_ref().map(|phone_number| phone_number.as_ref().to_owned()),
+        phone_verified: user.phone_verified,
+        two_fa_channel: user.two_fa_channel.as_str().to_owned(),
+        totp_enrollment: totp_enrollment.to_owned(),
+        passkeys,
+        remaining_recovery_codes,
+        api_keys,
+        pending_two_fa_code,
+        pending_password_reset,
+        pending_email_verification,
+        exported_at: Utc::now().to_rfc3339(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// Removes every code, token or queued email still waiting for `email`
+pub(super) async fn purge_pending_tokens(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state.two_fa_code_store
+        .write()
+        .await
+        .remove_code(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state.password_reset_token_store
+        .write()
+        .await
+        .remove_token(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state.email_verification_token_store
+        .write()
+        .await
+        .remove_token(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state.phone_verification_code_store
+        .write()
+        .await
+        .remove_code(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state.refresh_token_store
+        .write()
+        .await
+        .revoke_all_families(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Queued emails carry codes and links too, along with the address itself
+    state.email_outbox_store
+        .write()
+        .await
+        .purge_recipient(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountExport {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    pub verified: bool,
+    pub roles: Vec<String>,
+    pub disabled: bool,
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: Option<String>,
+    #[serde(rename = "phoneVerified")]
+    pub phone_verified: bool,
+    #[serde(rename = "twoFAChannel")]
+    pub two_fa_channel: String,
+    // none, pending until the first code confirms it, or confirmed
+    #[serde(rename = "totpEnrollment")]
+    pub totp_enrollment: String,
+    pub passkeys: Vec<ExportedPasskey>,
+    #[serde(rename = "remainingRecoveryCodes")]
+    pub remaining_recovery_codes: usize,
+    // Same as the key listing, so the keys themselves are never exported
+    #[serde(rename = "apiKeys")]
+    pub api_keys: Vec<ApiKeySummary>,
+    #[serde(rename = "pending2FACode")]
+    pub pending_two_fa_code: bool,
+    #[serde(rename = "pendingPasswordReset")]
+    pub pending_password_reset: bool,
+    #[serde(rename = "pendingEmailVerification")]
+    pub pending_email_verification: bool,
+    #[serde(rename = "exportedAt")]
+    pub exported_at: String,
+}
+
+// Public keys are left out, they are of no use to anyone but this service
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedPasskey {
+    pub credential_id: String,
+    pub name: String,
+    pub sign_count: u32,
+    pub created_at: i64,
+}
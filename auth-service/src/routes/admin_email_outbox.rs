@@ -0,0 +1,129 @@
+use axum::extract::{Path, Query, State};
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{
+    data_stores::{OutboxEmail, OutboxEmailId, OutboxStatus},
+    AuthAPIError, EmailOutboxStoreError,
+};
+use crate::utils::{
+    auth::authenticate_admin,
+    constants::{ADMIN_EMAIL_OUTBOX_DEFAULT_PAGE_SIZE, ADMIN_EMAIL_OUTBOX_MAX_PAGE_SIZE},
+};
+
+// Lists queued emails, the dead-lettered ones by default since those need someone to look at them
+#[tracing::instrument(name = "Admin list outbox emails", skip_all)]
+pub async fn list_outbox_emails(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Query(query): Query<ListOutboxEmailsQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+
+    let status = match query.status.as_deref() {
+        Some(status) => OutboxStatus::parse(status).map_err(|_| AuthAPIError::InvalidCredentials)?,
+        None => OutboxStatus::Dead,
+    };
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(ADMIN_EMAIL_OUTBOX_DEFAULT_PAGE_SIZE);
+    if page == 0 || per_page == 0 || per_page > ADMIN_EMAIL_OUTBOX_MAX_PAGE_SIZE {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+    let offset = (page - 1).checked_mul(per_page).ok_or(AuthAPIError::InvalidCredentials)?;
+
+    let (emails, total) = state
+        .email_outbox_store
+        .read()
+        .await
+        .list(status, offset, per_page)
+        .await
+        .map_err(outbox_store_error)?;
+
+    Ok(Json(OutboxEmailListResponse {
+        emails: emails.iter().map(OutboxEmailSummary::from).collect(),
+        page,
+        per_page,
+        total,
+    }))
+}
+
+// Gives an email a fresh set of attempts and makes it due right away
+#[tracing::instrument(name = "Admin replay outbox email", skip_all)]
+pub async fn replay_outbox_email(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+    let id = OutboxEmailId::parse(id).map_err(|_| AuthAPIError::OutboxEmailNotFound)?;
+
+    let email = state
+        .email_outbox_store
+        .write()
+        .await
+        .replay(&id)
+        .await
+        .map_err(outbox_store_error)?;
+    tracing::info!("Replaying outbox email {}", id.as_ref());
+
+    Ok(Json(OutboxEmailSummary::from(&email)))
+}
+
+fn outbox_store_error(e: EmailOutboxStoreError) -> AuthAPIError {
+    match e {
+        EmailOutboxStoreError::EmailNotFound => AuthAPIError::OutboxEmailNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListOutboxEmailsQuery {
+    pub status: Option<String>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+// Bodies are left out, they carry codes and tokens meant only for the recipient
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEmailSummary {
+    pub id: String,
+    pub recipient: String,
+    pub subject: String,
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub next_attempt_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+impl From<&OutboxEmail> for OutboxEmailSummary {
+    fn from(email: &OutboxEmail) -> Self {
+        Self {
+            id: email.id.as_ref().to_owned(),
+            recipient: email.recipient.as_ref().to_owned(),
+            subject: email.content.subject.clone(),
+            status: email.status.as_str().to_owned(),
+            attempts: email.attempts,
+            last_error: email.last_error.clone(),
+            created_at: email.created_at,
+            next_attempt_at: email.next_attempt_at,
+            expires_at: email.expires_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEmailListResponse {
+    pub emails: Vec<OutboxEmailSummary>,
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+}
@@ -0,0 +1,204 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{
+    data_stores::ClientSecret, Audience, AuthAPIError, OAuthClient, OAuthClientId, OAuthClientStoreError,
+    OAuthClientType, RedirectUri, Scopes,
+};
+use crate::utils::auth::{authenticate_admin, is_reserved_audience};
+
+// Registers an application that can send users to /oauth/authorize, or a machine client that
+// authenticates with a secret to call the given audiences on its own behalf
+#[tracing::instrument(name = "Admin create OAuth client", skip_all)]
+pub async fn create_oauth_client(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<CreateOAuthClientRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+
+    let name = request.name.trim().to_owned();
+    let client_type = match request.client_type.as_deref() {
+        Some(client_type) => OAuthClientType::parse(client_type).map_err(|_| AuthAPIError::InvalidCredentials)?,
+        None => OAuthClientType::Public,
+    };
+    // Public clients act for users through redirects, machine clients only for themselves towards audiences
+    let valid_shape = match client_type {
+        OAuthClientType::Public => !request.redirect_uris.is_empty() && request.allowed_audiences.is_empty(),
+        OAuthClientType::Machine => request.redirect_uris.is_empty() && !request.allowed_audiences.is_empty(),
+    };
+    let reserved_audience = request.allowed_audiences.iter().any(|audience| is_reserved_audience(audience));
+    if name.is_empty() || !valid_shape || reserved_audience {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+    let redirect_uris = request
+        .redirect_uris
+        .into_iter()
+        .map(RedirectUri::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let allowed_scopes = Scopes::from_tokens(request.allowed_scopes).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let allowed_audiences = request
+        .allowed_audiences
+        .into_iter()
+        .map(Audience::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let client = OAuthClient {
+        client_id: OAuthClientId::default(),
+        name,
+        client_type,
+        redirect_uris,
+        allowed_scopes,
+        allowed_audiences,
+        created_at: Utc::now().timestamp(),
+    };
+    let secret = (client_type == OAuthClientType::Machine).then(ClientSecret::default);
+    state
+        .oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone(), secret.clone())
+        .await
+        .map_err(oauth_client_store_error)?;
+    tracing::info!("Registered {} OAuth client {}", client_type.as_str(), client.client_id.as_ref());
+
+    // The secret is only stored hashed, so this is the one chance to read it
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateOAuthClientResponse {
+            client: OAuthClientSummary::from(&client),
+            client_secret: secret.map(|secret| secret.as_ref().to_owned()),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "Admin list OAuth clients", skip_all)]
+pub async fn list_oauth_clients(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+
+    let clients = state
+        .oauth_client_store
+        .read()
+        .await
+        .list_clients()
+        .await
+        .map_err(oauth_client_store_error)?;
+
+    Ok(Json(OAuthClientListResponse { clients: clients.iter().map(OAuthClientSummary::from).collect() }))
+}
+
+#[tracing::instrument(name = "Admin get OAuth client", skip_all)]
+pub async fn get_oauth_client(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(client_id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| AuthAPIError::OAuthClientNotFound)?;
+
+    let client = state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+        .map_err(oauth_client_store_error)?;
+
+    Ok(Json(OAuthClientSummary::from(&client)))
+}
+
+// Codes already issued to the client can no longer be exchanged once it is gone
+#[tracing::instrument(name = "Admin delete OAuth client", skip_all)]
+pub async fn delete_oauth_client(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(client_id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| AuthAPIError::OAuthClientNotFound)?;
+
+    state
+        .oauth_client_store
+        .write()
+        .await
+        .delete_client(&client_id)
+        .await
+        .map_err(oauth_client_store_error)?;
+    tracing::info!("Deleted OAuth client {}", client_id.as_ref());
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn oauth_client_store_error(e: OAuthClientStoreError) -> AuthAPIError {
+    match e {
+        OAuthClientStoreError::ClientNotFound => AuthAPIError::OAuthClientNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOAuthClientRequest {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub client_type: Option<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub allowed_scopes: Vec<String>,
+    #[serde(default)]
+    pub allowed_audiences: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthClientSummary {
+    pub client_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub client_type: String,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub allowed_audiences: Vec<String>,
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOAuthClientResponse {
+    #[serde(flatten)]
+    pub client: OAuthClientSummary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+impl From<&OAuthClient> for OAuthClientSummary {
+    fn from(client: &OAuthClient) -> Self {
+        Self {
+            client_id: client.client_id.as_ref().to_owned(),
+            name: client.name.clone(),
+            client_type: client.client_type.as_str().to_owned(),
+            redirect_uris: client.redirect_uris.iter().map(|uri| uri.as_ref().to_owned()).collect(),
+            allowed_scopes: client.allowed_scopes.iter().map(str::to_owned).collect(),
+            allowed_audiences: client.allowed_audiences.iter().map(|audience| audience.as_ref().to_owned()).collect(),
+            created_at: client.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct OAuthClientListResponse {
+    pub clients: Vec<OAuthClientSummary>,
+}
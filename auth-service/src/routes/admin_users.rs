@@ -0,0 +1,253 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, Role, User, UserStoreError as ErrorUser};
+use crate::utils::{
+    auth::authenticate_admin,
+    constants::{ADMIN_USERS_DEFAULT_PAGE_SIZE, ADMIN_USERS_MAX_PAGE_SIZE},
+};
+
+use super::account::purge_pending_tokens;
+
+#[tracing::instrument(name = "Admin list users", skip_all)]
+pub async fn list_users(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(ADMIN_USERS_DEFAULT_PAGE_SIZE);
+    if page == 0 || per_page == 0 || per_page > ADMIN_USERS_MAX_PAGE_SIZE {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+    let offset = (page - 1).checked_mul(per_page).ok_or(AuthAPIError::InvalidCredentials)?;
+
+    let email_prefix = query.email_prefix.unwrap_or_default();
+    let (users, total) = state
+        .user_store
+        .read()
+        .await
+        .list_users(&email_prefix, offset, per_page)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e)))?;
+
+    Ok(Json(UserListResponse {
+        users: users.iter().map(UserSummary::from).collect(),
+        page,
+        per_page,
+        total,
+    }))
+}
+
+#[tracing::instrument(name = "Admin get user", skip_all)]
+pub async fn get_user(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user = load_user(&state, &email).await?;
+    Ok(Json(UserSummary::from(&user)))
+}
+
+// Enables/disables an account, forces 2FA on or off and replaces its roles. Disabling or changing roles
+// also ends every session, so no token keeps carrying roles the user has lost.
+#[tracing::instrument(name = "Admin update user", skip_all)]
+pub async fn update_user(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(email): Path<String>,
+    Json(request): Json<UpdateUserRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let roles = request
+        .roles
+        .map(|roles| roles.into_iter().map(Role::parse).collect::<Result<Vec<_>, _>>())
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // The last admin could otherwise leave nobody able to use the admin API
+    if claims.sub == email.as_ref() {
+        let demoted = roles.as_ref().is_some_and(|roles| !roles.contains(&Role::admin()));
+        if request.disabled == Some(true) || demoted {
+            return Err(AuthAPIError::AdminSelfLockout);
+        }
+    }
+
+    // Only the store knows the roles catalog, so roles go first and an unknown one leaves the user untouched
+    let mut user_store = state.user_store.write().await;
+    let roles_changed = roles.is_some();
+    if let Some(roles) = roles {
+        user_store.set_roles(email.as_ref(), roles).await.map_err(user_store_error)?;
+    }
+    let mut result = Ok(());
+    if let Some(disabled) = request.disabled {
+        result = user_store.set_disabled(email.as_ref(), disabled).await;
+    }
+    let was_disabled = request.disabled == Some(true) && result.is_ok();
+    if let (Ok(_), Some(requires_2fa)) = (&result, request.requires_2fa) {
+        result = user_store.set_requires_2fa(email.as_ref(), requires_2fa).await;
+    }
+    drop(user_store);
+
+    // Whatever was written stays written, so sessions end even if a later field failed
+    if was_disabled || roles_changed {
+        revoke_sessions(&state, &email).await?;
+    }
+    result.map_err(user_store_error)?;
+
+    let user = load_user(&state, &email).await?;
+    Ok(Json(UserSummary::from(&user)))
+}
+
+#[tracing::instrument(name = "Admin force logout", skip_all)]
+pub async fn force_logout(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    load_user(&state, &email).await?;
+    revoke_sessions(&state, &email).await?;
+    // API keys outlive sessions, so they go too for the user to be signed out everywhere
+    state
+        .api_key_store
+        .write()
+        .await
+        .delete_all_keys(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin delete user", skip_all)]
+pub async fn delete_user(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = authenticate_admin(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    if claims.sub == email.as_ref() {
+        return Err(AuthAPIError::AdminSelfLockout);
+    }
+
+    state.user_store.write().await.delete_user(email.as_ref()).await.map_err(user_store_error)?;
+    purge_pending_tokens(&state, &email).await?;
+    revoke_sessions(&state, &email).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn load_user(state: &AppState, email: &Email) -> Result<User, AuthAPIError> {
+    state
+        .user_store
+        .read()
+        .await
+        .get_user(email.as_ref())
+        .await
+        .cloned()
+        .map_err(user_store_error)
+}
+
+// Invalidates every JWT, refresh token and pending 2FA login of `email`
+async fn revoke_sessions(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    // Tokens issued earlier in the current second have to go too, the user isn't getting a fresh one
+    let issued_before = Utc::now().timestamp() + 1;
+    state
+        .banned_token_store
+        .write()
+        .await
+        .revoke_user_tokens(email.as_ref(), issued_before)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_all_families(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+fn user_store_error(e: ErrorUser) -> AuthAPIError {
+    match e {
+        ErrorUser::UserNotFound => AuthAPIError::UserNotFound,
+        ErrorUser::UnknownRole => AuthAPIError::UnknownRole,
+        e => AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListUsersQuery {
+    pub email_prefix: Option<String>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateUserRequest {
+    pub disabled: Option<bool>,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: Option<bool>,
+    // Replaces every role of the user, each has to be in the roles catalog
+    pub roles: Option<Vec<String>>,
+}
+
+// Everything an admin sees about a user, the password hash never leaves the store
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct UserSummary {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    pub verified: bool,
+    pub disabled: bool,
+    pub roles: Vec<String>,
+}
+
+impl From<&User> for UserSummary {
+    fn from(user: &User) -> Self {
+        Self {
+            email: user.email.as_ref().to_owned(),
+            requires_2fa: user.requires_2fa,
+            verified: user.verified,
+            disabled: user.disabled,
+            roles: user.roles.iter().map(|role| role.as_ref().to_owned()).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserListResponse {
+    pub users: Vec<UserSummary>,
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+}
@@ -0,0 +1,150 @@
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{ApiKey, ApiKeyId, ApiKeyStoreError, ApiKeyToken, AuthAPIError, Email, Scopes};
+use crate::utils::auth::authenticate;
+
+const MAX_API_KEY_NAME_LENGTH: usize = 100;
+const MAX_API_KEY_LIFETIME_DAYS: u32 = 365;
+
+// Creates a key for scripts to act as the user. Only a session can do this, so a leaked key can't mint more.
+#[tracing::instrument(name = "Create API key", skip_all)]
+pub async fn create_api_key(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let name = request.name.trim().to_owned();
+    if name.is_empty() || name.chars().count() > MAX_API_KEY_NAME_LENGTH {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+    let scopes = Scopes::from_tokens(request.scopes).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let now = Utc::now().timestamp();
+    let expires_at = match request.expires_in_days {
+        Some(days) if (1..=MAX_API_KEY_LIFETIME_DAYS).contains(&days) => Some(now + days as i64 * 86_400),
+        Some(_) => return Err(AuthAPIError::InvalidCredentials),
+        None => None,
+    };
+
+    let token = ApiKeyToken::default();
+    let key = ApiKey {
+        id: token.id().clone(),
+        email,
+        name,
+        scopes,
+        created_at: now,
+        expires_at,
+        last_used_at: None,
+    };
+    state
+        .api_key_store
+        .write()
+        .await
+        .add_key(key.clone(), &token)
+        .await
+        .map_err(api_key_store_error)?;
+    tracing::info!("Created API key {}", key.id.as_ref());
+
+    // The key is only stored hashed, so this is the one chance to read it
+    Ok((
+        StatusCode::CREATED,
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(CreateApiKeyResponse { key: ApiKeySummary::from(&key), api_key: token.to_string() }),
+    ))
+}
+
+#[tracing::instrument(name = "List API keys", skip_all)]
+pub async fn list_api_keys(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let keys = state.api_key_store.read().await.list_keys(&email).await.map_err(api_key_store_error)?;
+
+    Ok(Json(ApiKeyListResponse { keys: keys.iter().map(ApiKeySummary::from).collect() }))
+}
+
+// Revoked keys stop working right away, there is no cached token left to expire
+#[tracing::instrument(name = "Revoke API key", skip_all)]
+pub async fn revoke_api_key(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    let id = ApiKeyId::parse(id).map_err(|_| AuthAPIError::ApiKeyNotFound)?;
+
+    state.api_key_store.write().await.delete_key(&email, &id).await.map_err(api_key_store_error)?;
+    tracing::info!("Revoked API key {}", id.as_ref());
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn api_key_store_error(e: ApiKeyStoreError) -> AuthAPIError {
+    match e {
+        ApiKeyStoreError::KeyNotFound => AuthAPIError::ApiKeyNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    // Keys without an expiry last until they are revoked
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeySummary {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+}
+
+impl From<&ApiKey> for ApiKeySummary {
+    fn from(key: &ApiKey) -> Self {
+        Self {
+            id: key.id.as_ref().to_owned(),
+            name: key.name.clone(),
+            prefix: key.prefix(),
+            scopes: key.scopes.iter().map(str::to_owned).collect(),
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyResponse {
+    #[serde(flatten)]
+    pub key: ApiKeySummary,
+    pub api_key: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ApiKeyListResponse {
+    pub keys: Vec<ApiKeySummary>,
+}
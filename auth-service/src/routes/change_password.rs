@@ -0,0 +1,114 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, Password, UserStoreError as ErrorUser};
+use crate::utils::auth::{authenticate, generate_auth_cookie, generate_refresh_cookie};
+use crate::utils::client_context::ClientContext;
+
+use super::login::record_failed_login;
+
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<Arc<AppState>>,
+    client: ClientContext,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (token, claims) = match authenticate(&jar, state.banned_token_store.clone()).await {
+        Ok(res) => res,
+        Err(e) => return (jar, Err(e)),
+    };
+    // Changing the password doesn't log the user in again, so the new session keeps the old login
+    let authentication = claims.authentication();
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let (current_password, new_password) = match (
+        Password::parse(request.current_password),
+        Password::parse(request.new_password),
+    ) {
+        (Ok(current), Ok(new)) => (current, new),
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    // Locked accounts are refused before the password is even checked
+    match state.login_attempt_store.read().await.lock_remaining(&email).await {
+        Ok(Some(seconds)) => return (jar, Err(AuthAPIError::AccountLocked(seconds))),
+        Ok(None) => {},
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    let mut user_store = state.user_store.write().await;
+
+    match user_store.validate_user(email.as_ref(), current_password.as_ref()).await {
+        Ok(_) => {},
+        // Wrong passwords count towards the lockout here too, or this would be a way around it
+        Err(ErrorUser::InvalidCredentials) => {
+            drop(user_store);
+            return (jar, Err(record_failed_login(&email, &state, &client).await));
+        }
+        Err(ErrorUser::UserNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e)))),
+    }
+
+    if let Err(e) = user_store.update_password(email.as_ref(), new_password).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))));
+    }
+    drop(user_store);
+
+    // Sign out every other session, then hand the caller a fresh token issued after the revocation
+    let mut banned_token_store = state.banned_token_store.write().await;
+    if let Err(e) = banned_token_store.revoke_user_tokens(email.as_ref(), Utc::now().timestamp()).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    if let Err(e) = banned_token_store.add_token(token).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    drop(banned_token_store);
+
+    if let Err(e) = state.refresh_token_store.write().await.revoke_all_families(&email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    if let Err(e) = state.api_key_store.write().await.delete_all_keys(&email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    let auth_cookie = match generate_auth_cookie(&email, &authentication, state.user_store.clone()).await {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("Generate token error: {:?}", e)))),
+    };
+    let refresh_cookie = match generate_refresh_cookie(&email, &authentication, state.refresh_token_store.clone()).await {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("Generate refresh token error: {:?}", e)))),
+    };
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    let response = Json(ChangePasswordResponse {
+        message: "Password changed successfully".to_string(),
+    });
+    (updated_jar, Ok((StatusCode::OK, response)))
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
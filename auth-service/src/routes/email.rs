@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email};
+use crate::services::email_templates::EmailTemplateKind;
+use crate::utils::auth::MAGIC_LINK_TTL_SECONDS;
+use crate::utils::client_context::ClientContext;
+use crate::utils::constants::{EMAIL_VERIFICATION_TOKEN_TTL_SECONDS, PASSWORD_RESET_TOKEN_TTL_SECONDS, TWO_FA_CODE_TTL_SECONDS};
+
+// Renders `kind` in the client's preferred language and queues it for the outbox worker, filling in the recipient and client IP for the copy
+pub(super) async fn send_templated_email(
+    state: &AppState,
+    recipient: &Email,
+    kind: EmailTemplateKind,
+    client: &ClientContext,
+    variables: &[(&'static str, String)],
+) -> Result<(), AuthAPIError> {
+    let mut values: HashMap<&str, String> = variables.iter().cloned().collect();
+    values.insert("email", recipient.as_ref().to_owned());
+    values.insert("ip", client.ip.clone());
+
+    let locale = state.email_templates.negotiate_locale(client.accept_language.as_deref());
+    let content = state.email_templates
+        .render(kind, locale, &values)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    // Delivery problems are retried in the background, only failing to queue the email fails the request.
+    // The code or token the email carries lives in Redis, so the outbox can't share a transaction with it:
+    // an email whose enqueue fails is lost and the user asks for a new one, and once queued it is sent at least once.
+    let expires_at = lifetime_seconds(kind).map(|seconds| Utc::now().timestamp() + seconds);
+    state.email_outbox_store
+        .write()
+        .await
+        .enqueue(recipient, &content, expires_at)
+        .await
+        .map(|_| ())
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// How long the code or link in the email works, retrying delivery after that is pointless
+fn lifetime_seconds(kind: EmailTemplateKind) -> Option<i64> {
+    match kind {
+        EmailTemplateKind::TwoFACode => Some(TWO_FA_CODE_TTL_SECONDS as i64),
+        EmailTemplateKind::MagicLink => Some(MAGIC_LINK_TTL_SECONDS),
+        EmailTemplateKind::PasswordReset => Some(PASSWORD_RESET_TOKEN_TTL_SECONDS as i64),
+        EmailTemplateKind::EmailVerification => Some(EMAIL_VERIFICATION_TOKEN_TTL_SECONDS as i64),
+        EmailTemplateKind::AccountLocked => None,
+    }
+}
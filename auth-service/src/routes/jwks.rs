@@ -0,0 +1,23 @@
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+
+use crate::domain::AuthAPIError;
+use crate::utils::constants::JWT_KEY_RING;
+
+// Publishes the public signing keys so other services can verify tokens without calling /verify-token
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks() -> Result<impl IntoResponse, AuthAPIError> {
+    // Includes rotated keys until their tokens expire. HMAC keys must never be published.
+    let jwks = JWT_KEY_RING
+        .read()
+        .map_err(|_| AuthAPIError::UnexpectedError(eyre!("JWT key ring lock poisoned")))?
+        .jwks(Utc::now().timestamp());
+
+    Ok((
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(jwks),
+    ))
+}
@@ -8,12 +8,20 @@ use std::sync::Arc;
 use color_eyre::eyre::{eyre, Result};
 
 use crate::app_state::AppState;
-use crate::domain::{EmailClient, UserStoreError as ErrorUser};
-use crate::domain::{AuthAPIError, Email, Password, data_stores::{LoginAttemptId, TwoFACode}};
-use crate::utils::auth::generate_auth_cookie;
+use crate::domain::{TotpSecretStoreError, UserStoreError as ErrorUser};
+use crate::domain::{AuthAPIError, AuthMethod, Authentication, Email, Password, data_stores::{LoginAttemptId, TwoFACode}};
+use crate::utils::auth::{generate_auth_cookie, generate_refresh_cookie};
+use crate::services::email_templates::EmailTemplateKind;
+use crate::utils::client_context::ClientContext;
+use crate::utils::constants::{LOGIN_LOCKOUT_BASE_SECONDS, LOGIN_LOCKOUT_MAX_SECONDS, LOGIN_LOCKOUT_THRESHOLD, TWO_FA_CODE_TTL_SECONDS};
+
+use super::email::send_templated_email;
+use super::phone::two_fa_phone_number;
+use super::sms::send_sms;
 
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    client: ClientContext,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<LoginResponse, AuthAPIError>) {
@@ -26,6 +34,13 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
     };
 
+    // Locked accounts are refused before the password is even checked
+    match state.login_attempt_store.read().await.lock_remaining(&email).await {
+        Ok(Some(seconds)) => return (jar, Err(AuthAPIError::AccountLocked(seconds))),
+        Ok(None) => {},
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
     let user_store = state.user_store.read().await;
 
     let result = user_store.validate_user(email.as_ref(), password.as_ref()).await;
@@ -37,23 +52,80 @@ pub async fn login(
                 Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials))
             };
 
-            let result = match user.requires_2fa {
-                true => handle_2fa(&user.email, &state, jar).await,
-                false => handle_no_2fa(&user.email, jar).await
+            if !user.verified {
+                return (jar, Err(AuthAPIError::EmailNotVerified));
+            }
+            if user.disabled {
+                return (jar, Err(AuthAPIError::AccountDisabled));
+            }
+
+            // Issuing the auth cookie reads the user store again, so let go of it first
+            let requires_2fa = user.requires_2fa;
+            drop(user_store);
+
+            // With 2FA the failures are only cleared once the second factor checks out too, otherwise
+            // logging in again would wipe out the wrong codes that were counted
+            if !requires_2fa {
+                if let Err(e) = state.login_attempt_store.write().await.clear_failures(&email).await {
+                    return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+                }
+            }
+
+            let result = match requires_2fa {
+                true => handle_2fa(&email, AuthMethod::Password, &state, &client, jar).await,
+                false => handle_no_2fa(&email, AuthMethod::Password, &state, jar).await
             };
 
             return result;
         },
         Err(e) => {
             if e == ErrorUser::InvalidCredentials {
-                return (jar, Err(AuthAPIError::IncorrectCredentials));
+                drop(user_store);
+                return (jar, Err(record_failed_login(&email, &state, &client).await));
             }
             return (jar, Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))));
         }
     }
 }
 
-async fn handle_2fa (email: &Email, state: &AppState, jar: CookieJar)
+// Counts a wrong password or 2FA code and locks the account once too many pile up, letting the owner know by email
+pub(super) async fn record_failed_login(email: &Email, state: &AppState, client: &ClientContext) -> AuthAPIError {
+    let mut login_attempt_store = state.login_attempt_store.write().await;
+    let failures = match login_attempt_store.record_failure(email).await {
+        Ok(failures) => failures,
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()),
+    };
+    if failures < *LOGIN_LOCKOUT_THRESHOLD {
+        return AuthAPIError::IncorrectCredentials;
+    }
+
+    let previous_locks = match login_attempt_store.lock_count(email).await {
+        Ok(count) => count,
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()),
+    };
+    let duration = lockout_duration(previous_locks);
+    if let Err(e) = login_attempt_store.lock(email, duration).await {
+        return AuthAPIError::UnexpectedError(e.into());
+    }
+    drop(login_attempt_store);
+
+    // The lock is already in place, so a failed notification shouldn't turn it into a server error
+    let variables = [("lock_minutes", duration.div_ceil(60).to_string())];
+    if let Err(e) = send_templated_email(state, email, EmailTemplateKind::AccountLocked, client, &variables).await {
+        tracing::error!("Failed to send account locked email: {:?}", e);
+    }
+
+    AuthAPIError::AccountLocked(duration)
+}
+
+// Every lock within the escalation window doubles the next one
+fn lockout_duration(previous_locks: u32) -> u64 {
+    LOGIN_LOCKOUT_BASE_SECONDS
+        .saturating_mul(1u64.checked_shl(previous_locks).unwrap_or(u64::MAX))
+        .min(LOGIN_LOCKOUT_MAX_SECONDS)
+}
+
+pub(super) async fn handle_2fa (email: &Email, first_factor: AuthMethod, state: &AppState, client: &ClientContext, jar: CookieJar)
     -> (CookieJar, Result<LoginResponse, AuthAPIError>) {
     // First, we must generate a new random login attempt ID and 2FA code
     let login_attempt_id = LoginAttemptId::default();
@@ -61,12 +133,44 @@ async fn handle_2fa (email: &Email, state: &AppState, jar: CookieJar)
 
     // Store the ID and code in our 2FA code store. Return `AuthAPIError::UnexpectedError` if the operation fails
     let mut two_fa_store = state.two_fa_code_store.write().await;
-    if let Err(e) = two_fa_store.add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone()).await {
+    if let Err(e) = two_fa_store.add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone(), first_factor).await {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    if let Err(e) = state.email_client.send_email(&email, "2FA Code", &two_fa_code.as_ref()).await {
-        return (jar, Err(AuthAPIError::UnexpectedError(eyre!("Email send error: {:?}", e))));
+    drop(two_fa_store);
+
+    // Users with a confirmed authenticator app read their code from it instead of their inbox or phone
+    let uses_totp = match state.totp_secret_store.read().await.get_secret(email).await {
+        Ok((_, confirmed)) => confirmed,
+        Err(TotpSecretStoreError::SecretNotFound) => false,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if !uses_totp {
+        let phone_number = match state.user_store.read().await.get_user(email.as_ref()).await {
+            Ok(user) => two_fa_phone_number(user),
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e)))),
+        };
+        let result = match phone_number {
+            Some(phone_number) => {
+                let message = format!(
+                    "Your login code is {}. It expires in {} minutes.",
+                    two_fa_code.as_ref(),
+                    TWO_FA_CODE_TTL_SECONDS / 60
+                );
+                send_sms(state, &phone_number, &message).await
+            }
+            None => {
+                let variables = [
+                    ("code", two_fa_code.as_ref().to_owned()),
+                    ("expiry_minutes", (TWO_FA_CODE_TTL_SECONDS / 60).to_string()),
+                ];
+                send_templated_email(state, email, EmailTemplateKind::TwoFACode, client, &variables).await
+            }
+        };
+        if let Err(e) = result {
+            return (jar, Err(e));
+        }
     }
 
     let response = TwoFactorAuthResponse{
@@ -76,15 +180,22 @@ async fn handle_2fa (email: &Email, state: &AppState, jar: CookieJar)
     return (jar, Ok(LoginResponse::TwoFactorAuth(response)));
 }
 
-async fn handle_no_2fa(email: &Email, jar: CookieJar)
+pub(super) async fn handle_no_2fa(email: &Email, first_factor: AuthMethod, state: &AppState, jar: CookieJar)
     -> (CookieJar, Result<LoginResponse, AuthAPIError>) {
-    let auth_cookie = match generate_auth_cookie(&email) {
+    let authentication = Authentication::now(vec![first_factor]);
+    let auth_cookie = match generate_auth_cookie(&email, &authentication, state.user_store.clone()).await {
         Ok(res)=> res,
         Err(e) => {
             return (jar, Err(AuthAPIError::UnexpectedError(eyre!("Generate token error: {:?}", e))));
         }
     };
-    let updated_jar = jar.add(auth_cookie);
+    let refresh_cookie = match generate_refresh_cookie(email, &authentication, state.refresh_token_store.clone()).await {
+        Ok(res) => res,
+        Err(e) => {
+            return (jar, Err(AuthAPIError::UnexpectedError(eyre!("Generate refresh token error: {:?}", e))));
+        }
+    };
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     // For non-2FA logins, we still return a TwoFactorAuthResponse but with empty loginAttemptId
     // This is for consistency with the API response format
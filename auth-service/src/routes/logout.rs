@@ -7,8 +7,8 @@ use std::sync::Arc;
 
 use crate::app_state::AppState;
 use crate::{
-    domain::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME}
+    domain::{data_stores::RefreshToken, AuthAPIError},
+    utils::{auth::validate_token, constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME}}
 };
 
 pub async fn logout(State(state): State<Arc<AppState>>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
@@ -19,17 +19,25 @@ pub async fn logout(State(state): State<Arc<AppState>>, jar: CookieJar) -> (Cook
         }
     };
     let token = cookie.value().to_owned();
-    let mut banned_token_store = state.banned_token_store.write().await;
 
     match validate_token(&token, state.banned_token_store.clone()).await {
         Ok(_) => {
+            // Only take the write lock once validation (which reads the store) is done
+            let mut banned_token_store = state.banned_token_store.write().await;
             banned_token_store.add_token(token).await.expect("Error adding token to banned token store");
         },
         Err(_) => {
             return (jar, Err(AuthAPIError::InvalidToken));
         }
     }
-    let jar = jar.remove(JWT_COOKIE_NAME);
+
+    // End the refresh token family of this session too, otherwise it could mint new JWTs
+    if let Some(refresh_token) = jar.get(REFRESH_COOKIE_NAME).and_then(|c| RefreshToken::parse(c.value().to_owned()).ok()) {
+        if let Err(e) = state.refresh_token_store.write().await.revoke_family(refresh_token.family_id()).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+    }
+    let jar = jar.remove(JWT_COOKIE_NAME).remove(REFRESH_COOKIE_NAME);
 
     (jar, Ok(StatusCode::OK))
 }
\ No newline at end of file
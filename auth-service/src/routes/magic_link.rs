@@ -0,0 +1,134 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{
+    data_stores::{MagicLinkId, MagicLinkNonce},
+    AuthAPIError, AuthMethod, Email, MagicLinkStoreError, UserStoreError as ErrorUser,
+};
+use crate::services::email_templates::EmailTemplateKind;
+use crate::utils::{
+    auth::{create_magic_link_nonce_cookie, generate_magic_link_token, validate_magic_link_token, MAGIC_LINK_TTL_SECONDS},
+    client_context::ClientContext,
+    constants::{AUTH_SERVICE_URL, MAGIC_LINK_NONCE_COOKIE_NAME},
+};
+
+use super::email::send_templated_email;
+use super::login::{handle_2fa, handle_no_2fa, LoginResponse};
+
+#[tracing::instrument(name = "Request magic link", skip_all)]
+pub async fn request_magic_link(
+    State(state): State<Arc<AppState>>,
+    client: ClientContext,
+    jar: CookieJar,
+    Json(request): Json<MagicLinkRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match Email::parse(request.email) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let can_log_in = match state.user_store.read().await.get_user(email.as_ref()).await {
+        Ok(user) => user.verified && !user.disabled,
+        Err(ErrorUser::UserNotFound) => false,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e)))),
+    };
+
+    // The nonce cookie is set either way so the response doesn't reveal whether the account exists
+    let nonce = MagicLinkNonce::default();
+
+    if can_log_in {
+        let link_id = MagicLinkId::default();
+        let token = match generate_magic_link_token(&email, &link_id) {
+            Ok(token) => token,
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+        };
+
+        if let Err(e) = state.magic_link_store.write().await.add_link(link_id, nonce.clone()).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+
+        let link = format!("{}/login/magic-link/callback?token={}", AUTH_SERVICE_URL.as_str(), token);
+        let variables = [("link", link), ("expiry_minutes", (MAGIC_LINK_TTL_SECONDS / 60).to_string())];
+        if let Err(e) = send_templated_email(&state, &email, EmailTemplateKind::MagicLink, &client, &variables).await {
+            return (jar, Err(e));
+        }
+    }
+
+    let jar = jar.add(create_magic_link_nonce_cookie(&nonce));
+    let response = Json(MagicLinkResponse {
+        message: "If the account exists, a login link has been sent".to_string(),
+    });
+    (jar, Ok((StatusCode::OK, response)))
+}
+
+#[tracing::instrument(name = "Magic link callback", skip_all)]
+pub async fn magic_link_callback(
+    State(state): State<Arc<AppState>>,
+    client: ClientContext,
+    jar: CookieJar,
+    Query(query): Query<MagicLinkCallbackQuery>,
+) -> (CookieJar, Result<LoginResponse, AuthAPIError>) {
+    let nonce = match jar.get(MAGIC_LINK_NONCE_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_owned(),
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let claims = match validate_magic_link_token(&query.token) {
+        Ok(claims) => claims,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let (email, link_id) = match (Email::parse(claims.sub), MagicLinkId::parse(claims.jti)) {
+        (Ok(email), Ok(link_id)) => (email, link_id),
+        _ => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    // The link is consumed before the nonce is checked, so a leaked link is dead after one try
+    let expected_nonce = match state.magic_link_store.write().await.take_link(&link_id).await {
+        Ok(nonce) => nonce,
+        Err(MagicLinkStoreError::LinkNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if expected_nonce.as_ref() != nonce {
+        tracing::warn!("Magic link opened in a different browser than the one that requested it");
+        return (jar, Err(AuthAPIError::InvalidToken));
+    }
+    let jar = jar.remove(MAGIC_LINK_NONCE_COOKIE_NAME);
+
+    let requires_2fa = match state.user_store.read().await.get_user(email.as_ref()).await {
+        Ok(user) if user.disabled => return (jar, Err(AuthAPIError::AccountDisabled)),
+        Ok(user) if user.verified => user.requires_2fa,
+        Ok(_) => return (jar, Err(AuthAPIError::EmailNotVerified)),
+        Err(ErrorUser::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e)))),
+    };
+
+    // The link only stands in for the password, 2FA users still have to provide their second factor
+    match requires_2fa {
+        true => handle_2fa(&email, AuthMethod::EmailLink, &state, &client, jar).await,
+        false => handle_no_2fa(&email, AuthMethod::EmailLink, &state, jar).await,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkCallbackQuery {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct MagicLinkResponse {
+    pub message: String,
+}
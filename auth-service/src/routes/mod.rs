@@ -1,11 +1,66 @@
+mod account;
+mod admin_email_outbox;
+mod admin_oauth_clients;
+mod admin_users;
+mod api_keys;
+mod change_password;
+mod email;
+mod jwks;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oauth_tokens;
+mod oidc;
+mod password_reset;
+mod phone;
+mod recovery_codes;
+mod refresh;
+mod signing_keys;
 mod signup;
+mod sms;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
+mod webauthn;
 
+pub use account::{delete_account, export_account, AccountExport, ExportedPasskey};
+pub use admin_email_outbox::{
+    list_outbox_emails, replay_outbox_email, OutboxEmailListResponse, OutboxEmailSummary,
+};
+pub use admin_oauth_clients::{
+    create_oauth_client, delete_oauth_client, get_oauth_client, list_oauth_clients, CreateOAuthClientResponse,
+    OAuthClientListResponse, OAuthClientSummary,
+};
+pub use admin_users::{
+    delete_user, force_logout, get_user, list_users, update_user, UserListResponse, UserSummary,
+};
+pub use api_keys::{
+    create_api_key, list_api_keys, revoke_api_key, ApiKeyListResponse, ApiKeySummary, CreateApiKeyResponse,
+};
+pub use change_password::{change_password, ChangePasswordResponse};
+pub use jwks::jwks;
 pub use login::{login, TwoFactorAuthResponse};
 pub use logout::logout;
+pub use magic_link::{magic_link_callback, request_magic_link, MagicLinkResponse};
+pub use oauth::{authorize, authorize_consent, token, TokenResponse};
+pub use oauth_tokens::{introspect, revoke, IntrospectionResponse};
+pub use oidc::{openid_configuration, userinfo, OpenIdConfiguration, UserInfoResponse};
+pub use password_reset::{password_reset_confirm, password_reset_request, PasswordResetResponse};
+pub use phone::{
+    remove_phone_number, set_phone_number, set_two_fa_channel, verify_phone_number, PhoneNumberResponse,
+    TwoFAChannelResponse,
+};
+pub use recovery_codes::{regenerate_recovery_codes, RecoveryCodesResponse};
+pub use refresh::refresh;
+pub use signing_keys::{rotate_signing_key, SigningKeyRotationResponse};
 pub use signup::{signup, SignupResponse};
+pub use totp::{confirm_totp, enroll_totp, ConfirmTotpResponse, TotpEnrollmentResponse};
 pub use verify_2fa::verify_2fa;
-pub use verify_token::verify_token;
\ No newline at end of file
+pub use verify_email::{resend_verification_email, verify_email, VerifyEmailResponse};
+pub use verify_token::verify_token;
+pub use webauthn::{
+    webauthn_login_finish, webauthn_login_start, webauthn_register_finish, webauthn_register_start,
+    CreationOptions, RequestOptions, WebauthnRegistrationResponse,
+};
\ No newline at end of file
@@ -0,0 +1,482 @@
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::{Form, Json};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::eyre::eyre;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{
+    data_stores::{AuthorizationCode, ClientSecret, ConsentId},
+    Audience, AuthAPIError, AuthorizationGrant, AuthorizationGrantStoreError, CodeChallenge, Email, OAuthClient,
+    OAuthClientId, OAuthClientStoreError, OAuthClientType, OAuthError, RedirectUri, Scopes, UserStoreError,
+    OPENID_SCOPE,
+};
+use crate::services::email_templates::escape_html;
+use crate::utils::{
+    auth::{authenticate, generate_access_token, generate_client_access_token, generate_id_token, TOKEN_TTL_SECONDS},
+    constants::AUTH_SERVICE_URL,
+};
+
+// Start of the authorization code flow (RFC 6749 section 4.1.1). Users without a session are sent to the
+// login page first, everyone else is asked whether the client may act on their behalf.
+#[tracing::instrument(name = "OAuth authorize", skip_all)]
+pub async fn authorize(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    uri: Uri,
+    Query(query): Query<AuthorizeQuery>,
+) -> Result<Response, OAuthError> {
+    // Until the client and redirect URI check out, errors are shown here instead of being sent to a URI
+    // that might belong to an attacker (RFC 6749 section 4.1.2.1)
+    let client_id = query.client_id.ok_or(OAuthError::InvalidRequest("Missing client_id"))?;
+    let client = match OAuthClientId::parse(client_id) {
+        Ok(client_id) => get_client(&state, &client_id).await?.ok_or(OAuthError::InvalidRequest("Unknown client"))?,
+        Err(_) => return Err(OAuthError::InvalidRequest("Unknown client")),
+    };
+    // Machine clients have no users to act for
+    if client.client_type != OAuthClientType::Public {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+    let redirect_uri = query.redirect_uri.ok_or(OAuthError::InvalidRequest("Missing redirect_uri"))?;
+    if !client.has_redirect_uri(&redirect_uri) {
+        return Err(OAuthError::InvalidRequest("redirect_uri is not registered for this client"));
+    }
+    let redirect_uri = RedirectUri::parse(redirect_uri).map_err(OAuthError::ServerError)?;
+
+    let grant = match validate_authorization_request(
+        &client,
+        query.response_type,
+        query.scope,
+        query.code_challenge,
+        query.code_challenge_method,
+    ) {
+        Ok(grant) => grant,
+        Err(e) => return Ok(redirect_with_error(&redirect_uri, e, query.state.as_deref()).into_response()),
+    };
+
+    let claims = match authenticate(&jar, state.banned_token_store.clone()).await {
+        Ok((_, claims)) => claims,
+        Err(_) => return Ok(redirect_to_login(&uri)),
+    };
+    let authentication = claims.authentication();
+    let email = Email::parse(claims.sub).map_err(|e| OAuthError::ServerError(eyre!(e)))?;
+
+    let (code_challenge, scopes) = grant;
+    let grant = AuthorizationGrant {
+        client_id: client.client_id.clone(),
+        redirect_uri,
+        email,
+        scopes,
+        code_challenge,
+        state: query.state,
+        nonce: query.nonce,
+        authentication,
+    };
+    let consent_id = ConsentId::default();
+    state
+        .authorization_grant_store
+        .write()
+        .await
+        .add_pending_consent(consent_id.clone(), grant.clone())
+        .await
+        .map_err(|e| OAuthError::ServerError(e.into()))?;
+
+    Ok(consent_page(&client, &grant, &consent_id))
+}
+
+// Answer to the consent page, sends the user back to the client with a code or an access_denied error
+#[tracing::instrument(name = "OAuth consent", skip_all)]
+pub async fn authorize_consent(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<ConsentForm>,
+) -> Result<Redirect, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let consent_id = ConsentId::parse(form.consent_id).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let grant = match state.authorization_grant_store.write().await.take_pending_consent(&consent_id).await {
+        Ok(grant) => grant,
+        Err(AuthorizationGrantStoreError::GrantNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    // The consent page was shown to whoever had the session at the time
+    if grant.email.as_ref() != claims.sub {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    match state.user_store.read().await.get_user(grant.email.as_ref()).await {
+        Ok(user) if user.disabled => return Err(AuthAPIError::AccountDisabled),
+        Ok(_) => {},
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+    }
+
+    if form.decision != "allow" {
+        tracing::info!("User denied OAuth client {}", grant.client_id.as_ref());
+        return Ok(redirect_with_error(&grant.redirect_uri, OAuthError::AccessDenied, grant.state.as_deref()));
+    }
+
+    let code = AuthorizationCode::default();
+    let mut redirect = Url::parse(grant.redirect_uri.as_ref()).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    redirect.query_pairs_mut().append_pair("code", code.as_ref());
+    if let Some(state) = &grant.state {
+        redirect.query_pairs_mut().append_pair("state", state);
+    }
+
+    state
+        .authorization_grant_store
+        .write()
+        .await
+        .add_code(code, grant)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Redirect::to(redirect.as_str()))
+}
+
+// Token endpoint (RFC 6749 section 3.2), for both users' clients and machine clients
+#[tracing::instrument(name = "OAuth token", skip_all)]
+pub async fn token(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(request): Form<TokenRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let response = match request.grant_type.as_deref() {
+        Some("authorization_code") => authorization_code_grant(&state, request).await?,
+        Some("client_credentials") => client_credentials_grant(&state, &headers, request).await?,
+        Some(_) => return Err(OAuthError::UnsupportedGrantType),
+        None => return Err(OAuthError::InvalidRequest("Missing grant_type")),
+    };
+    Ok((StatusCode::OK, [(header::CACHE_CONTROL, "no-store")], Json(response)))
+}
+
+// Exchanges an authorization code for an access token (RFC 6749 section 4.1.3), proving with the PKCE
+// code verifier that the caller is the client that started the flow
+async fn authorization_code_grant(state: &AppState, request: TokenRequest) -> Result<TokenResponse, OAuthError> {
+    let client_id = request.client_id.ok_or(OAuthError::InvalidRequest("Missing client_id"))?;
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+    let client = get_client(state, &client_id).await?.ok_or(OAuthError::InvalidClient)?;
+    if client.client_type != OAuthClientType::Public {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+
+    let code = request.code.ok_or(OAuthError::InvalidRequest("Missing code"))?;
+    let redirect_uri = request.redirect_uri.ok_or(OAuthError::InvalidRequest("Missing redirect_uri"))?;
+    let code_verifier = request.code_verifier.ok_or(OAuthError::InvalidRequest("Missing code_verifier"))?;
+
+    let code = AuthorizationCode::parse(code).map_err(|_| OAuthError::InvalidGrant("Invalid authorization code"))?;
+    // Taken before anything else is checked, so a code is spent by the first attempt to use it
+    let grant = match state.authorization_grant_store.write().await.take_code(&code).await {
+        Ok(grant) => grant,
+        Err(AuthorizationGrantStoreError::GrantNotFound) => {
+            return Err(OAuthError::InvalidGrant("Invalid authorization code"))
+        }
+        Err(e) => return Err(OAuthError::ServerError(e.into())),
+    };
+
+    if grant.client_id != client.client_id {
+        return Err(OAuthError::InvalidGrant("Authorization code was issued to another client"));
+    }
+    if grant.redirect_uri.as_ref() != redirect_uri {
+        return Err(OAuthError::InvalidGrant("redirect_uri does not match the authorization request"));
+    }
+    if !grant.code_challenge.verify(&code_verifier) {
+        return Err(OAuthError::InvalidGrant("Invalid code_verifier"));
+    }
+
+    match state.user_store.read().await.get_user(grant.email.as_ref()).await {
+        Ok(user) if !user.disabled => {},
+        Ok(_) | Err(UserStoreError::UserNotFound) => {
+            return Err(OAuthError::InvalidGrant("The user can no longer sign in"))
+        }
+        Err(e) => return Err(OAuthError::ServerError(eyre!("User store error: {:?}", e))),
+    }
+
+    let access_token = generate_access_token(&grant.email, &client.client_id, &grant.scopes)
+        .map_err(OAuthError::ServerError)?;
+    // OpenID Connect requests also learn who logged in (OpenID Connect Core section 3.1.3.3)
+    let id_token = match grant.scopes.contains(OPENID_SCOPE) {
+        true => Some(
+            generate_id_token(&grant.email, &client.client_id, grant.nonce.as_deref(), &grant.authentication)
+                .map_err(OAuthError::ServerError)?,
+        ),
+        false => None,
+    };
+    tracing::info!("Issued access token to OAuth client {}", client.client_id.as_ref());
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        scope: grant.scopes.to_string(),
+        id_token,
+    })
+}
+
+// Issues a machine client a token for one of its audiences (RFC 6749 section 4.4). There is no user
+// involved, so the client has to prove itself with its secret.
+async fn client_credentials_grant(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: TokenRequest,
+) -> Result<TokenResponse, OAuthError> {
+    let client = authenticate_client(state, headers, request.client_id, request.client_secret).await?;
+    if client.client_type != OAuthClientType::Machine {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+
+    let scopes = match request.scope {
+        Some(scope) => Scopes::parse(&scope).map_err(|_| OAuthError::InvalidScope)?,
+        None => client.allowed_scopes.clone(),
+    };
+    if !scopes.is_subset(&client.allowed_scopes) {
+        return Err(OAuthError::InvalidScope);
+    }
+    // Tokens are only good for one audience, which can be left out when the client has just the one
+    let audience = match request.audience {
+        Some(audience) if client.allows_audience(&audience) => {
+            Audience::parse(audience).map_err(OAuthError::ServerError)?
+        }
+        Some(_) => return Err(OAuthError::InvalidTarget),
+        None => match client.allowed_audiences.as_slice() {
+            [audience] => audience.clone(),
+            _ => return Err(OAuthError::InvalidTarget),
+        },
+    };
+
+    let access_token =
+        generate_client_access_token(&client.client_id, &audience, &scopes).map_err(OAuthError::ServerError)?;
+    tracing::info!("Issued {} token to OAuth client {}", audience.as_ref(), client.client_id.as_ref());
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        scope: scopes.to_string(),
+        id_token: None,
+    })
+}
+
+// Machine clients prove who they are with their secret, public clients can only name themselves
+pub(crate) async fn authenticate_client(
+    state: &AppState,
+    headers: &HeaderMap,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+) -> Result<OAuthClient, OAuthError> {
+    let (client_id, secret) = client_credentials(headers, client_id, client_secret)?;
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+    let client = get_client(state, &client_id).await?.ok_or(OAuthError::InvalidClient)?;
+
+    match (client.client_type, secret) {
+        (OAuthClientType::Public, None) => {},
+        (OAuthClientType::Machine, Some(secret)) => {
+            let secret = ClientSecret::parse(secret).map_err(|_| OAuthError::InvalidClient)?;
+            match state.oauth_client_store.read().await.verify_client_secret(&client_id, &secret).await {
+                Ok(()) => {},
+                Err(OAuthClientStoreError::ClientNotFound | OAuthClientStoreError::InvalidSecret) => {
+                    return Err(OAuthError::InvalidClient)
+                }
+                Err(e) => return Err(OAuthError::ServerError(e.into())),
+            }
+        }
+        _ => return Err(OAuthError::InvalidClient),
+    }
+    Ok(client)
+}
+
+// Client id and secret from HTTP Basic authentication or the request body (RFC 6749 section 2.3.1),
+// but not both at once
+fn client_credentials(
+    headers: &HeaderMap,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+) -> Result<(String, Option<String>), OAuthError> {
+    let basic = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "));
+    match (basic, client_id, client_secret) {
+        (Some(credentials), None, None) => {
+            let credentials = STANDARD
+                .decode(credentials.trim())
+                .ok()
+                .and_then(|credentials| String::from_utf8(credentials).ok())
+                .ok_or(OAuthError::InvalidClient)?;
+            let (client_id, secret) = credentials.split_once(':').ok_or(OAuthError::InvalidClient)?;
+            Ok((client_id.to_owned(), Some(secret.to_owned())))
+        }
+        (None, Some(client_id), client_secret) => Ok((client_id, client_secret)),
+        (Some(_), _, _) => Err(OAuthError::InvalidRequest("Client credentials were sent more than once")),
+        (None, None, _) => Err(OAuthError::InvalidClient),
+    }
+}
+
+async fn get_client(state: &AppState, client_id: &OAuthClientId) -> Result<Option<OAuthClient>, OAuthError> {
+    match state.oauth_client_store.read().await.get_client(client_id).await {
+        Ok(client) => Ok(Some(client)),
+        Err(OAuthClientStoreError::ClientNotFound) => Ok(None),
+        Err(e) => Err(OAuthError::ServerError(e.into())),
+    }
+}
+
+fn validate_authorization_request(
+    client: &OAuthClient,
+    response_type: Option<String>,
+    scope: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+) -> Result<(CodeChallenge, Scopes), OAuthError> {
+    if response_type.as_deref() != Some("code") {
+        return Err(OAuthError::UnsupportedResponseType);
+    }
+    // PKCE is required of every client, plain challenges would let an intercepted request redeem the code
+    if code_challenge_method.as_deref() != Some("S256") {
+        return Err(OAuthError::InvalidRequest("code_challenge_method must be S256"));
+    }
+    let code_challenge = code_challenge
+        .and_then(|challenge| CodeChallenge::parse(challenge).ok())
+        .ok_or(OAuthError::InvalidRequest("Missing or invalid code_challenge"))?;
+
+    // Without a scope the client gets everything it was registered for
+    let scopes = match scope {
+        Some(scope) => Scopes::parse(&scope).map_err(|_| OAuthError::InvalidScope)?,
+        None => client.allowed_scopes.clone(),
+    };
+    if !scopes.is_subset(&client.allowed_scopes) {
+        return Err(OAuthError::InvalidScope);
+    }
+    Ok((code_challenge, scopes))
+}
+
+fn redirect_with_error(redirect_uri: &RedirectUri, error: OAuthError, state: Option<&str>) -> Redirect {
+    let mut redirect = Url::parse(redirect_uri.as_ref()).expect("registered redirect URIs are valid URLs");
+    redirect
+        .query_pairs_mut()
+        .append_pair("error", error.code())
+        .append_pair("error_description", error.description());
+    if let Some(state) = state {
+        redirect.query_pairs_mut().append_pair("state", state);
+    }
+    Redirect::to(redirect.as_str())
+}
+
+// The login page comes back to the authorization request once the user is signed in
+fn redirect_to_login(uri: &Uri) -> Response {
+    let return_to = uri.path_and_query().map(|path| path.as_str()).unwrap_or("/oauth/authorize");
+    match Url::parse(AUTH_SERVICE_URL.as_str()) {
+        Ok(mut login) => {
+            login.set_path("/");
+            login.query_pairs_mut().append_pair("return_to", return_to);
+            Redirect::to(login.as_str()).into_response()
+        }
+        Err(e) => OAuthError::ServerError(e.into()).into_response(),
+    }
+}
+
+fn consent_page(client: &OAuthClient, grant: &AuthorizationGrant, consent_id: &ConsentId) -> Response {
+    let scopes: String = match grant.scopes.is_empty() {
+        true => "<li class=\"list-group-item\">Know who you are</li>".to_owned(),
+        false => grant
+            .scopes
+            .iter()
+            .map(|scope| format!("<li class=\"list-group-item\"><code>{}</code></li>", escape_html(scope)))
+            .collect(),
+    };
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Authorize {client_name}</title>
+    <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/bootstrap@5.2.2/dist/css/bootstrap.min.css">
+</head>
+<body>
+    <section class="position-relative py-4 py-xl-5">
+        <div class="container">
+            <div class="row d-flex justify-content-center">
+                <div class="col-md-6 col-xl-4">
+                    <div class="card mb-5">
+                        <div class="card-body">
+                            <h2 class="h4 text-center">Authorize {client_name}</h2>
+                            <p class="text-muted text-center">Signed in as {email}</p>
+                            <p>{client_name} would like to:</p>
+                            <ul class="list-group mb-3">{scopes}</ul>
+                            <form method="post" action="/oauth/authorize/consent">
+                                <input type="hidden" name="consent_id" value="{consent_id}">
+                                <div class="mb-2"><button class="btn btn-dark d-block w-100" type="submit" name="decision" value="allow">Allow</button></div>
+                                <div><button class="btn btn-outline-secondary d-block w-100" type="submit" name="decision" value="deny">Deny</button></div>
+                            </form>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        </div>
+    </section>
+</body>
+</html>
+"#,
+        client_name = escape_html(&client.name),
+        email = escape_html(grant.email.as_ref()),
+        scopes = scopes,
+        consent_id = consent_id.as_ref(),
+    );
+
+    // Framing the page would let another site trick the user into clicking Allow
+    (
+        [
+            (header::CACHE_CONTROL, "no-store"),
+            (header::X_FRAME_OPTIONS, "DENY"),
+            (header::CONTENT_SECURITY_POLICY, "frame-ancestors 'none'"),
+        ],
+        Html(page),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct AuthorizeQuery {
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub nonce: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConsentForm {
+    pub consent_id: String,
+    pub decision: String,
+}
+
+// Every field is optional so missing ones are reported as OAuth errors rather than rejected by the extractor
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub code_verifier: Option<String>,
+    pub scope: Option<String>,
+    pub audience: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
@@ -0,0 +1,164 @@
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::{Form, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{Email, OAuthClientType, OAuthError, API_KEY_PREFIX};
+use crate::utils::auth::{identify_token, validate_api_key, AccessTokenClaims, ApiKeyClaims, Claims, IssuedToken};
+
+use super::oauth::authenticate_client;
+
+// Token introspection (RFC 7662), for resource servers that would rather ask than verify JWTs themselves.
+// Only machine clients may introspect, as public clients can't keep their credentials to themselves.
+#[tracing::instrument(name = "OAuth introspect", skip_all)]
+pub async fn introspect(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(request): Form<TokenManagementRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client = authenticate_client(&state, &headers, request.client_id, request.client_secret).await?;
+    if client.client_type != OAuthClientType::Machine {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+    let token = request.token.ok_or(OAuthError::InvalidRequest("Missing token"))?;
+
+    // Anything that isn't a valid token, whatever the reason, is just inactive (RFC 7662 section 2.2)
+    let response = if token.starts_with(API_KEY_PREFIX) {
+        match validate_api_key(&token, state.api_key_store.clone(), state.user_store.clone()).await {
+            Ok(claims) => IntrospectionResponse::from(claims),
+            Err(_) => IntrospectionResponse::default(),
+        }
+    } else {
+        match identify_token(&token, state.banned_token_store.clone()).await {
+            Ok(IssuedToken::Session(claims)) => IntrospectionResponse::from(claims),
+            Ok(IssuedToken::Access(claims) | IssuedToken::Service(claims)) => IntrospectionResponse::from(claims),
+            Err(_) => IntrospectionResponse::default(),
+        }
+    };
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)))
+}
+
+// Token revocation (RFC 7009). Clients may revoke the tokens issued to them and, as whoever holds a
+// session token can already use it, session tokens too.
+#[tracing::instrument(name = "OAuth revoke", skip_all)]
+pub async fn revoke(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(request): Form<TokenManagementRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client = authenticate_client(&state, &headers, request.client_id, request.client_secret).await?;
+    let token = request.token.ok_or(OAuthError::InvalidRequest("Missing token"))?;
+
+    // Invalid and other clients' tokens are answered like the rest, so the endpoint can't be used to probe
+    // tokens (RFC 7009 section 2.2)
+    let (revocable, session_user) = match identify_token(&token, state.banned_token_store.clone()).await {
+        Ok(IssuedToken::Session(claims)) => (true, Email::parse(claims.sub).ok()),
+        Ok(IssuedToken::Access(claims) | IssuedToken::Service(claims)) => (claims.client_id == client.client_id.as_ref(), None),
+        Err(_) => (false, None),
+    };
+    if revocable {
+        state
+            .banned_token_store
+            .write()
+            .await
+            .add_token(token)
+            .await
+            .map_err(|e| OAuthError::ServerError(e.into()))?;
+        tracing::info!("OAuth client {} revoked a token", client.client_id.as_ref());
+    }
+    // A session token could otherwise be replaced straight away through its refresh token. Nothing in the
+    // token tells which refresh token family it came from, so every family of the user goes.
+    if let Some(email) = session_user {
+        state
+            .refresh_token_store
+            .write()
+            .await
+            .revoke_all_families(&email)
+            .await
+            .map_err(|e| OAuthError::ServerError(e.into()))?;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+// Shared by both endpoints, token_type_hint is accepted but not needed to tell tokens apart
+#[derive(Deserialize)]
+pub struct TokenManagementRequest {
+    pub token: Option<String>,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+}
+
+impl From<Claims> for IntrospectionResponse {
+    fn from(claims: Claims) -> Self {
+        Self {
+            active: true,
+            token_type: Some("Bearer".to_owned()),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            sub: Some(claims.sub),
+            aud: Some(claims.aud),
+            iss: Some(claims.iss),
+            jti: Some(claims.jti),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<AccessTokenClaims> for IntrospectionResponse {
+    fn from(claims: AccessTokenClaims) -> Self {
+        Self {
+            active: true,
+            scope: Some(claims.scope),
+            client_id: Some(claims.client_id),
+            token_type: Some("Bearer".to_owned()),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            sub: Some(claims.sub),
+            aud: Some(claims.aud),
+            iss: Some(claims.iss),
+            jti: Some(claims.jti),
+        }
+    }
+}
+
+// API keys aren't issued to a client, so they come without a client_id
+impl From<ApiKeyClaims> for IntrospectionResponse {
+    fn from(claims: ApiKeyClaims) -> Self {
+        Self {
+            active: true,
+            scope: Some(claims.scope),
+            exp: claims.exp.map(|exp| exp as usize),
+            sub: Some(claims.sub),
+            ..Default::default()
+        }
+    }
+}
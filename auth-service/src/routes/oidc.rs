@@ -0,0 +1,132 @@
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::IntoResponse;
+use axum::Json;
+use color_eyre::eyre::eyre;
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{OAuthError, Scopes, UserStoreError, OPENID_SCOPE};
+use crate::utils::{
+    auth::validate_access_token,
+    constants::{AUTH_SERVICE_URL, JWT_KEY_RING},
+};
+
+const EMAIL_SCOPE: &str = "email";
+const PHONE_SCOPE: &str = "phone";
+
+// OpenID Connect Discovery metadata, which lets relying parties configure themselves from the issuer URL alone
+#[tracing::instrument(name = "OpenID configuration", skip_all)]
+pub async fn openid_configuration() -> Result<impl IntoResponse, OAuthError> {
+    let algorithm = JWT_KEY_RING
+        .read()
+        .map_err(|_| OAuthError::ServerError(eyre!("JWT key ring lock poisoned")))?
+        .active()
+        .algorithm();
+
+    let issuer = AUTH_SERVICE_URL.as_str();
+    let configuration = OpenIdConfiguration {
+        issuer: issuer.to_owned(),
+        authorization_endpoint: format!("{}/oauth/authorize", issuer),
+        token_endpoint: format!("{}/oauth/token", issuer),
+        userinfo_endpoint: format!("{}/userinfo", issuer),
+        introspection_endpoint: format!("{}/oauth/introspect", issuer),
+        revocation_endpoint: format!("{}/oauth/revoke", issuer),
+        jwks_uri: format!("{}/.well-known/jwks.json", issuer),
+        response_types_supported: vec!["code".to_owned()],
+        grant_types_supported: vec!["authorization_code".to_owned(), "client_credentials".to_owned()],
+        subject_types_supported: vec!["public".to_owned()],
+        id_token_signing_alg_values_supported: vec![algorithm],
+        scopes_supported: [OPENID_SCOPE, EMAIL_SCOPE, PHONE_SCOPE].map(str::to_owned).to_vec(),
+        // Public clients prove themselves with PKCE instead of a secret, machine clients with their secret
+        token_endpoint_auth_methods_supported: ["none", "client_secret_basic", "client_secret_post"]
+            .map(str::to_owned)
+            .to_vec(),
+        code_challenge_methods_supported: vec!["S256".to_owned()],
+        claims_supported: [
+            "iss", "sub", "aud", "exp", "iat", "auth_time", "nonce", "amr",
+            "email", "email_verified", "phone_number", "phone_number_verified",
+        ]
+        .map(str::to_owned)
+        .to_vec(),
+    };
+
+    Ok(([(header::CACHE_CONTROL, "public, max-age=300")], Json(configuration)))
+}
+
+// Claims about the user an access token was issued for (OpenID Connect Core section 5.3), limited to its scopes
+#[tracing::instrument(name = "Userinfo", skip_all)]
+pub async fn userinfo(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, OAuthError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(OAuthError::InvalidToken)?;
+    let claims = validate_access_token(token, state.banned_token_store.clone())
+        .await
+        .map_err(|_| OAuthError::InvalidToken)?;
+
+    let scopes = Scopes::parse(&claims.scope).map_err(|_| OAuthError::InvalidToken)?;
+    if !scopes.contains(OPENID_SCOPE) {
+        return Err(OAuthError::InsufficientScope);
+    }
+
+    // The account may have gone away or been disabled since the token was issued
+    let user = match state.user_store.read().await.get_user(&claims.sub).await {
+        Ok(user) if !user.disabled => user.clone(),
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Err(OAuthError::InvalidToken),
+        Err(e) => return Err(OAuthError::ServerError(eyre!("User store error: {:?}", e))),
+    };
+
+    let mut response = UserInfoResponse { sub: claims.sub, ..Default::default() };
+    if scopes.contains(EMAIL_SCOPE) {
+        response.email = Some(user.email.as_ref().to_owned());
+        response.email_verified = Some(user.verified);
+    }
+    if scopes.contains(PHONE_SCOPE) {
+        if let Some(phone_number) = &user.phone_number {
+            response.phone_number = Some(phone_number.as_ref().to_owned());
+            response.phone_number_verified = Some(user.phone_verified);
+        }
+    }
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)))
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    // Authorization server metadata (RFC 8414)
+    pub introspection_endpoint: String,
+    pub revocation_endpoint: String,
+    pub jwks_uri: String,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+    pub scopes_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number_verified: Option<bool>,
+}
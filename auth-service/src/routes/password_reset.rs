@@ -0,0 +1,132 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, Password, UserStoreError as ErrorUser};
+use crate::domain::data_stores::PasswordResetToken;
+use crate::services::email_templates::EmailTemplateKind;
+use crate::utils::client_context::ClientContext;
+use crate::utils::constants::PASSWORD_RESET_TOKEN_TTL_SECONDS;
+
+use super::email::send_templated_email;
+
+#[tracing::instrument(name = "Password reset request", skip_all)]
+pub async fn password_reset_request(
+    State(state): State<Arc<AppState>>,
+    client: ClientContext,
+    Json(request): Json<PasswordResetRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user_exists = match state.user_store.read().await.get_user(email.as_ref()).await {
+        Ok(_) => true,
+        Err(ErrorUser::UserNotFound) => false,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+    };
+
+    // Respond the same way whether or not the account exists so this route can't be used to enumerate users
+    if user_exists {
+        let token = PasswordResetToken::default();
+        state.password_reset_token_store
+            .write()
+            .await
+            .add_token(email.clone(), token.clone())
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        let variables = [
+            ("token", token.as_ref().to_owned()),
+            ("expiry_minutes", (PASSWORD_RESET_TOKEN_TTL_SECONDS / 60).to_string()),
+        ];
+        send_templated_email(&state, &email, EmailTemplateKind::PasswordReset, &client, &variables).await?;
+    }
+
+    let response = Json(PasswordResetResponse {
+        message: "If the account exists, a password reset token has been sent".to_string(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Password reset confirm", skip_all)]
+pub async fn password_reset_confirm(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PasswordResetConfirmRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let token = PasswordResetToken::parse(request.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let new_password = Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    {
+        let mut token_store = state.password_reset_token_store.write().await;
+        let stored_token = token_store
+            .get_token(&email)
+            .await
+            .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+        if stored_token != token {
+            return Err(AuthAPIError::IncorrectCredentials);
+        }
+
+        // Consume the token before touching the password so it can only ever be used once
+        token_store
+            .remove_token(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    match state.user_store.write().await.update_password(email.as_ref(), new_password).await {
+        Ok(_) => {},
+        Err(ErrorUser::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+    }
+
+    // Sign the user out everywhere: any JWT issued before now stops validating
+    state.banned_token_store
+        .write()
+        .await
+        .revoke_user_tokens(email.as_ref(), Utc::now().timestamp())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state.refresh_token_store
+        .write()
+        .await
+        .revoke_all_families(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    // API keys stand in for the password too, whoever stole it may have minted some
+    state.api_key_store
+        .write()
+        .await
+        .delete_all_keys(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(PasswordResetResponse {
+        message: "Password reset successfully".to_string(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetConfirmRequest {
+    pub email: String,
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PasswordResetResponse {
+    pub message: String,
+}
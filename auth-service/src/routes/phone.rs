@@ -0,0 +1,189 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{
+    data_stores::TwoFACode, AuthAPIError, Email, PhoneNumber, PhoneVerificationCodeStoreError, TwoFAChannel, User,
+    UserStoreError as ErrorUser,
+};
+use crate::utils::{auth::authenticate, constants::{MAX_TWO_FA_ATTEMPTS, PHONE_VERIFICATION_CODE_TTL_SECONDS}};
+
+use super::sms::send_sms;
+
+#[tracing::instrument(name = "Set phone number", skip_all)]
+pub async fn set_phone_number(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<SetPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    let phone_number = PhoneNumber::parse(request.phone_number).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // The number stays unverified, and 2FA codes keep going by email, until the code sent to it comes back
+    from_user_store(state.user_store.write().await.set_phone_number(email.as_ref(), Some(phone_number.clone())).await)?;
+
+    let code = TwoFACode::default();
+    state.phone_verification_code_store
+        .write()
+        .await
+        .add_code(email, phone_number.clone(), code.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let message = format!(
+        "Your phone verification code is {}. It expires in {} minutes.",
+        code.as_ref(),
+        PHONE_VERIFICATION_CODE_TTL_SECONDS / 60
+    );
+    send_sms(&state, &phone_number, &message).await?;
+
+    let response = Json(PhoneNumberResponse {
+        message: "Verification code sent".to_string(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Verify phone number", skip_all)]
+pub async fn verify_phone_number(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<VerifyPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut phone_verification_code_store = state.phone_verification_code_store.write().await;
+    let (phone_number, expected_code) = match phone_verification_code_store.get_code(&email).await {
+        Ok(pending) => pending,
+        Err(PhoneVerificationCodeStoreError::CodeNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Every guess counts, so the code can't be enumerated within its lifetime
+    let attempts = phone_verification_code_store
+        .record_attempt(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if attempts > MAX_TWO_FA_ATTEMPTS {
+        phone_verification_code_store
+            .remove_code(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        return Err(AuthAPIError::PhoneVerificationAttemptsExceeded);
+    }
+    if code != expected_code {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    phone_verification_code_store
+        .remove_code(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(phone_verification_code_store);
+
+    // The code only proves the number it was sent to, which may have been replaced since
+    let mut user_store = state.user_store.write().await;
+    let user = from_user_store(user_store.get_user(email.as_ref()).await)?;
+    if user.phone_number.as_ref() != Some(&phone_number) {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+    from_user_store(user_store.mark_phone_verified(email.as_ref()).await)?;
+
+    let response = Json(PhoneNumberResponse {
+        message: "Phone number verified".to_string(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Remove phone number", skip_all)]
+pub async fn remove_phone_number(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    from_user_store(state.user_store.write().await.set_phone_number(email.as_ref(), None).await)?;
+    state.phone_verification_code_store
+        .write()
+        .await
+        .remove_code(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Set 2FA channel", skip_all)]
+pub async fn set_two_fa_channel(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<SetTwoFAChannelRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    let channel = TwoFAChannel::parse(&request.channel).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut user_store = state.user_store.write().await;
+    let user = from_user_store(user_store.get_user(email.as_ref()).await)?;
+    if channel == TwoFAChannel::Sms && !user.phone_verified {
+        return Err(AuthAPIError::PhoneNumberNotVerified);
+    }
+    from_user_store(user_store.set_two_fa_channel(email.as_ref(), channel).await)?;
+
+    let response = Json(TwoFAChannelResponse {
+        channel: channel.as_str().to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// The JWT outlived the account when the user is gone
+fn from_user_store<T>(result: Result<T, ErrorUser>) -> Result<T, AuthAPIError> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(ErrorUser::UserNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+    }
+}
+
+// The verified number login codes should be texted to, if the user picked SMS
+pub(super) fn two_fa_phone_number(user: &User) -> Option<PhoneNumber> {
+    match (user.two_fa_channel, user.phone_verified) {
+        (TwoFAChannel::Sms, true) => user.phone_number.clone(),
+        _ => None,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetPhoneNumberRequest {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyPhoneNumberRequest {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetTwoFAChannelRequest {
+    pub channel: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PhoneNumberResponse {
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TwoFAChannelResponse {
+    pub channel: String,
+}
@@ -0,0 +1,73 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{data_stores::RecoveryCode, AuthAPIError, Email, Password, UserStoreError as ErrorUser};
+use crate::utils::{auth::authenticate, client_context::ClientContext, constants::RECOVERY_CODE_COUNT};
+
+use super::login::record_failed_login;
+
+#[tracing::instrument(name = "Regenerate recovery codes", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<Arc<AppState>>,
+    client: ClientContext,
+    jar: CookieJar,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    let password = Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Locked accounts are refused before the password is even checked
+    match state.login_attempt_store.read().await.lock_remaining(&email).await {
+        Ok(Some(seconds)) => return Err(AuthAPIError::AccountLocked(seconds)),
+        Ok(None) => {},
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // The codes bypass the second factor, so a session alone is not enough to get new ones
+    let result = state.user_store.read().await.validate_user(email.as_ref(), password.as_ref()).await;
+    match result {
+        Ok(_) => {},
+        Err(ErrorUser::InvalidCredentials) => return Err(record_failed_login(&email, &state, &client).await),
+        Err(ErrorUser::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+    }
+
+    let recovery_codes = issue_recovery_codes(&state, &email).await?;
+
+    let response = Json(RecoveryCodesResponse { recovery_codes });
+    Ok((StatusCode::OK, response))
+}
+
+// Replaces the recovery codes of `email` with a fresh set and returns it. This is the only
+// time the codes are available in plain text.
+pub(crate) async fn issue_recovery_codes(state: &AppState, email: &Email) -> Result<Vec<String>, AuthAPIError> {
+    let codes: Vec<RecoveryCode> = (0..RECOVERY_CODE_COUNT).map(|_| RecoveryCode::default()).collect();
+
+    state.recovery_code_store
+        .write()
+        .await
+        .set_codes(email, codes.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(codes.iter().map(|code| code.as_ref().to_owned()).collect())
+}
+
+#[derive(Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
@@ -0,0 +1,78 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::eyre;
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{data_stores::RefreshToken, AuthAPIError, RefreshTokenStoreError, UserStoreError};
+use crate::utils::{
+    auth::{create_refresh_cookie, generate_auth_cookie},
+    constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+};
+
+#[tracing::instrument(name = "Refresh", skip_all)]
+pub async fn refresh(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let presented = match jar.get(REFRESH_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_owned(),
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let presented = match RefreshToken::parse(presented) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let mut refresh_token_store = state.refresh_token_store.write().await;
+
+    let (email, current, authentication) = match refresh_token_store.get_token(presented.family_id()).await {
+        Ok(res) => res,
+        Err(RefreshTokenStoreError::FamilyNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // A token of a live family that is no longer the current one has already been rotated,
+    // so someone is replaying it. Kill the whole family to log out both parties.
+    if presented != current {
+        tracing::warn!("Refresh token reuse detected, revoking token family");
+        if let Err(e) = refresh_token_store.revoke_family(presented.family_id()).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+        let jar = jar.remove(REFRESH_COOKIE_NAME).remove(JWT_COOKIE_NAME);
+        return (jar, Err(AuthAPIError::InvalidToken));
+    }
+
+    // A disabled or deleted account keeps no session, whatever refresh tokens are still around
+    let account_error = match state.user_store.read().await.get_user(email.as_ref()).await {
+        Ok(user) if user.disabled => Some(AuthAPIError::AccountDisabled),
+        Ok(_) => None,
+        Err(UserStoreError::UserNotFound) => Some(AuthAPIError::InvalidToken),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e)))),
+    };
+    if let Some(error) = account_error {
+        if let Err(e) = refresh_token_store.revoke_family(presented.family_id()).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+        let jar = jar.remove(REFRESH_COOKIE_NAME).remove(JWT_COOKIE_NAME);
+        return (jar, Err(error));
+    }
+
+    let rotated = current.rotate();
+    if let Err(e) = refresh_token_store.add_token(email.clone(), rotated.clone(), authentication.clone()).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    drop(refresh_token_store);
+
+    // The refreshed session still reports the login that started the family
+    let auth_cookie = match generate_auth_cookie(&email, &authentication, state.user_store.clone()).await {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("Generate token error: {:?}", e)))),
+    };
+    let updated_jar = jar.add(auth_cookie).add(create_refresh_cookie(&rotated));
+
+    (updated_jar, Ok(StatusCode::OK))
+}
@@ -0,0 +1,88 @@
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Context};
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{data_stores::StoredSigningKey, AuthAPIError, SigningKeyStoreError};
+use crate::services::signing_key_refresher::refresh_key_ring;
+use crate::utils::{
+    auth::{authenticate_admin_api_token, MAX_TOKEN_LIFETIME_SECONDS},
+    constants::{JWT_KEY_ACTIVATION_DELAY_SECONDS, JWT_KEY_RING, JWT_KEY_RING_REFRESH_SECONDS},
+    signing_key::{generate_private_key, JwtSigningKey},
+};
+
+// Generates a new signing key and stores it for every replica. The key is published right away but only
+// starts signing once every replica has had the chance to load it, and the previous key keeps verifying
+// until every token it signed has expired.
+#[tracing::instrument(name = "Rotate signing key", skip_all)]
+pub async fn rotate_signing_key(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<RotateSigningKeyRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_admin_api_token(&headers)?;
+
+    let algorithm = Algorithm::from_str(&request.algorithm).map_err(|_| AuthAPIError::UnsupportedSigningAlgorithm)?;
+    if !matches!(algorithm, Algorithm::EdDSA | Algorithm::RS256) {
+        return Err(AuthAPIError::UnsupportedSigningAlgorithm);
+    }
+    let private_key = tokio::task::spawn_blocking(move || generate_private_key(algorithm))
+        .await
+        .wrap_err("failed to join key generation task")
+        .map_err(AuthAPIError::UnexpectedError)?
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let kid = JwtSigningKey::from_pem(algorithm, &private_key)
+        .map_err(AuthAPIError::UnexpectedError)?
+        .kid()
+        .to_owned();
+
+    let activates_at = Utc::now().timestamp() + JWT_KEY_ACTIVATION_DELAY_SECONDS;
+    // Replicas go on signing with the previous key until their first refresh after the activation
+    let retire_at = activates_at + JWT_KEY_RING_REFRESH_SECONDS as i64 + MAX_TOKEN_LIFETIME_SECONDS;
+    let previous_kid = JWT_KEY_RING
+        .read()
+        .map_err(|_| AuthAPIError::UnexpectedError(eyre!("JWT key ring lock poisoned")))?
+        .active()
+        .kid()
+        .to_owned();
+
+    let key = StoredSigningKey { kid: kid.clone(), algorithm, private_key, activates_at, retire_at: None };
+    match state.signing_key_store.write().await.add_next_key(key, retire_at).await {
+        Ok(_) => {},
+        Err(SigningKeyStoreError::KeyAlreadyExists) => return Err(AuthAPIError::SigningKeyAlreadyInUse),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    // Other replicas pick the key up on their next refresh, this one publishes it straight away
+    refresh_key_ring(&state.signing_key_store).await.map_err(AuthAPIError::UnexpectedError)?;
+    tracing::info!("Rotating JWT signing key from {} to {} at {}", previous_kid, kid, activates_at);
+
+    Ok(Json(SigningKeyRotationResponse {
+        kid,
+        activates_at,
+        previous_kid,
+        previous_key_retires_at: retire_at,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateSigningKeyRequest {
+    // EdDSA or RS256, the private key itself never leaves the service
+    pub algorithm: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningKeyRotationResponse {
+    pub kid: String,
+    pub activates_at: i64,
+    pub previous_kid: String,
+    pub previous_key_retires_at: i64,
+}
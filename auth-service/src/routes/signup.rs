@@ -7,21 +7,36 @@ use serde::{Deserialize, Serialize};
 use crate::domain::{AuthAPIError, Email, Password};
 use crate::{domain::User, app_state::AppState};
 use crate::domain::UserStoreError as ErrorUser;
+use super::recovery_codes::issue_recovery_codes;
+use crate::utils::client_context::ClientContext;
+use super::verify_email::send_verification_email;
 
 #[tracing::instrument(name = "Signup", skip_all)]
-pub async fn signup(State(state): State<Arc<AppState>>,  Json(request): Json<SignupRequest>) -> Result<impl IntoResponse, AuthAPIError>{
+pub async fn signup(State(state): State<Arc<AppState>>, client: ClientContext, Json(request): Json<SignupRequest>) -> Result<impl IntoResponse, AuthAPIError>{
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
     let password = Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
     
-    let user = User::new(email, password, request.requires_2fa);
+    let user = User::new(email.clone(), password, request.requires_2fa);
     let mut user_store = state.user_store.write().await;
     
     let result = user_store.add_user(user).await;
+    drop(user_store);
 
     match result {
         Ok(_) => {
+            // The account can't be used to log in until the address is confirmed
+            send_verification_email(&state, &email, &client).await?;
+
+            // Without them, losing access to the mailbox would lock a 2FA user out for good
+            let recovery_codes = if request.requires_2fa {
+                Some(issue_recovery_codes(&state, &email).await?)
+            } else {
+                None
+            };
+
             let response = Json(SignupResponse {
-                message: "User created successfully".to_string()
+                message: "User created successfully".to_string(),
+                recovery_codes,
             });
             return Ok((StatusCode::CREATED, response));
         },
@@ -47,4 +62,6 @@ pub struct SignupRequest {
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct SignupResponse {
     pub message: String,
+    #[serde(rename = "recoveryCodes", default, skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
 }
\ No newline at end of file
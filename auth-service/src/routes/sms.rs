@@ -0,0 +1,12 @@
+use color_eyre::eyre::eyre;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, PhoneNumber};
+
+// Text messages are sent straight away, a provider failure fails the request so the user can try again
+pub(super) async fn send_sms(state: &AppState, recipient: &PhoneNumber, message: &str) -> Result<(), AuthAPIError> {
+    state.sms_client
+        .send_sms(recipient, message)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(eyre!("Failed to send SMS: {}", e)))
+}
@@ -0,0 +1,128 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::eyre;
+use qrcode::{render::svg, QrCode};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{
+    data_stores::{TotpSecret, TwoFACode},
+    AuthAPIError, Email, TotpSecretStoreError, UserStoreError as ErrorUser,
+};
+use crate::utils::auth::authenticate;
+
+use super::recovery_codes::issue_recovery_codes;
+
+#[tracing::instrument(name = "Enroll TOTP", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let mut totp_secret_store = state.totp_secret_store.write().await;
+
+    // A confirmed authenticator must not be silently replaced by a new, unconfirmed one
+    match totp_secret_store.get_secret(&email).await {
+        Ok((_, true)) => return Err(AuthAPIError::TotpAlreadyEnabled),
+        Ok(_) | Err(TotpSecretStoreError::SecretNotFound) => {},
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let secret = TotpSecret::default();
+    let otpauth_uri = secret.otpauth_uri(&email).map_err(AuthAPIError::UnexpectedError)?;
+    let qr_code_svg = QrCode::new(otpauth_uri.as_bytes())
+        .map_err(|e| AuthAPIError::UnexpectedError(eyre!("QR code error: {:?}", e)))?
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .build();
+
+    totp_secret_store
+        .add_secret(&email, secret.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(TotpEnrollmentResponse {
+        otpauth_uri,
+        secret: secret.as_ref().to_owned(),
+        qr_code_svg,
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Confirm TOTP", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut totp_secret_store = state.totp_secret_store.write().await;
+
+    let secret = match totp_secret_store.get_secret(&email).await {
+        Ok((_, true)) => return Err(AuthAPIError::TotpAlreadyEnabled),
+        Ok((secret, false)) => secret,
+        Err(TotpSecretStoreError::SecretNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Proves the authenticator app was set up correctly before it is required at login
+    let Some(time_step) = secret.verify(&email, code.as_ref()).map_err(AuthAPIError::UnexpectedError)? else {
+        return Err(AuthAPIError::IncorrectCredentials);
+    };
+    // The code used to confirm can't be replayed at the next login
+    match totp_secret_store.use_time_step(&email, time_step).await {
+        Ok(_) => {},
+        Err(TotpSecretStoreError::TimeStepAlreadyUsed) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    totp_secret_store
+        .confirm_secret(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(totp_secret_store);
+
+    match state.user_store.write().await.set_requires_2fa(email.as_ref(), true).await {
+        Ok(_) => {},
+        Err(ErrorUser::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+    }
+
+    let recovery_codes = issue_recovery_codes(&state, &email).await?;
+
+    let response = Json(ConfirmTotpResponse {
+        message: "TOTP enabled".to_string(),
+        recovery_codes,
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TotpEnrollmentResponse {
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+    pub secret: String,
+    #[serde(rename = "qrCodeSvg")]
+    pub qr_code_svg: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ConfirmTotpResponse {
+    pub message: String,
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
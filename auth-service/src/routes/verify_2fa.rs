@@ -5,12 +5,16 @@ use std::sync::Arc;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, data_stores::TwoFACode, data_stores::LoginAttemptId},
-    utils::auth::generate_auth_cookie,
+    domain::{AuthAPIError, AuthMethod, Authentication, Email, RecoveryCodeStoreError, TotpSecretStoreError, TwoFACodeStore, data_stores::TwoFACode, data_stores::LoginAttemptId, data_stores::RecoveryCode},
+    utils::{auth::{generate_auth_cookie, generate_refresh_cookie}, client_context::ClientContext, constants::MAX_TWO_FA_ATTEMPTS},
 };
 
+use super::login::record_failed_login;
+use super::phone::two_fa_phone_number;
+
 pub async fn verify_2fa(
     State(state): State<Arc<AppState>>,
+    client: ClientContext,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
@@ -28,13 +32,24 @@ pub async fn verify_2fa(
         }
     }; // Validate the login attempt ID in `request`
 
-    let two_fa_code = match TwoFACode::parse(request.two_fa_code) {
-        Ok(val) => val,
-        Err(_) => {
-            return (jar, Err(AuthAPIError::InvalidCredentials));
+    // A recovery code can be entered in place of the usual 2FA code
+    let two_fa_code = match TwoFACode::parse(request.two_fa_code.clone()) {
+        Ok(val) => SecondFactor::Code(val),
+        Err(_) => match RecoveryCode::parse(request.two_fa_code) {
+            Ok(val) => SecondFactor::RecoveryCode(val),
+            Err(_) => {
+                return (jar, Err(AuthAPIError::InvalidCredentials));
+            }
         }
     }; // Validate the 2FA code in `request`
 
+    // A lock also holds off whoever already has the password
+    match state.login_attempt_store.read().await.lock_remaining(&email).await {
+        Ok(Some(seconds)) => return (jar, Err(AuthAPIError::AccountLocked(seconds))),
+        Ok(None) => {},
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
     let mut two_fa_code_store = state.two_fa_code_store.write().await;
     let code_tuple = match two_fa_code_store.get_code(&email).await {
         Ok(val) => val,
@@ -47,18 +62,101 @@ pub async fn verify_2fa(
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
-    if two_fa_code != code_tuple.1 {
+    // Every guess counts, so the code can't be enumerated within its lifetime
+    let attempts = match two_fa_code_store.record_attempt(&email).await {
+        Ok(attempts) => attempts,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    if attempts > MAX_TWO_FA_ATTEMPTS {
+        return (jar, Err(invalidate_code(&mut *two_fa_code_store, &email).await));
+    }
+
+    // The method the code proves, which is how the login is reported in the session's `amr`
+    let (code_matches, second_factor) = match two_fa_code {
+        // The delivered code is only accepted when the user has no confirmed authenticator app
+        SecondFactor::Code(two_fa_code) => {
+            let totp_secret = state.totp_secret_store.read().await.get_secret(&email).await;
+            match totp_secret {
+                Ok((secret, true)) => match secret.verify(&email, two_fa_code.as_ref()) {
+                    // A code that was already accepted once counts as a wrong guess
+                    Ok(Some(time_step)) => match state.totp_secret_store.write().await.use_time_step(&email, time_step).await {
+                        Ok(_) => (true, AuthMethod::OneTimeCode),
+                        Err(TotpSecretStoreError::TimeStepAlreadyUsed) => (false, AuthMethod::OneTimeCode),
+                        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+                    },
+                    Ok(None) => (false, AuthMethod::OneTimeCode),
+                    Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+                },
+                Ok((_, false)) | Err(TotpSecretStoreError::SecretNotFound) => {
+                    let sent_by_sms = match state.user_store.read().await.get_user(email.as_ref()).await {
+                        Ok(user) => two_fa_phone_number(user).is_some(),
+                        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(color_eyre::eyre::eyre!("User store error: {:?}", e)))),
+                    };
+                    let method = if sent_by_sms { AuthMethod::Sms } else { AuthMethod::OneTimeCode };
+                    (two_fa_code == code_tuple.1, method)
+                }
+                Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+            }
+        }
+        // Using a recovery code burns it, whatever happens next
+        SecondFactor::RecoveryCode(recovery_code) => {
+            match state.recovery_code_store.write().await.use_code(&email, &recovery_code).await {
+                Ok(_) => (true, AuthMethod::OneTimeCode),
+                Err(RecoveryCodeStoreError::CodeNotFound) => (false, AuthMethod::OneTimeCode),
+                Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+            }
+        }
+    };
+
+    if !code_matches {
+        // Wrong codes count towards the same lockout as wrong passwords, and a lock ends the pending login
+        match record_failed_login(&email, &state, &client).await {
+            AuthAPIError::IncorrectCredentials => {},
+            AuthAPIError::AccountLocked(seconds) => {
+                if let Err(e) = two_fa_code_store.remove_code(&email).await {
+                    return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+                }
+                return (jar, Err(AuthAPIError::AccountLocked(seconds)));
+            }
+            e => return (jar, Err(e)),
+        }
+        if attempts == MAX_TWO_FA_ATTEMPTS {
+            return (jar, Err(invalidate_code(&mut *two_fa_code_store, &email).await));
+        }
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
+    // The account may have been disabled since the password was checked
+    match state.user_store.read().await.get_user(email.as_ref()).await {
+        Ok(user) if user.disabled => {
+            if let Err(e) = two_fa_code_store.remove_code(&email).await {
+                return (jar, Err(AuthAPIError::UnexpectedError(color_eyre::eyre::eyre!("Remove code error: {:?}", e))));
+            }
+            return (jar, Err(AuthAPIError::AccountDisabled));
+        }
+        Ok(_) => {},
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(color_eyre::eyre::eyre!("User store error: {:?}", e)))),
+    }
+
+    if let Err(e) = state.login_attempt_store.write().await.clear_failures(&email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
     // Generate JWT auth cookie
-    let auth_cookie = match generate_auth_cookie(&email) {
+    let authentication = Authentication::now(vec![code_tuple.2, second_factor]);
+    let auth_cookie = match generate_auth_cookie(&email, &authentication, state.user_store.clone()).await {
         Ok(cookie) => cookie,
         Err(e) => {
             return (jar, Err(AuthAPIError::UnexpectedError(color_eyre::eyre::eyre!("Generate token error: {:?}", e))));
         }
     };
-    let updated_jar = jar.add(auth_cookie);
+    let refresh_cookie = match generate_refresh_cookie(&email, &authentication, state.refresh_token_store.clone()).await {
+        Ok(cookie) => cookie,
+        Err(e) => {
+            return (jar, Err(AuthAPIError::UnexpectedError(color_eyre::eyre::eyre!("Generate refresh token error: {:?}", e))));
+        }
+    };
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     if let Err(e) = two_fa_code_store.remove_code(&email).await {
         return (updated_jar, Err(AuthAPIError::UnexpectedError(color_eyre::eyre::eyre!("Remove code error: {:?}", e))));
@@ -67,6 +165,19 @@ pub async fn verify_2fa(
     (updated_jar, Ok(StatusCode::OK.into_response()))
 }
 
+// Drops the pending code once its attempts run out, so the user has to log in again for a new one
+async fn invalidate_code(two_fa_code_store: &mut (dyn TwoFACodeStore + Send + Sync), email: &Email) -> AuthAPIError {
+    match two_fa_code_store.remove_code(email).await {
+        Ok(_) => AuthAPIError::TwoFAAttemptsExceeded,
+        Err(e) => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+enum SecondFactor {
+    Code(TwoFACode),
+    RecoveryCode(RecoveryCode),
+}
+
 #[derive(Deserialize)]
 pub struct Verify2FARequest{
     pub email: String,
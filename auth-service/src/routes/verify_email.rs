@@ -0,0 +1,128 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, UserStoreError as ErrorUser};
+use crate::domain::data_stores::EmailVerificationToken;
+use crate::services::email_templates::EmailTemplateKind;
+use crate::utils::client_context::ClientContext;
+use crate::utils::constants::EMAIL_VERIFICATION_TOKEN_TTL_SECONDS;
+
+use super::email::send_templated_email;
+
+// Minimum time between two verification emails sent to the same address
+const RESEND_COOLDOWN_SECONDS: i64 = 60;
+
+#[tracing::instrument(name = "Verify email", skip_all)]
+pub async fn verify_email(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let token = EmailVerificationToken::parse(request.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut token_store = state.email_verification_token_store.write().await;
+    let (stored_token, _) = token_store
+        .get_token(&email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    if stored_token != token {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    match state.user_store.write().await.mark_email_verified(email.as_ref()).await {
+        Ok(_) => {},
+        Err(ErrorUser::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+    }
+
+    token_store
+        .remove_token(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(VerifyEmailResponse {
+        message: "Email verified successfully".to_string(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Resend verification email", skip_all)]
+pub async fn resend_verification_email(
+    State(state): State<Arc<AppState>>,
+    client: ClientContext,
+    Json(request): Json<ResendVerificationEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let verified = match state.user_store.read().await.get_user(email.as_ref()).await {
+        Ok(user) => Some(user.verified),
+        Err(ErrorUser::UserNotFound) => None,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+    };
+
+    // Unknown and already verified accounts get the same response, without an email being sent
+    if verified == Some(false) {
+        let last_issued_at = state.email_verification_token_store
+            .read()
+            .await
+            .get_token(&email)
+            .await
+            .ok()
+            .map(|(_, issued_at)| issued_at);
+
+        if let Some(issued_at) = last_issued_at {
+            if Utc::now().timestamp() - issued_at < RESEND_COOLDOWN_SECONDS {
+                return Err(AuthAPIError::TooManyRequests);
+            }
+        }
+
+        send_verification_email(&state, &email, &client).await?;
+    }
+
+    let response = Json(VerifyEmailResponse {
+        message: "If the account exists and is unverified, a verification email has been sent".to_string(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// Issues a new verification token for `email`, replacing any previous one, and emails it
+pub(crate) async fn send_verification_email(state: &AppState, email: &Email, client: &ClientContext) -> Result<(), AuthAPIError> {
+    let token = EmailVerificationToken::default();
+
+    state.email_verification_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone(), Utc::now().timestamp())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let variables = [
+        ("token", token.as_ref().to_owned()),
+        ("expiry_hours", (EMAIL_VERIFICATION_TOKEN_TTL_SECONDS / 3600).to_string()),
+    ];
+    send_templated_email(state, email, EmailTemplateKind::EmailVerification, client, &variables).await
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub email: String,
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationEmailRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
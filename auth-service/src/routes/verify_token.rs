@@ -1,24 +1,34 @@
-use axum::response::IntoResponse;
-use axum::http::{StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use axum::extract::State;
 use serde::Deserialize;
 use std::sync::Arc;
-use crate::utils::auth::validate_token;
+use crate::domain::{AuthAPIError, API_KEY_PREFIX};
+use crate::utils::auth::{validate_api_key, validate_token};
 use crate::app_state::AppState;
 
+// Returns the token's claims so callers can make authorization decisions from its roles.
+// Personal API keys answer with the same sub and the key's scope, but no roles.
+#[tracing::instrument(name = "Verify token", skip_all)]
 pub async fn verify_token(
     State(state): State<Arc<AppState>>,
     Json(request): Json<VerifyTokenRequest>
-) -> impl IntoResponse {
-
-    match validate_token(&request.token, state.banned_token_store.clone()).await {
-        Ok(_) => StatusCode::OK.into_response(),
-        Err(_) => StatusCode::UNAUTHORIZED.into_response()
+) -> Result<Response, AuthAPIError> {
+    if request.token.starts_with(API_KEY_PREFIX) {
+        let claims = validate_api_key(&request.token, state.api_key_store.clone(), state.user_store.clone())
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+        return Ok(Json(claims).into_response());
     }
+
+    let claims = validate_token(&request.token, state.banned_token_store.clone())
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    Ok(Json(claims).into_response())
 }
 
 #[derive(Deserialize)]
 pub struct VerifyTokenRequest {
     pub token: String
-}
\ No newline at end of file
+}
@@ -0,0 +1,440 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::domain::{
+    data_stores::{LoginAttemptId, WebauthnCeremony, WebauthnChallenge, WebauthnCredential},
+    AuthAPIError, AuthMethod, Authentication, Email, UserStoreError as ErrorUser, WebauthnChallengeStoreError,
+    WebauthnCredentialStoreError,
+};
+use crate::utils::{
+    auth::{authenticate, generate_auth_cookie, generate_refresh_cookie},
+    constants::{WEBAUTHN_RP_ID, WEBAUTHN_RP_NAME},
+    webauthn::{
+        decode_base64url, encode_base64url, parse_attestation_object, parse_authenticator_data,
+        verify_client_data, verify_signature, COSE_ALG_ES256,
+    },
+};
+
+const WEBAUTHN_TIMEOUT_MILLISECONDS: u64 = 300_000; // matches the challenge TTL
+
+#[tracing::instrument(name = "Start WebAuthn registration", skip_all)]
+pub async fn webauthn_register_start(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let exclude_credentials = credential_descriptors(&state, &email).await?;
+
+    let challenge = WebauthnChallenge::default();
+    state.webauthn_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge.clone(), WebauthnCeremony::Registration(email.clone()))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(CreationOptions {
+        challenge: challenge.as_ref().to_owned(),
+        rp: RelyingParty {
+            id: WEBAUTHN_RP_ID.to_owned(),
+            name: WEBAUTHN_RP_NAME.to_owned(),
+        },
+        user: UserEntity {
+            // The user handle ends up on the authenticator, so it must not reveal the email
+            id: encode_base64url(&Sha256::digest(email.as_ref().as_bytes())),
+            name: email.as_ref().to_owned(),
+            display_name: email.as_ref().to_owned(),
+        },
+        pub_key_cred_params: vec![CredentialParameters {
+            credential_type: PUBLIC_KEY.to_owned(),
+            alg: COSE_ALG_ES256,
+        }],
+        timeout: WEBAUTHN_TIMEOUT_MILLISECONDS,
+        attestation: "none".to_owned(),
+        exclude_credentials,
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "preferred".to_owned(),
+            user_verification: "preferred".to_owned(),
+        },
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Finish WebAuthn registration", skip_all)]
+pub async fn webauthn_register_finish(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<RegistrationCredential>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (_, claims) = authenticate(&jar, state.banned_token_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let client_data_json = decode_base64url(&request.response.client_data_json).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let attestation_object = decode_base64url(&request.response.attestation_object).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let challenge = verify_client_data(&client_data_json, "webauthn.create").map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    match take_ceremony(&state, &challenge).await? {
+        WebauthnCeremony::Registration(challenge_email) if challenge_email == email => {},
+        _ => return Err(AuthAPIError::IncorrectCredentials),
+    }
+
+    let authenticator_data = parse_attestation_object(&attestation_object).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    let attested_credential = authenticator_data.attested_credential.ok_or(AuthAPIError::IncorrectCredentials)?;
+
+    let credential_id = encode_base64url(&attested_credential.credential_id);
+    if credential_id != request.id {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let credential = WebauthnCredential {
+        credential_id: credential_id.clone(),
+        email,
+        public_key: attested_credential.public_key,
+        sign_count: authenticator_data.sign_count,
+        name: request.name.unwrap_or_else(|| "Passkey".to_owned()),
+        created_at: Utc::now().timestamp(),
+    };
+
+    match state.webauthn_credential_store.write().await.add_credential(credential).await {
+        Ok(_) => {},
+        Err(WebauthnCredentialStoreError::CredentialAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(WebauthnRegistrationResponse {
+        message: "Passkey registered".to_string(),
+        credential_id,
+    });
+    Ok((StatusCode::CREATED, response))
+}
+
+#[tracing::instrument(name = "Start WebAuthn login", skip_all)]
+pub async fn webauthn_login_start(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<WebauthnLoginStartRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = request
+        .email
+        .map(Email::parse)
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // With a login attempt id the passkey replaces the emailed 2FA code, otherwise it is the
+    // whole login and the authenticator has to verify the user (PIN, biometrics) itself
+    let (ceremony, user_verification) = match (request.login_attempt_id, email) {
+        (Some(login_attempt_id), Some(email)) => {
+            let login_attempt_id = LoginAttemptId::parse(login_attempt_id).map_err(|_| AuthAPIError::InvalidCredentials)?;
+            let (stored_login_attempt_id, _, _) = state.two_fa_code_store
+                .read()
+                .await
+                .get_code(&email)
+                .await
+                .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+            if stored_login_attempt_id != login_attempt_id {
+                return Err(AuthAPIError::IncorrectCredentials);
+            }
+            (WebauthnCeremony::SecondFactor(email, login_attempt_id), "discouraged")
+        }
+        (Some(_), None) => return Err(AuthAPIError::InvalidCredentials),
+        (None, email) => (WebauthnCeremony::Passwordless(email), "required"),
+    };
+
+    let allow_credentials = match &ceremony {
+        WebauthnCeremony::SecondFactor(email, _) | WebauthnCeremony::Passwordless(Some(email)) => {
+            credential_descriptors(&state, email).await?
+        }
+        _ => Vec::new(),
+    };
+
+    let challenge = WebauthnChallenge::default();
+    state.webauthn_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge.clone(), ceremony)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(RequestOptions {
+        challenge: challenge.as_ref().to_owned(),
+        rp_id: WEBAUTHN_RP_ID.to_owned(),
+        timeout: WEBAUTHN_TIMEOUT_MILLISECONDS,
+        allow_credentials,
+        user_verification: user_verification.to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Finish WebAuthn login", skip_all)]
+pub async fn webauthn_login_finish(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<AssertionCredential>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (email, methods) = match verify_assertion(&state, request).await {
+        Ok(result) => result,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let authentication = Authentication::now(methods);
+    let auth_cookie = match generate_auth_cookie(&email, &authentication, state.user_store.clone()).await {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("Generate token error: {:?}", e)))),
+    };
+    let refresh_cookie = match generate_refresh_cookie(&email, &authentication, state.refresh_token_store.clone()).await {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!("Generate refresh token error: {:?}", e)))),
+    };
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
+
+// Checks an assertion against its challenge and stored credential and returns who signed in and how
+async fn verify_assertion(state: &AppState, request: AssertionCredential) -> Result<(Email, Vec<AuthMethod>), AuthAPIError> {
+    let client_data_json = decode_base64url(&request.response.client_data_json).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let authenticator_data = decode_base64url(&request.response.authenticator_data).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let signature = decode_base64url(&request.response.signature).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let challenge = verify_client_data(&client_data_json, "webauthn.get").map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    let ceremony = take_ceremony(state, &challenge).await?;
+
+    let credential = match state.webauthn_credential_store.read().await.get_credential(&request.id).await {
+        Ok(credential) => credential,
+        Err(WebauthnCredentialStoreError::CredentialNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let parsed = parse_authenticator_data(&authenticator_data).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    verify_signature(&credential.public_key, &authenticator_data, &client_data_json, &signature)
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    match &ceremony {
+        WebauthnCeremony::SecondFactor(email, _) if *email == credential.email => {},
+        WebauthnCeremony::Passwordless(None) if parsed.user_verified => {},
+        WebauthnCeremony::Passwordless(Some(email)) if *email == credential.email && parsed.user_verified => {},
+        _ => return Err(AuthAPIError::IncorrectCredentials),
+    }
+
+    // A counter that doesn't move forward means the credential's private key was cloned.
+    // Authenticators that don't keep a counter always report zero.
+    if parsed.sign_count != 0 || credential.sign_count != 0 {
+        match state.webauthn_credential_store
+            .write()
+            .await
+            .update_sign_count(&credential.credential_id, parsed.sign_count)
+            .await
+        {
+            Ok(_) => {},
+            Err(WebauthnCredentialStoreError::SignCountNotIncreased) => {
+                tracing::warn!("WebAuthn sign count did not increase, possible cloned authenticator");
+                return Err(AuthAPIError::IncorrectCredentials);
+            }
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    }
+
+    let methods = match ceremony {
+        WebauthnCeremony::SecondFactor(email, login_attempt_id) => {
+            // A lock also holds off whoever already has the password
+            match state.login_attempt_store.read().await.lock_remaining(&email).await {
+                Ok(Some(seconds)) => return Err(AuthAPIError::AccountLocked(seconds)),
+                Ok(None) => {},
+                Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+            }
+            let mut two_fa_code_store = state.two_fa_code_store.write().await;
+            let (pending_login_attempt_id, _, first_factor) = two_fa_code_store
+                .get_code(&email)
+                .await
+                .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+            // A challenge started for an earlier login can't finish a newer one
+            if login_attempt_id != pending_login_attempt_id {
+                return Err(AuthAPIError::IncorrectCredentials);
+            }
+            // The account may have been disabled since the password was checked
+            match state.user_store.read().await.get_user(email.as_ref()).await {
+                Ok(user) if user.disabled => {
+                    two_fa_code_store
+                        .remove_code(&email)
+                        .await
+                        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+                    return Err(AuthAPIError::AccountDisabled);
+                }
+                Ok(_) => {},
+                Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+            }
+            // The login attempt is complete, its emailed code must not be usable anymore
+            two_fa_code_store
+                .remove_code(&email)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            drop(two_fa_code_store);
+            state
+                .login_attempt_store
+                .write()
+                .await
+                .clear_failures(&email)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            vec![first_factor, AuthMethod::SecurityKey]
+        }
+        _ => {
+            // Passwordless logins skip /login, so its checks have to happen here
+            let user = match state.user_store.read().await.get_user(credential.email.as_ref()).await {
+                Ok(user) => user.clone(),
+                Err(ErrorUser::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+                Err(e) => return Err(AuthAPIError::UnexpectedError(eyre!("User store error: {:?}", e))),
+            };
+            if !user.verified {
+                return Err(AuthAPIError::EmailNotVerified);
+            }
+            if user.disabled {
+                return Err(AuthAPIError::AccountDisabled);
+            }
+            vec![AuthMethod::SecurityKey]
+        }
+    };
+
+    Ok((credential.email, methods))
+}
+
+async fn take_ceremony(state: &AppState, challenge: &WebauthnChallenge) -> Result<WebauthnCeremony, AuthAPIError> {
+    match state.webauthn_challenge_store.write().await.take_challenge(challenge).await {
+        Ok(ceremony) => Ok(ceremony),
+        Err(WebauthnChallengeStoreError::ChallengeNotFound) => Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+async fn credential_descriptors(state: &AppState, email: &Email) -> Result<Vec<CredentialDescriptor>, AuthAPIError> {
+    let credentials = state.webauthn_credential_store
+        .read()
+        .await
+        .get_credentials(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(credentials
+        .into_iter()
+        .map(|credential| CredentialDescriptor {
+            credential_type: PUBLIC_KEY.to_owned(),
+            id: credential.credential_id,
+        })
+        .collect())
+}
+
+const PUBLIC_KEY: &str = "public-key";
+
+// Request and response bodies follow the JSON shapes of the WebAuthn browser API
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    pub timeout: u64,
+    pub attestation: String,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub authenticator_selection: AuthenticatorSelection,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: u64,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub user_verification: String,
+}
+
+#[derive(Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub name: Option<String>,
+    pub response: AttestationResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Deserialize)]
+pub struct WebauthnLoginStartRequest {
+    pub email: Option<String>,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AssertionCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct WebauthnRegistrationResponse {
+    pub message: String,
+    #[serde(rename = "credentialId")]
+    pub credential_id: String,
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{
+    data_stores::{ApiKeyStore, ApiKeyStoreError},
+    ApiKey, ApiKeyId, ApiKeyToken, Email,
+};
+
+#[derive(Default)]
+pub struct HashmapApiKeyStore {
+    keys: HashMap<ApiKeyId, (ApiKey, String)>,
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for HashmapApiKeyStore {
+    async fn add_key(&mut self, key: ApiKey, token: &ApiKeyToken) -> Result<(), ApiKeyStoreError> {
+        self.keys.insert(key.id.clone(), (key, token.secret().to_owned()));
+        Ok(())
+    }
+
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let mut keys: Vec<ApiKey> = self.keys.values().map(|(key, _)| key).filter(|key| &key.email == email).cloned().collect();
+        keys.sort_by_key(|key| std::cmp::Reverse(key.created_at));
+        Ok(keys)
+    }
+
+    async fn delete_key(&mut self, email: &Email, id: &ApiKeyId) -> Result<(), ApiKeyStoreError> {
+        match self.keys.get(id) {
+            Some((key, _)) if &key.email == email => {
+                self.keys.remove(id);
+                Ok(())
+            }
+            _ => Err(ApiKeyStoreError::KeyNotFound),
+        }
+    }
+
+    async fn delete_all_keys(&mut self, email: &Email) -> Result<(), ApiKeyStoreError> {
+        self.keys.retain(|_, (key, _)| &key.email != email);
+        Ok(())
+    }
+
+    async fn use_key(&mut self, token: &ApiKeyToken) -> Result<ApiKey, ApiKeyStoreError> {
+        let now = Utc::now().timestamp();
+        match self.keys.get_mut(token.id()) {
+            Some((key, secret)) if secret == token.secret() && !key.is_expired(now) => {
+                key.last_used_at = Some(now);
+                Ok(key.clone())
+            }
+            _ => Err(ApiKeyStoreError::KeyNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Scopes;
+
+    fn api_key(token: &ApiKeyToken, email: &str, expires_at: Option<i64>) -> ApiKey {
+        ApiKey {
+            id: token.id().clone(),
+            email: Email::parse(email.to_owned()).unwrap(),
+            name: "Scripts".to_owned(),
+            scopes: Scopes::parse("reports:read").unwrap(),
+            created_at: Utc::now().timestamp(),
+            expires_at,
+            last_used_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_use_key_records_last_use() {
+        let mut store = HashmapApiKeyStore::default();
+        let token = ApiKeyToken::default();
+        store.add_key(api_key(&token, "test@example.com", None), &token).await.unwrap();
+
+        let key = store.use_key(&token).await.unwrap();
+        assert!(key.last_used_at.is_some());
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        assert_eq!(store.list_keys(&email).await.unwrap(), vec![key]);
+
+        // A token with the same id but another secret is not the key
+        let forged = ApiKeyToken::parse(&format!("ask_{}_{}", token.id().as_ref(), ApiKeyToken::default().secret())).unwrap();
+        assert_eq!(store.use_key(&forged).await, Err(ApiKeyStoreError::KeyNotFound));
+
+        let expired = ApiKeyToken::default();
+        store.add_key(api_key(&expired, "test@example.com", Some(Utc::now().timestamp() - 1)), &expired).await.unwrap();
+        assert_eq!(store.use_key(&expired).await, Err(ApiKeyStoreError::KeyNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_key_of_owner_only() {
+        let mut store = HashmapApiKeyStore::default();
+        let token = ApiKeyToken::default();
+        store.add_key(api_key(&token, "test@example.com", None), &token).await.unwrap();
+
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        assert_eq!(store.delete_key(&other, token.id()).await, Err(ApiKeyStoreError::KeyNotFound));
+
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        store.delete_key(&email, token.id()).await.unwrap();
+        assert_eq!(store.use_key(&token).await, Err(ApiKeyStoreError::KeyNotFound));
+        assert!(store.list_keys(&email).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_all_keys() {
+        let mut store = HashmapApiKeyStore::default();
+        let (first, second, other) = (ApiKeyToken::default(), ApiKeyToken::default(), ApiKeyToken::default());
+        store.add_key(api_key(&first, "test@example.com", None), &first).await.unwrap();
+        store.add_key(api_key(&second, "test@example.com", None), &second).await.unwrap();
+        store.add_key(api_key(&other, "other@example.com", None), &other).await.unwrap();
+
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        store.delete_all_keys(&email).await.unwrap();
+        assert!(store.list_keys(&email).await.unwrap().is_empty());
+        assert_eq!(store.use_key(&first).await, Err(ApiKeyStoreError::KeyNotFound));
+        assert!(store.use_key(&other).await.is_ok());
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{AuthorizationCode, AuthorizationGrantStore, AuthorizationGrantStoreError, ConsentId},
+    AuthorizationGrant,
+};
+
+#[derive(Default)]
+pub struct HashmapAuthorizationGrantStore {
+    pending_consents: HashMap<ConsentId, AuthorizationGrant>,
+    codes: HashMap<AuthorizationCode, AuthorizationGrant>,
+}
+
+#[async_trait::async_trait]
+impl AuthorizationGrantStore for HashmapAuthorizationGrantStore {
+    async fn add_pending_consent(&mut self, consent_id: ConsentId, grant: AuthorizationGrant) -> Result<(), AuthorizationGrantStoreError> {
+        self.pending_consents.insert(consent_id, grant);
+        Ok(())
+    }
+
+    async fn take_pending_consent(&mut self, consent_id: &ConsentId) -> Result<AuthorizationGrant, AuthorizationGrantStoreError> {
+        self.pending_consents.remove(consent_id).ok_or(AuthorizationGrantStoreError::GrantNotFound)
+    }
+
+    async fn add_code(&mut self, code: AuthorizationCode, grant: AuthorizationGrant) -> Result<(), AuthorizationGrantStoreError> {
+        self.codes.insert(code, grant);
+        Ok(())
+    }
+
+    async fn take_code(&mut self, code: &AuthorizationCode) -> Result<AuthorizationGrant, AuthorizationGrantStoreError> {
+        self.codes.remove(code).ok_or(AuthorizationGrantStoreError::GrantNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuthMethod, Authentication, CodeChallenge, Email, OAuthClientId, RedirectUri, Scopes};
+
+    fn grant() -> AuthorizationGrant {
+        AuthorizationGrant {
+            client_id: OAuthClientId::parse("8f14e45f-ceea-467f-a0e6-4b2a1e3f5c6d".to_owned()).unwrap(),
+            redirect_uri: RedirectUri::parse("https://app.example.com/callback".to_owned()).unwrap(),
+            email: Email::parse("test@example.com".to_owned()).unwrap(),
+            scopes: Scopes::parse("profile").unwrap(),
+            code_challenge: CodeChallenge::parse("E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".to_owned()).unwrap(),
+            state: Some("xyz".to_owned()),
+            nonce: Some("n-0S6_WzA2Mj".to_owned()),
+            authentication: Authentication { auth_time: 1_700_000_000, methods: vec![AuthMethod::Password] },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_take_code_is_single_use() {
+        let mut store = HashmapAuthorizationGrantStore::default();
+        let code = AuthorizationCode::default();
+
+        store.add_code(code.clone(), grant()).await.unwrap();
+        assert_eq!(store.take_code(&code).await, Ok(grant()));
+        assert_eq!(store.take_code(&code).await, Err(AuthorizationGrantStoreError::GrantNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_consents_and_codes_are_kept_apart() {
+        let mut store = HashmapAuthorizationGrantStore::default();
+        let consent_id = ConsentId::default();
+
+        store.add_pending_consent(consent_id.clone(), grant()).await.unwrap();
+        let code = AuthorizationCode::parse(consent_id.as_ref().to_owned()).unwrap();
+        assert_eq!(store.take_code(&code).await, Err(AuthorizationGrantStoreError::GrantNotFound));
+        assert_eq!(store.take_pending_consent(&consent_id).await, Ok(grant()));
+    }
+}
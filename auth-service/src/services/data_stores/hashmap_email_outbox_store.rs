@@ -0,0 +1,172 @@
+use chrono::Utc;
+
+use crate::domain::{
+    data_stores::{EmailOutboxStore, EmailOutboxStoreError, OutboxEmail, OutboxEmailId, OutboxStatus},
+    Email, EmailContent,
+};
+
+// Kept in insertion order so emails are claimed in the order they were queued
+#[derive(Default)]
+pub struct HashmapEmailOutboxStore {
+    emails: Vec<OutboxEmail>,
+}
+
+impl HashmapEmailOutboxStore {
+    fn get_mut(&mut self, id: &OutboxEmailId) -> Result<&mut OutboxEmail, EmailOutboxStoreError> {
+        self.emails.iter_mut().find(|email| &email.id == id).ok_or(EmailOutboxStoreError::EmailNotFound)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for HashmapEmailOutboxStore {
+    async fn enqueue(&mut self, recipient: &Email, content: &EmailContent, expires_at: Option<i64>) -> Result<OutboxEmailId, EmailOutboxStoreError> {
+        let now = Utc::now().timestamp();
+        let id = OutboxEmailId::default();
+        self.emails.push(OutboxEmail {
+            id: id.clone(),
+            recipient: recipient.clone(),
+            content: content.clone(),
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            next_attempt_at: now,
+            expires_at,
+        });
+        Ok(id)
+    }
+
+    async fn claim_due(&mut self, limit: u32, lease_seconds: u64) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let now = Utc::now().timestamp();
+        let mut due: Vec<&mut OutboxEmail> = self.emails
+            .iter_mut()
+            .filter(|email| email.status == OutboxStatus::Pending && email.next_attempt_at <= now)
+            .collect();
+        due.sort_by_key(|email| email.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|email| {
+                email.attempts += 1;
+                email.next_attempt_at = now + lease_seconds as i64;
+                email.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_sent(&mut self, id: &OutboxEmailId) -> Result<(), EmailOutboxStoreError> {
+        let index = self.emails.iter().position(|email| &email.id == id).ok_or(EmailOutboxStoreError::EmailNotFound)?;
+        self.emails.remove(index);
+        Ok(())
+    }
+
+    async fn mark_failed(&mut self, id: &OutboxEmailId, error: &str, retry_in_seconds: Option<u64>) -> Result<(), EmailOutboxStoreError> {
+        let email = self.get_mut(id)?;
+        email.last_error = Some(error.to_owned());
+        match retry_in_seconds {
+            Some(seconds) => email.next_attempt_at = Utc::now().timestamp() + seconds as i64,
+            None => email.status = OutboxStatus::Dead,
+        }
+        Ok(())
+    }
+
+    async fn list(&self, status: OutboxStatus, offset: u64, limit: u64) -> Result<(Vec<OutboxEmail>, u64), EmailOutboxStoreError> {
+        let mut emails: Vec<&OutboxEmail> = self.emails.iter().filter(|email| email.status == status).collect();
+        emails.sort_by_key(|email| std::cmp::Reverse(email.created_at));
+        let total = emails.len() as u64;
+        let page = emails.into_iter().skip(offset as usize).take(limit as usize).cloned().collect();
+        Ok((page, total))
+    }
+
+    async fn replay(&mut self, id: &OutboxEmailId) -> Result<OutboxEmail, EmailOutboxStoreError> {
+        let email = self.get_mut(id)?;
+        email.status = OutboxStatus::Pending;
+        email.attempts = 0;
+        email.next_attempt_at = Utc::now().timestamp();
+        Ok(email.clone())
+    }
+
+    async fn purge_recipient(&mut self, recipient: &Email) -> Result<(), EmailOutboxStoreError> {
+        self.emails.retain(|email| &email.recipient != recipient);
+        Ok(())
+    }
+
+    async fn purge_dead(&mut self, created_before: i64) -> Result<u64, EmailOutboxStoreError> {
+        let count = self.emails.len();
+        self.emails.retain(|email| email.status != OutboxStatus::Dead || email.created_at >= created_before);
+        Ok((count - self.emails.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content() -> EmailContent {
+        EmailContent { subject: "Subject".to_owned(), text: "Text".to_owned(), html: "<p>Html</p>".to_owned() }
+    }
+
+    #[tokio::test]
+    async fn test_claimed_emails_are_leased_until_they_fail() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let recipient = Email::parse("test@example.com".to_owned()).unwrap();
+        let id = store.enqueue(&recipient, &content(), None).await.unwrap();
+
+        let claimed = store.claim_due(10, 60).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!((claimed[0].id.clone(), claimed[0].attempts), (id.clone(), 1));
+        assert!(store.claim_due(10, 60).await.unwrap().is_empty());
+
+        store.mark_failed(&id, "connection refused", Some(0)).await.unwrap();
+        let claimed = store.claim_due(10, 60).await.unwrap();
+        assert_eq!(claimed[0].attempts, 2);
+        assert_eq!(claimed[0].last_error.as_deref(), Some("connection refused"));
+
+        store.mark_sent(&id).await.unwrap();
+        assert_eq!(store.list(OutboxStatus::Pending, 0, 10).await.unwrap().1, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_until_replayed() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let recipient = Email::parse("test@example.com".to_owned()).unwrap();
+        let id = store.enqueue(&recipient, &content(), None).await.unwrap();
+
+        store.claim_due(10, 60).await.unwrap();
+        store.mark_failed(&id, "mailbox unavailable", None).await.unwrap();
+        let (dead, total) = store.list(OutboxStatus::Dead, 0, 10).await.unwrap();
+        assert_eq!((dead[0].status, total), (OutboxStatus::Dead, 1));
+
+        // Dead emails are never claimed, even once their lease is over
+        store.get_mut(&id).unwrap().next_attempt_at = 0;
+        assert!(store.claim_due(10, 60).await.unwrap().is_empty());
+
+        let replayed = store.replay(&id).await.unwrap();
+        assert_eq!((replayed.status, replayed.attempts), (OutboxStatus::Pending, 0));
+        assert_eq!(store.claim_due(10, 60).await.unwrap().len(), 1);
+
+        assert_eq!(store.replay(&OutboxEmailId::default()).await.unwrap_err(), EmailOutboxStoreError::EmailNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_purges_recipient_and_old_dead_emails() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let recipient = Email::parse("test@example.com".to_owned()).unwrap();
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        store.enqueue(&recipient, &content(), None).await.unwrap();
+        let dead = store.enqueue(&other, &content(), None).await.unwrap();
+        store.mark_failed(&dead, "mailbox unavailable", None).await.unwrap();
+        store.enqueue(&other, &content(), None).await.unwrap();
+
+        store.purge_recipient(&recipient).await.unwrap();
+        assert_eq!(store.list(OutboxStatus::Pending, 0, 10).await.unwrap().1, 1);
+
+        // Only dead emails queued before the cutoff go, pending ones are left to the worker
+        let created_at = store.get_mut(&dead).unwrap().created_at;
+        assert_eq!(store.purge_dead(created_at).await.unwrap(), 0);
+        assert_eq!(store.purge_dead(created_at + 1).await.unwrap(), 1);
+        assert_eq!(store.list(OutboxStatus::Dead, 0, 10).await.unwrap().1, 0);
+        assert_eq!(store.list(OutboxStatus::Pending, 0, 10).await.unwrap().1, 1);
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{EmailVerificationToken, EmailVerificationTokenStore, EmailVerificationTokenStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapEmailVerificationTokenStore {
+    tokens: HashMap<Email, (EmailVerificationToken, i64)>,
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for HashmapEmailVerificationTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+        issued_at: i64,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        // Replace any existing token so only the most recently sent one verifies the email
+        self.tokens.insert(email, (token, issued_at));
+        Ok(())
+    }
+
+    async fn remove_token(&mut self, email: &Email) -> Result<(), EmailVerificationTokenStoreError> {
+        self.tokens.remove(email);
+        Ok(())
+    }
+
+    async fn get_token(
+        &self,
+        email: &Email,
+    ) -> Result<(EmailVerificationToken, i64), EmailVerificationTokenStoreError> {
+        self.tokens.get(email).cloned().ok_or(EmailVerificationTokenStoreError::TokenNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_token_replaces_existing() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        store.add_token(email.clone(), EmailVerificationToken::default(), 1).await.unwrap();
+        let new_token = EmailVerificationToken::default();
+        let result = store.add_token(email.clone(), new_token.clone(), 2).await;
+        assert!(result.is_ok());
+        assert_eq!(store.get_token(&email).await, Ok((new_token, 2)));
+    }
+
+    #[tokio::test]
+    async fn test_remove_token() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        store.add_token(email.clone(), EmailVerificationToken::default(), 1).await.unwrap();
+        let result = store.remove_token(&email).await;
+        assert!(result.is_ok());
+        assert_eq!(store.get_token(&email).await, Err(EmailVerificationTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_get_token_non_existing() {
+        let store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        let result = store.get_token(&email).await;
+        assert_eq!(result, Err(EmailVerificationTokenStoreError::TokenNotFound));
+    }
+}
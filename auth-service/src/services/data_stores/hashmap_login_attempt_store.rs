@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptStore, LoginAttemptStoreError},
+        Email,
+    },
+    utils::constants::{LOGIN_FAILURE_WINDOW_SECONDS, LOGIN_LOCKOUT_ESCALATION_WINDOW_SECONDS},
+};
+
+// Every entry keeps the unix timestamp it expires at, mirroring the Redis TTLs
+#[derive(Default)]
+pub struct HashmapLoginAttemptStore {
+    failures: HashMap<Email, (u32, i64)>,
+    locks: HashMap<Email, i64>,
+    lock_counts: HashMap<Email, (u32, i64)>,
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for HashmapLoginAttemptStore {
+    async fn record_failure(&mut self, email: &Email) -> Result<u32, LoginAttemptStoreError> {
+        let now = Utc::now().timestamp();
+        let entry = self.failures
+            .entry(email.clone())
+            .or_insert((0, now + LOGIN_FAILURE_WINDOW_SECONDS as i64));
+        if entry.1 <= now {
+            *entry = (0, now + LOGIN_FAILURE_WINDOW_SECONDS as i64);
+        }
+        entry.0 += 1;
+        Ok(entry.0)
+    }
+
+    async fn clear_failures(&mut self, email: &Email) -> Result<(), LoginAttemptStoreError> {
+        self.failures.remove(email);
+        Ok(())
+    }
+
+    async fn lock(&mut self, email: &Email, duration_seconds: u64) -> Result<(), LoginAttemptStoreError> {
+        let now = Utc::now().timestamp();
+        let count = self.lock_count(email).await?;
+        self.locks.insert(email.clone(), now + duration_seconds as i64);
+        self.failures.remove(email);
+        self.lock_counts.insert(email.clone(), (count + 1, now + LOGIN_LOCKOUT_ESCALATION_WINDOW_SECONDS as i64));
+        Ok(())
+    }
+
+    async fn lock_count(&self, email: &Email) -> Result<u32, LoginAttemptStoreError> {
+        let now = Utc::now().timestamp();
+        Ok(self.lock_counts
+            .get(email)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map_or(0, |(count, _)| *count))
+    }
+
+    async fn lock_remaining(&self, email: &Email) -> Result<Option<u64>, LoginAttemptStoreError> {
+        let now = Utc::now().timestamp();
+        Ok(self.locks
+            .get(email)
+            .filter(|locked_until| **locked_until > now)
+            .map(|locked_until| (locked_until - now) as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse("test@example.com".to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_record_failure_counts_until_cleared() {
+        let mut store = HashmapLoginAttemptStore::default();
+
+        assert_eq!(store.record_failure(&email()).await, Ok(1));
+        assert_eq!(store.record_failure(&email()).await, Ok(2));
+
+        store.clear_failures(&email()).await.unwrap();
+        assert_eq!(store.record_failure(&email()).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_lock_resets_failures_and_counts_locks() {
+        let mut store = HashmapLoginAttemptStore::default();
+        assert_eq!(store.lock_remaining(&email()).await, Ok(None));
+        assert_eq!(store.lock_count(&email()).await, Ok(0));
+
+        store.record_failure(&email()).await.unwrap();
+        store.lock(&email(), 300).await.unwrap();
+
+        let remaining = store.lock_remaining(&email()).await.unwrap().unwrap();
+        assert!(remaining > 0 && remaining <= 300);
+        assert_eq!(store.lock_count(&email()).await, Ok(1));
+        assert_eq!(store.record_failure(&email()).await, Ok(1));
+
+        store.lock(&email(), 600).await.unwrap();
+        assert_eq!(store.lock_count(&email()).await, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn test_expired_lock_is_not_reported() {
+        let mut store = HashmapLoginAttemptStore::default();
+        store.lock(&email(), 0).await.unwrap();
+
+        assert_eq!(store.lock_remaining(&email()).await, Ok(None));
+        assert_eq!(store.lock_count(&email()).await, Ok(1));
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use crate::domain::data_stores::{MagicLinkId, MagicLinkNonce, MagicLinkStore, MagicLinkStoreError};
+
+#[derive(Default)]
+pub struct HashmapMagicLinkStore {
+    links: HashMap<MagicLinkId, MagicLinkNonce>,
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for HashmapMagicLinkStore {
+    async fn add_link(&mut self, link_id: MagicLinkId, nonce: MagicLinkNonce) -> Result<(), MagicLinkStoreError> {
+        self.links.insert(link_id, nonce);
+        Ok(())
+    }
+
+    async fn take_link(&mut self, link_id: &MagicLinkId) -> Result<MagicLinkNonce, MagicLinkStoreError> {
+        self.links.remove(link_id).ok_or(MagicLinkStoreError::LinkNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_take_link_is_single_use() {
+        let mut store = HashmapMagicLinkStore::default();
+        let link_id = MagicLinkId::default();
+        let nonce = MagicLinkNonce::default();
+
+        store.add_link(link_id.clone(), nonce.clone()).await.unwrap();
+        assert_eq!(store.take_link(&link_id).await, Ok(nonce));
+        assert_eq!(store.take_link(&link_id).await, Err(MagicLinkStoreError::LinkNotFound));
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{ClientSecret, OAuthClientStore, OAuthClientStoreError},
+    OAuthClient, OAuthClientId,
+};
+
+// Kept in registration order so clients are listed oldest first
+#[derive(Default)]
+pub struct HashmapOAuthClientStore {
+    clients: Vec<OAuthClient>,
+    secrets: HashMap<OAuthClientId, ClientSecret>,
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for HashmapOAuthClientStore {
+    async fn add_client(&mut self, client: OAuthClient, secret: Option<ClientSecret>) -> Result<(), OAuthClientStoreError> {
+        if let Some(secret) = secret {
+            self.secrets.insert(client.client_id.clone(), secret);
+        }
+        self.clients.push(client);
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &OAuthClientId) -> Result<OAuthClient, OAuthClientStoreError> {
+        self.clients
+            .iter()
+            .find(|client| &client.client_id == client_id)
+            .cloned()
+            .ok_or(OAuthClientStoreError::ClientNotFound)
+    }
+
+    async fn list_clients(&self) -> Result<Vec<OAuthClient>, OAuthClientStoreError> {
+        Ok(self.clients.clone())
+    }
+
+    async fn delete_client(&mut self, client_id: &OAuthClientId) -> Result<(), OAuthClientStoreError> {
+        let index = self
+            .clients
+            .iter()
+            .position(|client| &client.client_id == client_id)
+            .ok_or(OAuthClientStoreError::ClientNotFound)?;
+        self.clients.remove(index);
+        self.secrets.remove(client_id);
+        Ok(())
+    }
+
+    async fn verify_client_secret(&self, client_id: &OAuthClientId, secret: &ClientSecret) -> Result<(), OAuthClientStoreError> {
+        if !self.clients.iter().any(|client| &client.client_id == client_id) {
+            return Err(OAuthClientStoreError::ClientNotFound);
+        }
+        match self.secrets.get(client_id) {
+            Some(stored) if stored == secret => Ok(()),
+            _ => Err(OAuthClientStoreError::InvalidSecret),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Audience, OAuthClientType, RedirectUri, Scopes};
+
+    #[tokio::test]
+    async fn test_add_get_and_delete_client() {
+        let mut store = HashmapOAuthClientStore::default();
+        let client = OAuthClient {
+            client_id: OAuthClientId::default(),
+            name: "Example app".to_owned(),
+            client_type: OAuthClientType::Public,
+            redirect_uris: vec![RedirectUri::parse("https://app.example.com/callback".to_owned()).unwrap()],
+            allowed_scopes: Scopes::parse("profile").unwrap(),
+            allowed_audiences: Vec::new(),
+            created_at: 0,
+        };
+
+        store.add_client(client.clone(), None).await.unwrap();
+        assert_eq!(store.get_client(&client.client_id).await, Ok(client.clone()));
+        assert_eq!(store.list_clients().await, Ok(vec![client.clone()]));
+
+        store.delete_client(&client.client_id).await.unwrap();
+        assert_eq!(store.get_client(&client.client_id).await, Err(OAuthClientStoreError::ClientNotFound));
+        assert_eq!(store.delete_client(&client.client_id).await, Err(OAuthClientStoreError::ClientNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_verify_client_secret() {
+        let mut store = HashmapOAuthClientStore::default();
+        let client = OAuthClient {
+            client_id: OAuthClientId::default(),
+            name: "Batch jobs".to_owned(),
+            client_type: OAuthClientType::Machine,
+            redirect_uris: Vec::new(),
+            allowed_scopes: Scopes::parse("reports:read").unwrap(),
+            allowed_audiences: vec![Audience::parse("app-service".to_owned()).unwrap()],
+            created_at: 0,
+        };
+        let secret = ClientSecret::default();
+        store.add_client(client.clone(), Some(secret.clone())).await.unwrap();
+
+        assert_eq!(store.verify_client_secret(&client.client_id, &secret).await, Ok(()));
+        assert_eq!(
+            store.verify_client_secret(&client.client_id, &ClientSecret::default()).await,
+            Err(OAuthClientStoreError::InvalidSecret)
+        );
+        assert_eq!(
+            store.verify_client_secret(&OAuthClientId::default(), &secret).await,
+            Err(OAuthClientStoreError::ClientNotFound)
+        );
+    }
+}
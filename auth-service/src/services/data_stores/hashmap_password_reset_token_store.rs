@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapPasswordResetTokenStore {
+    tokens: HashMap<Email, PasswordResetToken>,
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        // Replace any existing token for this email so only the latest reset request is usable
+        self.tokens.insert(email, token);
+        Ok(())
+    }
+
+    async fn remove_token(&mut self, email: &Email) -> Result<(), PasswordResetTokenStoreError> {
+        self.tokens.remove(email);
+        Ok(())
+    }
+
+    async fn get_token(
+        &self,
+        email: &Email,
+    ) -> Result<PasswordResetToken, PasswordResetTokenStoreError> {
+        self.tokens.get(email).cloned().ok_or(PasswordResetTokenStoreError::TokenNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_token_replaces_existing() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        store.add_token(email.clone(), PasswordResetToken::default()).await.unwrap();
+        let new_token = PasswordResetToken::default();
+        let result = store.add_token(email.clone(), new_token.clone()).await;
+        assert!(result.is_ok());
+        assert_eq!(store.get_token(&email).await, Ok(new_token));
+    }
+
+    #[tokio::test]
+    async fn test_remove_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        store.add_token(email.clone(), PasswordResetToken::default()).await.unwrap();
+        let result = store.remove_token(&email).await;
+        assert!(result.is_ok());
+        assert_eq!(store.get_token(&email).await, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_get_token_non_existing() {
+        let store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        let result = store.get_token(&email).await;
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+}
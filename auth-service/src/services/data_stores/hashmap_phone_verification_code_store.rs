@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{PhoneVerificationCodeStore, PhoneVerificationCodeStoreError, TwoFACode},
+    Email, PhoneNumber,
+};
+
+#[derive(Default)]
+pub struct HashmapPhoneVerificationCodeStore {
+    codes: HashMap<Email, (PhoneNumber, TwoFACode)>,
+    attempts: HashMap<Email, u32>,
+}
+
+#[async_trait::async_trait]
+impl PhoneVerificationCodeStore for HashmapPhoneVerificationCodeStore {
+    async fn add_code(
+        &mut self,
+        email: Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), PhoneVerificationCodeStoreError> {
+        self.attempts.remove(&email);
+        self.codes.insert(email, (phone_number, code));
+        Ok(())
+    }
+
+    async fn remove_code(&mut self, email: &Email) -> Result<(), PhoneVerificationCodeStoreError> {
+        self.codes.remove(email);
+        self.attempts.remove(email);
+        Ok(())
+    }
+
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(PhoneNumber, TwoFACode), PhoneVerificationCodeStoreError> {
+        self.codes.get(email).cloned().ok_or(PhoneVerificationCodeStoreError::CodeNotFound)
+    }
+
+    async fn record_attempt(&mut self, email: &Email) -> Result<u32, PhoneVerificationCodeStoreError> {
+        let attempts = self.attempts.entry(email.clone()).or_insert(0);
+        *attempts += 1;
+        Ok(*attempts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_new_code_replaces_pending_one_and_its_attempts() {
+        let mut store = HashmapPhoneVerificationCodeStore::default();
+        let email = Email::parse("test@test.com".to_owned()).unwrap();
+        let first_number = PhoneNumber::parse("+14155552671".to_owned()).unwrap();
+        let second_number = PhoneNumber::parse("+442079460958".to_owned()).unwrap();
+
+        store.add_code(email.clone(), first_number, TwoFACode::default()).await.unwrap();
+        assert_eq!(store.record_attempt(&email).await.unwrap(), 1);
+        assert_eq!(store.record_attempt(&email).await.unwrap(), 2);
+
+        let code = TwoFACode::default();
+        store.add_code(email.clone(), second_number.clone(), code.clone()).await.unwrap();
+        assert_eq!(store.get_code(&email).await.unwrap(), (second_number, code));
+        assert_eq!(store.record_attempt(&email).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_remove_code() {
+        let mut store = HashmapPhoneVerificationCodeStore::default();
+        let email = Email::parse("test@test.com".to_owned()).unwrap();
+        let phone_number = PhoneNumber::parse("+14155552671".to_owned()).unwrap();
+
+        store.add_code(email.clone(), phone_number, TwoFACode::default()).await.unwrap();
+        store.remove_code(&email).await.unwrap();
+        assert_eq!(store.get_code(&email).await, Err(PhoneVerificationCodeStoreError::CodeNotFound));
+    }
+}
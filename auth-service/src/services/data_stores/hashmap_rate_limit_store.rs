@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::data_stores::{
+    RateLimitBucket, RateLimitDecision, RateLimitPolicy, RateLimitStore, RateLimitStoreError,
+};
+
+#[derive(Default)]
+pub struct HashmapRateLimitStore {
+    buckets: HashMap<String, RateLimitBucket>,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn take_token(&mut self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, RateLimitStoreError> {
+        let now_ms = Utc::now().timestamp_millis();
+        let bucket = self.buckets
+            .entry(key.to_owned())
+            .or_insert_with(|| RateLimitBucket::full(policy, now_ms));
+        Ok(bucket.take(policy, now_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_buckets_are_kept_per_key() {
+        let mut store = HashmapRateLimitStore::default();
+        let policy = RateLimitPolicy::per_minute(1);
+
+        assert!(store.take_token("a", &policy).await.unwrap().allowed);
+        assert!(!store.take_token("a", &policy).await.unwrap().allowed);
+        assert!(store.take_token("b", &policy).await.unwrap().allowed);
+    }
+}
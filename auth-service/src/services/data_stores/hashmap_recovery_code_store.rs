@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{RecoveryCode, RecoveryCodeStore, RecoveryCodeStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapRecoveryCodeStore {
+    codes: HashMap<Email, Vec<RecoveryCode>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashmapRecoveryCodeStore {
+    async fn set_codes(&mut self, email: &Email, codes: Vec<RecoveryCode>) -> Result<(), RecoveryCodeStoreError> {
+        self.codes.insert(email.clone(), codes);
+        Ok(())
+    }
+
+    async fn use_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), RecoveryCodeStoreError> {
+        let codes = self.codes.get_mut(email).ok_or(RecoveryCodeStoreError::CodeNotFound)?;
+        let position = codes.iter().position(|c| c == code).ok_or(RecoveryCodeStoreError::CodeNotFound)?;
+        codes.remove(position);
+        Ok(())
+    }
+
+    async fn count_codes(&self, email: &Email) -> Result<usize, RecoveryCodeStoreError> {
+        Ok(self.codes.get(email).map_or(0, Vec::len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_use_code_is_single_use() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        let code = RecoveryCode::default();
+
+        store.set_codes(&email, vec![code.clone(), RecoveryCode::default()]).await.unwrap();
+        assert_eq!(store.use_code(&email, &code).await, Ok(()));
+        assert_eq!(store.use_code(&email, &code).await, Err(RecoveryCodeStoreError::CodeNotFound));
+        assert_eq!(store.count_codes(&email).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_set_codes_replaces_previous_set() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        let old_code = RecoveryCode::default();
+        let new_code = RecoveryCode::default();
+
+        store.set_codes(&email, vec![old_code.clone()]).await.unwrap();
+        store.set_codes(&email, vec![new_code.clone()]).await.unwrap();
+        assert_eq!(store.use_code(&email, &old_code).await, Err(RecoveryCodeStoreError::CodeNotFound));
+        assert_eq!(store.use_code(&email, &new_code).await, Ok(()));
+    }
+}
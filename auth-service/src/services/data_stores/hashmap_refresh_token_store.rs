@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{RefreshToken, RefreshTokenStore, RefreshTokenStoreError},
+    Authentication, Email,
+};
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    // family id -> (owner, current token of the family, how the owner logged in)
+    families: HashMap<String, (Email, RefreshToken, Authentication)>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: RefreshToken,
+        authentication: Authentication,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.families.insert(token.family_id().to_owned(), (email, token, authentication));
+        Ok(())
+    }
+
+    async fn get_token(
+        &self,
+        family_id: &str,
+    ) -> Result<(Email, RefreshToken, Authentication), RefreshTokenStoreError> {
+        self.families.get(family_id).cloned().ok_or(RefreshTokenStoreError::FamilyNotFound)
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        self.families.remove(family_id);
+        Ok(())
+    }
+
+    async fn revoke_all_families(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        self.families.retain(|_, (owner, _, _)| owner != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::AuthMethod;
+
+    fn authentication() -> Authentication {
+        Authentication { auth_time: 1_700_000_000, methods: vec![AuthMethod::Password] }
+    }
+
+    #[tokio::test]
+    async fn test_add_token_replaces_family_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        let token = RefreshToken::default();
+        store.add_token(email.clone(), token.clone(), authentication()).await.unwrap();
+        let rotated = token.rotate();
+        store.add_token(email.clone(), rotated.clone(), authentication()).await.unwrap();
+        assert_eq!(store.get_token(token.family_id()).await, Ok((email, rotated, authentication())));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        let token = RefreshToken::default();
+        store.add_token(email.clone(), token.clone(), authentication()).await.unwrap();
+        store.revoke_family(token.family_id()).await.unwrap();
+        assert_eq!(store.get_token(token.family_id()).await, Err(RefreshTokenStoreError::FamilyNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_families() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        let other_email = Email::parse("other@test.com".to_string()).unwrap();
+        let first = RefreshToken::default();
+        let second = RefreshToken::default();
+        let other = RefreshToken::default();
+        store.add_token(email.clone(), first.clone(), authentication()).await.unwrap();
+        store.add_token(email.clone(), second.clone(), authentication()).await.unwrap();
+        store.add_token(other_email.clone(), other.clone(), authentication()).await.unwrap();
+
+        store.revoke_all_families(&email).await.unwrap();
+        assert!(store.get_token(first.family_id()).await.is_err());
+        assert!(store.get_token(second.family_id()).await.is_err());
+        assert_eq!(store.get_token(other.family_id()).await, Ok((other_email, other, authentication())));
+    }
+}
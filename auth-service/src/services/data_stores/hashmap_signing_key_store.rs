@@ -0,0 +1,78 @@
+use crate::domain::data_stores::{SigningKeyStore, SigningKeyStoreError, StoredSigningKey};
+
+#[derive(Default)]
+pub struct HashmapSigningKeyStore {
+    keys: Vec<StoredSigningKey>,
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for HashmapSigningKeyStore {
+    async fn get_keys(&self, now: i64) -> Result<Vec<StoredSigningKey>, SigningKeyStoreError> {
+        let mut keys: Vec<StoredSigningKey> = self
+            .keys
+            .iter()
+            .filter(|key| key.retire_at.is_none_or(|retire_at| retire_at > now))
+            .cloned()
+            .collect();
+        keys.sort_by_key(|key| key.activates_at);
+        Ok(keys)
+    }
+
+    async fn add_initial_keys(&mut self, keys: Vec<StoredSigningKey>) -> Result<(), SigningKeyStoreError> {
+        if self.keys.is_empty() {
+            self.keys = keys;
+        }
+        Ok(())
+    }
+
+    async fn add_next_key(&mut self, key: StoredSigningKey, retire_at: i64) -> Result<(), SigningKeyStoreError> {
+        if self.keys.iter().any(|existing| existing.kid == key.kid) {
+            return Err(SigningKeyStoreError::KeyAlreadyExists);
+        }
+        for existing in self.keys.iter_mut().filter(|existing| existing.retire_at.is_none()) {
+            existing.retire_at = Some(retire_at);
+        }
+        self.keys.push(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::Algorithm;
+
+    fn key(kid: &str, activates_at: i64) -> StoredSigningKey {
+        StoredSigningKey {
+            kid: kid.to_owned(),
+            algorithm: Algorithm::HS256,
+            private_key: kid.as_bytes().to_vec(),
+            activates_at,
+            retire_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_initial_keys_only_seed_an_empty_store() {
+        let mut store = HashmapSigningKeyStore::default();
+        store.add_initial_keys(vec![key("first", 0)]).await.unwrap();
+        store.add_initial_keys(vec![key("other", 0)]).await.unwrap();
+        assert_eq!(store.get_keys(0).await.unwrap(), vec![key("first", 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_next_key_retires_previous_keys() {
+        let mut store = HashmapSigningKeyStore::default();
+        store.add_initial_keys(vec![key("first", 0)]).await.unwrap();
+        store.add_next_key(key("second", 100), 1_000).await.unwrap();
+
+        let kids: Vec<_> = store.get_keys(999).await.unwrap().into_iter().map(|key| key.kid).collect();
+        assert_eq!(kids, vec!["first", "second"]);
+        assert_eq!(store.get_keys(1_000).await.unwrap(), vec![key("second", 100)]);
+
+        assert_eq!(
+            store.add_next_key(key("second", 200), 2_000).await,
+            Err(SigningKeyStoreError::KeyAlreadyExists)
+        );
+    }
+}
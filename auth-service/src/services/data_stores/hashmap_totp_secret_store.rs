@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{TotpSecret, TotpSecretStore, TotpSecretStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapTotpSecretStore {
+    // email -> (secret, enrollment confirmed)
+    secrets: HashMap<Email, (TotpSecret, bool)>,
+    last_used_steps: HashMap<Email, u64>,
+}
+
+#[async_trait::async_trait]
+impl TotpSecretStore for HashmapTotpSecretStore {
+    async fn add_secret(&mut self, email: &Email, secret: TotpSecret) -> Result<(), TotpSecretStoreError> {
+        self.secrets.insert(email.clone(), (secret, false));
+        self.last_used_steps.remove(email);
+        Ok(())
+    }
+
+    async fn get_secret(&self, email: &Email) -> Result<(TotpSecret, bool), TotpSecretStoreError> {
+        self.secrets.get(email).cloned().ok_or(TotpSecretStoreError::SecretNotFound)
+    }
+
+    async fn confirm_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        let (_, confirmed) = self.secrets.get_mut(email).ok_or(TotpSecretStoreError::SecretNotFound)?;
+        *confirmed = true;
+        Ok(())
+    }
+
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        self.secrets.remove(email);
+        self.last_used_steps.remove(email);
+        Ok(())
+    }
+
+    async fn use_time_step(&mut self, email: &Email, time_step: u64) -> Result<(), TotpSecretStoreError> {
+        if !self.secrets.contains_key(email) {
+            return Err(TotpSecretStoreError::SecretNotFound);
+        }
+        if self.last_used_steps.get(email).is_some_and(|last| *last >= time_step) {
+            return Err(TotpSecretStoreError::TimeStepAlreadyUsed);
+        }
+        self.last_used_steps.insert(email.clone(), time_step);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_confirm_secret() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        let secret = TotpSecret::default();
+
+        store.add_secret(&email, secret.clone()).await.unwrap();
+        assert_eq!(store.get_secret(&email).await, Ok((secret.clone(), false)));
+
+        store.confirm_secret(&email).await.unwrap();
+        assert_eq!(store.get_secret(&email).await, Ok((secret, true)));
+    }
+
+    #[tokio::test]
+    async fn test_add_secret_resets_confirmation() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        store.add_secret(&email, TotpSecret::default()).await.unwrap();
+        store.confirm_secret(&email).await.unwrap();
+
+        let secret = TotpSecret::default();
+        store.add_secret(&email, secret.clone()).await.unwrap();
+        assert_eq!(store.get_secret(&email).await, Ok((secret, false)));
+    }
+
+    #[tokio::test]
+    async fn test_time_steps_are_used_once() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        assert_eq!(store.use_time_step(&email, 10).await, Err(TotpSecretStoreError::SecretNotFound));
+
+        store.add_secret(&email, TotpSecret::default()).await.unwrap();
+        store.use_time_step(&email, 10).await.unwrap();
+        assert_eq!(store.use_time_step(&email, 10).await, Err(TotpSecretStoreError::TimeStepAlreadyUsed));
+        assert_eq!(store.use_time_step(&email, 9).await, Err(TotpSecretStoreError::TimeStepAlreadyUsed));
+        store.use_time_step(&email, 11).await.unwrap();
+
+        // A new enrollment starts over
+        store.add_secret(&email, TotpSecret::default()).await.unwrap();
+        store.use_time_step(&email, 5).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_secret() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        assert_eq!(store.get_secret(&email).await, Err(TotpSecretStoreError::SecretNotFound));
+        assert_eq!(store.confirm_secret(&email).await, Err(TotpSecretStoreError::SecretNotFound));
+
+        store.add_secret(&email, TotpSecret::default()).await.unwrap();
+        store.remove_secret(&email).await.unwrap();
+        assert_eq!(store.get_secret(&email).await, Err(TotpSecretStoreError::SecretNotFound));
+    }
+}
@@ -2,12 +2,13 @@ use std::collections::HashMap;
 
 use crate::domain::{
     data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+    AuthMethod, Email,
 };
 
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: HashMap<Email, (LoginAttemptId, TwoFACode, AuthMethod)>,
+    attempts: HashMap<Email, u32>,
 }
 
 #[async_trait::async_trait]
@@ -17,23 +18,32 @@ impl TwoFACodeStore for HashmapTwoFACodeStore{
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
+        first_factor: AuthMethod,
     ) -> Result<(), TwoFACodeStoreError> {
         // Replace any existing code for this email (allows re-login to invalidate old codes)
-        self.codes.insert(email, (login_attempt_id, code));
+        self.attempts.remove(&email);
+        self.codes.insert(email, (login_attempt_id, code, first_factor));
         Ok(())
     }
 
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
         self.codes.remove(&email);
+        self.attempts.remove(email);
         Ok(())
     }
 
     async fn get_code(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+    ) -> Result<(LoginAttemptId, TwoFACode, AuthMethod), TwoFACodeStoreError> {
         self.codes.get(email).cloned().ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)
     }
+
+    async fn record_attempt(&mut self, email: &Email) -> Result<u32, TwoFACodeStoreError> {
+        let attempts = self.attempts.entry(email.clone()).or_insert(0);
+        *attempts += 1;
+        Ok(*attempts)
+    }
 }
 
 #[cfg(test)]
@@ -46,7 +56,7 @@ mod tests {
         let email = Email::parse("test@test.com".to_string()).unwrap();
         let login_id = LoginAttemptId::default();
         let code = TwoFACode::default();
-        let result = store.add_code(email.clone(), login_id.clone(), code.clone()).await;
+        let result = store.add_code(email.clone(), login_id.clone(), code.clone(), AuthMethod::Password).await;
         assert!(result.is_ok());
     }
 
@@ -56,10 +66,10 @@ mod tests {
         let email = Email::parse("test@test.com".to_string()).unwrap();
         let login_id = LoginAttemptId::default();
         let code = TwoFACode::default();
-        store.add_code(email.clone(), login_id.clone(), code.clone()).await.unwrap();
+        store.add_code(email.clone(), login_id.clone(), code.clone(), AuthMethod::Password).await.unwrap();
         let new_login_id = LoginAttemptId::default();
         let new_code = TwoFACode::default();
-        let result = store.add_code(email.clone(), new_login_id.clone(), new_code.clone()).await;
+        let result = store.add_code(email.clone(), new_login_id.clone(), new_code.clone(), AuthMethod::Password).await;
         // Should succeed and replace the old code
         assert!(result.is_ok());
         // Verify the new code is stored
@@ -74,7 +84,7 @@ mod tests {
         let email = Email::parse("test@test.com".to_string()).unwrap();
         let login_id = LoginAttemptId::default();
         let code = TwoFACode::default();
-        store.add_code(email.clone(), login_id.clone(), code.clone()).await.unwrap();
+        store.add_code(email.clone(), login_id.clone(), code.clone(), AuthMethod::Password).await.unwrap();
         let result = store.remove_code(&email).await;
         assert!(result.is_ok());
         let get_result = store.get_code(&email).await;
@@ -95,9 +105,9 @@ mod tests {
         let email = Email::parse("test@test.com".to_string()).unwrap();
         let login_id = LoginAttemptId::default();
         let code = TwoFACode::default();
-        store.add_code(email.clone(), login_id.clone(), code.clone()).await.unwrap();
+        store.add_code(email.clone(), login_id.clone(), code.clone(), AuthMethod::Password).await.unwrap();
         let result = store.get_code(&email).await;
-        assert_eq!(result, Ok((login_id, code)));
+        assert_eq!(result, Ok((login_id, code, AuthMethod::Password)));
     }
 
     #[tokio::test]
@@ -107,4 +117,20 @@ mod tests {
         let result = store.get_code(&email).await;
         assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
     }
+
+    #[tokio::test]
+    async fn test_record_attempt_resets_with_new_code() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse("test@test.com".to_string()).unwrap();
+        store.add_code(email.clone(), LoginAttemptId::default(), TwoFACode::default(), AuthMethod::Password).await.unwrap();
+
+        assert_eq!(store.record_attempt(&email).await, Ok(1));
+        assert_eq!(store.record_attempt(&email).await, Ok(2));
+
+        store.add_code(email.clone(), LoginAttemptId::default(), TwoFACode::default(), AuthMethod::Password).await.unwrap();
+        assert_eq!(store.record_attempt(&email).await, Ok(1));
+
+        store.remove_code(&email).await.unwrap();
+        assert_eq!(store.record_attempt(&email).await, Ok(1));
+    }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use crate::domain::{User, UserStore, UserStoreError};
+use crate::domain::{Password, PhoneNumber, Role, TwoFAChannel, User, UserStore, UserStoreError};
 
 #[derive(Default)]
 pub struct HashmapUserStore {
@@ -32,11 +32,74 @@ impl UserStore for HashmapUserStore {
             Err(e) => Err(e),
         }
     }
+
+    async fn update_password(&mut self, email: &str, password: Password) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.password = password;
+        Ok(())
+    }
+
+    async fn mark_email_verified(&mut self, email: &str) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.verified = true;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &str) -> Result<(), UserStoreError> {
+        self.users.remove(email).map(|_| ()).ok_or(UserStoreError::UserNotFound)
+    }
+
+    async fn set_requires_2fa(&mut self, email: &str, requires_2fa: bool) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.requires_2fa = requires_2fa;
+        Ok(())
+    }
+
+    async fn set_roles(&mut self, email: &str, roles: Vec<Role>) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.roles = roles;
+        Ok(())
+    }
+
+    async fn set_disabled(&mut self, email: &str, disabled: bool) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.disabled = disabled;
+        Ok(())
+    }
+
+    async fn set_phone_number(&mut self, email: &str, phone_number: Option<PhoneNumber>) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.phone_number = phone_number;
+        user.phone_verified = false;
+        user.two_fa_channel = TwoFAChannel::Email;
+        Ok(())
+    }
+
+    async fn mark_phone_verified(&mut self, email: &str) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.phone_verified = true;
+        Ok(())
+    }
+
+    async fn set_two_fa_channel(&mut self, email: &str, channel: TwoFAChannel) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.two_fa_channel = channel;
+        Ok(())
+    }
+
+    async fn list_users(&self, email_prefix: &str, offset: u64, limit: u64) -> Result<(Vec<User>, u64), UserStoreError> {
+        let mut matching: Vec<&User> = self.users.values().filter(|user| user.email.as_ref().starts_with(email_prefix)).collect();
+        matching.sort_by(|a, b| a.email.as_ref().cmp(b.email.as_ref()));
+
+        let total = matching.len() as u64;
+        let page = matching.into_iter().skip(offset as usize).take(limit as usize).cloned().collect();
+        Ok((page, total))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::domain::{Email, Password};
+    use crate::domain::Email;
 
     use super::*;
 
@@ -46,6 +109,12 @@ mod tests {
             email: Email("test@mytest.com".to_string()),
             password: Password("password123".to_string()),
             requires_2fa: false,
+            verified: false,
+            disabled: false,
+            roles: Vec::new(),
+            phone_number: None,
+            phone_verified: false,
+            two_fa_channel: TwoFAChannel::Email,
         };
         let mut store = HashmapUserStore::default();
         let result = store.add_user(user).await;
@@ -58,6 +127,12 @@ mod tests {
             email: Email("test@mytest.com".to_string()),
             password: Password("password123".to_string()),
             requires_2fa: false,
+            verified: false,
+            disabled: false,
+            roles: Vec::new(),
+            phone_number: None,
+            phone_verified: false,
+            two_fa_channel: TwoFAChannel::Email,
         };
         let mut store = HashmapUserStore::default();
         let add_result = store.add_user(user.clone()).await;
@@ -73,6 +148,12 @@ mod tests {
             email: Email("test@mytest.com".to_string()),
             password: Password("password123".to_string()),
             requires_2fa: false,
+            verified: false,
+            disabled: false,
+            roles: Vec::new(),
+            phone_number: None,
+            phone_verified: false,
+            two_fa_channel: TwoFAChannel::Email,
         };
         let mut store = HashmapUserStore::default();
         let add_result = store.add_user(user.clone()).await;
@@ -84,4 +165,135 @@ mod tests {
         let not_found_result = store.validate_user("nonexistent@test.com", "password").await;
         assert_eq!(not_found_result, Err(UserStoreError::UserNotFound));
     }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let user = User {
+            email: Email("test@mytest.com".to_string()),
+            password: Password("password123".to_string()),
+            requires_2fa: false,
+            verified: false,
+            disabled: false,
+            roles: Vec::new(),
+            phone_number: None,
+            phone_verified: false,
+            two_fa_channel: TwoFAChannel::Email,
+        };
+        let mut store = HashmapUserStore::default();
+        store.add_user(user.clone()).await.unwrap();
+        let update_result = store.update_password(user.email.as_ref(), Password("newpassword123".to_string())).await;
+        assert!(update_result.is_ok());
+        assert_eq!(store.validate_user(user.email.as_ref(), "password123").await, Err(UserStoreError::InvalidCredentials));
+        assert!(store.validate_user(user.email.as_ref(), "newpassword123").await.is_ok());
+        let not_found_result = store.update_password("nonexistent@test.com", Password("newpassword123".to_string())).await;
+        assert_eq!(not_found_result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_mark_email_verified() {
+        let user = User::new(Email("test@mytest.com".to_string()), Password("password123".to_string()), false);
+        let mut store = HashmapUserStore::default();
+        store.add_user(user.clone()).await.unwrap();
+        assert!(!store.get_user(user.email.as_ref()).await.unwrap().verified);
+        let result = store.mark_email_verified(user.email.as_ref()).await;
+        assert!(result.is_ok());
+        assert!(store.get_user(user.email.as_ref()).await.unwrap().verified);
+        let not_found_result = store.mark_email_verified("nonexistent@test.com").await;
+        assert_eq!(not_found_result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let user = User::new(Email("test@mytest.com".to_string()), Password("password123".to_string()), false);
+        let mut store = HashmapUserStore::default();
+        store.add_user(user.clone()).await.unwrap();
+        let result = store.delete_user(user.email.as_ref()).await;
+        assert!(result.is_ok());
+        assert_eq!(store.get_user(user.email.as_ref()).await, Err(UserStoreError::UserNotFound));
+        let not_found_result = store.delete_user(user.email.as_ref()).await;
+        assert_eq!(not_found_result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_requires_2fa() {
+        let user = User::new(Email("test@mytest.com".to_string()), Password("password123".to_string()), false);
+        let mut store = HashmapUserStore::default();
+        store.add_user(user.clone()).await.unwrap();
+        store.set_requires_2fa(user.email.as_ref(), true).await.unwrap();
+        assert!(store.get_user(user.email.as_ref()).await.unwrap().requires_2fa);
+        let not_found_result = store.set_requires_2fa("missing@mytest.com", true).await;
+        assert_eq!(not_found_result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_roles() {
+        let user = User::new(Email("test@mytest.com".to_string()), Password("password123".to_string()), false);
+        let mut store = HashmapUserStore::default();
+        store.add_user(user.clone()).await.unwrap();
+        assert!(store.get_user(user.email.as_ref()).await.unwrap().roles.is_empty());
+        store.set_roles(user.email.as_ref(), vec![Role::admin()]).await.unwrap();
+        assert_eq!(store.get_user(user.email.as_ref()).await.unwrap().roles, vec![Role::admin()]);
+        store.set_roles(user.email.as_ref(), Vec::new()).await.unwrap();
+        assert!(store.get_user(user.email.as_ref()).await.unwrap().roles.is_empty());
+        let not_found_result = store.set_roles("nonexistent@test.com", vec![Role::admin()]).await;
+        assert_eq!(not_found_result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_disabled() {
+        let user = User::new(Email("test@mytest.com".to_string()), Password("password123".to_string()), false);
+        let mut store = HashmapUserStore::default();
+        store.add_user(user.clone()).await.unwrap();
+        store.set_disabled(user.email.as_ref(), true).await.unwrap();
+        assert!(store.get_user(user.email.as_ref()).await.unwrap().disabled);
+        store.set_disabled(user.email.as_ref(), false).await.unwrap();
+        assert!(!store.get_user(user.email.as_ref()).await.unwrap().disabled);
+        let not_found_result = store.set_disabled("nonexistent@test.com", true).await;
+        assert_eq!(not_found_result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_changing_phone_number_resets_verification_and_channel() {
+        let user = User::new(Email("test@mytest.com".to_string()), Password("password123".to_string()), true);
+        let mut store = HashmapUserStore::default();
+        store.add_user(user.clone()).await.unwrap();
+        let phone_number = PhoneNumber::parse("+14155552671".to_owned()).unwrap();
+
+        store.set_phone_number(user.email.as_ref(), Some(phone_number.clone())).await.unwrap();
+        store.mark_phone_verified(user.email.as_ref()).await.unwrap();
+        store.set_two_fa_channel(user.email.as_ref(), TwoFAChannel::Sms).await.unwrap();
+        let stored = store.get_user(user.email.as_ref()).await.unwrap();
+        assert_eq!((stored.phone_number.clone(), stored.phone_verified, stored.two_fa_channel), (Some(phone_number), true, TwoFAChannel::Sms));
+
+        let new_number = PhoneNumber::parse("+442079460958".to_owned()).unwrap();
+        store.set_phone_number(user.email.as_ref(), Some(new_number.clone())).await.unwrap();
+        let stored = store.get_user(user.email.as_ref()).await.unwrap();
+        assert_eq!((stored.phone_number.clone(), stored.phone_verified, stored.two_fa_channel), (Some(new_number), false, TwoFAChannel::Email));
+
+        let not_found_result = store.mark_phone_verified("nonexistent@test.com").await;
+        assert_eq!(not_found_result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_list_users() {
+        let mut store = HashmapUserStore::default();
+        for email in ["carol@mytest.com", "alice@mytest.com", "bob@mytest.com", "alex@mytest.com"] {
+            let user = User::new(Email(email.to_string()), Password("password123".to_string()), false);
+            store.add_user(user).await.unwrap();
+        }
+
+        let (users, total) = store.list_users("", 1, 2).await.unwrap();
+        let emails: Vec<&str> = users.iter().map(|user| user.email.as_ref()).collect();
+        assert_eq!(emails, vec!["alice@mytest.com", "bob@mytest.com"]);
+        assert_eq!(total, 4);
+
+        let (users, total) = store.list_users("al", 0, 10).await.unwrap();
+        let emails: Vec<&str> = users.iter().map(|user| user.email.as_ref()).collect();
+        assert_eq!(emails, vec!["alex@mytest.com", "alice@mytest.com"]);
+        assert_eq!(total, 2);
+
+        let (users, total) = store.list_users("zed", 0, 10).await.unwrap();
+        assert!(users.is_empty());
+        assert_eq!(total, 0);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use crate::domain::data_stores::{
+    WebauthnCeremony, WebauthnChallenge, WebauthnChallengeStore, WebauthnChallengeStoreError,
+};
+
+#[derive(Default)]
+pub struct HashmapWebauthnChallengeStore {
+    challenges: HashMap<String, WebauthnCeremony>,
+}
+
+#[async_trait::async_trait]
+impl WebauthnChallengeStore for HashmapWebauthnChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: WebauthnChallenge,
+        ceremony: WebauthnCeremony,
+    ) -> Result<(), WebauthnChallengeStoreError> {
+        self.challenges.insert(challenge.as_ref().to_owned(), ceremony);
+        Ok(())
+    }
+
+    async fn take_challenge(
+        &mut self,
+        challenge: &WebauthnChallenge,
+    ) -> Result<WebauthnCeremony, WebauthnChallengeStoreError> {
+        self.challenges
+            .remove(challenge.as_ref())
+            .ok_or(WebauthnChallengeStoreError::ChallengeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+
+    #[tokio::test]
+    async fn test_take_challenge_is_single_use() {
+        let mut store = HashmapWebauthnChallengeStore::default();
+        let challenge = WebauthnChallenge::default();
+        let ceremony = WebauthnCeremony::Registration(Email::parse("test@test.com".to_owned()).unwrap());
+
+        store.add_challenge(challenge.clone(), ceremony.clone()).await.unwrap();
+        assert_eq!(store.take_challenge(&challenge).await, Ok(ceremony));
+        assert_eq!(store.take_challenge(&challenge).await, Err(WebauthnChallengeStoreError::ChallengeNotFound));
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{WebauthnCredential, WebauthnCredentialStore, WebauthnCredentialStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapWebauthnCredentialStore {
+    credentials: HashMap<String, WebauthnCredential>,
+}
+
+#[async_trait::async_trait]
+impl WebauthnCredentialStore for HashmapWebauthnCredentialStore {
+    async fn add_credential(&mut self, credential: WebauthnCredential) -> Result<(), WebauthnCredentialStoreError> {
+        if self.credentials.contains_key(&credential.credential_id) {
+            return Err(WebauthnCredentialStoreError::CredentialAlreadyExists);
+        }
+        self.credentials.insert(credential.credential_id.clone(), credential);
+        Ok(())
+    }
+
+    async fn get_credential(&self, credential_id: &str) -> Result<WebauthnCredential, WebauthnCredentialStoreError> {
+        self.credentials
+            .get(credential_id)
+            .cloned()
+            .ok_or(WebauthnCredentialStoreError::CredentialNotFound)
+    }
+
+    async fn get_credentials(&self, email: &Email) -> Result<Vec<WebauthnCredential>, WebauthnCredentialStoreError> {
+        Ok(self.credentials.values().filter(|c| &c.email == email).cloned().collect())
+    }
+
+    async fn update_sign_count(&mut self, credential_id: &str, sign_count: u32) -> Result<(), WebauthnCredentialStoreError> {
+        let credential = self
+            .credentials
+            .get_mut(credential_id)
+            .ok_or(WebauthnCredentialStoreError::CredentialNotFound)?;
+        if sign_count <= credential.sign_count {
+            return Err(WebauthnCredentialStoreError::SignCountNotIncreased);
+        }
+        credential.sign_count = sign_count;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credential(credential_id: &str, email: &str) -> WebauthnCredential {
+        WebauthnCredential {
+            credential_id: credential_id.to_owned(),
+            email: Email::parse(email.to_owned()).unwrap(),
+            public_key: vec![4; 65],
+            sign_count: 0,
+            name: "Security key".to_owned(),
+            created_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_credential() {
+        let mut store = HashmapWebauthnCredentialStore::default();
+        let credential = credential("cred-1", "test@test.com");
+        store.add_credential(credential.clone()).await.unwrap();
+        assert_eq!(store.get_credential("cred-1").await, Ok(credential.clone()));
+        assert_eq!(
+            store.add_credential(credential).await,
+            Err(WebauthnCredentialStoreError::CredentialAlreadyExists)
+        );
+        assert_eq!(store.get_credential("cred-2").await, Err(WebauthnCredentialStoreError::CredentialNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_get_credentials_for_user() {
+        let mut store = HashmapWebauthnCredentialStore::default();
+        store.add_credential(credential("cred-1", "test@test.com")).await.unwrap();
+        store.add_credential(credential("cred-2", "test@test.com")).await.unwrap();
+        store.add_credential(credential("cred-3", "other@test.com")).await.unwrap();
+
+        let email = Email::parse("test@test.com".to_owned()).unwrap();
+        let mut ids: Vec<_> = store.get_credentials(&email).await.unwrap().into_iter().map(|c| c.credential_id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["cred-1", "cred-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_sign_count() {
+        let mut store = HashmapWebauthnCredentialStore::default();
+        store.add_credential(credential("cred-1", "test@test.com")).await.unwrap();
+        store.update_sign_count("cred-1", 7).await.unwrap();
+        assert_eq!(store.get_credential("cred-1").await.unwrap().sign_count, 7);
+        for sign_count in [7, 6] {
+            assert_eq!(
+                store.update_sign_count("cred-1", sign_count).await,
+                Err(WebauthnCredentialStoreError::SignCountNotIncreased)
+            );
+        }
+        assert_eq!(
+            store.update_sign_count("cred-2", 1).await,
+            Err(WebauthnCredentialStoreError::CredentialNotFound)
+        );
+    }
+}
@@ -1,9 +1,10 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use crate::domain::{BannedTokenStoreError, BannedTokenStore};
 
 #[derive(Default)]
 pub struct HashsetBannedTokenStore {
     tokens: HashSet<String>,
+    user_revocations: HashMap<String, i64>,
 }
 
 #[async_trait::async_trait]
@@ -20,17 +21,26 @@ impl BannedTokenStore for HashsetBannedTokenStore {
     async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
         Ok(self.tokens.contains(token))
     }
+
+    async fn revoke_user_tokens(&mut self, email: &str, issued_before: i64) -> Result<(), BannedTokenStoreError> {
+        self.user_revocations.insert(email.to_owned(), issued_before);
+        Ok(())
+    }
+
+    async fn get_user_revocation(&self, email: &str) -> Result<Option<i64>, BannedTokenStoreError> {
+        Ok(self.user_revocations.get(email).copied())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashSet;
 
     #[tokio::test]
     async fn test_add_token_new() {
         let mut store = HashsetBannedTokenStore {
             tokens: HashSet::new(),
+            user_revocations: HashMap::new(),
         };
         let result = store.add_token("token1".to_string()).await;
         assert!(result.is_ok());
@@ -41,6 +51,7 @@ mod tests {
     async fn test_add_token_existing() {
         let mut store = HashsetBannedTokenStore {
             tokens: HashSet::new(),
+            user_revocations: HashMap::new(),
         };
         store.add_token("token1".to_string()).await.unwrap();
         let result = store.add_token("token1".to_string()).await;
@@ -51,6 +62,7 @@ mod tests {
     async fn test_contains_token_true() {
         let mut store = HashsetBannedTokenStore {
             tokens: HashSet::new(),
+            user_revocations: HashMap::new(),
         };
         store.add_token("token1".to_string()).await.unwrap();
         let result = store.contains_token("token1").await;
@@ -61,8 +73,18 @@ mod tests {
     async fn test_contains_token_false() {
         let store = HashsetBannedTokenStore {
             tokens: HashSet::new(),
+            user_revocations: HashMap::new(),
         };
         let result = store.contains_token("token1").await;
         assert_eq!(result, Ok(false));
     }
+
+    #[tokio::test]
+    async fn test_revoke_user_tokens() {
+        let mut store = HashsetBannedTokenStore::default();
+        assert_eq!(store.get_user_revocation("test@test.com").await, Ok(None));
+        store.revoke_user_tokens("test@test.com", 1_700_000_000).await.unwrap();
+        assert_eq!(store.get_user_revocation("test@test.com").await, Ok(Some(1_700_000_000)));
+        assert_eq!(store.get_user_revocation("other@test.com").await, Ok(None));
+    }
 }
\ No newline at end of file
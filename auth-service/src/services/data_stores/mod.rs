@@ -1,13 +1,77 @@
 mod hashmap_user_store;
+mod hashmap_api_key_store;
+mod hashmap_authorization_grant_store;
 mod hashset_banned_token_store;
 pub mod hashmap_two_fa_code_store;
+mod hashmap_password_reset_token_store;
+mod hashmap_email_verification_token_store;
+mod hashmap_email_outbox_store;
+mod hashmap_magic_link_store;
+mod hashmap_oauth_client_store;
+mod hashmap_phone_verification_code_store;
+mod hashmap_login_attempt_store;
+mod hashmap_rate_limit_store;
+mod hashmap_refresh_token_store;
+mod hashmap_signing_key_store;
+mod hashmap_recovery_code_store;
+mod hashmap_totp_secret_store;
+mod hashmap_webauthn_challenge_store;
+mod hashmap_webauthn_credential_store;
 mod postgres_user_store;
+mod postgres_api_key_store;
+mod postgres_email_outbox_store;
+mod postgres_oauth_client_store;
+mod postgres_recovery_code_store;
+mod postgres_signing_key_store;
+mod postgres_totp_secret_store;
+mod postgres_webauthn_credential_store;
+mod redis_authorization_grant_store;
 mod redis_banned_token_store;
 mod redis_two_fa_code_store;
+mod redis_password_reset_token_store;
+mod redis_email_verification_token_store;
+mod redis_magic_link_store;
+mod redis_phone_verification_code_store;
+mod redis_login_attempt_store;
+mod redis_rate_limit_store;
+mod redis_refresh_token_store;
+mod redis_webauthn_challenge_store;
 
 pub use hashmap_user_store::HashmapUserStore;
+pub use hashmap_api_key_store::HashmapApiKeyStore;
+pub use hashmap_authorization_grant_store::HashmapAuthorizationGrantStore;
 pub use hashset_banned_token_store::HashsetBannedTokenStore;
 pub use hashmap_two_fa_code_store::HashmapTwoFACodeStore;
+pub use hashmap_password_reset_token_store::HashmapPasswordResetTokenStore;
+pub use hashmap_email_verification_token_store::HashmapEmailVerificationTokenStore;
+pub use hashmap_email_outbox_store::HashmapEmailOutboxStore;
+pub use hashmap_magic_link_store::HashmapMagicLinkStore;
+pub use hashmap_oauth_client_store::HashmapOAuthClientStore;
+pub use hashmap_phone_verification_code_store::HashmapPhoneVerificationCodeStore;
+pub use hashmap_login_attempt_store::HashmapLoginAttemptStore;
+pub use hashmap_rate_limit_store::HashmapRateLimitStore;
+pub use hashmap_refresh_token_store::HashmapRefreshTokenStore;
+pub use hashmap_signing_key_store::HashmapSigningKeyStore;
+pub use hashmap_recovery_code_store::HashmapRecoveryCodeStore;
+pub use hashmap_totp_secret_store::HashmapTotpSecretStore;
+pub use hashmap_webauthn_challenge_store::HashmapWebauthnChallengeStore;
+pub use hashmap_webauthn_credential_store::HashmapWebauthnCredentialStore;
 pub use postgres_user_store::PostgresUserStore;
+pub use postgres_api_key_store::PostgresApiKeyStore;
+pub use postgres_email_outbox_store::PostgresEmailOutboxStore;
+pub use postgres_oauth_client_store::PostgresOAuthClientStore;
+pub use postgres_recovery_code_store::PostgresRecoveryCodeStore;
+pub use postgres_signing_key_store::PostgresSigningKeyStore;
+pub use postgres_totp_secret_store::PostgresTotpSecretStore;
+pub use postgres_webauthn_credential_store::PostgresWebauthnCredentialStore;
+pub use redis_authorization_grant_store::RedisAuthorizationGrantStore;
 pub use redis_banned_token_store::RedisBannedTokenStore;
-pub use redis_two_fa_code_store::RedisTwoFACodeStore;
\ No newline at end of file
+pub use redis_two_fa_code_store::RedisTwoFACodeStore;
+pub use redis_password_reset_token_store::RedisPasswordResetTokenStore;
+pub use redis_email_verification_token_store::RedisEmailVerificationTokenStore;
+pub use redis_magic_link_store::RedisMagicLinkStore;
+pub use redis_phone_verification_code_store::RedisPhoneVerificationCodeStore;
+pub use redis_login_attempt_store::RedisLoginAttemptStore;
+pub use redis_rate_limit_store::RedisRateLimitStore;
+pub use redis_refresh_token_store::RedisRefreshTokenStore;
+pub use redis_webauthn_challenge_store::RedisWebauthnChallengeStore;
\ No newline at end of file
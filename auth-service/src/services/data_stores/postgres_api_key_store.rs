@@ -0,0 +1,155 @@
+use color_eyre::eyre::{eyre, Context};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{ApiKeyStore, ApiKeyStoreError},
+    ApiKey, ApiKeyId, ApiKeyToken, Email, Scopes,
+};
+
+pub struct PostgresApiKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresApiKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for PostgresApiKeyStore {
+    #[tracing::instrument(name = "Adding API key to PostgreSQL", skip_all)]
+    async fn add_key(&mut self, key: ApiKey, token: &ApiKeyToken) -> Result<(), ApiKeyStoreError> {
+        let scopes: Vec<String> = key.scopes.iter().map(str::to_owned).collect();
+        sqlx::query!(
+            r#"
+            INSERT INTO api_keys (id, email, name, secret_hash, scopes, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, to_timestamp($6), to_timestamp($7))
+            "#,
+            key.id.as_ref(),
+            key.email.as_ref(),
+            key.name,
+            token.secret_hash(),
+            &scopes,
+            key.created_at as f64,
+            key.expires_at.map(|expires_at| expires_at as f64)
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to add API key")
+        .map_err(ApiKeyStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing API keys from PostgreSQL", skip_all)]
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let rows = sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            SELECT id, email, name, scopes,
+                EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!",
+                EXTRACT(EPOCH FROM expires_at)::BIGINT AS expires_at,
+                EXTRACT(EPOCH FROM last_used_at)::BIGINT AS last_used_at
+            FROM api_keys
+            WHERE email = $1
+            ORDER BY created_at DESC, id
+            "#,
+            email.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("failed to list API keys")
+        .map_err(ApiKeyStoreError::UnexpectedError)?;
+
+        rows.into_iter().map(ApiKey::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Deleting API key from PostgreSQL", skip_all)]
+    async fn delete_key(&mut self, email: &Email, id: &ApiKeyId) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM api_keys WHERE id = $1 AND email = $2
+            "#,
+            id.as_ref(),
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to delete API key")
+        .map_err(ApiKeyStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting all API keys of user from PostgreSQL", skip_all)]
+    async fn delete_all_keys(&mut self, email: &Email) -> Result<(), ApiKeyStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM api_keys WHERE email = $1
+            "#,
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to delete API keys")
+        .map_err(ApiKeyStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    // The secret hash is deterministic, so the lookup, the check and the update are a single statement
+    #[tracing::instrument(name = "Using API key in PostgreSQL", skip_all)]
+    async fn use_key(&mut self, token: &ApiKeyToken) -> Result<ApiKey, ApiKeyStoreError> {
+        let row = sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            UPDATE api_keys SET last_used_at = NOW()
+            WHERE id = $1 AND secret_hash = $2 AND (expires_at IS NULL OR expires_at > NOW())
+            RETURNING id, email, name, scopes,
+                EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!",
+                EXTRACT(EPOCH FROM expires_at)::BIGINT AS expires_at,
+                EXTRACT(EPOCH FROM last_used_at)::BIGINT AS last_used_at
+            "#,
+            token.id().as_ref(),
+            token.secret_hash()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("failed to use API key")
+        .map_err(ApiKeyStoreError::UnexpectedError)?
+        .ok_or(ApiKeyStoreError::KeyNotFound)?;
+
+        ApiKey::try_from(row)
+    }
+}
+
+struct ApiKeyRow {
+    id: String,
+    email: String,
+    name: String,
+    scopes: Vec<String>,
+    created_at: i64,
+    expires_at: Option<i64>,
+    last_used_at: Option<i64>,
+}
+
+impl TryFrom<ApiKeyRow> for ApiKey {
+    type Error = ApiKeyStoreError;
+
+    fn try_from(row: ApiKeyRow) -> Result<Self, Self::Error> {
+        let invalid = |e| ApiKeyStoreError::UnexpectedError(eyre!("Invalid stored API key: {}", e));
+        Ok(ApiKey {
+            id: ApiKeyId::parse(row.id).map_err(invalid)?,
+            email: Email::parse(row.email).map_err(|e| invalid(eyre!(e)))?,
+            name: row.name,
+            scopes: Scopes::from_tokens(row.scopes).map_err(invalid)?,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+        })
+    }
+}
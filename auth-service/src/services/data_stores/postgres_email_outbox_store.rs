@@ -0,0 +1,260 @@
+use color_eyre::eyre::{eyre, Context, Result};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{EmailOutboxStore, EmailOutboxStoreError, OutboxEmail, OutboxEmailId, OutboxStatus},
+    Email, EmailContent,
+};
+
+pub struct PostgresEmailOutboxStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for PostgresEmailOutboxStore {
+    #[tracing::instrument(name = "Enqueuing email in PostgreSQL", skip_all)]
+    async fn enqueue(&mut self, recipient: &Email, content: &EmailContent, expires_at: Option<i64>) -> Result<OutboxEmailId, EmailOutboxStoreError> {
+        let id = OutboxEmailId::default();
+        sqlx::query!(
+            r#"
+            INSERT INTO email_outbox (id, recipient, subject, text_body, html_body, expires_at)
+            VALUES ($1, $2, $3, $4, $5, to_timestamp($6))
+            "#,
+            id.as_ref(),
+            recipient.as_ref(),
+            content.subject,
+            content.text,
+            content.html,
+            expires_at.map(|expires_at| expires_at as f64)
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to enqueue email")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        Ok(id)
+    }
+
+    // SKIP LOCKED lets several instances drain the outbox without sending an email twice.
+    // UPDATE ... RETURNING has no order, so the outer query hands emails out in the order they were queued.
+    #[tracing::instrument(name = "Claiming due emails in PostgreSQL", skip_all)]
+    async fn claim_due(&mut self, limit: u32, lease_seconds: u64) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let rows = sqlx::query_as!(
+            OutboxEmailRow,
+            r#"
+            WITH claimed AS (
+                UPDATE email_outbox
+                SET attempts = attempts + 1, next_attempt_at = NOW() + make_interval(secs => $2)
+                WHERE id IN (
+                    SELECT id FROM email_outbox
+                    WHERE status = 'pending' AND next_attempt_at <= NOW()
+                    ORDER BY next_attempt_at
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING *
+            )
+            SELECT id AS "id!", recipient AS "recipient!", subject AS "subject!", text_body AS "text_body!",
+                html_body AS "html_body!", status AS "status!", attempts AS "attempts!", last_error,
+                EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!",
+                EXTRACT(EPOCH FROM next_attempt_at)::BIGINT AS "next_attempt_at!",
+                FLOOR(EXTRACT(EPOCH FROM expires_at))::BIGINT AS expires_at
+            FROM claimed
+            ORDER BY created_at, id
+            "#,
+            limit as i64,
+            lease_seconds as f64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("failed to claim due emails")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        rows.into_iter().map(OutboxEmail::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Removing sent email from PostgreSQL", skip_all)]
+    async fn mark_sent(&mut self, id: &OutboxEmailId) -> Result<(), EmailOutboxStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM email_outbox WHERE id = $1
+            "#,
+            id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to remove sent email")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxStoreError::EmailNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Recording failed email in PostgreSQL", skip_all)]
+    async fn mark_failed(&mut self, id: &OutboxEmailId, error: &str, retry_in_seconds: Option<u64>) -> Result<(), EmailOutboxStoreError> {
+        let result = match retry_in_seconds {
+            Some(seconds) => sqlx::query!(
+                r#"
+                UPDATE email_outbox SET last_error = $2, next_attempt_at = NOW() + make_interval(secs => $3)
+                WHERE id = $1
+                "#,
+                id.as_ref(),
+                error,
+                seconds as f64
+            )
+            .execute(&self.pool)
+            .await,
+            None => sqlx::query!(
+                r#"
+                UPDATE email_outbox SET last_error = $2, status = 'dead'
+                WHERE id = $1
+                "#,
+                id.as_ref(),
+                error
+            )
+            .execute(&self.pool)
+            .await,
+        }
+        .wrap_err("failed to record email failure")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxStoreError::EmailNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing outbox emails from PostgreSQL", skip_all)]
+    async fn list(&self, status: OutboxStatus, offset: u64, limit: u64) -> Result<(Vec<OutboxEmail>, u64), EmailOutboxStoreError> {
+        let rows = sqlx::query_as!(
+            OutboxEmailRow,
+            r#"
+            SELECT id, recipient, subject, text_body, html_body, status, attempts, last_error,
+                EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!",
+                EXTRACT(EPOCH FROM next_attempt_at)::BIGINT AS "next_attempt_at!",
+                FLOOR(EXTRACT(EPOCH FROM expires_at))::BIGINT AS expires_at
+            FROM email_outbox
+            WHERE status = $1
+            ORDER BY created_at DESC, id
+            LIMIT $2 OFFSET $3
+            "#,
+            status.as_str(),
+            limit as i64,
+            offset as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("failed to list outbox emails")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM email_outbox WHERE status = $1
+            "#,
+            status.as_str()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .wrap_err("failed to count outbox emails")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        let emails = rows.into_iter().map(OutboxEmail::try_from).collect::<Result<_, _>>()?;
+        Ok((emails, total as u64))
+    }
+
+    #[tracing::instrument(name = "Replaying outbox email in PostgreSQL", skip_all)]
+    async fn replay(&mut self, id: &OutboxEmailId) -> Result<OutboxEmail, EmailOutboxStoreError> {
+        let row = sqlx::query_as!(
+            OutboxEmailRow,
+            r#"
+            UPDATE email_outbox SET status = 'pending', attempts = 0, next_attempt_at = NOW()
+            WHERE id = $1
+            RETURNING id, recipient, subject, text_body, html_body, status, attempts, last_error,
+                EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!",
+                EXTRACT(EPOCH FROM next_attempt_at)::BIGINT AS "next_attempt_at!",
+                FLOOR(EXTRACT(EPOCH FROM expires_at))::BIGINT AS expires_at
+            "#,
+            id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("failed to replay outbox email")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?
+        .ok_or(EmailOutboxStoreError::EmailNotFound)?;
+
+        OutboxEmail::try_from(row)
+    }
+
+    #[tracing::instrument(name = "Purging recipient's outbox emails from PostgreSQL", skip_all)]
+    async fn purge_recipient(&mut self, recipient: &Email) -> Result<(), EmailOutboxStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM email_outbox WHERE recipient = $1
+            "#,
+            recipient.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to purge recipient's outbox emails")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Purging dead outbox emails from PostgreSQL", skip_all)]
+    async fn purge_dead(&mut self, created_before: i64) -> Result<u64, EmailOutboxStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM email_outbox WHERE status = 'dead' AND created_at < to_timestamp($1)
+            "#,
+            created_before as f64
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to purge dead outbox emails")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+struct OutboxEmailRow {
+    id: String,
+    recipient: String,
+    subject: String,
+    text_body: String,
+    html_body: String,
+    status: String,
+    attempts: i32,
+    last_error: Option<String>,
+    created_at: i64,
+    next_attempt_at: i64,
+    expires_at: Option<i64>,
+}
+
+impl TryFrom<OutboxEmailRow> for OutboxEmail {
+    type Error = EmailOutboxStoreError;
+
+    fn try_from(row: OutboxEmailRow) -> Result<Self, Self::Error> {
+        Ok(OutboxEmail {
+            id: OutboxEmailId::parse(row.id).map_err(EmailOutboxStoreError::UnexpectedError)?,
+            recipient: Email::parse(row.recipient)
+                .map_err(|e| EmailOutboxStoreError::UnexpectedError(eyre!("Invalid outbox recipient: {}", e)))?,
+            content: EmailContent { subject: row.subject, text: row.text_body, html: row.html_body },
+            status: OutboxStatus::parse(&row.status).map_err(EmailOutboxStoreError::UnexpectedError)?,
+            attempts: row.attempts as u32,
+            last_error: row.last_error,
+            created_at: row.created_at,
+            next_attempt_at: row.next_attempt_at,
+            expires_at: row.expires_at,
+        })
+    }
+}
@@ -0,0 +1,155 @@
+use color_eyre::eyre::{eyre, Context, Result};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{ClientSecret, OAuthClientStore, OAuthClientStoreError},
+    Audience, OAuthClient, OAuthClientId, OAuthClientType, RedirectUri, Scopes,
+};
+
+pub struct PostgresOAuthClientStore {
+    pool: PgPool,
+}
+
+impl PostgresOAuthClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for PostgresOAuthClientStore {
+    #[tracing::instrument(name = "Adding OAuth client to PostgreSQL", skip_all)]
+    async fn add_client(&mut self, client: OAuthClient, secret: Option<ClientSecret>) -> Result<(), OAuthClientStoreError> {
+        let redirect_uris: Vec<String> = client.redirect_uris.iter().map(|uri| uri.as_ref().to_owned()).collect();
+        let allowed_scopes: Vec<String> = client.allowed_scopes.iter().map(str::to_owned).collect();
+        let allowed_audiences: Vec<String> = client.allowed_audiences.iter().map(|audience| audience.as_ref().to_owned()).collect();
+        // Secrets are as good as the client itself, so only their hashes are persisted
+        let secret_hash = secret.map(|secret| secret.secret_hash());
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_clients
+                (client_id, name, client_type, redirect_uris, allowed_scopes, allowed_audiences, secret_hash, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, to_timestamp($8))
+            "#,
+            client.client_id.as_ref(),
+            client.name,
+            client.client_type.as_str(),
+            &redirect_uris,
+            &allowed_scopes,
+            &allowed_audiences,
+            secret_hash,
+            client.created_at as f64
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to store OAuth client")
+        .map_err(OAuthClientStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving OAuth client from PostgreSQL", skip_all)]
+    async fn get_client(&self, client_id: &OAuthClientId) -> Result<OAuthClient, OAuthClientStoreError> {
+        let row = sqlx::query_as!(
+            OAuthClientRow,
+            r#"
+            SELECT client_id, name, client_type, redirect_uris, allowed_scopes, allowed_audiences,
+                EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!"
+            FROM oauth_clients WHERE client_id = $1
+            "#,
+            client_id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("failed to fetch OAuth client")
+        .map_err(OAuthClientStoreError::UnexpectedError)?
+        .ok_or(OAuthClientStoreError::ClientNotFound)?;
+
+        OAuthClient::try_from(row)
+    }
+
+    #[tracing::instrument(name = "Listing OAuth clients from PostgreSQL", skip_all)]
+    async fn list_clients(&self) -> Result<Vec<OAuthClient>, OAuthClientStoreError> {
+        let rows = sqlx::query_as!(
+            OAuthClientRow,
+            r#"
+            SELECT client_id, name, client_type, redirect_uris, allowed_scopes, allowed_audiences,
+                EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!"
+            FROM oauth_clients ORDER BY created_at, client_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("failed to list OAuth clients")
+        .map_err(OAuthClientStoreError::UnexpectedError)?;
+
+        rows.into_iter().map(OAuthClient::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Deleting OAuth client from PostgreSQL", skip_all)]
+    async fn delete_client(&mut self, client_id: &OAuthClientId) -> Result<(), OAuthClientStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM oauth_clients WHERE client_id = $1
+            "#,
+            client_id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to delete OAuth client")
+        .map_err(OAuthClientStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(OAuthClientStoreError::ClientNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Verifying OAuth client secret in PostgreSQL", skip_all)]
+    async fn verify_client_secret(&self, client_id: &OAuthClientId, secret: &ClientSecret) -> Result<(), OAuthClientStoreError> {
+        let matches = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(secret_hash = $2, FALSE) AS "matches!" FROM oauth_clients WHERE client_id = $1
+            "#,
+            client_id.as_ref(),
+            secret.secret_hash()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("failed to fetch OAuth client secret")
+        .map_err(OAuthClientStoreError::UnexpectedError)?
+        .ok_or(OAuthClientStoreError::ClientNotFound)?;
+
+        if !matches {
+            return Err(OAuthClientStoreError::InvalidSecret);
+        }
+        Ok(())
+    }
+}
+
+struct OAuthClientRow {
+    client_id: String,
+    name: String,
+    client_type: String,
+    redirect_uris: Vec<String>,
+    allowed_scopes: Vec<String>,
+    allowed_audiences: Vec<String>,
+    created_at: i64,
+}
+
+impl TryFrom<OAuthClientRow> for OAuthClient {
+    type Error = OAuthClientStoreError;
+
+    fn try_from(row: OAuthClientRow) -> Result<Self, Self::Error> {
+        let invalid = |e| OAuthClientStoreError::UnexpectedError(eyre!("Invalid stored OAuth client: {}", e));
+        Ok(OAuthClient {
+            client_id: OAuthClientId::parse(row.client_id).map_err(invalid)?,
+            name: row.name,
+            client_type: OAuthClientType::parse(&row.client_type).map_err(invalid)?,
+            redirect_uris: row.redirect_uris.into_iter().map(RedirectUri::parse).collect::<Result<_, _>>().map_err(invalid)?,
+            allowed_scopes: Scopes::from_tokens(row.allowed_scopes).map_err(invalid)?,
+            allowed_audiences: row.allowed_audiences.into_iter().map(Audience::parse).collect::<Result<_, _>>().map_err(invalid)?,
+            created_at: row.created_at,
+        })
+    }
+}
@@ -0,0 +1,165 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+};
+use color_eyre::eyre::{eyre, Context, Result};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{RecoveryCode, RecoveryCodeStore, RecoveryCodeStoreError},
+    Email,
+};
+
+pub struct PostgresRecoveryCodeStore {
+    pool: PgPool,
+}
+
+impl PostgresRecoveryCodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for PostgresRecoveryCodeStore {
+    #[tracing::instrument(name = "Storing recovery codes in PostgreSQL", skip_all)]
+    async fn set_codes(&mut self, email: &Email, codes: Vec<RecoveryCode>) -> Result<(), RecoveryCodeStoreError> {
+        let code_hashes = compute_code_hashes(codes).await.map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .wrap_err("failed to start transaction")
+            .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes WHERE email = $1
+            "#,
+            email.as_ref()
+        )
+        .execute(&mut *transaction)
+        .await
+        .wrap_err("failed to remove previous recovery codes")
+        .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (email, code_hash)
+            SELECT $1, code_hash FROM UNNEST($2::TEXT[]) AS code_hash
+            "#,
+            email.as_ref(),
+            &code_hashes
+        )
+        .execute(&mut *transaction)
+        .await
+        .wrap_err("failed to store recovery codes")
+        .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        transaction
+            .commit()
+            .await
+            .wrap_err("failed to commit recovery codes")
+            .map_err(RecoveryCodeStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Using recovery code in PostgreSQL", skip_all)]
+    async fn use_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), RecoveryCodeStoreError> {
+        let records = sqlx::query!(
+            r#"
+            SELECT id, code_hash FROM recovery_codes WHERE email = $1
+            "#,
+            email.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("failed to fetch recovery codes")
+        .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        let candidates = records.into_iter().map(|record| (record.id, record.code_hash)).collect();
+        let id = find_matching_code(code.clone(), candidates)
+            .await
+            .map_err(RecoveryCodeStoreError::UnexpectedError)?
+            .ok_or(RecoveryCodeStoreError::CodeNotFound)?;
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to remove used recovery code")
+        .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        // A concurrent request may have used the same code in the meantime
+        if result.rows_affected() == 0 {
+            return Err(RecoveryCodeStoreError::CodeNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Counting recovery codes in PostgreSQL", skip_all)]
+    async fn count_codes(&self, email: &Email) -> Result<usize, RecoveryCodeStoreError> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM recovery_codes WHERE email = $1
+            "#,
+            email.as_ref()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .wrap_err("failed to count recovery codes")
+        .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+
+        Ok(count as usize)
+    }
+}
+
+// Recovery codes are as good as a second factor, so only their Argon2 hashes are persisted
+#[tracing::instrument(name = "Computing recovery code hashes", skip_all)]
+async fn compute_code_hashes(codes: Vec<RecoveryCode>) -> Result<Vec<String>> {
+    let current_span: tracing::Span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        current_span.in_scope(|| {
+            let argon2 = Argon2::new(
+                Algorithm::Argon2id,
+                Version::V0x13,
+                Params::new(15000, 2, 1, None).map_err(|e| eyre!("Invalid Argon2 params: {:?}", e))?,
+            );
+            codes
+                .iter()
+                .map(|code| {
+                    let salt: SaltString = SaltString::generate(&mut OsRng);
+                    argon2
+                        .hash_password(code.as_ref().as_bytes(), &salt)
+                        .map(|hash| hash.to_string())
+                        .map_err(|e| eyre!("failed to hash recovery code: {:?}", e))
+                })
+                .collect()
+        })
+    })
+    .await
+    .wrap_err("recovery code hashing task failed")?
+}
+
+// Returns the id of the stored hash `code` matches, if any
+#[tracing::instrument(name = "Verifying recovery code", skip_all)]
+async fn find_matching_code(code: RecoveryCode, candidates: Vec<(i64, String)>) -> Result<Option<i64>> {
+    let current_span: tracing::Span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        current_span.in_scope(|| {
+            for (id, code_hash) in candidates {
+                let code_hash = PasswordHash::new(&code_hash).map_err(|e| eyre!("Invalid recovery code hash: {:?}", e))?;
+                if Argon2::default().verify_password(code.as_ref().as_bytes(), &code_hash).is_ok() {
+                    return Ok(Some(id));
+                }
+            }
+            Ok(None)
+        })
+    })
+    .await
+    .wrap_err("recovery code verification task failed")?
+}
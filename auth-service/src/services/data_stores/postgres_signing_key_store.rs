@@ -0,0 +1,180 @@
+use color_eyre::eyre::{eyre, Context, Result};
+use jsonwebtoken::Algorithm;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::str::FromStr;
+
+use crate::{
+    domain::data_stores::{SigningKeyStore, SigningKeyStoreError, StoredSigningKey},
+    utils::{constants::JWT_KEY_ENCRYPTION_KEY, encryption},
+};
+
+pub struct PostgresSigningKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresSigningKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for PostgresSigningKeyStore {
+    #[tracing::instrument(name = "Retrieving signing keys from PostgreSQL", skip_all)]
+    async fn get_keys(&self, now: i64) -> Result<Vec<StoredSigningKey>, SigningKeyStoreError> {
+        let rows = sqlx::query_as!(
+            SigningKeyRow,
+            r#"
+            SELECT kid, algorithm, encrypted_private_key,
+                FLOOR(EXTRACT(EPOCH FROM activates_at))::BIGINT AS "activates_at!",
+                FLOOR(EXTRACT(EPOCH FROM retire_at))::BIGINT AS retire_at
+            FROM jwt_signing_keys
+            WHERE retire_at IS NULL OR retire_at > to_timestamp($1)
+            ORDER BY activates_at, kid
+            "#,
+            now as f64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("failed to fetch signing keys")
+        .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        rows.into_iter()
+            .map(|row| StoredSigningKey::try_from(row).map_err(SigningKeyStoreError::UnexpectedError))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Seeding signing keys in PostgreSQL", skip_all)]
+    async fn add_initial_keys(&mut self, keys: Vec<StoredSigningKey>) -> Result<(), SigningKeyStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .wrap_err("failed to start transaction")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        // Replicas starting at the same time must not each seed their own keys
+        sqlx::query!("LOCK TABLE jwt_signing_keys IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *transaction)
+            .await
+            .wrap_err("failed to lock signing keys")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        let seeded = sqlx::query_scalar!(r#"SELECT EXISTS (SELECT 1 FROM jwt_signing_keys) AS "seeded!""#)
+            .fetch_one(&mut *transaction)
+            .await
+            .wrap_err("failed to check for signing keys")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+        if !seeded {
+            for key in &keys {
+                insert_key(&mut transaction, key).await?;
+            }
+        }
+
+        transaction
+            .commit()
+            .await
+            .wrap_err("failed to commit signing keys")
+            .map_err(SigningKeyStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Adding next signing key to PostgreSQL", skip_all)]
+    async fn add_next_key(&mut self, key: StoredSigningKey, retire_at: i64) -> Result<(), SigningKeyStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .wrap_err("failed to start transaction")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE jwt_signing_keys SET retire_at = to_timestamp($1) WHERE retire_at IS NULL
+            "#,
+            retire_at as f64
+        )
+        .execute(&mut *transaction)
+        .await
+        .wrap_err("failed to retire signing keys")
+        .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        insert_key(&mut transaction, &key).await?;
+
+        transaction
+            .commit()
+            .await
+            .wrap_err("failed to commit signing key")
+            .map_err(SigningKeyStoreError::UnexpectedError)
+    }
+}
+
+// Private keys are encrypted at rest under JWT_KEY_ENCRYPTION_KEY
+async fn insert_key(transaction: &mut Transaction<'_, Postgres>, key: &StoredSigningKey) -> Result<(), SigningKeyStoreError> {
+    let encrypted_private_key = encryption::encrypt(&JWT_KEY_ENCRYPTION_KEY, &key.private_key)
+        .wrap_err("failed to encrypt signing key")
+        .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO jwt_signing_keys (kid, algorithm, encrypted_private_key, activates_at, retire_at)
+        VALUES ($1, $2, $3, to_timestamp($4), to_timestamp($5))
+        "#,
+        key.kid,
+        algorithm_name(key.algorithm),
+        encrypted_private_key,
+        key.activates_at as f64,
+        key.retire_at.map(|retire_at| retire_at as f64)
+    )
+    .execute(&mut **transaction)
+    .await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(db_err)) if db_err.code() == Some("23505".into()) => {
+            Err(SigningKeyStoreError::KeyAlreadyExists)
+        }
+        Err(e) => Err(SigningKeyStoreError::UnexpectedError(eyre!(e).wrap_err("failed to store signing key"))),
+    }
+}
+
+// The names jsonwebtoken parses algorithms from, which its Debug output matches
+fn algorithm_name(algorithm: Algorithm) -> String {
+    format!("{:?}", algorithm)
+}
+
+struct SigningKeyRow {
+    kid: String,
+    algorithm: String,
+    encrypted_private_key: Vec<u8>,
+    activates_at: i64,
+    retire_at: Option<i64>,
+}
+
+impl TryFrom<SigningKeyRow> for StoredSigningKey {
+    type Error = color_eyre::eyre::Report;
+
+    fn try_from(row: SigningKeyRow) -> Result<Self> {
+        let algorithm = Algorithm::from_str(&row.algorithm).wrap_err("invalid stored signing key algorithm")?;
+        let private_key = encryption::decrypt(&JWT_KEY_ENCRYPTION_KEY, &row.encrypted_private_key)
+            .wrap_err_with(|| format!("failed to decrypt signing key {}", row.kid))?;
+        Ok(StoredSigningKey {
+            kid: row.kid,
+            algorithm,
+            private_key,
+            activates_at: row.activates_at,
+            retire_at: row.retire_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_algorithm_names_round_trip() {
+        for algorithm in [Algorithm::EdDSA, Algorithm::RS256, Algorithm::HS256] {
+            assert_eq!(Algorithm::from_str(&algorithm_name(algorithm)).unwrap(), algorithm);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use color_eyre::eyre::{Context, Result};
+use sqlx::PgPool;
+
+use crate::{
+    domain::{
+        data_stores::{TotpSecret, TotpSecretStore, TotpSecretStoreError},
+        Email,
+    },
+    utils::{constants::TOTP_ENCRYPTION_KEY, encryption},
+};
+
+pub struct PostgresTotpSecretStore {
+    pool: PgPool,
+}
+
+impl PostgresTotpSecretStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpSecretStore for PostgresTotpSecretStore {
+    #[tracing::instrument(name = "Adding TOTP secret to PostgreSQL", skip_all)]
+    async fn add_secret(&mut self, email: &Email, secret: TotpSecret) -> Result<(), TotpSecretStoreError> {
+        let encrypted_secret = encrypt_secret(&secret).map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO totp_secrets (email, encrypted_secret, confirmed)
+            VALUES ($1, $2, FALSE)
+            ON CONFLICT (email) DO UPDATE
+            SET encrypted_secret = EXCLUDED.encrypted_secret, confirmed = FALSE, last_used_step = NULL
+            "#,
+            email.as_ref(),
+            encrypted_secret
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to store TOTP secret")
+        .map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving TOTP secret from PostgreSQL", skip_all)]
+    async fn get_secret(&self, email: &Email) -> Result<(TotpSecret, bool), TotpSecretStoreError> {
+        let record = sqlx::query!(
+            r#"
+            SELECT encrypted_secret, confirmed FROM totp_secrets WHERE email = $1
+            "#,
+            email.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("failed to fetch TOTP secret")
+        .map_err(TotpSecretStoreError::UnexpectedError)?
+        .ok_or(TotpSecretStoreError::SecretNotFound)?;
+
+        let secret = decrypt_secret(&record.encrypted_secret).map_err(TotpSecretStoreError::UnexpectedError)?;
+        Ok((secret, record.confirmed))
+    }
+
+    #[tracing::instrument(name = "Confirming TOTP secret in PostgreSQL", skip_all)]
+    async fn confirm_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_secrets SET confirmed = TRUE WHERE email = $1
+            "#,
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to confirm TOTP secret")
+        .map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpSecretStoreError::SecretNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing TOTP secret from PostgreSQL", skip_all)]
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM totp_secrets WHERE email = $1
+            "#,
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to remove TOTP secret")
+        .map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using TOTP time step in PostgreSQL", skip_all)]
+    async fn use_time_step(&mut self, email: &Email, time_step: u64) -> Result<(), TotpSecretStoreError> {
+        let time_step = i64::try_from(time_step)
+            .wrap_err("TOTP time step out of range")
+            .map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        // A single conditional update, so concurrent requests can't both accept the same code
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_secrets SET last_used_step = $2
+            WHERE email = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            email.as_ref(),
+            time_step
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to record TOTP time step")
+        .map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpSecretStoreError::TimeStepAlreadyUsed);
+        }
+        Ok(())
+    }
+}
+
+// Secrets are encrypted at rest under TOTP_ENCRYPTION_KEY
+fn encrypt_secret(secret: &TotpSecret) -> Result<Vec<u8>> {
+    encryption::encrypt(&TOTP_ENCRYPTION_KEY, secret.as_ref().as_bytes()).wrap_err("failed to encrypt TOTP secret")
+}
+
+fn decrypt_secret(encrypted: &[u8]) -> Result<TotpSecret> {
+    let plaintext = encryption::decrypt(&TOTP_ENCRYPTION_KEY, encrypted).wrap_err("failed to decrypt TOTP secret")?;
+    let secret = String::from_utf8(plaintext).wrap_err("decrypted TOTP secret is not valid UTF-8")?;
+    TotpSecret::parse(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_secret() {
+        let secret = TotpSecret::default();
+        let encrypted = encrypt_secret(&secret).unwrap();
+        assert!(!encrypted.windows(secret.as_ref().len()).any(|w| w == secret.as_ref().as_bytes()));
+        assert_eq!(decrypt_secret(&encrypted).unwrap(), secret);
+
+        // Every encryption uses a fresh nonce
+        assert_ne!(encrypt_secret(&secret).unwrap(), encrypted);
+    }
+
+    #[test]
+    fn test_decrypt_rejects_tampered_secret() {
+        let mut encrypted = encrypt_secret(&TotpSecret::default()).unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(decrypt_secret(&encrypted).is_err());
+    }
+}
@@ -5,11 +5,11 @@ use argon2::{
     PasswordVerifier, Version,
 };
 
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 
 use crate::domain::{
     data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+    Email, Password, PhoneNumber, Role, TwoFAChannel, User,
 };
 
 pub struct PostgresUserStore {
@@ -29,33 +29,46 @@ impl UserStore for PostgresUserStore {
             .await
             .map_err(|_| UserStoreError::UnexpectedError)?;
 
+        let mut transaction = self.pool.begin().await.map_err(|_| UserStoreError::UnexpectedError)?;
+
         let result = sqlx::query!(
             r#"
-            INSERT INTO users (email, password_hash, requires_2fa)
-            VALUES ($1, $2, $3)
+            INSERT INTO users (email, password_hash, requires_2fa, verified, disabled, phone_number, phone_verified, two_fa_channel)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             "#,
             user.email.0,
             password_hash,
-            user.requires_2fa
+            user.requires_2fa,
+            user.verified,
+            user.disabled,
+            user.phone_number.as_ref().map(|phone_number| phone_number.as_ref()),
+            user.phone_verified,
+            user.two_fa_channel.as_str()
         )
-        .execute(&self.pool)
+        .execute(&mut *transaction)
         .await;
 
         match result {
-            Ok(_) => {
-                Ok(())
-            }
+            Ok(_) => {}
             Err(sqlx::Error::Database(db_err)) if db_err.code() == Some("23505".into()) => {
-                Err(UserStoreError::UserAlreadyExists)
+                return Err(UserStoreError::UserAlreadyExists);
             }
-            Err(_) => Err(UserStoreError::UnexpectedError),
+            Err(_) => return Err(UserStoreError::UnexpectedError),
         }
+
+        insert_roles(&mut transaction, user.email.as_ref(), &user.roles).await?;
+        transaction.commit().await.map_err(|_| UserStoreError::UnexpectedError)
     }
 
     async fn get_user(&self, email: &str) -> Result<&User, UserStoreError> {
         let record = sqlx::query!(
             r#"
-            SELECT email, password_hash, requires_2fa FROM users WHERE email = $1
+            SELECT users.email, password_hash, requires_2fa, verified, disabled, phone_number, phone_verified, two_fa_channel,
+                COALESCE(array_agg(user_roles.role ORDER BY user_roles.role) FILTER (WHERE user_roles.role IS NOT NULL), '{}') AS "roles!"
+            FROM users
+            LEFT JOIN user_roles ON user_roles.email = users.email
+            WHERE users.email = $1
+            GROUP BY users.email
             "#,
             email
         )
@@ -69,7 +82,13 @@ impl UserStore for PostgresUserStore {
                 Ok(Box::leak(Box::new(User {
                     email,
                     password,
-                    requires_2fa: rec.requires_2fa
+                    requires_2fa: rec.requires_2fa,
+                    verified: rec.verified,
+                    disabled: rec.disabled,
+                    roles: parse_roles(rec.roles)?,
+                    phone_number: parse_phone_number(rec.phone_number)?,
+                    phone_verified: rec.phone_verified,
+                    two_fa_channel: parse_two_fa_channel(&rec.two_fa_channel)?,
                 })))
             }
             Err(sqlx::Error::RowNotFound) => Err(UserStoreError::UserNotFound),
@@ -99,6 +118,293 @@ impl UserStore for PostgresUserStore {
             Err(_) => Err(UserStoreError::InvalidCredentials),
         }
     }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(&mut self, email: &str, password: Password) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.0)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET password_hash = $1 WHERE email = $2
+            "#,
+            password_hash,
+            email
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(res) if res.rows_affected() == 0 => Err(UserStoreError::UserNotFound),
+            Ok(_) => Ok(()),
+            Err(_) => Err(UserStoreError::UnexpectedError),
+        }
+    }
+
+    #[tracing::instrument(name = "Marking user email as verified in PostgreSQL", skip_all)]
+    async fn mark_email_verified(&mut self, email: &str) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET verified = TRUE WHERE email = $1
+            "#,
+            email
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(res) if res.rows_affected() == 0 => Err(UserStoreError::UserNotFound),
+            Ok(_) => Ok(()),
+            Err(_) => Err(UserStoreError::UnexpectedError),
+        }
+    }
+
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&mut self, email: &str) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users WHERE email = $1
+            "#,
+            email
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(res) if res.rows_affected() == 0 => Err(UserStoreError::UserNotFound),
+            Ok(_) => Ok(()),
+            Err(_) => Err(UserStoreError::UnexpectedError),
+        }
+    }
+
+    #[tracing::instrument(name = "Setting user 2FA requirement in PostgreSQL", skip_all)]
+    async fn set_requires_2fa(&mut self, email: &str, requires_2fa: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET requires_2fa = $1 WHERE email = $2
+            "#,
+            requires_2fa,
+            email
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(res) if res.rows_affected() == 0 => Err(UserStoreError::UserNotFound),
+            Ok(_) => Ok(()),
+            Err(_) => Err(UserStoreError::UnexpectedError),
+        }
+    }
+
+    #[tracing::instrument(name = "Setting user roles in PostgreSQL", skip_all)]
+    async fn set_roles(&mut self, email: &str, roles: Vec<Role>) -> Result<(), UserStoreError> {
+        let mut transaction = self.pool.begin().await.map_err(|_| UserStoreError::UnexpectedError)?;
+
+        // Locks the user row so concurrent updates can't interleave their deletes and inserts
+        let user = sqlx::query!(
+            r#"
+            SELECT email FROM users WHERE email = $1 FOR UPDATE
+            "#,
+            email
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+        if user.is_none() {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM user_roles WHERE email = $1
+            "#,
+            email
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        insert_roles(&mut transaction, email, &roles).await?;
+        transaction.commit().await.map_err(|_| UserStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Setting user disabled flag in PostgreSQL", skip_all)]
+    async fn set_disabled(&mut self, email: &str, disabled: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET disabled = $1 WHERE email = $2
+            "#,
+            disabled,
+            email
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(res) if res.rows_affected() == 0 => Err(UserStoreError::UserNotFound),
+            Ok(_) => Ok(()),
+            Err(_) => Err(UserStoreError::UnexpectedError),
+        }
+    }
+
+    #[tracing::instrument(name = "Setting user phone number in PostgreSQL", skip_all)]
+    async fn set_phone_number(&mut self, email: &str, phone_number: Option<PhoneNumber>) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET phone_number = $1, phone_verified = FALSE, two_fa_channel = 'email' WHERE email = $2
+            "#,
+            phone_number.as_ref().map(|phone_number| phone_number.as_ref()),
+            email
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(res) if res.rows_affected() == 0 => Err(UserStoreError::UserNotFound),
+            Ok(_) => Ok(()),
+            Err(_) => Err(UserStoreError::UnexpectedError),
+        }
+    }
+
+    #[tracing::instrument(name = "Marking user phone number as verified in PostgreSQL", skip_all)]
+    async fn mark_phone_verified(&mut self, email: &str) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET phone_verified = TRUE WHERE email = $1
+            "#,
+            email
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(res) if res.rows_affected() == 0 => Err(UserStoreError::UserNotFound),
+            Ok(_) => Ok(()),
+            Err(_) => Err(UserStoreError::UnexpectedError),
+        }
+    }
+
+    #[tracing::instrument(name = "Setting user 2FA channel in PostgreSQL", skip_all)]
+    async fn set_two_fa_channel(&mut self, email: &str, channel: TwoFAChannel) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET two_fa_channel = $1 WHERE email = $2
+            "#,
+            channel.as_str(),
+            email
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(res) if res.rows_affected() == 0 => Err(UserStoreError::UserNotFound),
+            Ok(_) => Ok(()),
+            Err(_) => Err(UserStoreError::UnexpectedError),
+        }
+    }
+
+    #[tracing::instrument(name = "Listing users from PostgreSQL", skip_all)]
+    async fn list_users(&self, email_prefix: &str, offset: u64, limit: u64) -> Result<(Vec<User>, u64), UserStoreError> {
+        // The prefix is matched literally, LIKE wildcards typed into a search box must not widen it
+        let pattern = format!(
+            "{}%",
+            email_prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        let offset = i64::try_from(offset).map_err(|_| UserStoreError::UnexpectedError)?;
+        let limit = i64::try_from(limit).map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM users WHERE email LIKE $1
+            "#,
+            pattern
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let records = sqlx::query!(
+            r#"
+            SELECT users.email, password_hash, requires_2fa, verified, disabled, phone_number, phone_verified, two_fa_channel,
+                COALESCE(array_agg(user_roles.role ORDER BY user_roles.role) FILTER (WHERE user_roles.role IS NOT NULL), '{}') AS "roles!"
+            FROM users
+            LEFT JOIN user_roles ON user_roles.email = users.email
+            WHERE users.email LIKE $1
+            GROUP BY users.email
+            ORDER BY users.email
+            OFFSET $2 LIMIT $3
+            "#,
+            pattern,
+            offset,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let users = records
+            .into_iter()
+            .map(|rec| {
+                Ok(User {
+                    email: Email(rec.email),
+                    password: Password(rec.password_hash),
+                    requires_2fa: rec.requires_2fa,
+                    verified: rec.verified,
+                    disabled: rec.disabled,
+                    roles: parse_roles(rec.roles)?,
+                    phone_number: parse_phone_number(rec.phone_number)?,
+                    phone_verified: rec.phone_verified,
+                    two_fa_channel: parse_two_fa_channel(&rec.two_fa_channel)?,
+                })
+            })
+            .collect::<Result<Vec<_>, UserStoreError>>()?;
+
+        Ok((users, total as u64))
+    }
+}
+
+fn parse_roles(roles: Vec<String>) -> Result<Vec<Role>, UserStoreError> {
+    roles
+        .into_iter()
+        .map(Role::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| UserStoreError::UnexpectedError)
+}
+
+fn parse_phone_number(phone_number: Option<String>) -> Result<Option<PhoneNumber>, UserStoreError> {
+    phone_number
+        .map(PhoneNumber::parse)
+        .transpose()
+        .map_err(|_| UserStoreError::UnexpectedError)
+}
+
+fn parse_two_fa_channel(channel: &str) -> Result<TwoFAChannel, UserStoreError> {
+    TwoFAChannel::parse(channel).map_err(|_| UserStoreError::UnexpectedError)
+}
+
+// Assigns `roles` to the user. Roles have to be in the roles catalog already, the foreign key rejects the rest
+async fn insert_roles(connection: &mut PgConnection, email: &str, roles: &[Role]) -> Result<(), UserStoreError> {
+    if roles.is_empty() {
+        return Ok(());
+    }
+    let roles: Vec<String> = roles.iter().map(|role| role.as_ref().to_owned()).collect();
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO user_roles (email, role) SELECT $1, * FROM UNNEST($2::text[])
+        "#,
+        email,
+        &roles
+    )
+    .execute(&mut *connection)
+    .await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(db_err)) if db_err.code() == Some("23503".into()) => Err(UserStoreError::UnknownRole),
+        Err(_) => Err(UserStoreError::UnexpectedError),
+    }
 }
 
 // Helper function to verify if a given password matches an expected hash
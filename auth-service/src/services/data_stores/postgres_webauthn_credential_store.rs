@@ -0,0 +1,128 @@
+use color_eyre::eyre::{eyre, Context};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{WebauthnCredential, WebauthnCredentialStore, WebauthnCredentialStoreError},
+    Email,
+};
+
+pub struct PostgresWebauthnCredentialStore {
+    pool: PgPool,
+}
+
+impl PostgresWebauthnCredentialStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebauthnCredentialStore for PostgresWebauthnCredentialStore {
+    #[tracing::instrument(name = "Adding WebAuthn credential to PostgreSQL", skip_all)]
+    async fn add_credential(&mut self, credential: WebauthnCredential) -> Result<(), WebauthnCredentialStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webauthn_credentials (credential_id, email, public_key, sign_count, name, created_at)
+            VALUES ($1, $2, $3, $4, $5, to_timestamp($6))
+            "#,
+            credential.credential_id,
+            credential.email.as_ref(),
+            credential.public_key,
+            i64::from(credential.sign_count),
+            credential.name,
+            credential.created_at as f64
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(db_err)) if db_err.code() == Some("23505".into()) => {
+                Err(WebauthnCredentialStoreError::CredentialAlreadyExists)
+            }
+            Err(e) => Err(WebauthnCredentialStoreError::UnexpectedError(
+                eyre!(e).wrap_err("failed to store WebAuthn credential"),
+            )),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving WebAuthn credential from PostgreSQL", skip_all)]
+    async fn get_credential(&self, credential_id: &str) -> Result<WebauthnCredential, WebauthnCredentialStoreError> {
+        let record = sqlx::query!(
+            r#"
+            SELECT credential_id, email, public_key, sign_count, name, EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!"
+            FROM webauthn_credentials WHERE credential_id = $1
+            "#,
+            credential_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("failed to fetch WebAuthn credential")
+        .map_err(WebauthnCredentialStoreError::UnexpectedError)?
+        .ok_or(WebauthnCredentialStoreError::CredentialNotFound)?;
+
+        Ok(WebauthnCredential {
+            credential_id: record.credential_id,
+            email: Email(record.email),
+            public_key: record.public_key,
+            sign_count: sign_count_from_db(record.sign_count)?,
+            name: record.name,
+            created_at: record.created_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving user WebAuthn credentials from PostgreSQL", skip_all)]
+    async fn get_credentials(&self, email: &Email) -> Result<Vec<WebauthnCredential>, WebauthnCredentialStoreError> {
+        let records = sqlx::query!(
+            r#"
+            SELECT credential_id, email, public_key, sign_count, name, EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!"
+            FROM webauthn_credentials WHERE email = $1 ORDER BY created_at
+            "#,
+            email.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("failed to fetch WebAuthn credentials")
+        .map_err(WebauthnCredentialStoreError::UnexpectedError)?;
+
+        records
+            .into_iter()
+            .map(|record| {
+                Ok(WebauthnCredential {
+                    credential_id: record.credential_id,
+                    email: Email(record.email),
+                    public_key: record.public_key,
+                    sign_count: sign_count_from_db(record.sign_count)?,
+                    name: record.name,
+                    created_at: record.created_at,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Updating WebAuthn sign count in PostgreSQL", skip_all)]
+    async fn update_sign_count(&mut self, credential_id: &str, sign_count: u32) -> Result<(), WebauthnCredentialStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webauthn_credentials SET sign_count = $2 WHERE credential_id = $1 AND sign_count < $2
+            "#,
+            credential_id,
+            i64::from(sign_count)
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to update WebAuthn sign count")
+        .map_err(WebauthnCredentialStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebauthnCredentialStoreError::SignCountNotIncreased);
+        }
+        Ok(())
+    }
+}
+
+fn sign_count_from_db(sign_count: i64) -> Result<u32, WebauthnCredentialStoreError> {
+    u32::try_from(sign_count)
+        .wrap_err("stored WebAuthn sign count is out of range")
+        .map_err(WebauthnCredentialStoreError::UnexpectedError)
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::{eyre, Context, Report};
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{AuthorizationCode, AuthorizationGrantStore, AuthorizationGrantStoreError, ConsentId},
+    Authentication, AuthorizationGrant, CodeChallenge, Email, OAuthClientId, RedirectUri, Scopes,
+};
+
+pub struct RedisAuthorizationGrantStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisAuthorizationGrantStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    async fn add(&mut self, key: String, grant: AuthorizationGrant, ttl_seconds: u64) -> Result<(), AuthorizationGrantStoreError> {
+        let serialized = serde_json::to_string(&StoredGrant::from(grant))
+            .wrap_err("failed to serialize authorization grant")
+            .map_err(AuthorizationGrantStoreError::UnexpectedError)?;
+        let mut conn = self.conn.write().await;
+        conn.set_ex::<_, _, ()>(&key, serialized, ttl_seconds)
+            .wrap_err("failed to set authorization grant in Redis")
+            .map_err(AuthorizationGrantStoreError::UnexpectedError)
+    }
+
+    async fn take(&mut self, key: String) -> Result<AuthorizationGrant, AuthorizationGrantStoreError> {
+        let mut conn = self.conn.write().await;
+        // GETDEL makes sure a replayed code or a double submitted consent form is only honoured once
+        let value: Option<String> = conn
+            .get_del(&key)
+            .wrap_err("failed to take authorization grant from Redis")
+            .map_err(AuthorizationGrantStoreError::UnexpectedError)?;
+        let value = value.ok_or(AuthorizationGrantStoreError::GrantNotFound)?;
+
+        let stored: StoredGrant = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize authorization grant")
+            .map_err(AuthorizationGrantStoreError::UnexpectedError)?;
+        stored.try_into().map_err(AuthorizationGrantStoreError::UnexpectedError)
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationGrantStore for RedisAuthorizationGrantStore {
+    async fn add_pending_consent(&mut self, consent_id: ConsentId, grant: AuthorizationGrant) -> Result<(), AuthorizationGrantStoreError> {
+        self.add(get_key(CONSENT_PREFIX, consent_id.as_ref()), grant, CONSENT_TTL_SECONDS).await
+    }
+
+    async fn take_pending_consent(&mut self, consent_id: &ConsentId) -> Result<AuthorizationGrant, AuthorizationGrantStoreError> {
+        self.take(get_key(CONSENT_PREFIX, consent_id.as_ref())).await
+    }
+
+    async fn add_code(&mut self, code: AuthorizationCode, grant: AuthorizationGrant) -> Result<(), AuthorizationGrantStoreError> {
+        self.add(get_key(CODE_PREFIX, code.as_ref()), grant, CODE_TTL_SECONDS).await
+    }
+
+    async fn take_code(&mut self, code: &AuthorizationCode) -> Result<AuthorizationGrant, AuthorizationGrantStoreError> {
+        self.take(get_key(CODE_PREFIX, code.as_ref())).await
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredGrant {
+    client_id: String,
+    redirect_uri: String,
+    email: String,
+    scope: String,
+    code_challenge: String,
+    state: Option<String>,
+    nonce: Option<String>,
+    #[serde(default)]
+    auth_time: i64,
+    #[serde(default)]
+    amr: Vec<String>,
+}
+
+impl From<AuthorizationGrant> for StoredGrant {
+    fn from(grant: AuthorizationGrant) -> Self {
+        Self {
+            client_id: grant.client_id.as_ref().to_owned(),
+            redirect_uri: grant.redirect_uri.as_ref().to_owned(),
+            email: grant.email.0,
+            scope: grant.scopes.to_string(),
+            code_challenge: grant.code_challenge.as_ref().to_owned(),
+            state: grant.state,
+            nonce: grant.nonce,
+            auth_time: grant.authentication.auth_time,
+            amr: grant.authentication.amr(),
+        }
+    }
+}
+
+impl TryFrom<StoredGrant> for AuthorizationGrant {
+    type Error = Report;
+
+    fn try_from(stored: StoredGrant) -> Result<Self, Self::Error> {
+        Ok(Self {
+            client_id: OAuthClientId::parse(stored.client_id)?,
+            redirect_uri: RedirectUri::parse(stored.redirect_uri)?,
+            email: Email::parse(stored.email).map_err(|e| eyre!(e))?,
+            scopes: Scopes::parse(&stored.scope)?,
+            code_challenge: CodeChallenge::parse(stored.code_challenge)?,
+            state: stored.state,
+            nonce: stored.nonce,
+            authentication: Authentication::from_amr(stored.auth_time, &stored.amr),
+        })
+    }
+}
+
+const CONSENT_TTL_SECONDS: u64 = 600; // 10 minutes to read the consent page
+// RFC 6749 section 4.1.2 recommends codes live for at most 10 minutes, clients exchange them right away
+const CODE_TTL_SECONDS: u64 = 60;
+const CONSENT_PREFIX: &str = "oauth_consent:";
+const CODE_PREFIX: &str = "oauth_code:";
+
+fn get_key(prefix: &str, id: &str) -> String {
+    format!("{}{}", prefix, id)
+}
@@ -6,7 +6,7 @@ use tokio::sync::RwLock;
 
 use crate::{
     domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
+    utils::auth::MAX_TOKEN_LIFETIME_SECONDS,
 };
 
 pub struct RedisBannedTokenStore {
@@ -23,10 +23,10 @@ impl RedisBannedTokenStore {
 impl BannedTokenStore for RedisBannedTokenStore {
     async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError> {
         let key = get_key(&token);
-        let ttl = TOKEN_TTL_SECONDS as u64;
+        let ttl = MAX_TOKEN_LIFETIME_SECONDS as u64;
         let mut conn = self.conn.write().await;
         conn.set_ex(&key, true, ttl)
-            .wrap_err("failed to cast TOKEN_TTL_SECONDS to u64")
+            .wrap_err("failed to ban token in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
         Ok(())
@@ -40,11 +40,35 @@ impl BannedTokenStore for RedisBannedTokenStore {
             .map_err(BannedTokenStoreError::UnexpectedError)?;
         Ok(is_banned)
     }
+
+    async fn revoke_user_tokens(&mut self, email: &str, issued_before: i64) -> Result<(), BannedTokenStoreError> {
+        let key = get_user_revocation_key(email);
+        // Every token issued before the revocation stops validating within MAX_TOKEN_LIFETIME_SECONDS,
+        // leeway included, so the marker does not need to outlive them
+        let ttl = MAX_TOKEN_LIFETIME_SECONDS as u64;
+        let mut conn = self.conn.write().await;
+        conn.set_ex::<_, _, ()>(&key, issued_before, ttl)
+            .wrap_err("failed to set user token revocation in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)
+    }
+
+    async fn get_user_revocation(&self, email: &str) -> Result<Option<i64>, BannedTokenStoreError> {
+        let key = get_user_revocation_key(email);
+        let mut conn = self.conn.write().await;
+        conn.get(&key)
+            .wrap_err("failed to get user token revocation from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)
+    }
 }
 
 // We are using a key prefix to prevent collisions and organize data!
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const USER_REVOCATION_KEY_PREFIX: &str = "revoked_user_tokens:";
 
 fn get_key(token: &str) -> String {
     format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
+}
+
+fn get_user_revocation_key(email: &str) -> String {
+    format!("{}{}", USER_REVOCATION_KEY_PREFIX, email)
 }
\ No newline at end of file
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{EmailVerificationToken, EmailVerificationTokenStore, EmailVerificationTokenStoreError},
+        Email,
+    },
+    utils::constants::EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisEmailVerificationTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisEmailVerificationTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for RedisEmailVerificationTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+        issued_at: i64,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let key = get_key(&email);
+        let entry = EmailVerificationTuple(token.as_ref().to_string(), issued_at);
+        let serialized = serde_json::to_string(&entry)
+            .wrap_err("failed to serialize email verification tuple")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+        let mut conn = self.conn.write().await;
+        conn.set_ex::<_, _, ()>(&key, serialized, EMAIL_VERIFICATION_TOKEN_TTL_SECONDS)
+            .wrap_err("failed to set email verification token in Redis")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)
+    }
+
+    async fn remove_token(&mut self, email: &Email) -> Result<(), EmailVerificationTokenStoreError> {
+        let key = get_key(email);
+        let mut conn = self.conn.write().await;
+        conn.del::<_, ()>(&key)
+            .wrap_err("failed to delete email verification token from Redis")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)
+    }
+
+    async fn get_token(
+        &self,
+        email: &Email,
+    ) -> Result<(EmailVerificationToken, i64), EmailVerificationTokenStoreError> {
+        let key = get_key(email);
+        let mut conn = self.conn.write().await;
+        let value: String = conn.get(&key).map_err(|_| EmailVerificationTokenStoreError::TokenNotFound)?;
+
+        let entry: EmailVerificationTuple = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize email verification tuple")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        let token = EmailVerificationToken::parse(entry.0)
+            .map_err(|_| EmailVerificationTokenStoreError::UnexpectedError(eyre!("Invalid email verification token")))?;
+
+        Ok((token, entry.1))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EmailVerificationTuple(pub String, pub i64);
+
+const EMAIL_VERIFICATION_TOKEN_PREFIX: &str = "email_verification_token:";
+
+fn get_key(email: &Email) -> String {
+    format!("{}{}", EMAIL_VERIFICATION_TOKEN_PREFIX, email.as_ref())
+}
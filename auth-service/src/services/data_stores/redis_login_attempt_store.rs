@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptStore, LoginAttemptStoreError},
+        Email,
+    },
+    utils::constants::{LOGIN_FAILURE_WINDOW_SECONDS, LOGIN_LOCKOUT_ESCALATION_WINDOW_SECONDS},
+};
+
+pub struct RedisLoginAttemptStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisLoginAttemptStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for RedisLoginAttemptStore {
+    async fn record_failure(&mut self, email: &Email) -> Result<u32, LoginAttemptStoreError> {
+        let key = get_key(FAILED_LOGIN_PREFIX, email);
+        let mut conn = self.conn.write().await;
+        let failures: u32 = conn
+            .incr(&key, 1)
+            .wrap_err("failed to count failed login in Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+
+        // The window starts with the first failure, so later ones don't keep extending it
+        if failures == 1 {
+            conn.expire::<_, ()>(&key, LOGIN_FAILURE_WINDOW_SECONDS as i64)
+                .wrap_err("failed to set failed login expiry in Redis")
+                .map_err(LoginAttemptStoreError::UnexpectedError)?;
+        }
+
+        Ok(failures)
+    }
+
+    async fn clear_failures(&mut self, email: &Email) -> Result<(), LoginAttemptStoreError> {
+        let key = get_key(FAILED_LOGIN_PREFIX, email);
+        let mut conn = self.conn.write().await;
+        conn.del::<_, ()>(&key)
+            .wrap_err("failed to clear failed logins from Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)
+    }
+
+    async fn lock(&mut self, email: &Email, duration_seconds: u64) -> Result<(), LoginAttemptStoreError> {
+        let lock_key = get_key(LOGIN_LOCK_PREFIX, email);
+        let count_key = get_key(LOGIN_LOCK_COUNT_PREFIX, email);
+        let failures_key = get_key(FAILED_LOGIN_PREFIX, email);
+        let mut conn = self.conn.write().await;
+
+        conn.set_ex::<_, _, ()>(&lock_key, 1, duration_seconds)
+            .wrap_err("failed to set login lock in Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+        conn.del::<_, ()>(&failures_key)
+            .wrap_err("failed to clear failed logins from Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+        conn.incr::<_, _, ()>(&count_key, 1)
+            .wrap_err("failed to count login lock in Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+        conn.expire::<_, ()>(&count_key, LOGIN_LOCKOUT_ESCALATION_WINDOW_SECONDS as i64)
+            .wrap_err("failed to set login lock count expiry in Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)
+    }
+
+    async fn lock_count(&self, email: &Email) -> Result<u32, LoginAttemptStoreError> {
+        let key = get_key(LOGIN_LOCK_COUNT_PREFIX, email);
+        let mut conn = self.conn.write().await;
+        let count: Option<u32> = conn
+            .get(&key)
+            .wrap_err("failed to get login lock count from Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+        Ok(count.unwrap_or(0))
+    }
+
+    async fn lock_remaining(&self, email: &Email) -> Result<Option<u64>, LoginAttemptStoreError> {
+        let key = get_key(LOGIN_LOCK_PREFIX, email);
+        let mut conn = self.conn.write().await;
+        // TTL is negative when the key doesn't exist
+        let ttl: i64 = conn
+            .ttl(&key)
+            .wrap_err("failed to get login lock from Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+        Ok((ttl > 0).then_some(ttl as u64))
+    }
+}
+
+const FAILED_LOGIN_PREFIX: &str = "failed_login:";
+const LOGIN_LOCK_PREFIX: &str = "login_lock:";
+const LOGIN_LOCK_COUNT_PREFIX: &str = "login_lock_count:";
+
+fn get_key(prefix: &str, email: &Email) -> String {
+    format!("{}{}", prefix, email.as_ref())
+}
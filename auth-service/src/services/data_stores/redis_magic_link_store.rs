@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::data_stores::{MagicLinkId, MagicLinkNonce, MagicLinkStore, MagicLinkStoreError},
+    utils::auth::MAGIC_LINK_TTL_SECONDS,
+};
+
+pub struct RedisMagicLinkStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisMagicLinkStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for RedisMagicLinkStore {
+    async fn add_link(&mut self, link_id: MagicLinkId, nonce: MagicLinkNonce) -> Result<(), MagicLinkStoreError> {
+        let key = get_key(&link_id);
+        let mut conn = self.conn.write().await;
+        // Expires together with the signed link it belongs to
+        conn.set_ex::<_, _, ()>(&key, nonce.as_ref(), MAGIC_LINK_TTL_SECONDS as u64)
+            .wrap_err("failed to set magic link in Redis")
+            .map_err(MagicLinkStoreError::UnexpectedError)
+    }
+
+    async fn take_link(&mut self, link_id: &MagicLinkId) -> Result<MagicLinkNonce, MagicLinkStoreError> {
+        let key = get_key(link_id);
+        let mut conn = self.conn.write().await;
+        // GETDEL makes sure two concurrent clicks can't both consume the same link
+        let value: Option<String> = conn
+            .get_del(&key)
+            .wrap_err("failed to take magic link from Redis")
+            .map_err(MagicLinkStoreError::UnexpectedError)?;
+        let value = value.ok_or(MagicLinkStoreError::LinkNotFound)?;
+
+        MagicLinkNonce::parse(value).map_err(MagicLinkStoreError::UnexpectedError)
+    }
+}
+
+const MAGIC_LINK_PREFIX: &str = "magic_link:";
+
+fn get_key(link_id: &MagicLinkId) -> String {
+    format!("{}{}", MAGIC_LINK_PREFIX, link_id.as_ref())
+}
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError},
+        Email,
+    },
+    utils::constants::PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisPasswordResetTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPasswordResetTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for RedisPasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let key = get_key(&email);
+        let mut conn = self.conn.write().await;
+        conn.set_ex::<_, _, ()>(&key, token.as_ref(), PASSWORD_RESET_TOKEN_TTL_SECONDS)
+            .wrap_err("failed to set password reset token in Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)
+    }
+
+    async fn remove_token(&mut self, email: &Email) -> Result<(), PasswordResetTokenStoreError> {
+        let key = get_key(email);
+        let mut conn = self.conn.write().await;
+        conn.del::<_, ()>(&key)
+            .wrap_err("failed to delete password reset token from Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)
+    }
+
+    async fn get_token(
+        &self,
+        email: &Email,
+    ) -> Result<PasswordResetToken, PasswordResetTokenStoreError> {
+        let key = get_key(email);
+        let mut conn = self.conn.write().await;
+        let token: String = conn.get(&key).map_err(|_| PasswordResetTokenStoreError::TokenNotFound)?;
+
+        PasswordResetToken::parse(token).map_err(PasswordResetTokenStoreError::UnexpectedError)
+    }
+}
+
+const PASSWORD_RESET_TOKEN_PREFIX: &str = "password_reset_token:";
+
+fn get_key(email: &Email) -> String {
+    format!("{}{}", PASSWORD_RESET_TOKEN_PREFIX, email.as_ref())
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{PhoneVerificationCodeStore, PhoneVerificationCodeStoreError, TwoFACode},
+        Email, PhoneNumber,
+    },
+    utils::constants::PHONE_VERIFICATION_CODE_TTL_SECONDS,
+};
+
+pub struct RedisPhoneVerificationCodeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPhoneVerificationCodeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PhoneVerificationCodeStore for RedisPhoneVerificationCodeStore {
+    async fn add_code(
+        &mut self,
+        email: Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), PhoneVerificationCodeStoreError> {
+        let pending = PendingVerification(phone_number.as_ref().to_owned(), code.as_ref().to_owned());
+        let serialized = serde_json::to_string(&pending)
+            .wrap_err("failed to serialize phone verification code")
+            .map_err(PhoneVerificationCodeStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+        conn.set_ex::<_, _, ()>(get_key(&email), serialized, PHONE_VERIFICATION_CODE_TTL_SECONDS)
+            .wrap_err("failed to set phone verification code in Redis")
+            .map_err(PhoneVerificationCodeStoreError::UnexpectedError)?;
+        // A new code gets a fresh set of attempts
+        conn.del::<_, ()>(get_attempts_key(&email))
+            .wrap_err("failed to reset phone verification attempts in Redis")
+            .map_err(PhoneVerificationCodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn remove_code(&mut self, email: &Email) -> Result<(), PhoneVerificationCodeStoreError> {
+        let keys = [get_key(email), get_attempts_key(email)];
+        let mut conn = self.conn.write().await;
+        conn.del(&keys)
+            .wrap_err("failed to delete phone verification code from Redis")
+            .map_err(PhoneVerificationCodeStoreError::UnexpectedError)
+    }
+
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(PhoneNumber, TwoFACode), PhoneVerificationCodeStoreError> {
+        let mut conn = self.conn.write().await;
+        let serialized: Option<String> = conn
+            .get(get_key(email))
+            .wrap_err("failed to get phone verification code from Redis")
+            .map_err(PhoneVerificationCodeStoreError::UnexpectedError)?;
+        let serialized = serialized.ok_or(PhoneVerificationCodeStoreError::CodeNotFound)?;
+
+        let pending: PendingVerification = serde_json::from_str(&serialized)
+            .wrap_err("failed to deserialize phone verification code")
+            .map_err(PhoneVerificationCodeStoreError::UnexpectedError)?;
+        let phone_number = PhoneNumber::parse(pending.0)
+            .map_err(|e| PhoneVerificationCodeStoreError::UnexpectedError(eyre!(e)))?;
+        let code = TwoFACode::parse(pending.1)
+            .map_err(|_| PhoneVerificationCodeStoreError::UnexpectedError(eyre!("Invalid phone verification code")))?;
+
+        Ok((phone_number, code))
+    }
+
+    async fn record_attempt(&mut self, email: &Email) -> Result<u32, PhoneVerificationCodeStoreError> {
+        let key = get_attempts_key(email);
+        let mut conn = self.conn.write().await;
+        let attempts: u32 = conn
+            .incr(&key, 1)
+            .wrap_err("failed to count phone verification attempt in Redis")
+            .map_err(PhoneVerificationCodeStoreError::UnexpectedError)?;
+        // Never outlives the code it counts against
+        if attempts == 1 {
+            conn.expire::<_, ()>(&key, PHONE_VERIFICATION_CODE_TTL_SECONDS as i64)
+                .wrap_err("failed to set phone verification attempts expiry in Redis")
+                .map_err(PhoneVerificationCodeStoreError::UnexpectedError)?;
+        }
+        Ok(attempts)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PendingVerification(String, String);
+
+const PHONE_VERIFICATION_CODE_PREFIX: &str = "phone_verification_code:";
+const PHONE_VERIFICATION_ATTEMPTS_PREFIX: &str = "phone_verification_attempts:";
+
+fn get_key(email: &Email) -> String {
+    format!("{}{}", PHONE_VERIFICATION_CODE_PREFIX, email.as_ref())
+}
+
+fn get_attempts_key(email: &Email) -> String {
+    format!("{}{}", PHONE_VERIFICATION_ATTEMPTS_PREFIX, email.as_ref())
+}
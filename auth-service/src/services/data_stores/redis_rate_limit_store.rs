@@ -0,0 +1,63 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::Utc;
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::{
+    RateLimitBucket, RateLimitDecision, RateLimitPolicy, RateLimitStore, RateLimitStoreError,
+};
+
+pub struct RedisRateLimitStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn take_token(&mut self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, RateLimitStoreError> {
+        let key = get_key(key);
+        let mut conn = self.conn.write().await;
+
+        // Other replicas share the bucket, so the update is retried whenever one of them changed it in between
+        redis::transaction(&mut *conn, &[&key], |conn, pipe| {
+            let now_ms = Utc::now().timestamp_millis();
+            let stored: HashMap<String, String> = conn.hgetall(&key)?;
+            let mut bucket = parse_bucket(&stored).unwrap_or_else(|| RateLimitBucket::full(policy, now_ms));
+            let decision = bucket.take(policy, now_ms);
+
+            let result: Option<()> = pipe
+                .hset(&key, CREDIT_FIELD, bucket.credit_ms)
+                .ignore()
+                .hset(&key, UPDATED_AT_FIELD, bucket.updated_at_ms)
+                .ignore()
+                .pexpire(&key, policy.full_refill_ms() as i64)
+                .ignore()
+                .query(conn)?;
+            Ok(result.map(|_| decision))
+        })
+        .wrap_err("failed to take rate limit token in Redis")
+        .map_err(RateLimitStoreError::UnexpectedError)
+    }
+}
+
+fn parse_bucket(stored: &HashMap<String, String>) -> Option<RateLimitBucket> {
+    Some(RateLimitBucket {
+        credit_ms: stored.get(CREDIT_FIELD)?.parse().ok()?,
+        updated_at_ms: stored.get(UPDATED_AT_FIELD)?.parse().ok()?,
+    })
+}
+
+const RATE_LIMIT_PREFIX: &str = "rate_limit:";
+const CREDIT_FIELD: &str = "credit_ms";
+const UPDATED_AT_FIELD: &str = "updated_at";
+
+fn get_key(key: &str) -> String {
+    format!("{}{}", RATE_LIMIT_PREFIX, key)
+}
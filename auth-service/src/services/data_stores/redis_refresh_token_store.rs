@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{RefreshToken, RefreshTokenStore, RefreshTokenStoreError},
+        Authentication, Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: RefreshToken,
+        authentication: Authentication,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let family_key = get_family_key(token.family_id());
+        let user_key = get_user_key(&email);
+        let ttl = REFRESH_TOKEN_TTL_SECONDS as u64;
+        let entry = RefreshTokenTuple(
+            email.as_ref().to_owned(),
+            token.as_ref().to_owned(),
+            authentication.auth_time,
+            authentication.amr(),
+        );
+        let serialized = serde_json::to_string(&entry)
+            .wrap_err("failed to serialize refresh token tuple")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+        conn.set_ex::<_, _, ()>(&family_key, serialized, ttl)
+            .wrap_err("failed to set refresh token in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        // Index the family under its owner so all of a user's families can be revoked at once
+        conn.sadd::<_, _, ()>(&user_key, token.family_id())
+            .wrap_err("failed to index refresh token family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        conn.expire::<_, ()>(&user_key, ttl as i64)
+            .wrap_err("failed to set expiry of refresh token family index in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)
+    }
+
+    async fn get_token(
+        &self,
+        family_id: &str,
+    ) -> Result<(Email, RefreshToken, Authentication), RefreshTokenStoreError> {
+        let key = get_family_key(family_id);
+        let mut conn = self.conn.write().await;
+        let value: String = conn.get(&key).map_err(|_| RefreshTokenStoreError::FamilyNotFound)?;
+
+        let entry: RefreshTokenTuple = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize refresh token tuple")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let email = Email::parse(entry.0)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError(eyre!("Invalid refresh token owner")))?;
+        let token = RefreshToken::parse(entry.1).map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let authentication = Authentication::from_amr(entry.2, &entry.3);
+
+        Ok((email, token, authentication))
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let key = get_family_key(family_id);
+        let mut conn = self.conn.write().await;
+        conn.del::<_, ()>(&key)
+            .wrap_err("failed to delete refresh token family from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)
+    }
+
+    async fn revoke_all_families(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let user_key = get_user_key(email);
+        let mut conn = self.conn.write().await;
+        let family_ids: Vec<String> = conn.smembers(&user_key)
+            .wrap_err("failed to get refresh token families from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        for family_id in family_ids {
+            conn.del::<_, ()>(get_family_key(&family_id))
+                .wrap_err("failed to delete refresh token family from Redis")
+                .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        }
+
+        conn.del::<_, ()>(&user_key)
+            .wrap_err("failed to delete refresh token family index from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenTuple(
+    pub String,
+    pub String,
+    // Families created before logins were recorded carry no authentication time or methods
+    #[serde(default)] pub i64,
+    #[serde(default)] pub Vec<String>,
+);
+
+const REFRESH_TOKEN_FAMILY_PREFIX: &str = "refresh_token_family:";
+const REFRESH_TOKEN_USER_FAMILIES_PREFIX: &str = "refresh_token_user_families:";
+
+fn get_family_key(family_id: &str) -> String {
+    format!("{}{}", REFRESH_TOKEN_FAMILY_PREFIX, family_id)
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!("{}{}", REFRESH_TOKEN_USER_FAMILIES_PREFIX, email.as_ref())
+}
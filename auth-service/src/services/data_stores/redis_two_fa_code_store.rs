@@ -6,9 +6,12 @@ use serde_json;
 use tokio::sync::RwLock;
 use color_eyre::eyre::Context;
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+        AuthMethod, Email,
+    },
+    utils::constants::TWO_FA_CODE_TTL_SECONDS,
 };
 
 pub struct RedisTwoFACodeStore {
@@ -28,25 +31,34 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
+        first_factor: AuthMethod,
     ) -> Result<(), TwoFACodeStoreError> {
         let key = get_key(&email);
-        let ttl = TEN_MINUTES_IN_SECONDS as u64;
+        let ttl = TWO_FA_CODE_TTL_SECONDS;
         let mut conn = self.conn.write().await;
-        let two_fa_tuple = TwoFATuple(login_attempt_id.as_ref().to_string(), code.as_ref().to_string());
+        let two_fa_tuple = TwoFATuple(
+            login_attempt_id.as_ref().to_string(),
+            code.as_ref().to_string(),
+            first_factor.as_str().to_owned(),
+        );
         let serialized = serde_json::to_string(&two_fa_tuple)
             .wrap_err("failed to serialize 2FA tuple")
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
         let _ = conn.set_ex(&key, serialized, ttl)
             .wrap_err("falied to set 2FA code in redis")
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
-        
+        // A new code gets a fresh set of attempts
+        conn.del::<_, ()>(get_attempts_key(&email))
+            .wrap_err("failed to reset 2FA attempts in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
         Ok(())
     }
 
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(&email);
+        let keys = [get_key(email), get_attempts_key(email)];
         let mut conn = self.conn.write().await;
-        conn.del(&key)
+        conn.del(&keys)
             .wrap_err("failed to delete 2FA code from Redis")
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))
     }
@@ -54,7 +66,7 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     async fn get_code(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+    ) -> Result<(LoginAttemptId, TwoFACode, AuthMethod), TwoFACodeStoreError> {
         let key = get_key(&email);
         let mut conn = self.conn.write().await;
         let code: String = conn.get(&key).map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
@@ -69,16 +81,48 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         let two_fa_code = TwoFACode::parse(two_fa_tuple.1)
             .map_err(|_| TwoFACodeStoreError::UnexpectedError(color_eyre::eyre::eyre!("Invalid 2FA code").into()))?;
 
-        Ok((login_attempt_id, two_fa_code))
+        let first_factor = AuthMethod::parse(&two_fa_tuple.2)
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(color_eyre::eyre::eyre!(e)))?;
+
+        Ok((login_attempt_id, two_fa_code, first_factor))
+    }
+
+    async fn record_attempt(&mut self, email: &Email) -> Result<u32, TwoFACodeStoreError> {
+        let key = get_attempts_key(email);
+        let mut conn = self.conn.write().await;
+        let attempts: u32 = conn
+            .incr(&key, 1)
+            .wrap_err("failed to count 2FA attempt in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        // Never outlives the code it counts against
+        if attempts == 1 {
+            conn.expire::<_, ()>(&key, TWO_FA_CODE_TTL_SECONDS as i64)
+                .wrap_err("failed to set 2FA attempts expiry in Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        }
+        Ok(attempts)
     }
 }
 
 #[derive(Serialize, Deserialize)]
-struct TwoFATuple(pub String, pub String);
+struct TwoFATuple(
+    pub String,
+    pub String,
+    // Codes stored before the first factor was recorded all came from a password login
+    #[serde(default = "default_first_factor")] pub String,
+);
+
+fn default_first_factor() -> String {
+    AuthMethod::Password.as_str().to_owned()
+}
 
-const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts:";
 
 fn get_key(email: &Email) -> String {
     format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref())
+}
+
+fn get_attempts_key(email: &Email) -> String {
+    format!("{}{}", TWO_FA_ATTEMPTS_PREFIX, email.as_ref())
 }
\ No newline at end of file
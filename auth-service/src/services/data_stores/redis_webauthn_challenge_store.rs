@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{
+        LoginAttemptId, WebauthnCeremony, WebauthnChallenge, WebauthnChallengeStore,
+        WebauthnChallengeStoreError,
+    },
+    Email,
+};
+
+pub struct RedisWebauthnChallengeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisWebauthnChallengeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebauthnChallengeStore for RedisWebauthnChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: WebauthnChallenge,
+        ceremony: WebauthnCeremony,
+    ) -> Result<(), WebauthnChallengeStoreError> {
+        let key = get_key(&challenge);
+        let serialized = serde_json::to_string(&StoredCeremony::from(ceremony))
+            .wrap_err("failed to serialize WebAuthn ceremony")
+            .map_err(WebauthnChallengeStoreError::UnexpectedError)?;
+        let mut conn = self.conn.write().await;
+        conn.set_ex::<_, _, ()>(&key, serialized, WEBAUTHN_CHALLENGE_TTL_SECONDS)
+            .wrap_err("failed to set WebAuthn challenge in Redis")
+            .map_err(WebauthnChallengeStoreError::UnexpectedError)
+    }
+
+    async fn take_challenge(
+        &mut self,
+        challenge: &WebauthnChallenge,
+    ) -> Result<WebauthnCeremony, WebauthnChallengeStoreError> {
+        let key = get_key(challenge);
+        let mut conn = self.conn.write().await;
+        // GETDEL makes sure two concurrent ceremonies can't both consume the same challenge
+        let value: Option<String> = conn
+            .get_del(&key)
+            .wrap_err("failed to take WebAuthn challenge from Redis")
+            .map_err(WebauthnChallengeStoreError::UnexpectedError)?;
+        let value = value.ok_or(WebauthnChallengeStoreError::ChallengeNotFound)?;
+
+        let stored: StoredCeremony = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize WebAuthn ceremony")
+            .map_err(WebauthnChallengeStoreError::UnexpectedError)?;
+        stored.try_into().map_err(WebauthnChallengeStoreError::UnexpectedError)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum StoredCeremony {
+    Registration(String),
+    SecondFactor(String, String),
+    Passwordless(Option<String>),
+}
+
+impl From<WebauthnCeremony> for StoredCeremony {
+    fn from(ceremony: WebauthnCeremony) -> Self {
+        match ceremony {
+            WebauthnCeremony::Registration(email) => Self::Registration(email.0),
+            WebauthnCeremony::SecondFactor(email, login_attempt_id) => {
+                Self::SecondFactor(email.0, login_attempt_id.as_ref().to_owned())
+            }
+            WebauthnCeremony::Passwordless(email) => Self::Passwordless(email.map(|e| e.0)),
+        }
+    }
+}
+
+impl TryFrom<StoredCeremony> for WebauthnCeremony {
+    type Error = color_eyre::eyre::Report;
+
+    fn try_from(stored: StoredCeremony) -> Result<Self, Self::Error> {
+        let parse_email = |email: String| Email::parse(email).map_err(|e| eyre!(e));
+        Ok(match stored {
+            StoredCeremony::Registration(email) => Self::Registration(parse_email(email)?),
+            StoredCeremony::SecondFactor(email, login_attempt_id) => {
+                Self::SecondFactor(parse_email(email)?, LoginAttemptId::parse(login_attempt_id)?)
+            }
+            StoredCeremony::Passwordless(email) => Self::Passwordless(email.map(parse_email).transpose()?),
+        })
+    }
+}
+
+const WEBAUTHN_CHALLENGE_TTL_SECONDS: u64 = 300; // 5 minutes
+const WEBAUTHN_CHALLENGE_PREFIX: &str = "webauthn_challenge:";
+
+fn get_key(challenge: &WebauthnChallenge) -> String {
+    format!("{}{}", WEBAUTHN_CHALLENGE_PREFIX, challenge.as_ref())
+}
@@ -0,0 +1,195 @@
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use color_eyre::eyre::Result;
+
+use crate::{
+    app_state::{EmailClientType, EmailOutboxStoreType},
+    utils::constants::{
+        EMAIL_OUTBOX_BATCH_SIZE, EMAIL_OUTBOX_DEAD_RETENTION_SECONDS, EMAIL_OUTBOX_LEASE_SECONDS, EMAIL_OUTBOX_MAX_ATTEMPTS,
+        EMAIL_OUTBOX_RETRY_BASE_SECONDS, EMAIL_OUTBOX_RETRY_MAX_SECONDS, EMAIL_OUTBOX_SWEEP_INTERVAL_SECONDS,
+    },
+};
+
+const EXPIRED_ERROR: &str = "expired before delivery";
+
+// Delivers the emails routes put in the outbox, retrying failures until they run out of attempts or expire
+pub struct EmailOutboxWorker {
+    outbox: EmailOutboxStoreType,
+    email_client: EmailClientType,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(outbox: EmailOutboxStoreType, email_client: EmailClientType) -> Self {
+        Self { outbox, email_client }
+    }
+
+    // Polls the outbox until the process exits, going straight for the next batch while there is a backlog
+    pub async fn run(self, poll_interval: Duration) {
+        let mut last_sweep: Option<Instant> = None;
+        loop {
+            if last_sweep.is_none_or(|at| at.elapsed() >= Duration::from_secs(EMAIL_OUTBOX_SWEEP_INTERVAL_SECONDS)) {
+                if let Err(e) = self.purge_dead_letters().await {
+                    tracing::error!("Failed to purge dead outbox emails: {:?}", e);
+                }
+                last_sweep = Some(Instant::now());
+            }
+            match self.deliver_due().await {
+                Ok(claimed) if claimed == EMAIL_OUTBOX_BATCH_SIZE as usize => continue,
+                Ok(_) => {},
+                Err(e) => tracing::error!("Failed to deliver outbox emails: {:?}", e),
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    // Sends one batch of due emails and returns how many were attempted
+    #[tracing::instrument(name = "Delivering outbox emails", skip_all)]
+    pub async fn deliver_due(&self) -> Result<usize> {
+        let emails = self.outbox.write().await.claim_due(EMAIL_OUTBOX_BATCH_SIZE, EMAIL_OUTBOX_LEASE_SECONDS).await?;
+
+        for email in &emails {
+            // A code or link that no longer works would only confuse the recipient
+            let now = Utc::now().timestamp();
+            if email.expires_at.is_some_and(|expires_at| expires_at <= now) {
+                tracing::warn!("Email {} expired before it could be delivered, dead-lettering it", email.id.as_ref());
+                self.outbox.write().await.mark_failed(&email.id, EXPIRED_ERROR, None).await?;
+                continue;
+            }
+
+            match self.email_client.send_email(&email.recipient, &email.content).await {
+                Ok(()) => self.outbox.write().await.mark_sent(&email.id).await?,
+                Err(e) => {
+                    let retry_in_seconds = (email.attempts < EMAIL_OUTBOX_MAX_ATTEMPTS)
+                        .then(|| retry_delay(email.attempts))
+                        .filter(|seconds| email.expires_at.is_none_or(|expires_at| now + (*seconds as i64) < expires_at));
+                    match retry_in_seconds {
+                        Some(seconds) => tracing::warn!("Email {} failed, retrying in {}s: {}", email.id.as_ref(), seconds, e),
+                        None => tracing::error!("Email {} failed {} times, dead-lettering it: {}", email.id.as_ref(), email.attempts, e),
+                    }
+                    self.outbox.write().await.mark_failed(&email.id, &e, retry_in_seconds).await?;
+                }
+            }
+        }
+
+        Ok(emails.len())
+    }
+
+    // Dead emails hold the recipient's address and whatever codes or links they carried, so they are only
+    // kept long enough to be looked into and replayed
+    #[tracing::instrument(name = "Purging dead outbox emails", skip_all)]
+    pub async fn purge_dead_letters(&self) -> Result<u64> {
+        let created_before = Utc::now().timestamp() - EMAIL_OUTBOX_DEAD_RETENTION_SECONDS;
+        let purged = self.outbox.write().await.purge_dead(created_before).await?;
+        if purged > 0 {
+            tracing::info!("Purged {} dead outbox emails", purged);
+        }
+        Ok(purged)
+    }
+}
+
+// Every failed attempt doubles the wait before the next one, up to the cap
+fn retry_delay(attempts: u32) -> u64 {
+    EMAIL_OUTBOX_RETRY_BASE_SECONDS
+        .saturating_mul(1u64.checked_shl(attempts.saturating_sub(1)).unwrap_or(u64::MAX))
+        .min(EMAIL_OUTBOX_RETRY_MAX_SECONDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::domain::{data_stores::OutboxStatus, Email, EmailContent};
+    use crate::services::{mock_email_client::MockEmailClient, HashmapEmailOutboxStore};
+
+    fn worker(email_client: &MockEmailClient) -> (EmailOutboxWorker, EmailOutboxStoreType) {
+        let outbox: EmailOutboxStoreType = Arc::new(RwLock::new(HashmapEmailOutboxStore::default()));
+        (EmailOutboxWorker::new(outbox.clone(), Arc::new(email_client.clone())), outbox)
+    }
+
+    fn content() -> EmailContent {
+        EmailContent { subject: "Subject".to_owned(), text: "Text".to_owned(), html: "<p>Html</p>".to_owned() }
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        assert_eq!(retry_delay(1), EMAIL_OUTBOX_RETRY_BASE_SECONDS);
+        assert_eq!(retry_delay(2), EMAIL_OUTBOX_RETRY_BASE_SECONDS * 2);
+        assert_eq!(retry_delay(3), EMAIL_OUTBOX_RETRY_BASE_SECONDS * 4);
+        assert_eq!(retry_delay(64), EMAIL_OUTBOX_RETRY_MAX_SECONDS);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_due_sends_and_removes_emails() {
+        let email_client = MockEmailClient::default();
+        let (worker, outbox) = worker(&email_client);
+        let recipient = Email::parse("test@example.com".to_owned()).unwrap();
+        outbox.write().await.enqueue(&recipient, &content(), None).await.unwrap();
+
+        assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        assert_eq!(email_client.last_email_to(&recipient).await, Some(content()));
+        assert_eq!(outbox.read().await.list(OutboxStatus::Pending, 0, 10).await.unwrap().1, 0);
+        assert_eq!(worker.deliver_due().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_due_backs_off_then_dead_letters() {
+        let email_client = MockEmailClient::default();
+        email_client.set_failing(true);
+        let (worker, outbox) = worker(&email_client);
+        let recipient = Email::parse("test@example.com".to_owned()).unwrap();
+        let id = outbox.write().await.enqueue(&recipient, &content(), None).await.unwrap();
+
+        assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        let (pending, _) = outbox.read().await.list(OutboxStatus::Pending, 0, 10).await.unwrap();
+        assert_eq!(pending[0].attempts, 1);
+        assert!(pending[0].last_error.is_some());
+        assert!(pending[0].next_attempt_at >= pending[0].created_at + EMAIL_OUTBOX_RETRY_BASE_SECONDS as i64);
+        // Not due again until the backoff is over
+        assert_eq!(worker.deliver_due().await.unwrap(), 0);
+
+        for _ in 1..EMAIL_OUTBOX_MAX_ATTEMPTS {
+            outbox.write().await.mark_failed(&id, "fast forward", Some(0)).await.unwrap();
+            assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        }
+        let (dead, _) = outbox.read().await.list(OutboxStatus::Dead, 0, 10).await.unwrap();
+        assert_eq!(dead[0].attempts, EMAIL_OUTBOX_MAX_ATTEMPTS);
+        assert!(email_client.last_email_to(&recipient).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_deliver_due_dead_letters_expired_emails() {
+        let email_client = MockEmailClient::default();
+        let (worker, outbox) = worker(&email_client);
+        let recipient = Email::parse("test@example.com".to_owned()).unwrap();
+        let expires_at = Utc::now().timestamp() - 1;
+        outbox.write().await.enqueue(&recipient, &content(), Some(expires_at)).await.unwrap();
+
+        assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        assert!(email_client.last_email_to(&recipient).await.is_none());
+        let (dead, _) = outbox.read().await.list(OutboxStatus::Dead, 0, 10).await.unwrap();
+        assert_eq!(dead[0].last_error.as_deref(), Some(EXPIRED_ERROR));
+
+        // Kept for a while so it can be looked into
+        assert_eq!(worker.purge_dead_letters().await.unwrap(), 0);
+        assert_eq!(outbox.read().await.list(OutboxStatus::Dead, 0, 10).await.unwrap().1, 1);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_due_does_not_retry_past_expiry() {
+        let email_client = MockEmailClient::default();
+        email_client.set_failing(true);
+        let (worker, outbox) = worker(&email_client);
+        let recipient = Email::parse("test@example.com".to_owned()).unwrap();
+        let expires_at = Utc::now().timestamp() + EMAIL_OUTBOX_RETRY_BASE_SECONDS as i64 - 1;
+        outbox.write().await.enqueue(&recipient, &content(), Some(expires_at)).await.unwrap();
+
+        assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        let (dead, _) = outbox.read().await.list(OutboxStatus::Dead, 0, 10).await.unwrap();
+        assert_eq!(dead[0].attempts, 1);
+        assert_eq!(dead[0].expires_at, Some(expires_at));
+    }
+}
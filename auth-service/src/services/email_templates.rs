@@ -0,0 +1,492 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+};
+
+use color_eyre::eyre::{eyre, Result};
+
+use crate::domain::EmailContent;
+
+// Every template must exist in this locale, other locales fall back to it for the templates they don't translate
+pub const DEFAULT_LOCALE: &str = "en";
+
+const SUBJECT_FILE: &str = "subject.txt";
+const TEXT_FILE: &str = "body.txt";
+const HTML_FILE: &str = "body.html";
+
+// Variables every template may use on top of its own
+const COMMON_VARIABLES: [&str; 2] = ["email", "ip"];
+
+// The emails the service sends, each read from `<locale>/<name>/` in the templates directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmailTemplateKind {
+    TwoFACode,
+    AccountLocked,
+    PasswordReset,
+    EmailVerification,
+    MagicLink,
+}
+
+impl EmailTemplateKind {
+    pub const ALL: [EmailTemplateKind; 5] = [
+        EmailTemplateKind::TwoFACode,
+        EmailTemplateKind::AccountLocked,
+        EmailTemplateKind::PasswordReset,
+        EmailTemplateKind::EmailVerification,
+        EmailTemplateKind::MagicLink,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplateKind::TwoFACode => "two_fa_code",
+            EmailTemplateKind::AccountLocked => "account_locked",
+            EmailTemplateKind::PasswordReset => "password_reset",
+            EmailTemplateKind::EmailVerification => "email_verification",
+            EmailTemplateKind::MagicLink => "magic_link",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    // The email is useless without these, so both bodies have to show them
+    fn required_variables(&self) -> &'static [&'static str] {
+        match self {
+            EmailTemplateKind::TwoFACode => &["code"],
+            EmailTemplateKind::AccountLocked => &[],
+            EmailTemplateKind::PasswordReset => &["token"],
+            EmailTemplateKind::EmailVerification => &["token"],
+            EmailTemplateKind::MagicLink => &["link"],
+        }
+    }
+
+    fn optional_variables(&self) -> &'static [&'static str] {
+        match self {
+            EmailTemplateKind::TwoFACode => &["expiry_minutes"],
+            EmailTemplateKind::AccountLocked => &["lock_minutes"],
+            EmailTemplateKind::PasswordReset => &["expiry_minutes"],
+            EmailTemplateKind::EmailVerification => &["expiry_hours"],
+            EmailTemplateKind::MagicLink => &["expiry_minutes"],
+        }
+    }
+
+    fn allows(&self, variable: &str) -> bool {
+        COMMON_VARIABLES.contains(&variable)
+            || self.required_variables().contains(&variable)
+            || self.optional_variables().contains(&variable)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Variable(String),
+}
+
+// A template split into literal text and `{{ variable }}` placeholders
+#[derive(Debug, Clone, PartialEq)]
+struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    fn parse(source: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_owned()));
+            }
+            let after_open = &rest[start + 2..];
+            let end = after_open
+                .find("}}")
+                .ok_or_else(|| "unterminated {{ placeholder".to_owned())?;
+            let name = after_open[..end].trim();
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+                return Err(format!("invalid placeholder {{{{{}}}}}", &after_open[..end]));
+            }
+            segments.push(Segment::Variable(name.to_owned()));
+            rest = &after_open[end + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_owned()));
+        }
+
+        Ok(Self { segments })
+    }
+
+    fn variables(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Variable(name) => Some(name.as_str()),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    fn render(&self, variables: &HashMap<&str, String>, escape: fn(&str) -> String) -> Result<String> {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => output.push_str(text),
+                Segment::Variable(name) => {
+                    let value = variables
+                        .get(name.as_str())
+                        .ok_or_else(|| eyre!("No value for email template variable {}", name))?;
+                    output.push_str(&escape(value));
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LocalizedTemplate {
+    subject: Template,
+    text: Template,
+    html: Template,
+}
+
+// Email copy loaded from disk at startup, so it can change without a code change
+#[derive(Debug, Clone)]
+pub struct EmailTemplates {
+    templates: HashMap<String, HashMap<EmailTemplateKind, LocalizedTemplate>>,
+}
+
+impl EmailTemplates {
+    // Reads `<dir>/<locale>/<template>/{subject.txt,body.txt,body.html}` and reports every problem found at once
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir)
+            .map_err(|e| eyre!("Failed to read email templates directory {}: {}", dir.display(), e))?;
+
+        let mut templates = HashMap::new();
+        let mut problems = Vec::new();
+
+        for entry in entries {
+            let path = entry.map_err(|e| eyre!("Failed to read email templates directory: {}", e))?.path();
+            // Loose files such as a README sit next to the locales
+            if !path.is_dir() {
+                continue;
+            }
+            let Some(locale) = path.file_name().and_then(|name| name.to_str()).map(str::to_ascii_lowercase) else {
+                problems.push(format!("{}: locale directory name is not valid UTF-8", path.display()));
+                continue;
+            };
+            if !is_valid_locale(&locale) {
+                problems.push(format!("{}: {} is not a locale like en or pt-br", path.display(), locale));
+                continue;
+            }
+            let localized = load_locale(&path, &mut problems)?;
+            if templates.insert(locale.clone(), localized).is_some() {
+                problems.push(format!("{}: locale {} is defined twice", path.display(), locale));
+            }
+        }
+
+        let default_templates = templates.get(DEFAULT_LOCALE);
+        for kind in EmailTemplateKind::ALL {
+            if !default_templates.is_some_and(|localized| localized.contains_key(&kind)) {
+                problems.push(format!("{}: missing required template {}", dir.join(DEFAULT_LOCALE).display(), kind.name()));
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(eyre!("Invalid email templates:\n{}", problems.join("\n")));
+        }
+        Ok(Self { templates })
+    }
+
+    // Picks the best available locale from an Accept-Language header, honouring q-values
+    pub fn negotiate_locale(&self, accept_language: Option<&str>) -> &str {
+        let mut preferences: Vec<(String, f32)> = accept_language
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let tag = parts.next()?.trim().to_ascii_lowercase();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable, so equally weighted tags keep the client's order
+        preferences.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        for (tag, _) in preferences {
+            let primary = tag.split('-').next().unwrap_or_default();
+            for candidate in [tag.as_str(), primary] {
+                if let Some((locale, _)) = self.templates.get_key_value(candidate) {
+                    return locale;
+                }
+            }
+        }
+        DEFAULT_LOCALE
+    }
+
+    // Fills in `kind` for `locale`, falling back to the default locale when it has no translation
+    pub fn render(&self, kind: EmailTemplateKind, locale: &str, variables: &HashMap<&str, String>) -> Result<EmailContent> {
+        let template = self
+            .templates
+            .get(locale)
+            .and_then(|localized| localized.get(&kind))
+            .or_else(|| self.templates.get(DEFAULT_LOCALE).and_then(|localized| localized.get(&kind)))
+            .ok_or_else(|| eyre!("No {} email template", kind.name()))?;
+
+        Ok(EmailContent {
+            subject: template.subject.render(variables, str::to_owned)?,
+            text: template.text.render(variables, str::to_owned)?,
+            html: template.html.render(variables, escape_html)?,
+        })
+    }
+}
+
+fn load_locale(dir: &Path, problems: &mut Vec<String>) -> Result<HashMap<EmailTemplateKind, LocalizedTemplate>> {
+    let mut localized = HashMap::new();
+    let entries = fs::read_dir(dir).map_err(|e| eyre!("Failed to read {}: {}", dir.display(), e))?;
+
+    for entry in entries {
+        let path = entry.map_err(|e| eyre!("Failed to read {}: {}", dir.display(), e))?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        let Some(kind) = EmailTemplateKind::from_name(name) else {
+            problems.push(format!("{}: unknown email template {}", path.display(), name));
+            continue;
+        };
+        if let Some(template) = load_template(&path, kind, problems) {
+            localized.insert(kind, template);
+        }
+    }
+
+    Ok(localized)
+}
+
+fn load_template(dir: &Path, kind: EmailTemplateKind, problems: &mut Vec<String>) -> Option<LocalizedTemplate> {
+    let before = problems.len();
+    let subject = read_part(&dir.join(SUBJECT_FILE), kind, false, problems);
+    let text = read_part(&dir.join(TEXT_FILE), kind, true, problems);
+    let html = read_part(&dir.join(HTML_FILE), kind, true, problems);
+
+    if let Some(subject) = &subject {
+        if subject.segments.iter().any(|segment| matches!(segment, Segment::Literal(text) if text.contains('\n'))) {
+            problems.push(format!("{}: subject must be a single line", dir.join(SUBJECT_FILE).display()));
+        }
+    }
+
+    if problems.len() > before {
+        return None;
+    }
+    Some(LocalizedTemplate { subject: subject?, text: text?, html: html? })
+}
+
+fn read_part(path: &Path, kind: EmailTemplateKind, is_body: bool, problems: &mut Vec<String>) -> Option<Template> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            problems.push(format!("{}: {}", path.display(), e));
+            return None;
+        }
+    };
+    // Editors add a trailing newline, which would otherwise end up in subjects
+    let source = if is_body { source.as_str() } else { source.trim() };
+    if source.trim().is_empty() {
+        problems.push(format!("{}: must not be empty", path.display()));
+        return None;
+    }
+
+    let template = match Template::parse(source) {
+        Ok(template) => template,
+        Err(e) => {
+            problems.push(format!("{}: {}", path.display(), e));
+            return None;
+        }
+    };
+
+    let before = problems.len();
+    for variable in template.variables() {
+        if !kind.allows(variable) {
+            problems.push(format!("{}: unknown variable {} for {}", path.display(), variable, kind.name()));
+        }
+    }
+    if is_body {
+        for required in kind.required_variables() {
+            if !template.variables().any(|variable| variable == *required) {
+                problems.push(format!("{}: must use {{{{{}}}}}", path.display(), required));
+            }
+        }
+    }
+
+    (problems.len() == before).then_some(template)
+}
+
+fn is_valid_locale(locale: &str) -> bool {
+    let mut subtags = locale.split('-');
+    let language = subtags.next().unwrap_or_default();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && subtags.all(|subtag| (2..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+pub(crate) fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // A scratch templates directory, removed when dropped
+    struct TemplateDir(PathBuf);
+
+    impl TemplateDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("email_templates_{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, locale: &str, kind: &str, subject: &str, text: &str, html: &str) {
+            let dir = self.0.join(locale).join(kind);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(SUBJECT_FILE), subject).unwrap();
+            fs::write(dir.join(TEXT_FILE), text).unwrap();
+            fs::write(dir.join(HTML_FILE), html).unwrap();
+        }
+
+        // A minimal but complete default locale
+        fn with_defaults() -> Self {
+            let dir = Self::new();
+            for kind in EmailTemplateKind::ALL {
+                let body = kind
+                    .required_variables()
+                    .iter()
+                    .map(|variable| format!("{{{{{}}}}}", variable))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let body = format!("{} for {{{{ email }}}}", body);
+                dir.write(DEFAULT_LOCALE, kind.name(), kind.name(), &body, &format!("<p>{}</p>", body));
+            }
+            dir
+        }
+    }
+
+    impl Drop for TemplateDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn variables(pairs: &[(&'static str, &str)]) -> HashMap<&'static str, String> {
+        pairs.iter().map(|(name, value)| (*name, value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_template() {
+        let template = Template::parse("Code: {{ code }}, valid for {{expiry_minutes}} minutes").unwrap();
+        assert_eq!(template.variables().collect::<Vec<_>>(), vec!["code", "expiry_minutes"]);
+
+        assert!(Template::parse("Code: {{ code").is_err());
+        assert!(Template::parse("Code: {{ Code }}").is_err());
+        assert!(Template::parse("Code: {{}}").is_err());
+    }
+
+    #[test]
+    fn test_render_escapes_html_part_only() {
+        let dir = TemplateDir::with_defaults();
+        let templates = EmailTemplates::load(&dir.0).unwrap();
+
+        let content = templates
+            .render(EmailTemplateKind::MagicLink, DEFAULT_LOCALE, &variables(&[("link", "https://a.test/?x=1&y=<2>"), ("email", "a@b.c")]))
+            .unwrap();
+        assert_eq!(content.subject, "magic_link");
+        assert_eq!(content.text, "https://a.test/?x=1&y=<2> for a@b.c");
+        assert_eq!(content.html, "<p>https://a.test/?x=1&amp;y=&lt;2&gt; for a@b.c</p>");
+    }
+
+    #[test]
+    fn test_render_fails_without_variable_value() {
+        let dir = TemplateDir::with_defaults();
+        let templates = EmailTemplates::load(&dir.0).unwrap();
+
+        assert!(templates
+            .render(EmailTemplateKind::TwoFACode, DEFAULT_LOCALE, &variables(&[("code", "123456")]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_render_falls_back_to_default_locale() {
+        let dir = TemplateDir::with_defaults();
+        dir.write("fr", "two_fa_code", "Code", "Votre code : {{code}}", "<p>Votre code : {{code}}</p>");
+        let templates = EmailTemplates::load(&dir.0).unwrap();
+        let values = variables(&[("code", "123456"), ("token", "abc"), ("email", "a@b.c")]);
+
+        let content = templates.render(EmailTemplateKind::TwoFACode, "fr", &values).unwrap();
+        assert_eq!(content.text, "Votre code : 123456");
+        let content = templates.render(EmailTemplateKind::PasswordReset, "fr", &values).unwrap();
+        assert_eq!(content.text, "abc for a@b.c");
+    }
+
+    #[test]
+    fn test_negotiate_locale() {
+        let dir = TemplateDir::with_defaults();
+        dir.write("fr", "two_fa_code", "Code", "{{code}}", "{{code}}");
+        dir.write("pt-BR", "two_fa_code", "Code", "{{code}}", "{{code}}");
+        let templates = EmailTemplates::load(&dir.0).unwrap();
+
+        assert_eq!(templates.negotiate_locale(None), "en");
+        assert_eq!(templates.negotiate_locale(Some("fr-CA,fr;q=0.9")), "fr");
+        assert_eq!(templates.negotiate_locale(Some("pt-BR")), "pt-br");
+        assert_eq!(templates.negotiate_locale(Some("de, en;q=0.5, fr;q=0.8")), "fr");
+        assert_eq!(templates.negotiate_locale(Some("fr;q=0, de")), "en");
+        assert_eq!(templates.negotiate_locale(Some("*")), "en");
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_templates() {
+        let dir = TemplateDir::with_defaults();
+        dir.write("fr", "two_fa_code", "Code", "Votre code : {{ secret }}", "<p>{{code}}</p>");
+        dir.write("fr", "welcome", "Bienvenue", "Bonjour", "<p>Bonjour</p>");
+        dir.write("de", "magic_link", "Link\nzwei Zeilen", "{{link}}", "Kein Link");
+        fs::remove_file(dir.0.join(DEFAULT_LOCALE).join("account_locked").join(HTML_FILE)).unwrap();
+
+        let error = EmailTemplates::load(&dir.0).unwrap_err().to_string();
+        assert!(error.contains("unknown variable secret for two_fa_code"));
+        assert!(error.contains("must use {{code}}"));
+        assert!(error.contains("unknown email template welcome"));
+        assert!(error.contains("subject must be a single line"));
+        assert!(error.contains("must use {{link}}"));
+        assert!(error.contains("account_locked"));
+    }
+
+    #[test]
+    fn test_load_requires_complete_default_locale() {
+        let dir = TemplateDir::new();
+        dir.write(DEFAULT_LOCALE, "two_fa_code", "Code", "{{code}}", "{{code}}");
+
+        let error = EmailTemplates::load(&dir.0).unwrap_err().to_string();
+        assert!(error.contains("missing required template magic_link"));
+        assert!(!error.contains("missing required template two_fa_code"));
+    }
+
+    #[test]
+    fn test_shipped_templates_are_valid() {
+        let templates = EmailTemplates::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("email_templates")).unwrap();
+        assert!(templates.templates.len() > 1);
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Context, Result};
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::domain::{PhoneNumber, SmsClient};
+
+#[derive(Clone)]
+pub struct HttpSmsConfig {
+    // Endpoint every message is POSTed to as JSON
+    pub url: String,
+    // Sent as a bearer token, no Authorization header is sent when it is empty
+    pub api_key: String,
+    // Number or alphanumeric sender ID the provider shows as the sender
+    pub sender: String,
+}
+
+// Sends messages through an SMS provider's HTTP API
+pub struct HttpSmsClient {
+    http_client: reqwest::Client,
+    url: Url,
+    api_key: String,
+    sender: String,
+}
+
+impl HttpSmsClient {
+    pub fn new(config: HttpSmsConfig) -> Result<Self> {
+        let url = Url::parse(&config.url).wrap_err("Invalid SMS provider URL")?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(eyre!("SMS provider URL must be http or https"));
+        }
+        if config.sender.is_empty() {
+            return Err(eyre!("SMS sender must not be empty"));
+        }
+        let http_client = reqwest::Client::builder()
+            .timeout(SMS_TIMEOUT)
+            .build()
+            .wrap_err("Failed to build SMS HTTP client")?;
+
+        Ok(Self { http_client, url, api_key: config.api_key, sender: config.sender })
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for HttpSmsClient {
+    #[tracing::instrument(name = "Sending SMS over HTTP", skip_all)]
+    async fn send_sms(
+        &self,
+        recipient: &PhoneNumber,
+        message: &str,
+    ) -> Result<(), String> {
+        let body = SmsRequest { from: &self.sender, to: recipient.as_ref(), body: message };
+        let mut request = self.http_client.post(self.url.clone()).json(&body);
+        if !self.api_key.is_empty() {
+            request = request.bearer_auth(&self.api_key);
+        }
+
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("SMS provider responded with {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct SmsRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    body: &'a str,
+}
+
+const SMS_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(url: &str, sender: &str) -> HttpSmsConfig {
+        HttpSmsConfig { url: url.to_owned(), api_key: String::new(), sender: sender.to_owned() }
+    }
+
+    #[test]
+    fn test_new_validates_config() {
+        assert!(HttpSmsClient::new(config("https://sms.example.com/v1/messages", "AuthService")).is_ok());
+        assert!(HttpSmsClient::new(config("sms.example.com/v1/messages", "AuthService")).is_err());
+        assert!(HttpSmsClient::new(config("ftp://sms.example.com", "AuthService")).is_err());
+        assert!(HttpSmsClient::new(config("https://sms.example.com/v1/messages", "")).is_err());
+    }
+}
@@ -1,26 +1,62 @@
-use crate::domain::{Email, EmailClient};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::domain::{Email, EmailClient, EmailContent};
 
 use color_eyre::Result;
+use tokio::sync::RwLock;
 
 #[derive(Clone, Default)]
-pub struct MockEmailClient;
+pub struct MockEmailClient {
+    // Shared between clones so tests can read what the app sent
+    sent_emails: Arc<RwLock<Vec<(Email, EmailContent)>>>,
+    failing: Arc<AtomicBool>,
+}
+
+impl MockEmailClient {
+    // Makes every send fail until switched off again, to exercise retries
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst);
+    }
+
+    // Returns the latest email sent to `recipient`
+    pub async fn last_email_to(&self, recipient: &Email) -> Option<EmailContent> {
+        self.sent_emails
+            .read()
+            .await
+            .iter()
+            .rev()
+            .find(|(email, _)| email == recipient)
+            .map(|(_, content)| content.clone())
+    }
+}
 
 #[async_trait::async_trait]
 impl EmailClient for MockEmailClient {
     async fn send_email(
         &self,
         recipient: &Email,
-        subject: &str,
-        content: &str,
+        content: &EmailContent,
     ) -> Result<(), String> {
-        // Our mock email client will simply log the recipient, subject, and content to standard output
+        if self.failing.load(Ordering::SeqCst) {
+            return Err("Mock email client is failing".to_owned());
+        }
+
+        // Our mock email client will simply log the recipient, subject, and text part to standard output
         tracing::debug!(
             "Sending email to {} with subject: {} and content: {}",
             recipient.as_ref(),
-            subject,
-            content
+            content.subject,
+            content.text
         );
 
+        self.sent_emails
+            .write()
+            .await
+            .push((recipient.clone(), content.clone()));
+
         Ok(())
     }
-}
\ No newline at end of file
+}
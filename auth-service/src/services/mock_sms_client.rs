@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use crate::domain::{PhoneNumber, SmsClient};
+
+use color_eyre::Result;
+use tokio::sync::RwLock;
+
+#[derive(Clone, Default)]
+pub struct MockSmsClient {
+    // Shared between clones so tests can read what the app sent
+    sent_messages: Arc<RwLock<Vec<(PhoneNumber, String)>>>,
+}
+
+impl MockSmsClient {
+    // Returns the latest message sent to `recipient`
+    pub async fn last_sms_to(&self, recipient: &PhoneNumber) -> Option<String> {
+        self.sent_messages
+            .read()
+            .await
+            .iter()
+            .rev()
+            .find(|(phone_number, _)| phone_number == recipient)
+            .map(|(_, message)| message.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for MockSmsClient {
+    async fn send_sms(
+        &self,
+        recipient: &PhoneNumber,
+        message: &str,
+    ) -> Result<(), String> {
+        // Our mock SMS client will simply log the recipient and message to standard output
+        tracing::debug!("Sending SMS to {} with content: {}", recipient.as_ref(), message);
+
+        self.sent_messages
+            .write()
+            .await
+            .push((recipient.clone(), message.to_owned()));
+
+        Ok(())
+    }
+}
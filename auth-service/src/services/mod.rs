@@ -1,4 +1,10 @@
 pub mod data_stores;
+pub mod email_outbox_worker;
+pub mod email_templates;
+pub mod http_sms_client;
 pub mod mock_email_client;
+pub mod mock_sms_client;
+pub mod signing_key_refresher;
+pub mod smtp_email_client;
 
 pub use data_stores::*;
\ No newline at end of file
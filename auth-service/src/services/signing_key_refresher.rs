@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+
+use crate::{
+    app_state::SigningKeyStoreType,
+    utils::{
+        constants::{JWT_KEY_RING, JWT_SIGNING_KEYS},
+        signing_key::JwtKeyRing,
+    },
+};
+
+// Keeps the process wide JWT key ring in step with the signing key store, which is how a rotation
+// on one replica reaches the others
+pub struct SigningKeyRefresher {
+    store: SigningKeyStoreType,
+}
+
+impl SigningKeyRefresher {
+    pub fn new(store: SigningKeyStoreType) -> Self {
+        Self { store }
+    }
+
+    // Seeds an empty store with the configured keys, then switches to whatever the store holds
+    pub async fn initialize(&self) -> Result<()> {
+        self.store.write().await.add_initial_keys(JWT_SIGNING_KEYS.clone()).await?;
+        refresh_key_ring(&self.store).await
+    }
+
+    // Reloads the key ring until the process exits, keeping the current one when the store can't be read
+    pub async fn run(self, refresh_interval: Duration) {
+        loop {
+            tokio::time::sleep(refresh_interval).await;
+            if let Err(e) = refresh_key_ring(&self.store).await {
+                tracing::error!("Failed to refresh JWT key ring: {:?}", e);
+            }
+        }
+    }
+}
+
+#[tracing::instrument(name = "Refreshing JWT key ring", skip_all)]
+pub async fn refresh_key_ring(store: &SigningKeyStoreType) -> Result<()> {
+    let now = Utc::now().timestamp();
+    let keys = store.read().await.get_keys(now).await?;
+    let ring = JwtKeyRing::from_stored(&keys, now)?;
+    *JWT_KEY_RING.write().map_err(|_| eyre!("JWT key ring lock poisoned"))? = ring;
+    Ok(())
+}
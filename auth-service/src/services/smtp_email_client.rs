@@ -0,0 +1,106 @@
+use std::{str::FromStr, time::Duration};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::domain::{Email, EmailClient, EmailContent};
+
+// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmtpTls {
+    // Plain text, only meant for local relays and test sinks
+    None,
+    // Upgrades a plain connection with STARTTLS, usually on port 587
+    StartTls,
+    // TLS from the first byte, usually on port 465
+    Implicit,
+}
+
+impl FromStr for SmtpTls {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "starttls" => Ok(Self::StartTls),
+            "tls" => Ok(Self::Implicit),
+            _ => Err(eyre!("SMTP TLS mode must be none, starttls or tls")),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    // No authentication is attempted when the username is empty
+    pub username: String,
+    pub password: String,
+    pub sender: String,
+}
+
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Mailbox,
+}
+
+impl SmtpEmailClient {
+    pub fn new(config: SmtpConfig) -> Result<Self> {
+        let sender = config.sender.parse::<Mailbox>().wrap_err("Invalid email sender")?;
+
+        let builder = match config.tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                .wrap_err("Failed to configure STARTTLS")?,
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+                .wrap_err("Failed to configure TLS")?,
+        };
+        let mut builder = builder.port(config.port).timeout(Some(SMTP_TIMEOUT));
+        if !config.username.is_empty() {
+            builder = builder.credentials(Credentials::new(config.username, config.password));
+        }
+
+        Ok(Self { transport: builder.build(), sender })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email over SMTP", skip_all)]
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        content: &EmailContent,
+    ) -> Result<(), String> {
+        let recipient = recipient.as_ref().parse::<Mailbox>().map_err(|e| e.to_string())?;
+        let message = Message::builder()
+            .from(self.sender.clone())
+            .to(recipient)
+            .subject(&content.subject)
+            .multipart(MultiPart::alternative_plain_html(content.text.clone(), content.html.clone()))
+            .map_err(|e| e.to_string())?;
+
+        self.transport.send(message).await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smtp_tls_from_str() {
+        assert_eq!("none".parse::<SmtpTls>().unwrap(), SmtpTls::None);
+        assert_eq!("STARTTLS".parse::<SmtpTls>().unwrap(), SmtpTls::StartTls);
+        assert_eq!("tls".parse::<SmtpTls>().unwrap(), SmtpTls::Implicit);
+        assert!("ssl".parse::<SmtpTls>().is_err());
+    }
+}
@@ -1,17 +1,58 @@
+use axum::http::{header::AUTHORIZATION, HeaderMap};
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Utc;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
-use serde::{Deserialize, Serialize};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use color_eyre::eyre::{Context, ContextCompat, eyre, Result};
 
-use crate::{app_state::BannedTokenStoreType, domain::Email};
+use axum_extra::extract::CookieJar;
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use crate::{
+    app_state::{ApiKeyStoreType, BannedTokenStoreType, RefreshTokenStoreType, UserStoreType},
+    domain::{
+        data_stores::{MagicLinkId, MagicLinkNonce, RefreshToken},
+        ApiKeyToken, Audience, AuthAPIError, Authentication, Email, OAuthClientId, Role, Scopes, ADMIN_ROLE,
+    },
+};
+
+use super::constants::{ADMIN_API_TOKEN, AUTH_SERVICE_URL, JWT_COOKIE_NAME, JWT_KEY_RING, MAGIC_LINK_NONCE_COOKIE_NAME, REFRESH_COOKIE_NAME};
 
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 604_800; // 7 days
+pub const MAGIC_LINK_TTL_SECONDS: i64 = 900; // 15 minutes
+// Longest a signed JWT stays valid, including the 60 seconds of leeway `Validation` allows past `exp`
+pub const MAX_TOKEN_LIFETIME_SECONDS: i64 = if TOKEN_TTL_SECONDS > MAGIC_LINK_TTL_SECONDS {
+    TOKEN_TTL_SECONDS
+} else {
+    MAGIC_LINK_TTL_SECONDS
+} + 60;
+
+// Audience of session tokens, the ones set in the JWT cookie
+pub const AUTH_TOKEN_AUDIENCE: &str = "session";
+// Audience of magic link tokens, which keeps them from being accepted as auth tokens and vice versa
+const MAGIC_LINK_AUDIENCE: &str = "magic-link";
+
+// Issues a session token carrying the user's current roles
+pub async fn generate_auth_cookie(
+    email: &Email,
+    authentication: &Authentication,
+    user_store: UserStoreType,
+) -> Result<Cookie<'static>> {
+    let user = user_store
+        .read()
+        .await
+        .get_user(email.as_ref())
+        .await
+        .map_err(|e| eyre!("failed to load user roles: {:?}", e))?
+        .clone();
+    // Routes reject disabled accounts with a proper error, this only guards against one slipping through
+    if user.disabled {
+        return Err(eyre!("refusing to issue a token to a disabled account"));
+    }
+    let roles = user.roles;
 
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+    let token = generate_auth_token(email, &roles, authentication)?;
     Ok(create_auth_cookie(token))
 }
 
@@ -25,13 +66,39 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
+// Starts a new refresh token family for `email` and returns its first token as a cookie
+pub async fn generate_refresh_cookie(
+    email: &Email,
+    authentication: &Authentication,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<Cookie<'static>> {
+    let token = RefreshToken::default();
+    refresh_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone(), authentication.clone())
+        .await
+        .wrap_err("failed to store refresh token")?;
+
+    Ok(create_refresh_cookie(&token))
+}
+
+pub fn create_refresh_cookie(token: &RefreshToken) -> Cookie<'static> {
+    Cookie::build((REFRESH_COOKIE_NAME, token.as_ref().to_owned()))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        .build()
+}
+
 #[derive(Debug)]
 pub enum GenerateTokenError {
     TokenError(jsonwebtoken::errors::Error),
     UnexpectedError,
 }
 
-fn generate_auth_token(email: &Email) -> Result<String> {
+pub fn generate_auth_token(email: &Email, roles: &[Role], authentication: &Authentication) -> Result<String> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("failed to create 10 minute time delta")?;
 
@@ -47,9 +114,19 @@ fn generate_auth_token(email: &Email) -> Result<String> {
         exp
     ))?;
 
-    let sub = email.as_ref().to_owned();
+    let iat: usize = Utc::now().timestamp().try_into().wrap_err("failed to cast iat time to usize")?;
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        sub: email.as_ref().to_owned(),
+        exp,
+        iat,
+        jti: uuid::Uuid::new_v4().to_string(),
+        iss: AUTH_SERVICE_URL.to_owned(),
+        aud: AUTH_TOKEN_AUDIENCE.to_owned(),
+        roles: roles.iter().map(|role| role.as_ref().to_owned()).collect(),
+        auth_time: authentication.auth_time.try_into().wrap_err("failed to cast auth_time to usize")?,
+        amr: authentication.amr(),
+    };
 
     create_token(&claims)
 }
@@ -67,43 +144,396 @@ pub async fn validate_token(
         Err(e) => return Err(e.into()),
     }
 
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    let claims = decode_token::<Claims>(token, Some(AUTH_TOKEN_AUDIENCE)).wrap_err("failed to decode token")?;
+    check_user_revocation(&claims.sub, claims.iat, &banned_token_store).await?;
+
+    Ok(claims)
+}
+
+// Checks a personal API key, recording its use. Keys only carry their scopes, never the user's roles, so a leaked
+// key can't be used to act as an admin.
+pub async fn validate_api_key(
+    token: &str,
+    api_key_store: ApiKeyStoreType,
+    user_store: UserStoreType,
+) -> Result<ApiKeyClaims> {
+    let token = ApiKeyToken::parse(token)?;
+    let key = api_key_store.write().await.use_key(&token).await?;
+
+    let user = user_store
+        .read()
+        .await
+        .get_user(key.email.as_ref())
+        .await
+        .map_err(|e| eyre!("failed to load API key owner: {:?}", e))?
+        .clone();
+    if user.disabled {
+        return Err(eyre!("API key belongs to a disabled account"));
+    }
+
+    Ok(ApiKeyClaims {
+        sub: key.email.as_ref().to_owned(),
+        scope: key.scopes.to_string(),
+        key_id: key.id.as_ref().to_owned(),
+        exp: key.expires_at,
+    })
+}
+
+// Tokens issued before the user's last revocation (e.g. a password reset) are no longer valid
+async fn check_user_revocation(sub: &str, iat: usize, banned_token_store: &BannedTokenStoreType) -> Result<()> {
+    let revoked_before = banned_token_store.read().await.get_user_revocation(sub).await?;
+    if let Some(revoked_before) = revoked_before {
+        if (iat as i64) < revoked_before {
+            return Err(eyre!("token was issued before the user's tokens were revoked"));
+        }
+    }
+    Ok(())
+}
+
+// Validates the JWT cookie of a request, returning the raw token along with its claims
+pub async fn authenticate(
+    jar: &CookieJar,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<(String, Claims), AuthAPIError> {
+    let token = jar
+        .get(JWT_COOKIE_NAME)
+        .ok_or(AuthAPIError::MissingToken)?
+        .value()
+        .to_owned();
+
+    let claims = validate_token(&token, banned_token_store)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    Ok((token, claims))
+}
+
+// Like `authenticate`, but also requires the admin role
+pub async fn authenticate_admin(
+    jar: &CookieJar,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<Claims, AuthAPIError> {
+    let (_, claims) = authenticate(jar, banned_token_store).await?;
+    if !claims.roles.iter().any(|role| role == ADMIN_ROLE) {
+        return Err(AuthAPIError::Forbidden);
+    }
+    Ok(claims)
+}
+
+// Checks the `Authorization: Bearer` header of operational admin requests against ADMIN_API_TOKEN
+pub fn authenticate_admin_api_token(headers: &HeaderMap) -> Result<(), AuthAPIError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(AuthAPIError::MissingToken)?;
+
+    // Comparing digests keeps the comparison time independent of how much of the token matched
+    let matches = !ADMIN_API_TOKEN.is_empty()
+        && Sha256::digest(token.as_bytes()) == Sha256::digest(ADMIN_API_TOKEN.as_bytes());
+    if !matches {
+        return Err(AuthAPIError::InvalidToken);
+    }
+    Ok(())
+}
+
+// Signs a token that lets `email` log in once through the magic link callback
+pub fn generate_magic_link_token(email: &Email, link_id: &MagicLinkId) -> Result<String> {
+    let exp = Utc::now().timestamp() + MAGIC_LINK_TTL_SECONDS;
+    let exp: usize = exp.try_into().wrap_err("failed to cast exp time to usize")?;
+
+    let claims = MagicLinkClaims {
+        sub: email.as_ref().to_owned(),
+        exp,
+        jti: link_id.as_ref().to_owned(),
+        iss: AUTH_SERVICE_URL.to_owned(),
+        aud: MAGIC_LINK_AUDIENCE.to_owned(),
+    };
+
+    sign_token(&claims).wrap_err("failed to create magic link token")
+}
+
+pub fn validate_magic_link_token(token: &str) -> Result<MagicLinkClaims> {
+    decode_token(token, Some(MAGIC_LINK_AUDIENCE)).wrap_err("failed to decode magic link token")
+}
+
+// Binds a magic link to the browser that asked for it, the link alone is not enough to log in
+pub fn create_magic_link_nonce_cookie(nonce: &MagicLinkNonce) -> Cookie<'static> {
+    Cookie::build((MAGIC_LINK_NONCE_COOKIE_NAME, nonce.as_ref().to_owned()))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax) // the link is opened as a cross-site top-level navigation from a mail client
+        .max_age(time::Duration::seconds(MAGIC_LINK_TTL_SECONDS))
+        .build()
+}
+
+// Issues an OAuth access token for `client_id`, which is its audience so it can't be used as a session token
+pub fn generate_access_token(email: &Email, client_id: &OAuthClientId, scopes: &Scopes) -> Result<String> {
+    let iat = Utc::now().timestamp();
+    let claims = AccessTokenClaims {
+        sub: email.as_ref().to_owned(),
+        exp: (iat + TOKEN_TTL_SECONDS).try_into().wrap_err("failed to cast exp time to usize")?,
+        iat: iat.try_into().wrap_err("failed to cast iat time to usize")?,
+        jti: uuid::Uuid::new_v4().to_string(),
+        iss: AUTH_SERVICE_URL.to_owned(),
+        aud: client_id.as_ref().to_owned(),
+        client_id: client_id.as_ref().to_owned(),
+        scope: scopes.to_string(),
+    };
+
+    sign_token(&claims).wrap_err("failed to create access token")
+}
+
+// Checks an OAuth access token presented to a resource endpoint such as /userinfo
+pub async fn validate_access_token(
+    token: &str,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<AccessTokenClaims> {
+    if banned_token_store.read().await.contains_token(token).await? {
+        return Err(eyre!("token is banned"));
+    }
+
+    // Every client is its own audience, so the audience is checked against the client the token names instead
+    let claims = decode_token::<AccessTokenClaims>(token, None).wrap_err("failed to decode access token")?;
+    if claims.aud != claims.client_id {
+        return Err(eyre!("access token audience does not match its client"));
+    }
+    if claims.sub == claims.client_id {
+        return Err(eyre!("client credentials tokens do not act for a user"));
+    }
+    check_user_revocation(&claims.sub, claims.iat, &banned_token_store).await?;
+
+    Ok(claims)
+}
+
+// Any token this service hands out that can be presented to someone else
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssuedToken {
+    Session(Claims),
+    // Issued to a client to act for a user
+    Access(AccessTokenClaims),
+    // Issued to a machine client to act for itself
+    Service(AccessTokenClaims),
+}
+
+// Tells which kind of token `token` is, failing unless it is currently valid. Service tokens are accepted
+// whatever their audience, as the caller isn't necessarily the service they were meant for.
+pub async fn identify_token(token: &str, banned_token_store: BannedTokenStoreType) -> Result<IssuedToken> {
+    if let Ok(claims) = validate_token(token, banned_token_store.clone()).await {
+        return Ok(IssuedToken::Session(claims));
+    }
+    if let Ok(claims) = validate_access_token(token, banned_token_store.clone()).await {
+        return Ok(IssuedToken::Access(claims));
+    }
+
+    if banned_token_store.read().await.contains_token(token).await? {
+        return Err(eyre!("token is banned"));
+    }
+    let claims = decode_token::<AccessTokenClaims>(token, None).wrap_err("failed to decode token")?;
+    if claims.sub != claims.client_id {
+        return Err(eyre!("token acts for a user rather than its client"));
+    }
+    Ok(IssuedToken::Service(claims))
+}
+
+// Session and magic link tokens must never be confused with tokens minted for a service
+pub fn is_reserved_audience(audience: &str) -> bool {
+    audience == AUTH_TOKEN_AUDIENCE || audience == MAGIC_LINK_AUDIENCE
+}
+
+// Issues a client credentials token (RFC 6749 section 4.4), with which a machine client calls `audience`
+// as itself rather than on behalf of a user
+pub fn generate_client_access_token(client_id: &OAuthClientId, audience: &Audience, scopes: &Scopes) -> Result<String> {
+    if is_reserved_audience(audience.as_ref()) {
+        return Err(eyre!("audience {} is reserved", audience.as_ref()));
+    }
+    let iat = Utc::now().timestamp();
+    let claims = AccessTokenClaims {
+        sub: client_id.as_ref().to_owned(),
+        exp: (iat + TOKEN_TTL_SECONDS).try_into().wrap_err("failed to cast exp time to usize")?,
+        iat: iat.try_into().wrap_err("failed to cast iat time to usize")?,
+        jti: uuid::Uuid::new_v4().to_string(),
+        iss: AUTH_SERVICE_URL.to_owned(),
+        aud: audience.as_ref().to_owned(),
+        client_id: client_id.as_ref().to_owned(),
+        scope: scopes.to_string(),
+    };
+
+    sign_token(&claims).wrap_err("failed to create client access token")
+}
+
+// Issues an OpenID Connect ID token telling `client_id` who logged in, when and how
+pub fn generate_id_token(
+    email: &Email,
+    client_id: &OAuthClientId,
+    nonce: Option<&str>,
+    authentication: &Authentication,
+) -> Result<String> {
+    let iat = Utc::now().timestamp();
+    let claims = IdTokenClaims {
+        iss: AUTH_SERVICE_URL.to_owned(),
+        sub: email.as_ref().to_owned(),
+        aud: client_id.as_ref().to_owned(),
+        exp: (iat + TOKEN_TTL_SECONDS).try_into().wrap_err("failed to cast exp time to usize")?,
+        iat: iat.try_into().wrap_err("failed to cast iat time to usize")?,
+        auth_time: authentication.auth_time.try_into().wrap_err("failed to cast auth_time to usize")?,
+        nonce: nonce.map(str::to_owned),
+        amr: authentication.amr(),
+    };
+
+    sign_token(&claims).wrap_err("failed to create ID token")
 }
 
 fn create_token(claims: &Claims) -> Result<String> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
-    )
-    .wrap_err("failed to create token")
+    sign_token(claims).wrap_err("failed to create token")
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+// Signs with the active key and names it in the `kid` header so consumers can pick it from the JWKS
+fn sign_token<T: Serialize>(claims: &T) -> Result<String> {
+    let key = JWT_KEY_RING
+        .read()
+        .map_err(|_| eyre!("JWT key ring lock poisoned"))?
+        .active()
+        .clone();
+
+    let mut header = Header::new(key.algorithm());
+    header.kid = Some(key.kid().to_owned());
+
+    encode(&header, claims, key.encoding_key()).wrap_err("failed to sign token")
+}
+
+// Without an `audience` the `aud` claim is left to the caller to check
+fn decode_token<T: DeserializeOwned>(token: &str, audience: Option<&str>) -> Result<T> {
+    let header = decode_header(token).wrap_err("failed to decode token header")?;
+    let kid = header.kid.ok_or(eyre!("token has no key id"))?;
+    let key = JWT_KEY_RING
+        .read()
+        .map_err(|_| eyre!("JWT key ring lock poisoned"))?
+        .find(&kid, Utc::now().timestamp())
+        .cloned()
+        .ok_or(eyre!("token was signed with an unknown or retired key"))?;
+
+    // Pinning the algorithm to the key's rejects tokens that try to downgrade it through the header
+    let mut validation = Validation::new(key.algorithm());
+    match audience {
+        Some(audience) => validation.set_audience(&[audience]),
+        None => validation.validate_aud = false,
+    }
+    validation.set_issuer(&[AUTH_SERVICE_URL.as_str()]);
+
+    decode::<T>(token, key.decoding_key(), &validation)
+        .map(|data| data.claims)
+        .wrap_err("failed to verify token")
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    pub iat: usize,
+    // Unique per token, so two tokens issued in the same second can be told apart
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    pub roles: Vec<String>,
+    // When and how the user logged in, carried over as the session is refreshed.
+    // Defaulted so tokens issued before these were added still decode.
+    #[serde(default)]
+    pub auth_time: usize,
+    #[serde(default)]
+    pub amr: Vec<String>,
+}
+
+impl Claims {
+    pub fn authentication(&self) -> Authentication {
+        Authentication::from_amr(self.auth_time as i64, &self.amr)
+    }
+}
+
+// Claims of an OAuth access token, laid out as in RFC 9068
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AccessTokenClaims {
+    pub sub: String,
+    pub exp: usize,
+    pub iat: usize,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    pub client_id: String,
+    // Space separated, as in the token response
+    pub scope: String,
+}
+
+// What a personal API key stands for: the same sub as a session token, the key's scopes and no roles
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ApiKeyClaims {
+    pub sub: String,
+    // Space separated, as in OAuth access tokens
+    pub scope: String,
+    pub key_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+}
+
+// Claims of an OpenID Connect ID token
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    pub auth_time: usize,
+    // Echoed from the authorization request so the client can tie the token to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub amr: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MagicLinkClaims {
+    pub sub: String,
+    pub exp: usize,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::AuthMethod;
+
+    fn password_login() -> Authentication {
+        Authentication { auth_time: 1_700_000_000, methods: vec![AuthMethod::Password] }
+    }
 
     #[tokio::test]
     async fn test_generate_auth_cookie() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+        use crate::domain::{Password, User, UserStore};
+        use crate::services::data_stores::{HashmapUserStore, HashsetBannedTokenStore};
+
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let mut user_store = HashmapUserStore::default();
+        user_store.add_user(User::new(email.clone(), Password("password123".to_owned()), false)).await.unwrap();
+        user_store.set_roles(email.as_ref(), vec![Role::admin()]).await.unwrap();
+        let user_store = Arc::new(RwLock::new(user_store));
+
+        let cookie = generate_auth_cookie(&email, &password_login(), user_store.clone()).await.unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let claims = validate_token(cookie.value(), banned_store).await.unwrap();
+        assert_eq!(claims.roles, vec!["admin".to_owned()]);
+
+        let unknown = Email::parse("unknown@example.com".to_owned()).unwrap();
+        assert!(generate_auth_cookie(&unknown, &password_login(), user_store).await.is_err());
     }
 
     #[tokio::test]
@@ -117,13 +547,52 @@ mod tests {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+        use crate::domain::RefreshTokenStore;
+        use crate::services::data_stores::HashmapRefreshTokenStore;
+
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let refresh_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let cookie = generate_refresh_cookie(&email, &password_login(), refresh_store.clone()).await.unwrap();
+        assert_eq!(cookie.name(), REFRESH_COOKIE_NAME);
+        assert_eq!(cookie.http_only(), Some(true));
+
+        let token = RefreshToken::parse(cookie.value().to_owned()).unwrap();
+        let stored = refresh_store.read().await.get_token(token.family_id()).await.unwrap();
+        assert_eq!(stored, (email, token, password_login()));
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, &[], &password_login()).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
+    #[tokio::test]
+    async fn test_auth_token_claims() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+        use crate::services::data_stores::HashsetBannedTokenStore;
+
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let roles = vec![Role::admin(), Role::parse("support".to_owned()).unwrap()];
+        let first = generate_auth_token(&email, &roles, &password_login()).unwrap();
+        let second = generate_auth_token(&email, &roles, &password_login()).unwrap();
+        assert_ne!(first, second);
+
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let claims = validate_token(&first, banned_store).await.unwrap();
+        assert_eq!(claims.iss, AUTH_SERVICE_URL.as_str());
+        assert_eq!(claims.aud, AUTH_TOKEN_AUDIENCE);
+        assert_eq!(claims.roles, vec!["admin".to_owned(), "support".to_owned()]);
+        assert!(uuid::Uuid::parse_str(&claims.jti).is_ok());
+        assert_eq!(claims.authentication(), password_login());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         use std::sync::Arc;
@@ -131,7 +600,7 @@ mod tests {
         use crate::services::data_stores::HashsetBannedTokenStore;
 
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, &[], &password_login()).unwrap();
         let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
         let result = validate_token(&token, banned_store).await.unwrap();
         assert_eq!(result.sub, "test@example.com");
@@ -144,6 +613,56 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_user_tokens() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+        use crate::domain::BannedTokenStore;
+        use crate::services::data_stores::HashsetBannedTokenStore;
+
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = generate_auth_token(&email, &[], &password_login()).unwrap();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let issued_before = Utc::now().timestamp() + 1;
+        banned_store.write().await.revoke_user_tokens(email.as_ref(), issued_before).await.unwrap();
+        let result = validate_token(&token, banned_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_magic_link_token_round_trip() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let link_id = MagicLinkId::default();
+        let token = generate_magic_link_token(&email, &link_id).unwrap();
+        let claims = validate_magic_link_token(&token).unwrap();
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.jti, link_id.as_ref());
+    }
+
+    #[tokio::test]
+    async fn test_magic_link_and_auth_tokens_are_not_interchangeable() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+        use crate::services::data_stores::HashsetBannedTokenStore;
+
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let magic_link_token = generate_magic_link_token(&email, &MagicLinkId::default()).unwrap();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        assert!(validate_token(&magic_link_token, banned_store).await.is_err());
+
+        let auth_token = generate_auth_token(&email, &[], &password_login()).unwrap();
+        assert!(validate_magic_link_token(&auth_token).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_carry_the_signing_key_id() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = generate_auth_token(&email, &[], &password_login()).unwrap();
+        let header = decode_header(&token).unwrap();
+        let ring = JWT_KEY_RING.read().unwrap();
+        assert!(ring.find(header.kid.as_deref().unwrap(), Utc::now().timestamp()).is_some());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         use std::sync::Arc;
@@ -155,4 +674,118 @@ mod tests {
         let result = validate_token(&token, banned_store).await;
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_access_tokens_are_validated_against_their_client() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+        use crate::services::data_stores::HashsetBannedTokenStore;
+
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let client_id = OAuthClientId::default();
+        let scopes = Scopes::parse("openid email").unwrap();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let access_token = generate_access_token(&email, &client_id, &scopes).unwrap();
+        let claims = validate_access_token(&access_token, banned_store.clone()).await.unwrap();
+        assert_eq!((claims.sub.as_str(), claims.client_id), ("test@example.com", client_id.as_ref().to_owned()));
+
+        // Neither session nor ID tokens name a client of their own
+        let session_token = generate_auth_token(&email, &[], &password_login()).unwrap();
+        assert!(validate_access_token(&session_token, banned_store.clone()).await.is_err());
+        let id_token = generate_id_token(&email, &client_id, None, &password_login()).unwrap();
+        assert!(validate_access_token(&id_token, banned_store).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_service_tokens_are_scoped_to_their_audience() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+        use crate::services::data_stores::HashsetBannedTokenStore;
+
+        let client_id = OAuthClientId::default();
+        let audience = Audience::parse("app-service".to_owned()).unwrap();
+        let scopes = Scopes::parse("reports:read").unwrap();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        // Resource servers check the audience themselves against the published keys
+        let service_token = generate_client_access_token(&client_id, &audience, &scopes).unwrap();
+        let claims = decode_token::<AccessTokenClaims>(&service_token, Some("app-service")).unwrap();
+        assert_eq!((claims.sub, claims.scope), (client_id.as_ref().to_owned(), "reports:read".to_owned()));
+        assert!(decode_token::<AccessTokenClaims>(&service_token, Some("billing-service")).is_err());
+
+        // Service tokens don't pass as user tokens
+        assert!(validate_access_token(&service_token, banned_store.clone()).await.is_err());
+        assert!(validate_token(&service_token, banned_store).await.is_err());
+
+        // Reserved audiences can't be requested at all
+        let session = Audience::parse(AUTH_TOKEN_AUDIENCE.to_owned()).unwrap();
+        assert!(generate_client_access_token(&client_id, &session, &scopes).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_identify_token() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+        use crate::domain::BannedTokenStore;
+        use crate::services::data_stores::HashsetBannedTokenStore;
+
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let client_id = OAuthClientId::default();
+        let scopes = Scopes::parse("reports:read").unwrap();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let session_token = generate_auth_token(&email, &[], &password_login()).unwrap();
+        assert!(matches!(identify_token(&session_token, banned_store.clone()).await, Ok(IssuedToken::Session(_))));
+        let access_token = generate_access_token(&email, &client_id, &scopes).unwrap();
+        assert!(matches!(identify_token(&access_token, banned_store.clone()).await, Ok(IssuedToken::Access(_))));
+        let audience = Audience::parse("app-service".to_owned()).unwrap();
+        let service_token = generate_client_access_token(&client_id, &audience, &scopes).unwrap();
+        assert!(matches!(identify_token(&service_token, banned_store.clone()).await, Ok(IssuedToken::Service(_))));
+
+        // ID tokens aren't bearer tokens and banned tokens are no longer valid
+        let id_token = generate_id_token(&email, &client_id, None, &password_login()).unwrap();
+        assert!(identify_token(&id_token, banned_store.clone()).await.is_err());
+        banned_store.write().await.add_token(service_token.clone()).await.unwrap();
+        assert!(identify_token(&service_token, banned_store).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_api_key() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+        use crate::domain::{ApiKey, ApiKeyStore, Password, User, UserStore};
+        use crate::services::data_stores::{HashmapApiKeyStore, HashmapUserStore};
+
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let mut user_store = HashmapUserStore::default();
+        user_store.add_user(User::new(email.clone(), Password("password123".to_owned()), false)).await.unwrap();
+        user_store.set_roles(email.as_ref(), vec![Role::admin()]).await.unwrap();
+        let user_store = Arc::new(RwLock::new(user_store));
+
+        let token = ApiKeyToken::default();
+        let key = ApiKey {
+            id: token.id().clone(),
+            email: email.clone(),
+            name: "Scripts".to_owned(),
+            scopes: Scopes::parse("reports:read").unwrap(),
+            created_at: Utc::now().timestamp(),
+            expires_at: None,
+            last_used_at: None,
+        };
+        let mut api_key_store = HashmapApiKeyStore::default();
+        api_key_store.add_key(key, &token).await.unwrap();
+        let api_key_store = Arc::new(RwLock::new(api_key_store));
+
+        let claims = validate_api_key(&token.to_string(), api_key_store.clone(), user_store.clone()).await.unwrap();
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.scope, "reports:read");
+        assert!(api_key_store.read().await.list_keys(&email).await.unwrap()[0].last_used_at.is_some());
+
+        assert!(validate_api_key(&ApiKeyToken::default().to_string(), api_key_store.clone(), user_store.clone()).await.is_err());
+        assert!(validate_api_key("invalid", api_key_store.clone(), user_store.clone()).await.is_err());
+
+        user_store.write().await.set_disabled(email.as_ref(), true).await.unwrap();
+        assert!(validate_api_key(&token.to_string(), api_key_store, user_store).await.is_err());
+    }
+}
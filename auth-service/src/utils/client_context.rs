@@ -0,0 +1,35 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, Extensions},
+};
+
+// What the emails sent on behalf of a request need to know about the client behind it
+#[derive(Debug, Clone)]
+pub struct ClientContext {
+    pub ip: String,
+    pub accept_language: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let accept_language = parts
+            .headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+
+        Ok(Self { ip: client_ip(&parts.extensions), accept_language })
+    }
+}
+
+// The peer address the server accepted the connection from
+pub fn client_ip(extensions: &Extensions) -> String {
+    extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
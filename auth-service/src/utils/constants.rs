@@ -1,14 +1,67 @@
 use dotenvy::dotenv;
 use lazy_static::lazy_static;
-use std::env as std_env;
+use jsonwebtoken::Algorithm;
+use std::{env as std_env, str::FromStr, sync::RwLock};
+
+use crate::services::{
+    http_sms_client::HttpSmsConfig,
+    smtp_email_client::{SmtpConfig, SmtpTls},
+};
+
+use crate::domain::data_stores::StoredSigningKey;
+
+use super::{
+    auth::MAX_TOKEN_LIFETIME_SECONDS,
+    signing_key::{JwtKeyRing, JwtSigningKey},
+};
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_COOKIE_NAME: &str = "refresh_token";
+pub const MAGIC_LINK_NONCE_COOKIE_NAME: &str = "magic_link_nonce";
+pub const TOTP_ISSUER: &str = "auth-service";
+pub const WEBAUTHN_RP_NAME: &str = "auth-service";
+pub const RECOVERY_CODE_COUNT: usize = 10;
+pub const MAX_TWO_FA_ATTEMPTS: u32 = 5;
+pub const TWO_FA_CODE_TTL_SECONDS: u64 = 600; // 10 minutes
+pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: u64 = 900; // 15 minutes
+pub const EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: u64 = 86_400; // 24 hours
+pub const PHONE_VERIFICATION_CODE_TTL_SECONDS: u64 = 600; // 10 minutes
+pub const ADMIN_USERS_DEFAULT_PAGE_SIZE: u64 = 20;
+pub const ADMIN_USERS_MAX_PAGE_SIZE: u64 = 100;
+pub const ADMIN_EMAIL_OUTBOX_DEFAULT_PAGE_SIZE: u64 = 20;
+pub const ADMIN_EMAIL_OUTBOX_MAX_PAGE_SIZE: u64 = 100;
+pub const EMAIL_OUTBOX_POLL_INTERVAL_MS: u64 = 1_000;
+pub const EMAIL_OUTBOX_BATCH_SIZE: u32 = 20;
+pub const EMAIL_OUTBOX_LEASE_SECONDS: u64 = 120; // well past the SMTP timeout, so a slow send isn't claimed twice
+pub const EMAIL_OUTBOX_MAX_ATTEMPTS: u32 = 8;
+pub const EMAIL_OUTBOX_RETRY_BASE_SECONDS: u64 = 30; // doubled for every failed attempt
+pub const EMAIL_OUTBOX_RETRY_MAX_SECONDS: u64 = 3_600; // 1 hour
+pub const EMAIL_OUTBOX_DEAD_RETENTION_SECONDS: i64 = 604_800; // 7 days from being queued
+pub const EMAIL_OUTBOX_SWEEP_INTERVAL_SECONDS: u64 = 3_600; // 1 hour
+pub const JWT_KEY_RING_REFRESH_SECONDS: u64 = 30;
+pub const JWT_KEY_ACTIVATION_DELAY_SECONDS: i64 = 90; // every replica refreshes its key ring at least twice before a new key signs
+pub const LOGIN_FAILURE_WINDOW_SECONDS: u64 = 900; // 15 minutes
+pub const LOGIN_LOCKOUT_BASE_SECONDS: u64 = 300; // 5 minutes, doubled for every earlier lock
+pub const LOGIN_LOCKOUT_MAX_SECONDS: u64 = 86_400; // 1 day
+pub const LOGIN_LOCKOUT_ESCALATION_WINDOW_SECONDS: u64 = 86_400; // 1 day
 
 // Define a lazily evaluated static. lazy_static is needed because std_env::var is not a const function.
 lazy_static! {
-    pub static ref JWT_SECRET: String = set_token();
+    pub static ref JWT_SIGNING_KEYS: Vec<StoredSigningKey> = set_jwt_signing_keys();
+    // Starts out with the configured keys and is replaced by the signing key store's once the service is up
+    pub static ref JWT_KEY_RING: RwLock<JwtKeyRing> = RwLock::new(set_jwt_key_ring());
+    pub static ref JWT_KEY_ENCRYPTION_KEY: String = set_jwt_key_encryption_key();
+    pub static ref ADMIN_API_TOKEN: String = set_admin_api_token();
+    pub static ref LOGIN_LOCKOUT_THRESHOLD: u32 = set_login_lockout_threshold();
+    pub static ref SMTP_CONFIG: Option<SmtpConfig> = set_smtp_config();
+    pub static ref SMS_CONFIG: Option<HttpSmsConfig> = set_sms_config();
+    pub static ref EMAIL_TEMPLATES_DIR: String = set_email_templates_dir();
     pub static ref DATABASE_URL: String = set_database_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host(); 
+    pub static ref TOTP_ENCRYPTION_KEY: String = set_totp_encryption_key();
+    pub static ref WEBAUTHN_RP_ID: String = set_webauthn_rp_id();
+    pub static ref WEBAUTHN_ORIGIN: String = set_webauthn_origin();
+    pub static ref AUTH_SERVICE_URL: String = set_auth_service_url();
 }
 
 fn set_token() -> String {
@@ -20,6 +73,150 @@ fn set_token() -> String {
     secret
 }
 
+// Signs with the PEM key at JWT_SIGNING_KEY_PATH when set, otherwise falls back to the shared JWT_SECRET for local development.
+// Keys listed in JWT_VERIFY_KEY_PATHS were active before this deployment and keep verifying until their tokens expire.
+// These only seed an empty signing key store, after that the store decides which keys sign and verify.
+fn set_jwt_signing_keys() -> Vec<StoredSigningKey> {
+    dotenv().ok();
+    let algorithm = std_env::var(env::JWT_SIGNING_ALGORITHM_ENV_VAR).unwrap_or("EdDSA".to_owned());
+    let algorithm = Algorithm::from_str(&algorithm).expect("JWT_SIGNING_ALGORITHM must be RS256 or EdDSA.");
+
+    let now = chrono::Utc::now().timestamp();
+    let path = std_env::var(env::JWT_SIGNING_KEY_PATH_ENV_VAR).unwrap_or_default();
+    let active = if path.is_empty() {
+        stored_signing_key(Algorithm::HS256, set_token().into_bytes(), now, None)
+    } else {
+        stored_signing_key(algorithm, read_signing_key(path.as_str()), now, None)
+    };
+
+    // Activated before the active key, so they only verify
+    let retire_at = now + MAX_TOKEN_LIFETIME_SECONDS;
+    let verify_paths = std_env::var(env::JWT_VERIFY_KEY_PATHS_ENV_VAR).unwrap_or_default();
+    let verify_only = verify_paths
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(|path| stored_signing_key(algorithm, read_signing_key(path), 0, Some(retire_at)));
+    std::iter::once(active).chain(verify_only).collect()
+}
+
+fn set_jwt_key_ring() -> JwtKeyRing {
+    JwtKeyRing::from_stored(&JWT_SIGNING_KEYS, chrono::Utc::now().timestamp())
+        .unwrap_or_else(|e| panic!("Invalid JWT signing keys: {:?}", e))
+}
+
+fn read_signing_key(path: &str) -> Vec<u8> {
+    std::fs::read(path).unwrap_or_else(|e| panic!("Failed to read JWT signing key {}: {}", path, e))
+}
+
+fn stored_signing_key(algorithm: Algorithm, private_key: Vec<u8>, activates_at: i64, retire_at: Option<i64>) -> StoredSigningKey {
+    let key = JwtSigningKey::from_private_key(algorithm, &private_key)
+        .unwrap_or_else(|e| panic!("Invalid JWT signing key: {:?}", e));
+    StoredSigningKey { kid: key.kid().to_owned(), algorithm, private_key, activates_at, retire_at }
+}
+
+// Encrypts the private keys in the signing key store
+fn set_jwt_key_encryption_key() -> String {
+    dotenv().ok();
+    let key = std_env::var(env::JWT_KEY_ENCRYPTION_KEY_ENV_VAR).expect("JWT_KEY_ENCRYPTION_KEY must be set.");
+    if key.is_empty() {
+        panic!("JWT_KEY_ENCRYPTION_KEY must not be empty.");
+    }
+    key
+}
+
+// Bearer token for operational admin routes such as key rotation, which are disabled when it is empty
+fn set_admin_api_token() -> String {
+    dotenv().ok();
+    std_env::var(env::ADMIN_API_TOKEN_ENV_VAR).unwrap_or_default()
+}
+
+// Wrong passwords and 2FA codes allowed within the failure window before the account gets locked
+fn set_login_lockout_threshold() -> u32 {
+    dotenv().ok();
+    match std_env::var(env::LOGIN_LOCKOUT_THRESHOLD_ENV_VAR) {
+        Ok(value) => match value.parse() {
+            Ok(threshold) if threshold > 0 => threshold,
+            _ => panic!("LOGIN_LOCKOUT_THRESHOLD must be a positive integer."),
+        },
+        Err(_) => 5,
+    }
+}
+
+// Emails go out over SMTP when EMAIL_CLIENT is "smtp", otherwise they are only logged by the mock client
+fn set_smtp_config() -> Option<SmtpConfig> {
+    dotenv().ok();
+    let client = std_env::var(env::EMAIL_CLIENT_ENV_VAR).unwrap_or("mock".to_owned());
+    match client.as_str() {
+        "mock" => return None,
+        "smtp" => {},
+        _ => panic!("EMAIL_CLIENT must be mock or smtp."),
+    }
+
+    let host = std_env::var(env::SMTP_HOST_ENV_VAR).unwrap_or_default();
+    if host.is_empty() {
+        panic!("SMTP_HOST must be set when EMAIL_CLIENT is smtp.");
+    }
+    let tls = std_env::var(env::SMTP_TLS_ENV_VAR)
+        .unwrap_or("starttls".to_owned())
+        .parse()
+        .expect("SMTP_TLS must be none, starttls or tls.");
+    let default_port = if tls == SmtpTls::Implicit { 465 } else { 587 };
+    let port = std_env::var(env::SMTP_PORT_ENV_VAR)
+        .ok()
+        .filter(|port| !port.is_empty())
+        .map(|port| port.parse().expect("SMTP_PORT must be a port number."))
+        .unwrap_or(default_port);
+    let sender = std_env::var(env::EMAIL_SENDER_ENV_VAR).expect("EMAIL_SENDER must be set when EMAIL_CLIENT is smtp.");
+
+    Some(SmtpConfig {
+        host,
+        port,
+        tls,
+        username: std_env::var(env::SMTP_USERNAME_ENV_VAR).unwrap_or_default(),
+        password: std_env::var(env::SMTP_PASSWORD_ENV_VAR).unwrap_or_default(),
+        sender,
+    })
+}
+
+// Text messages go out through the HTTP provider when SMS_CLIENT is "http", otherwise they are only logged by the mock client
+fn set_sms_config() -> Option<HttpSmsConfig> {
+    dotenv().ok();
+    let client = std_env::var(env::SMS_CLIENT_ENV_VAR).unwrap_or("mock".to_owned());
+    match client.as_str() {
+        "mock" => return None,
+        "http" => {},
+        _ => panic!("SMS_CLIENT must be mock or http."),
+    }
+
+    let url = std_env::var(env::SMS_PROVIDER_URL_ENV_VAR).unwrap_or_default();
+    if url.is_empty() {
+        panic!("SMS_PROVIDER_URL must be set when SMS_CLIENT is http.");
+    }
+    let sender = std_env::var(env::SMS_SENDER_ENV_VAR).expect("SMS_SENDER must be set when SMS_CLIENT is http.");
+
+    Some(HttpSmsConfig {
+        url,
+        api_key: std_env::var(env::SMS_PROVIDER_API_KEY_ENV_VAR).unwrap_or_default(),
+        sender,
+    })
+}
+
+// Relative paths resolve against the working directory, like the static assets
+fn set_email_templates_dir() -> String {
+    dotenv().ok();
+    std_env::var(env::EMAIL_TEMPLATES_DIR_ENV_VAR).unwrap_or("email_templates".to_owned())
+}
+
+fn set_totp_encryption_key() -> String {
+    dotenv().ok();
+    let key = std_env::var(env::TOTP_ENCRYPTION_KEY_ENV_VAR).expect("TOTP_ENCRYPTION_KEY must be set.");
+    if key.is_empty() {
+        panic!("TOTP_ENCRYPTION_KEY must not be empty.");
+    }
+    key
+}
+
 fn set_database_url() -> String {
     dotenv().ok(); // Load environment variables
     let db_url = std_env::var("DATABASE_URL").expect("DATABASE_URL must be set.");
@@ -35,9 +232,46 @@ fn set_redis_host() -> String {
     std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or("localhost:6379".to_owned())
 }
 
+fn set_webauthn_rp_id() -> String {
+    dotenv().ok();
+    std_env::var(env::WEBAUTHN_RP_ID_ENV_VAR).unwrap_or("localhost".to_owned())
+}
+
+fn set_webauthn_origin() -> String {
+    dotenv().ok();
+    std_env::var(env::WEBAUTHN_ORIGIN_ENV_VAR).unwrap_or("http://localhost:8000".to_owned())
+}
+
+fn set_auth_service_url() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR).unwrap_or("http://localhost:3000".to_owned())
+}
+
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_SIGNING_KEY_PATH_ENV_VAR: &str = "JWT_SIGNING_KEY_PATH";
+    pub const JWT_SIGNING_ALGORITHM_ENV_VAR: &str = "JWT_SIGNING_ALGORITHM";
+    pub const JWT_VERIFY_KEY_PATHS_ENV_VAR: &str = "JWT_VERIFY_KEY_PATHS";
+    pub const JWT_KEY_ENCRYPTION_KEY_ENV_VAR: &str = "JWT_KEY_ENCRYPTION_KEY";
+    pub const ADMIN_API_TOKEN_ENV_VAR: &str = "ADMIN_API_TOKEN";
+    pub const LOGIN_LOCKOUT_THRESHOLD_ENV_VAR: &str = "LOGIN_LOCKOUT_THRESHOLD";
+    pub const EMAIL_CLIENT_ENV_VAR: &str = "EMAIL_CLIENT";
+    pub const EMAIL_SENDER_ENV_VAR: &str = "EMAIL_SENDER";
+    pub const EMAIL_TEMPLATES_DIR_ENV_VAR: &str = "EMAIL_TEMPLATES_DIR";
+    pub const SMTP_HOST_ENV_VAR: &str = "SMTP_HOST";
+    pub const SMTP_PORT_ENV_VAR: &str = "SMTP_PORT";
+    pub const SMTP_TLS_ENV_VAR: &str = "SMTP_TLS";
+    pub const SMTP_USERNAME_ENV_VAR: &str = "SMTP_USERNAME";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const SMS_CLIENT_ENV_VAR: &str = "SMS_CLIENT";
+    pub const SMS_PROVIDER_URL_ENV_VAR: &str = "SMS_PROVIDER_URL";
+    pub const SMS_PROVIDER_API_KEY_ENV_VAR: &str = "SMS_PROVIDER_API_KEY";
+    pub const SMS_SENDER_ENV_VAR: &str = "SMS_SENDER";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
+    pub const TOTP_ENCRYPTION_KEY_ENV_VAR: &str = "TOTP_ENCRYPTION_KEY";
+    pub const WEBAUTHN_RP_ID_ENV_VAR: &str = "WEBAUTHN_RP_ID";
+    pub const WEBAUTHN_ORIGIN_ENV_VAR: &str = "WEBAUTHN_ORIGIN";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
 }
 
 pub mod prod {
@@ -0,0 +1,63 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use color_eyre::eyre::{eyre, Context, Result};
+use sha2::{Digest, Sha256};
+
+// AES-GCM nonces are 96 bits and are stored in front of the ciphertext
+const NONCE_LENGTH: usize = 12;
+
+// Secrets kept at rest are encrypted with AES-256-GCM under a key derived from a configured passphrase
+fn cipher(passphrase: &str) -> Aes256Gcm {
+    let key = Sha256::digest(passphrase.as_bytes());
+    Aes256Gcm::new(&key)
+}
+
+pub fn encrypt(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher(passphrase)
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| eyre!("failed to encrypt secret: {:?}", e))?;
+
+    let mut encrypted = nonce.to_vec();
+    encrypted.extend(ciphertext);
+    Ok(encrypted)
+}
+
+pub fn decrypt(passphrase: &str, encrypted: &[u8]) -> Result<Vec<u8>> {
+    if encrypted.len() <= NONCE_LENGTH {
+        return Err(eyre!("encrypted secret is too short"));
+    }
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH);
+    let nonce: [u8; NONCE_LENGTH] = nonce.try_into().wrap_err("invalid secret nonce")?;
+    cipher(passphrase)
+        .decrypt(&Nonce::from(nonce), ciphertext)
+        .map_err(|e| eyre!("failed to decrypt secret: {:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let encrypted = encrypt("passphrase", b"secret").unwrap();
+        assert!(!encrypted.windows(6).any(|w| w == b"secret"));
+        assert_eq!(decrypt("passphrase", &encrypted).unwrap(), b"secret");
+
+        // Every encryption uses a fresh nonce
+        assert_ne!(encrypt("passphrase", b"secret").unwrap(), encrypted);
+    }
+
+    #[test]
+    fn test_decrypt_rejects_tampered_or_foreign_secrets() {
+        let mut encrypted = encrypt("passphrase", b"secret").unwrap();
+        assert!(decrypt("other passphrase", &encrypted).is_err());
+
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(decrypt("passphrase", &encrypted).is_err());
+        assert!(decrypt("passphrase", &encrypted[..NONCE_LENGTH]).is_err());
+    }
+}
@@ -1,3 +1,8 @@
 pub mod auth;
+pub mod client_context;
 pub mod constants;
-pub mod tracing;
\ No newline at end of file
+pub mod encryption;
+pub mod rate_limit;
+pub mod signing_key;
+pub mod tracing;
+pub mod webauthn;
\ No newline at end of file
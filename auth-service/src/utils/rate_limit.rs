@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        data_stores::{RateLimitDecision, RateLimitPolicy},
+        AuthAPIError,
+    },
+};
+
+use super::client_context::client_ip;
+
+pub struct RateLimitRule {
+    pub route: &'static str,
+    pub policy: RateLimitPolicy,
+    // Keys the bucket by the `email` in the JSON body instead of the client IP, so guessing at one account runs out
+    // no matter how many addresses the guesses come from
+    pub per_email: bool,
+}
+
+pub const RATE_LIMIT_RULES: &[RateLimitRule] = &[
+    RateLimitRule { route: "/signup", policy: RateLimitPolicy::per_minute(10), per_email: false },
+    RateLimitRule { route: "/login", policy: RateLimitPolicy::per_minute(30), per_email: false },
+    RateLimitRule { route: "/login", policy: RateLimitPolicy::per_minute(10), per_email: true },
+    RateLimitRule { route: "/verify-2fa", policy: RateLimitPolicy::per_minute(30), per_email: false },
+    RateLimitRule { route: "/verify-2fa", policy: RateLimitPolicy::per_minute(10), per_email: true },
+    // Every request texts a code, which costs money and can be used to spam a number
+    RateLimitRule { route: "/account/phone", policy: RateLimitPolicy::per_minute(5), per_email: false },
+];
+
+const MAX_BUFFERED_BODY_BYTES: usize = 64 * 1024;
+const RATE_LIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const RATE_LIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+const RATE_LIMIT_RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+// Takes a token from every bucket the route has and refuses the request with a 429 once any of them runs dry
+pub async fn rate_limit(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_owned();
+    let rules: Vec<&RateLimitRule> = RATE_LIMIT_RULES.iter().filter(|rule| rule.route == path).collect();
+    if rules.is_empty() {
+        return next.run(request).await;
+    }
+
+    let client_ip = client_ip(request.extensions());
+
+    let (request, email) = if rules.iter().any(|rule| rule.per_email) {
+        match read_email(request).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        }
+    } else {
+        (request, None)
+    };
+
+    let mut rate_limit_store = state.rate_limit_store.write().await;
+    let mut tightest: Option<RateLimitDecision> = None;
+    for rule in rules {
+        // Without an email the route rejects the body anyway
+        let Some(key) = bucket_key(rule, &client_ip, email.as_deref()) else {
+            continue;
+        };
+        let decision = match rate_limit_store.take_token(&key, &rule.policy).await {
+            Ok(decision) => decision,
+            Err(e) => return AuthAPIError::UnexpectedError(e.into()).into_response(),
+        };
+        if tightest.is_none_or(|current| is_tighter(&decision, &current)) {
+            tightest = Some(decision);
+        }
+    }
+    drop(rate_limit_store);
+
+    let Some(decision) = tightest else {
+        return next.run(request).await;
+    };
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        let mut response = AuthAPIError::TooManyRequests.into_response();
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(decision.retry_after_seconds));
+        response
+    };
+    insert_rate_limit_headers(response.headers_mut(), &decision);
+    response
+}
+
+fn bucket_key(rule: &RateLimitRule, client_ip: &str, email: Option<&str>) -> Option<String> {
+    match (rule.per_email, email) {
+        (false, _) => Some(format!("{}:{}", rule.route, client_ip)),
+        (true, Some(email)) => Some(format!("{}:{}", rule.route, email)),
+        (true, None) => None,
+    }
+}
+
+// Buffers the JSON body to find the account it targets, then hands the request on unchanged
+async fn read_email(request: Request) -> Result<(Request, Option<String>), Response> {
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_BUFFERED_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+    let email = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|body| Some(body.get("email")?.as_str()?.trim().to_lowercase()));
+
+    Ok((Request::from_parts(parts, Body::from(bytes)), email))
+}
+
+// A refusal beats any allowance, otherwise whichever has the longest wait or the fewest requests left
+fn is_tighter(decision: &RateLimitDecision, current: &RateLimitDecision) -> bool {
+    match (decision.allowed, current.allowed) {
+        (false, true) => true,
+        (true, false) => false,
+        (false, false) => decision.retry_after_seconds > current.retry_after_seconds,
+        (true, true) => decision.remaining < current.remaining,
+    }
+}
+
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert(RATE_LIMIT_LIMIT, HeaderValue::from(decision.limit));
+    headers.insert(RATE_LIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(RATE_LIMIT_RESET, HeaderValue::from(decision.reset_seconds));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_email_buckets_ignore_client_ip() {
+        let rule = RateLimitRule { route: "/login", policy: RateLimitPolicy::per_minute(10), per_email: true };
+        let key = bucket_key(&rule, "10.0.0.1", Some("user@example.com"));
+        assert_eq!(key, Some("/login:user@example.com".to_owned()));
+        assert_eq!(bucket_key(&rule, "10.0.0.2", Some("user@example.com")), key);
+        assert_eq!(bucket_key(&rule, "10.0.0.1", None), None);
+
+        let rule = RateLimitRule { route: "/login", policy: RateLimitPolicy::per_minute(30), per_email: false };
+        assert_eq!(bucket_key(&rule, "10.0.0.1", Some("user@example.com")), Some("/login:10.0.0.1".to_owned()));
+    }
+}
@@ -0,0 +1,379 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use ed25519_dalek::{
+    pkcs8::{DecodePrivateKey, EncodePrivateKey},
+    SigningKey,
+};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters,
+        OctetKeyPairType, PublicKeyUse, ThumbprintHash,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use rand::Rng;
+use rsa::{pkcs8::LineEnding, RsaPrivateKey};
+
+use crate::domain::data_stores::StoredSigningKey;
+
+// Key id of the development fallback, HMAC secrets are never published so they need no thumbprint
+const HMAC_KEY_ID: &str = "hmac";
+const RSA_KEY_BITS: usize = 2048;
+
+// Key the service signs its JWTs with, along with what consumers need to verify them
+#[derive(Clone)]
+pub struct JwtSigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    // Public half of the key, published on the JWKS route. HMAC secrets have none.
+    jwk: Option<Jwk>,
+}
+
+impl JwtSigningKey {
+    // Loads a PKCS#8 private key. Only RS256 and EdDSA are supported.
+    pub fn from_pem(algorithm: Algorithm, pem: &[u8]) -> Result<Self> {
+        let (encoding_key, algorithm_parameters) = match algorithm {
+            Algorithm::RS256 => {
+                let encoding_key = EncodingKey::from_rsa_pem(pem).wrap_err("invalid RSA private key")?;
+                let jwk = Jwk::from_encoding_key(&encoding_key, algorithm).wrap_err("failed to extract RSA public key")?;
+                (encoding_key, jwk.algorithm)
+            }
+            Algorithm::EdDSA => {
+                let encoding_key = EncodingKey::from_ed_pem(pem).wrap_err("invalid Ed25519 private key")?;
+                // jsonwebtoken can't derive the public half of Ed25519 keys, so ed25519-dalek does it
+                let signing_key = SigningKey::from_pkcs8_der(encoding_key.inner())
+                    .map_err(|e| eyre!("invalid Ed25519 private key: {:?}", e))?;
+                let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(signing_key.verifying_key().as_bytes()),
+                });
+                (encoding_key, parameters)
+            }
+            _ => return Err(eyre!("unsupported JWT signing algorithm {:?}", algorithm)),
+        };
+
+        let mut jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(match algorithm {
+                    Algorithm::RS256 => KeyAlgorithm::RS256,
+                    _ => KeyAlgorithm::EdDSA,
+                }),
+                ..Default::default()
+            },
+            algorithm: algorithm_parameters,
+        };
+        // RFC 7638 thumbprints give every key a stable id without any extra configuration
+        let kid = jwk.thumbprint(ThumbprintHash::SHA256);
+        jwk.common.key_id = Some(kid.clone());
+
+        let decoding_key = DecodingKey::from_jwk(&jwk).wrap_err("failed to build decoding key")?;
+
+        Ok(Self { kid, algorithm, encoding_key, decoding_key, jwk: Some(jwk) })
+    }
+
+    // Shared secret HS256 key, for local development where tokens are only checked through /verify-token
+    pub fn from_secret(secret: &[u8]) -> Self {
+        Self {
+            kid: HMAC_KEY_ID.to_owned(),
+            algorithm: Algorithm::HS256,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    // Reads a key back from the signing key store
+    pub fn from_private_key(algorithm: Algorithm, private_key: &[u8]) -> Result<Self> {
+        match algorithm {
+            Algorithm::HS256 => Ok(Self::from_secret(private_key)),
+            _ => Self::from_pem(algorithm, private_key),
+        }
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+}
+
+// Creates a PKCS#8 PEM private key for `algorithm`, so rotating never needs key material sent over the wire.
+// RSA key generation takes a while, callers should keep it off the async runtime.
+pub fn generate_private_key(algorithm: Algorithm) -> Result<Vec<u8>> {
+    let pem = match algorithm {
+        Algorithm::EdDSA => {
+            let bytes: [u8; 32] = rand::rng().random();
+            SigningKey::from_bytes(&bytes)
+                .to_pkcs8_pem(LineEnding::LF)
+                .map_err(|e| eyre!("failed to encode Ed25519 private key: {:?}", e))?
+        }
+        Algorithm::RS256 => RsaPrivateKey::new(&mut rsa::rand_core::OsRng, RSA_KEY_BITS)
+            .wrap_err("failed to generate RSA private key")?
+            .to_pkcs8_pem(LineEnding::LF)
+            .map_err(|e| eyre!("failed to encode RSA private key: {:?}", e))?,
+        _ => return Err(eyre!("unsupported JWT signing algorithm {:?}", algorithm)),
+    };
+    Ok(pem.as_bytes().to_vec())
+}
+
+// The active signing key plus the keys it replaced. Replaced keys keep verifying the tokens they signed
+// until `retire_at`, a unix timestamp, so rotating never logs anyone out.
+#[derive(Clone)]
+pub struct JwtKeyRing {
+    active: JwtSigningKey,
+    verify_only: Vec<(JwtSigningKey, i64)>,
+}
+
+impl JwtKeyRing {
+    pub fn new(active: JwtSigningKey) -> Self {
+        Self { active, verify_only: Vec::new() }
+    }
+
+    // The newest key that has activated at `now` signs, every other key only verifies. Keys waiting to
+    // activate verify and are published right away, so every replica knows a key before any signs with it.
+    pub fn from_stored(keys: &[StoredSigningKey], now: i64) -> Result<Self> {
+        let active_kid = keys
+            .iter()
+            .filter(|key| key.activates_at <= now)
+            .max_by_key(|key| key.activates_at)
+            .map(|key| key.kid.as_str())
+            .ok_or(eyre!("no JWT signing key has activated"))?;
+
+        let mut active = None;
+        let mut verify_only = Vec::new();
+        for stored in keys {
+            let key = JwtSigningKey::from_private_key(stored.algorithm, &stored.private_key)
+                .wrap_err_with(|| format!("invalid stored JWT signing key {}", stored.kid))?;
+            if key.kid() != stored.kid {
+                return Err(eyre!("stored JWT signing key {} has key id {}", stored.kid, key.kid()));
+            }
+            if stored.kid == active_kid {
+                active = Some(key);
+            } else {
+                verify_only.push((key, stored.retire_at.unwrap_or(i64::MAX)));
+            }
+        }
+
+        let mut ring = Self::new(active.ok_or(eyre!("no JWT signing key has activated"))?);
+        for (key, retire_at) in verify_only {
+            ring.add_verify_only_key(key, retire_at)?;
+        }
+        Ok(ring)
+    }
+
+    pub fn active(&self) -> &JwtSigningKey {
+        &self.active
+    }
+
+    // Keys that still verify tokens at `now`, along with when they retire
+    pub fn verify_only_keys(&self, now: i64) -> impl Iterator<Item = (&JwtSigningKey, i64)> {
+        self.verify_only
+            .iter()
+            .filter(move |(_, retire_at)| *retire_at > now)
+            .map(|(key, retire_at)| (key, *retire_at))
+    }
+
+    pub fn add_verify_only_key(&mut self, key: JwtSigningKey, retire_at: i64) -> Result<()> {
+        self.ensure_unknown(&key)?;
+        self.verify_only.push((key, retire_at));
+        Ok(())
+    }
+
+    // Looks up the key a token names in its `kid` header
+    pub fn find(&self, kid: &str, now: i64) -> Option<&JwtSigningKey> {
+        if self.active.kid() == kid {
+            return Some(&self.active);
+        }
+        self.verify_only_keys(now).map(|(key, _)| key).find(|key| key.kid() == kid)
+    }
+
+    // Public halves of every key that can still verify a token, HMAC keys are left out
+    pub fn jwks(&self, now: i64) -> JwkSet {
+        let keys = std::iter::once(&self.active)
+            .chain(self.verify_only_keys(now).map(|(key, _)| key))
+            .filter_map(|key| key.jwk().cloned())
+            .collect();
+        JwkSet { keys }
+    }
+
+    // A kid must name a single key, otherwise re-adding a key would change when it retires
+    fn ensure_unknown(&self, key: &JwtSigningKey) -> Result<()> {
+        let known = self.active.kid() == key.kid() || self.verify_only.iter().any(|(k, _)| k.kid() == key.kid());
+        if known {
+            return Err(eyre!("signing key {} is already in the key ring", key.kid()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, encode, Header, Validation};
+    use serde::{Deserialize, Serialize};
+
+    const ED25519_PEM: &[u8] = include_bytes!("../../tests/fixtures/jwt_ed25519.pem");
+    const RS256_PEM: &[u8] = include_bytes!("../../tests/fixtures/jwt_rs256.pem");
+    const NEXT_ED25519_PEM: &[u8] = include_bytes!("../../tests/fixtures/jwt_ed25519_next.pem");
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    // Signs with the private key and verifies with nothing but the published JWK
+    fn assert_round_trip(key: &JwtSigningKey) {
+        let claims = TestClaims { sub: "test@example.com".to_owned(), exp: 4_102_444_800 };
+        let mut header = Header::new(key.algorithm());
+        header.kid = Some(key.kid().to_owned());
+        let token = encode(&header, &claims, key.encoding_key()).unwrap();
+
+        let jwk = key.jwk().expect("asymmetric keys have a JWK");
+        let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+        let decoded = decode::<TestClaims>(&token, &decoding_key, &Validation::new(key.algorithm())).unwrap();
+        assert_eq!(decoded.claims, claims);
+        assert_eq!(decoded.header.kid.as_deref(), Some(key.kid()));
+    }
+
+    #[test]
+    fn test_ed25519_key_from_pem() {
+        let key = JwtSigningKey::from_pem(Algorithm::EdDSA, ED25519_PEM).unwrap();
+        let jwk = key.jwk().unwrap();
+        assert_eq!(jwk.common.key_id.as_deref(), Some(key.kid()));
+        assert!(matches!(jwk.algorithm, AlgorithmParameters::OctetKeyPair(_)));
+        assert_round_trip(&key);
+    }
+
+    #[test]
+    fn test_rs256_key_from_pem() {
+        let key = JwtSigningKey::from_pem(Algorithm::RS256, RS256_PEM).unwrap();
+        let jwk = key.jwk().unwrap();
+        assert_eq!(jwk.common.key_id.as_deref(), Some(key.kid()));
+        assert!(matches!(jwk.algorithm, AlgorithmParameters::RSA(_)));
+        assert_round_trip(&key);
+    }
+
+    #[test]
+    fn test_kid_is_stable_and_unique_per_key() {
+        let first = JwtSigningKey::from_pem(Algorithm::EdDSA, ED25519_PEM).unwrap();
+        let second = JwtSigningKey::from_pem(Algorithm::EdDSA, ED25519_PEM).unwrap();
+        let rsa = JwtSigningKey::from_pem(Algorithm::RS256, RS256_PEM).unwrap();
+        assert_eq!(first.kid(), second.kid());
+        assert_ne!(first.kid(), rsa.kid());
+    }
+
+    #[test]
+    fn test_from_pem_rejects_mismatched_or_unsupported_algorithms() {
+        assert!(JwtSigningKey::from_pem(Algorithm::EdDSA, RS256_PEM).is_err());
+        assert!(JwtSigningKey::from_pem(Algorithm::RS256, ED25519_PEM).is_err());
+        assert!(JwtSigningKey::from_pem(Algorithm::HS256, ED25519_PEM).is_err());
+    }
+
+    #[test]
+    fn test_hmac_key_is_not_published() {
+        let key = JwtSigningKey::from_secret(b"secret");
+        assert_eq!(key.algorithm(), Algorithm::HS256);
+        assert!(key.jwk().is_none());
+    }
+
+    fn stored(algorithm: Algorithm, pem: &[u8], activates_at: i64, retire_at: Option<i64>) -> StoredSigningKey {
+        StoredSigningKey {
+            kid: JwtSigningKey::from_pem(algorithm, pem).unwrap().kid().to_owned(),
+            algorithm,
+            private_key: pem.to_vec(),
+            activates_at,
+            retire_at,
+        }
+    }
+
+    #[test]
+    fn test_key_ring_from_stored_keys() {
+        let first = stored(Algorithm::EdDSA, ED25519_PEM, 0, Some(1_900));
+        let second = stored(Algorithm::EdDSA, NEXT_ED25519_PEM, 1_000, None);
+        let keys = vec![first.clone(), second.clone()];
+
+        // The next key verifies and is published before it starts signing
+        let ring = JwtKeyRing::from_stored(&keys, 999).unwrap();
+        assert_eq!(ring.active().kid(), first.kid);
+        assert!(ring.find(&second.kid, 999).is_some());
+        assert_eq!(ring.jwks(999).keys.len(), 2);
+
+        let ring = JwtKeyRing::from_stored(&keys, 1_000).unwrap();
+        assert_eq!(ring.active().kid(), second.kid);
+        assert_eq!(ring.find(&first.kid, 1_899).map(|k| k.kid()), Some(first.kid.as_str()));
+        assert!(ring.find(&first.kid, 1_900).is_none());
+        assert_eq!(ring.jwks(1_900).keys.len(), 1);
+    }
+
+    #[test]
+    fn test_key_ring_from_stored_keys_needs_an_active_key() {
+        let keys = vec![stored(Algorithm::EdDSA, ED25519_PEM, 1_000, None)];
+        assert!(JwtKeyRing::from_stored(&keys, 999).is_err());
+        assert!(JwtKeyRing::from_stored(&[], 999).is_err());
+
+        // A key id that doesn't match the key means the row was tampered with
+        let mut key = stored(Algorithm::EdDSA, ED25519_PEM, 0, None);
+        key.kid = "other".to_owned();
+        assert!(JwtKeyRing::from_stored(&[key], 999).is_err());
+    }
+
+    #[test]
+    fn test_key_ring_rejects_known_keys() {
+        let first = JwtSigningKey::from_pem(Algorithm::EdDSA, ED25519_PEM).unwrap();
+        let second = JwtSigningKey::from_pem(Algorithm::EdDSA, NEXT_ED25519_PEM).unwrap();
+        let mut ring = JwtKeyRing::new(first.clone());
+
+        assert!(ring.add_verify_only_key(first, 2_400).is_err());
+        ring.add_verify_only_key(second.clone(), 2_400).unwrap();
+        assert!(ring.add_verify_only_key(second, 2_400).is_err());
+    }
+
+    #[test]
+    fn test_key_ring_does_not_publish_hmac_keys() {
+        let hmac = StoredSigningKey {
+            kid: "hmac".to_owned(),
+            algorithm: Algorithm::HS256,
+            private_key: b"secret".to_vec(),
+            activates_at: 0,
+            retire_at: Some(1_900),
+        };
+        let ring = JwtKeyRing::from_stored(std::slice::from_ref(&hmac), 0).unwrap();
+        assert_eq!(ring.active().algorithm(), Algorithm::HS256);
+        assert!(ring.jwks(0).keys.is_empty());
+
+        let key = stored(Algorithm::EdDSA, ED25519_PEM, 1_000, None);
+        let jwks = JwtKeyRing::from_stored(&[hmac, key.clone()], 1_000).unwrap().jwks(1_000);
+        assert_eq!(jwks.keys.len(), 1);
+        assert!(jwks.find(&key.kid).is_some());
+    }
+
+    #[test]
+    fn test_generated_keys_sign_and_verify() {
+        for algorithm in [Algorithm::EdDSA, Algorithm::RS256] {
+            let pem = generate_private_key(algorithm).unwrap();
+            let key = JwtSigningKey::from_pem(algorithm, &pem).unwrap();
+            assert_round_trip(&key);
+            assert_ne!(generate_private_key(algorithm).unwrap(), pem);
+        }
+        assert!(generate_private_key(Algorithm::HS256).is_err());
+    }
+}
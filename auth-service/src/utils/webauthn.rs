@@ -0,0 +1,245 @@
+use std::io::Cursor;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::domain::data_stores::WebauthnChallenge;
+
+use super::constants::{WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID};
+
+// COSE identifier of ECDSA with SHA-256 on P-256 (ES256), the only algorithm we accept
+pub const COSE_ALG_ES256: i64 = -7;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+pub fn encode_base64url(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn decode_base64url(value: &str) -> Result<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(value).wrap_err("invalid base64url value")
+}
+
+#[derive(Deserialize)]
+struct CollectedClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+// Checks the client data the browser signed over and returns the challenge it answers
+pub fn verify_client_data(client_data_json: &[u8], expected_type: &str) -> Result<WebauthnChallenge> {
+    let client_data: CollectedClientData =
+        serde_json::from_slice(client_data_json).wrap_err("invalid client data JSON")?;
+
+    if client_data.ceremony_type != expected_type {
+        return Err(eyre!("unexpected ceremony type {}", client_data.ceremony_type));
+    }
+    // The origin check is what makes WebAuthn phishing resistant
+    if client_data.origin != *WEBAUTHN_ORIGIN {
+        return Err(eyre!("unexpected origin {}", client_data.origin));
+    }
+
+    WebauthnChallenge::parse(client_data.challenge)
+}
+
+pub struct AuthenticatorData {
+    pub user_verified: bool,
+    pub sign_count: u32,
+    pub attested_credential: Option<AttestedCredential>,
+}
+
+pub struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    // Uncompressed SEC1 encoding of the P-256 public key
+    pub public_key: Vec<u8>,
+}
+
+pub fn parse_authenticator_data(bytes: &[u8]) -> Result<AuthenticatorData> {
+    if bytes.len() < 37 {
+        return Err(eyre!("authenticator data is too short"));
+    }
+
+    let rp_id_hash = Sha256::digest(WEBAUTHN_RP_ID.as_bytes());
+    if bytes[..32] != rp_id_hash[..] {
+        return Err(eyre!("authenticator data was created for another relying party"));
+    }
+
+    let flags = bytes[32];
+    if flags & FLAG_USER_PRESENT == 0 {
+        return Err(eyre!("user was not present"));
+    }
+
+    let sign_count = u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]);
+
+    let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+        Some(parse_attested_credential(&bytes[37..])?)
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        user_verified: flags & FLAG_USER_VERIFIED != 0,
+        sign_count,
+        attested_credential,
+    })
+}
+
+// Attested credential data is a 16 byte AAGUID, a 2 byte length, the credential id and its COSE key
+fn parse_attested_credential(bytes: &[u8]) -> Result<AttestedCredential> {
+    if bytes.len() < 18 {
+        return Err(eyre!("attested credential data is too short"));
+    }
+    let id_length = u16::from_be_bytes([bytes[16], bytes[17]]) as usize;
+    let credential_id = bytes
+        .get(18..18 + id_length)
+        .wrap_err("credential id is truncated")?
+        .to_vec();
+
+    // Extensions may follow the key, so only read one CBOR item
+    let mut reader = Cursor::new(&bytes[18 + id_length..]);
+    let cose_key: Value = ciborium::from_reader(&mut reader).wrap_err("invalid credential public key")?;
+
+    Ok(AttestedCredential {
+        credential_id,
+        public_key: parse_cose_key(cose_key)?,
+    })
+}
+
+fn parse_cose_key(cose_key: Value) -> Result<Vec<u8>> {
+    let entries = cose_key.into_map().map_err(|_| eyre!("credential public key is not a map"))?;
+    let mut x = None;
+    let mut y = None;
+    let (mut kty, mut alg, mut crv) = (None, None, None);
+
+    for (label, value) in entries {
+        let label = label.as_integer().map(i128::from).wrap_err("invalid COSE key label")?;
+        match label {
+            1 => kty = value.as_integer().map(i128::from),
+            3 => alg = value.as_integer().map(i128::from),
+            -1 => crv = value.as_integer().map(i128::from),
+            -2 => x = value.into_bytes().ok(),
+            -3 => y = value.into_bytes().ok(),
+            _ => {}
+        }
+    }
+
+    // EC2 key type on the P-256 curve
+    if kty != Some(2) || alg != Some(COSE_ALG_ES256 as i128) || crv != Some(1) {
+        return Err(eyre!("unsupported credential public key, only ES256 is accepted"));
+    }
+    let (x, y) = (x.wrap_err("missing x coordinate")?, y.wrap_err("missing y coordinate")?);
+    if x.len() != 32 || y.len() != 32 {
+        return Err(eyre!("invalid P-256 coordinates"));
+    }
+
+    let mut public_key = vec![0x04];
+    public_key.extend(x);
+    public_key.extend(y);
+    VerifyingKey::from_sec1_bytes(&public_key).wrap_err("credential public key is not on the P-256 curve")?;
+
+    Ok(public_key)
+}
+
+// Returns the authenticator data of a newly created credential
+pub fn parse_attestation_object(bytes: &[u8]) -> Result<AuthenticatorData> {
+    let attestation: Value = ciborium::from_reader(bytes).wrap_err("invalid attestation object")?;
+    let entries = attestation.into_map().map_err(|_| eyre!("attestation object is not a map"))?;
+
+    // Registration asks for "none" attestation: we trust the user's session rather than the
+    // authenticator's make and model, so the attestation statement itself is not inspected
+    let auth_data = entries
+        .into_iter()
+        .find(|(key, _)| key.as_text() == Some("authData"))
+        .and_then(|(_, value)| value.into_bytes().ok())
+        .wrap_err("attestation object has no authenticator data")?;
+
+    let authenticator_data = parse_authenticator_data(&auth_data)?;
+    if authenticator_data.attested_credential.is_none() {
+        return Err(eyre!("attestation object has no attested credential"));
+    }
+    Ok(authenticator_data)
+}
+
+// Assertion signatures cover the authenticator data followed by the SHA-256 of the client data
+pub fn verify_signature(
+    public_key: &[u8],
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    let key = VerifyingKey::from_sec1_bytes(public_key).wrap_err("invalid stored public key")?;
+    let signature = Signature::from_der(signature).wrap_err("invalid signature encoding")?;
+
+    let mut message = authenticator_data.to_vec();
+    message.extend(Sha256::digest(client_data_json));
+
+    key.verify(&message, &signature).wrap_err("signature verification failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    fn authenticator_data(flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend(sign_count.to_be_bytes());
+        data
+    }
+
+    fn client_data(ceremony_type: &str, challenge: &WebauthnChallenge, origin: &str) -> Vec<u8> {
+        serde_json::json!({"type": ceremony_type, "challenge": challenge.as_ref(), "origin": origin})
+            .to_string()
+            .into_bytes()
+    }
+
+    #[test]
+    fn test_verify_client_data() {
+        let challenge = WebauthnChallenge::default();
+        let data = client_data("webauthn.get", &challenge, &WEBAUTHN_ORIGIN);
+        assert_eq!(verify_client_data(&data, "webauthn.get").unwrap(), challenge);
+        assert!(verify_client_data(&data, "webauthn.create").is_err());
+
+        let phished = client_data("webauthn.get", &challenge, "https://evil.example.com");
+        assert!(verify_client_data(&phished, "webauthn.get").is_err());
+    }
+
+    #[test]
+    fn test_parse_authenticator_data() {
+        let data = parse_authenticator_data(&authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 5)).unwrap();
+        assert!(data.user_verified);
+        assert_eq!(data.sign_count, 5);
+        assert!(data.attested_credential.is_none());
+
+        assert!(parse_authenticator_data(&authenticator_data(0, 5)).is_err());
+
+        let mut other_rp = authenticator_data(FLAG_USER_PRESENT, 5);
+        other_rp[0] ^= 1;
+        assert!(parse_authenticator_data(&other_rp).is_err());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let signing_key = SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng);
+        let public_key = signing_key.verifying_key().to_encoded_point(false).as_bytes().to_vec();
+        let auth_data = authenticator_data(FLAG_USER_PRESENT, 1);
+        let client_data = client_data("webauthn.get", &WebauthnChallenge::default(), &WEBAUTHN_ORIGIN);
+
+        let mut message = auth_data.clone();
+        message.extend(Sha256::digest(&client_data));
+        let signature: Signature = signing_key.sign(&message);
+        let signature = signature.to_der();
+
+        assert!(verify_signature(&public_key, &auth_data, &client_data, signature.as_bytes()).is_ok());
+        assert!(verify_signature(&public_key, &authenticator_data(FLAG_USER_PRESENT, 2), &client_data, signature.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{data_stores::{TotpSecret, WebauthnCredential}, Email},
+    routes::AccountExport,
+    utils::constants::{JWT_COOKIE_NAME, LOGIN_LOCKOUT_THRESHOLD, RECOVERY_CODE_COUNT},
+};
+
+// Signs up a 2FA user and completes a full login so the cookie jar holds a valid session
+async fn signup_and_login_with_2fa(app: &TestApp, email: &str) -> String {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    app.verify_email(email).await;
+
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+
+    let parsed_email = Email::parse(email.to_owned()).unwrap();
+    let (login_attempt_id, code, _) = app.two_fa_code_store
+        .read()
+        .await
+        .get_code(&parsed_email)
+        .await
+        .expect("2FA code should be stored");
+
+    let verify_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": code.as_ref()
+    });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    token
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.delete_account(&serde_json::json!({"password": "password123"})).await;
+    assert_eq!(response.status(), 400);
+
+    let response = app.get_account_export().await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_is_incorrect() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    signup_and_login_with_2fa(&app, &random_email).await;
+
+    let response = app.delete_account(&serde_json::json!({"password": "wrongpassword"})).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.get_account_export().await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_account_after_repeated_wrong_passwords() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    signup_and_login_with_2fa(&app, &random_email).await;
+
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.delete_account(&serde_json::json!({"password": "wrongpassword"})).await;
+        assert_eq!(response.status(), 401);
+    }
+    let response = app.delete_account(&serde_json::json!({"password": "wrongpassword"})).await;
+    assert_eq!(response.status(), 423);
+
+    // Even the right password is refused while the lock lasts
+    let response = app.delete_account(&serde_json::json!({"password": "password123"})).await;
+    assert_eq!(response.status(), 423);
+    let response = app.get_account_export().await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_delete_account_and_clear_its_state() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let token = signup_and_login_with_2fa(&app, &random_email).await;
+
+    // Start a second login so there is a pending 2FA code for the account
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+
+    let response = app.delete_account(&serde_json::json!({"password": "password123"})).await;
+    assert_eq!(response.status(), 204);
+
+    let email = Email::parse(random_email.clone()).unwrap();
+    assert!(app.two_fa_code_store.read().await.get_code(&email).await.is_err());
+
+    let response = app.post_verify_token(&serde_json::json!({"token": token})).await;
+    assert_eq!(response.status(), 401);
+
+    // The email address is free to be used again
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_export_account_data() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    signup_and_login_with_2fa(&app, &random_email).await;
+    let email = Email::parse(random_email.clone()).unwrap();
+    app.totp_secret_store.write().await.add_secret(&email, TotpSecret::default()).await.unwrap();
+    let passkey = WebauthnCredential {
+        credential_id: "credential-1".to_owned(),
+        email: email.clone(),
+        public_key: vec![4; 65],
+        sign_count: 7,
+        name: "Laptop".to_owned(),
+        created_at: 1_700_000_000,
+    };
+    app.webauthn_credential_store.write().await.add_credential(passkey).await.unwrap();
+    let response = app.post_api_key(&serde_json::json!({"name": "CI", "scopes": ["read"]})).await;
+    assert_eq!(response.status(), 201);
+
+    let response = app.get_account_export().await;
+    assert_eq!(response.status(), 200);
+
+    let export = response
+        .json::<AccountExport>()
+        .await
+        .expect("Could not deserialize response body to AccountExport");
+    assert_eq!(export.email, random_email);
+    assert!(export.requires_2fa);
+    assert!(export.verified);
+    assert!(export.roles.is_empty());
+    assert!(!export.disabled);
+    assert_eq!(export.phone_number, None);
+    assert_eq!(export.two_fa_channel, "email");
+    assert_eq!(export.totp_enrollment, "pending");
+    assert_eq!(export.passkeys.len(), 1);
+    let passkey = &export.passkeys[0];
+    assert_eq!((passkey.credential_id.as_str(), passkey.name.as_str()), ("credential-1", "Laptop"));
+    assert_eq!((passkey.sign_count, passkey.created_at), (7, 1_700_000_000));
+    assert_eq!(export.remaining_recovery_codes, RECOVERY_CODE_COUNT);
+    assert_eq!(export.api_keys.len(), 1);
+    assert_eq!(export.api_keys[0].name, "CI");
+    assert_eq!(export.api_keys[0].scopes, vec!["read"]);
+    assert_eq!(export.api_keys[0].last_used_at, None);
+    assert!(!export.pending_two_fa_code);
+    assert!(!export.pending_email_verification);
+    app.clean_up().await;
+}
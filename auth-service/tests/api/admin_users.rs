@@ -0,0 +1,258 @@
+use auth_service::{
+    domain::{Email, Password, User},
+    routes::{TwoFactorAuthResponse, UserListResponse, UserSummary},
+    ErrorResponse,
+};
+
+use crate::helpers::TestApp;
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_admin_users(&[]).await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_not_admin() {
+    let mut app = TestApp::new().await;
+    let (email, _) = app.login_verified_user().await;
+
+    let response = app.get_admin_users(&[]).await;
+    assert_eq!(response.status(), 403);
+    let response = app.delete_admin_user(&email).await;
+    assert_eq!(response.status(), 403);
+    assert!(app.user_store.read().await.get_user(&email).await.is_ok());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_users_by_email_prefix_and_page() {
+    let mut app = TestApp::new().await;
+    let admin = app.login_as_admin().await;
+
+    for email in ["bob@example.com", "alice@example.com", "alex@example.com", "al_x@example.com"] {
+        let user = User::new(Email::parse(email.to_owned()).unwrap(), Password::parse("password123".to_owned()).unwrap(), false);
+        app.user_store.write().await.add_user(user).await.unwrap();
+    }
+
+    let response = app.get_admin_users(&[("emailPrefix", "al"), ("perPage", "2")]).await;
+    assert_eq!(response.status(), 200);
+    let list = response.json::<UserListResponse>().await.expect("Could not deserialize response body to UserListResponse");
+    let emails: Vec<&str> = list.users.iter().map(|user| user.email.as_str()).collect();
+    assert_eq!(emails, vec!["al_x@example.com", "alex@example.com"]);
+    assert_eq!((list.page, list.per_page, list.total), (1, 2, 3));
+
+    let response = app.get_admin_users(&[("emailPrefix", "al"), ("perPage", "2"), ("page", "2")]).await;
+    let list = response.json::<UserListResponse>().await.unwrap();
+    let emails: Vec<&str> = list.users.iter().map(|user| user.email.as_str()).collect();
+    assert_eq!(emails, vec!["alice@example.com"]);
+
+    // LIKE wildcards in the prefix are matched literally
+    let response = app.get_admin_users(&[("emailPrefix", "al_")]).await;
+    let list = response.json::<UserListResponse>().await.unwrap();
+    assert_eq!(list.total, 1);
+
+    let response = app.get_admin_users(&[]).await;
+    let list = response.json::<UserListResponse>().await.unwrap();
+    assert_eq!(list.total, 5);
+    assert!(list.users.iter().any(|user| user.email == admin && user.roles == vec!["admin".to_owned()]));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_pagination_is_invalid() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+
+    for query in [[("page", "0")], [("perPage", "0")], [("perPage", "101")]] {
+        let response = app.get_admin_users(&query).await;
+        assert_eq!(response.status(), 400, "Failed for query: {:?}", query);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_get_user_or_return_404() {
+    let mut app = TestApp::new().await;
+    let email = app.create_verified_user(true).await;
+    app.login_as_admin().await;
+
+    let response = app.get_admin_user(&email).await;
+    assert_eq!(response.status(), 200);
+    let user = response.json::<UserSummary>().await.expect("Could not deserialize response body to UserSummary");
+    assert_eq!(
+        user,
+        UserSummary { email, requires_2fa: true, verified: true, disabled: false, roles: Vec::new() }
+    );
+
+    let response = app.get_admin_user("nobody@example.com").await;
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.json::<ErrorResponse>().await.unwrap().error, "User not found".to_owned());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_disable_user_and_end_their_sessions() {
+    let mut app = TestApp::new().await;
+    let (email, token) = app.login_verified_user().await;
+    app.login_as_admin().await;
+
+    let response = app.patch_admin_user(&email, &serde_json::json!({"disabled": true})).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.json::<UserSummary>().await.unwrap().disabled);
+
+    let response = app.post_verify_token(&serde_json::json!({"token": token})).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(response.json::<ErrorResponse>().await.unwrap().error, "Account disabled".to_owned());
+
+    let response = app.patch_admin_user(&email, &serde_json::json!({"disabled": false})).await;
+    assert_eq!(response.status(), 200);
+    assert!(!response.json::<UserSummary>().await.unwrap().disabled);
+
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_force_2fa() {
+    let mut app = TestApp::new().await;
+    let email = app.create_verified_user(false).await;
+    app.login_as_admin().await;
+
+    let response = app.patch_admin_user(&email, &serde_json::json!({"requires2FA": true})).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.json::<UserSummary>().await.unwrap().requires_2fa);
+
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let json_body = response.json::<TwoFactorAuthResponse>().await.unwrap();
+    assert_eq!(json_body.message, "2FA Required".to_owned());
+
+    let response = app.patch_admin_user("nobody@example.com", &serde_json::json!({"requires2FA": true})).await;
+    assert_eq!(response.status(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_force_logout() {
+    let mut app = TestApp::new().await;
+    let (email, token) = app.login_verified_user().await;
+    app.login_as_admin().await;
+
+    let response = app.post_admin_force_logout(&email).await;
+    assert_eq!(response.status(), 204);
+
+    let response = app.post_verify_token(&serde_json::json!({"token": token})).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.post_admin_force_logout("nobody@example.com").await;
+    assert_eq!(response.status(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_delete_user() {
+    let mut app = TestApp::new().await;
+    let (email, token) = app.login_verified_user().await;
+    app.login_as_admin().await;
+
+    let response = app.delete_admin_user(&email).await;
+    assert_eq!(response.status(), 204);
+
+    let response = app.get_admin_user(&email).await;
+    assert_eq!(response.status(), 404);
+    let response = app.post_verify_token(&serde_json::json!({"token": token})).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.delete_admin_user(&email).await;
+    assert_eq!(response.status(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_replace_roles_and_end_sessions() {
+    let mut app = TestApp::new().await;
+    let (email, token) = app.login_verified_user().await;
+    app.login_as_admin().await;
+
+    let response = app.patch_admin_user(&email, &serde_json::json!({"roles": ["Not a role"]})).await;
+    assert_eq!(response.status(), 400);
+    let response = app.patch_admin_user(&email, &serde_json::json!({"roles": ["support"]})).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(response.json::<ErrorResponse>().await.unwrap().error, "Unknown role".to_owned());
+
+    app.add_role("support").await;
+    let response = app.patch_admin_user(&email, &serde_json::json!({"roles": ["support"]})).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.json::<UserSummary>().await.unwrap().roles, vec!["support".to_owned()]);
+
+    // The old token doesn't carry the new roles
+    let response = app.post_verify_token(&serde_json::json!({"token": token})).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_leave_user_untouched_if_any_role_is_unknown() {
+    let mut app = TestApp::new().await;
+    let (email, token) = app.login_verified_user().await;
+    app.login_as_admin().await;
+
+    let body = serde_json::json!({"disabled": true, "requires2FA": true, "roles": ["typo"]});
+    let response = app.patch_admin_user(&email, &body).await;
+    assert_eq!(response.status(), 400);
+
+    let summary = app.get_admin_user(&email).await.json::<UserSummary>().await.unwrap();
+    assert!(!summary.disabled && !summary.requires_2fa);
+    assert!(summary.roles.is_empty());
+    let response = app.post_verify_token(&serde_json::json!({"token": token})).await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_let_admin_lock_themselves_out() {
+    let mut app = TestApp::new().await;
+    let admin = app.login_as_admin().await;
+
+    for body in [
+        serde_json::json!({"disabled": true}),
+        serde_json::json!({"roles": []}),
+        serde_json::json!({"requires2FA": true, "disabled": true}),
+    ] {
+        let response = app.patch_admin_user(&admin, &body).await;
+        assert_eq!(response.status(), 409, "Failed for input: {:?}", body);
+        assert_eq!(
+            response.json::<ErrorResponse>().await.unwrap().error,
+            "Admins can't disable, delete or demote themselves".to_owned()
+        );
+    }
+    let response = app.delete_admin_user(&admin).await;
+    assert_eq!(response.status(), 409);
+
+    let response = app.get_admin_user(&admin).await;
+    assert_eq!(response.status(), 200);
+    let summary = response.json::<UserSummary>().await.unwrap();
+    assert!(!summary.disabled && !summary.requires_2fa);
+    assert_eq!(summary.roles, vec!["admin".to_owned()]);
+
+    app.clean_up().await;
+}
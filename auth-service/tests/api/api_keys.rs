@@ -0,0 +1,160 @@
+use auth_service::{
+    domain::{Email, Role},
+    routes::{ApiKeyListResponse, CreateApiKeyResponse},
+    utils::auth::ApiKeyClaims,
+};
+
+use crate::helpers::TestApp;
+
+// Signs a new user in and returns their email
+async fn create_api_key(app: &TestApp, body: &serde_json::Value) -> CreateApiKeyResponse {
+    let response = app.post_api_key(body).await;
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    response.json::<CreateApiKeyResponse>().await.expect("Could not deserialize response body")
+}
+
+#[tokio::test]
+async fn should_create_api_key_shown_once() {
+    let mut app = TestApp::new().await;
+    let (email, _) = app.login_verified_user().await;
+
+    let body = serde_json::json!({"name": "Deploy script", "scopes": ["reports:read"], "expiresInDays": 30});
+    let created = create_api_key(&app, &body).await;
+    assert!(created.api_key.starts_with(&format!("{}_", created.key.prefix)));
+    assert!(created.key.prefix.starts_with("ask_"));
+    assert_eq!((created.key.name.as_str(), created.key.scopes.clone()), ("Deploy script", vec!["reports:read".to_owned()]));
+    assert_eq!(created.key.expires_at, Some(created.key.created_at + 30 * 86_400));
+    assert!(created.key.last_used_at.is_none());
+
+    // Listings never include the key itself
+    let response = app.get_api_keys().await;
+    assert_eq!(response.status(), 200);
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert!(!body.to_string().contains(&created.api_key));
+    let list = serde_json::from_value::<ApiKeyListResponse>(body).unwrap();
+    assert_eq!(list.keys, vec![created.key]);
+
+    let response = app.post_verify_token(&serde_json::json!({"token": created.api_key})).await;
+    assert_eq!(response.status(), 200);
+    let claims = response.json::<ApiKeyClaims>().await.expect("Could not deserialize response body");
+    assert_eq!((claims.sub, claims.scope), (email, "reports:read".to_owned()));
+
+    let list = app.get_api_keys().await.json::<ApiKeyListResponse>().await.unwrap();
+    assert!(list.keys[0].last_used_at.is_some());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_never_grant_roles_through_api_keys() {
+    let mut app = TestApp::new().await;
+    let (email, _) = app.login_verified_user().await;
+    let created = create_api_key(&app, &serde_json::json!({"name": "Admin script"})).await;
+    assert!(created.key.expires_at.is_none());
+
+    app.user_store.write().await.set_roles(&email, vec![Role::admin()]).await.unwrap();
+    let response = app.post_verify_token(&serde_json::json!({"token": created.api_key})).await;
+    assert_eq!(response.status(), 200);
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert!(body.get("roles").is_none());
+
+    // Keys of disabled accounts stop working with the account
+    app.user_store.write().await.set_disabled(&email, true).await.unwrap();
+    let response = app.post_verify_token(&serde_json::json!({"token": created.api_key})).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_api_key() {
+    let mut app = TestApp::new().await;
+    app.login_verified_user().await;
+    let created = create_api_key(&app, &serde_json::json!({"name": "Old script"})).await;
+
+    // Other users can't see or revoke the key
+    app.login_verified_user().await;
+    assert!(app.get_api_keys().await.json::<ApiKeyListResponse>().await.unwrap().keys.is_empty());
+    assert_eq!(app.delete_api_key(&created.key.id).await.status(), 404);
+    let response = app.post_verify_token(&serde_json::json!({"token": created.api_key})).await;
+    assert_eq!(response.status(), 200);
+
+    let other = create_api_key(&app, &serde_json::json!({"name": "Other script"})).await;
+    assert_eq!(app.delete_api_key(&other.key.id).await.status(), 204);
+    let response = app.post_verify_token(&serde_json::json!({"token": other.api_key})).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(app.delete_api_key(&other.key.id).await.status(), 404);
+    assert_eq!(app.delete_api_key("not-a-key-id").await.status(), 404);
+
+    // A key with a forged secret is no key at all
+    let forged = format!("{}_{}", created.key.prefix, "A".repeat(43));
+    let response = app.post_verify_token(&serde_json::json!({"token": forged})).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_api_keys_with_the_password_or_a_forced_logout() {
+    let mut app = TestApp::new().await;
+    let (email, _) = app.login_verified_user().await;
+    let created = create_api_key(&app, &serde_json::json!({"name": "Script"})).await;
+    let body = serde_json::json!({"currentPassword": "password123", "newPassword": "newpassword123"});
+    assert_eq!(app.post_change_password(&body).await.status(), 200);
+    assert_eq!(app.post_verify_token(&serde_json::json!({"token": created.api_key})).await.status(), 401);
+    assert!(app.get_api_keys().await.json::<ApiKeyListResponse>().await.unwrap().keys.is_empty());
+
+    let created = create_api_key(&app, &serde_json::json!({"name": "Script"})).await;
+    let response = app.post_password_reset_request(&serde_json::json!({"email": email})).await;
+    assert_eq!(response.status(), 200);
+    let token = app
+        .password_reset_token_store
+        .read()
+        .await
+        .get_token(&Email::parse(email.clone()).unwrap())
+        .await
+        .expect("Password reset token should be stored");
+    let body = serde_json::json!({"email": email, "token": token.as_ref(), "newPassword": "newpassword456"});
+    assert_eq!(app.post_password_reset_confirm(&body).await.status(), 200);
+    assert_eq!(app.post_verify_token(&serde_json::json!({"token": created.api_key})).await.status(), 401);
+
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "newpassword456"})).await;
+    assert_eq!(response.status(), 200);
+    let created = create_api_key(&app, &serde_json::json!({"name": "Script"})).await;
+    app.login_as_admin().await;
+    assert_eq!(app.post_admin_force_logout(&email).await.status(), 204);
+    assert_eq!(app.post_verify_token(&serde_json::json!({"token": created.api_key})).await.status(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_for_invalid_api_key_request() {
+    let mut app = TestApp::new().await;
+    app.login_verified_user().await;
+
+    let bodies = [
+        serde_json::json!({"name": " "}),
+        serde_json::json!({"name": "x".repeat(101)}),
+        serde_json::json!({"name": "Script", "scopes": ["bad scope"]}),
+        serde_json::json!({"name": "Script", "expiresInDays": 0}),
+        serde_json::json!({"name": "Script", "expiresInDays": 366}),
+    ];
+    for body in bodies {
+        let response = app.post_api_key(&body).await;
+        assert_eq!(response.status(), 400, "Failed for input: {:?}", body);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_a_session_to_manage_api_keys() {
+    let mut app = TestApp::new().await;
+
+    assert_eq!(app.post_api_key(&serde_json::json!({"name": "Script"})).await.status(), 400);
+    assert_eq!(app.get_api_keys().await.status(), 400);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,141 @@
+use crate::helpers::{get_cookie, get_random_email, TestApp};
+use auth_service::{routes::ChangePasswordResponse, utils::constants::{JWT_COOKIE_NAME, LOGIN_LOCKOUT_THRESHOLD}};
+use reqwest::Url;
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let body = serde_json::json!({"currentPassword": "password123", "newPassword": "newpassword123"});
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let body = serde_json::json!({"currentPassword": "password123", "newPassword": "newpassword123"});
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+
+    let test_cases = [
+        serde_json::json!({"currentPassword": "password123"}),
+        serde_json::json!({"newPassword": "newpassword123"}),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_change_password(&test_case).await;
+        assert_eq!(
+            response.status(),
+            422,
+            "The API did not fail with 422 when payload was {}",
+            test_case
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+
+    let body = serde_json::json!({"currentPassword": "password123", "newPassword": "short"});
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_current_password_is_incorrect() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+
+    let body = serde_json::json!({"currentPassword": "wrongpassword", "newPassword": "newpassword123"});
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_account_after_repeated_wrong_current_passwords() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+
+    let body = serde_json::json!({"currentPassword": "wrongpassword", "newPassword": "newpassword123"});
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_change_password(&body).await;
+        assert_eq!(response.status(), 401);
+    }
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), 423);
+
+    // Even the right password is refused while the lock lasts
+    let body = serde_json::json!({"currentPassword": "password123", "newPassword": "newpassword123"});
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), 423);
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "password123"})).await;
+    assert_eq!(response.status(), 423);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_change_password_and_revoke_other_sessions() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let other_session_token = get_cookie(&app.signup_and_login(&random_email).await, JWT_COOKIE_NAME);
+
+    // The caller's own session, stored in the cookie jar
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let current_token = get_cookie(&response, JWT_COOKIE_NAME);
+
+    // Tokens carry second-resolution issue times, so make sure the change happens in a later second
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    let body = serde_json::json!({"currentPassword": "password123", "newPassword": "newpassword123"});
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), 200);
+
+    let new_token = get_cookie(&response, JWT_COOKIE_NAME);
+    response
+        .json::<ChangePasswordResponse>()
+        .await
+        .expect("Could not deserialize response body to ChangePasswordResponse");
+
+    for token in [other_session_token, current_token] {
+        let response = app.post_verify_token(&serde_json::json!({"token": token})).await;
+        assert_eq!(response.status(), 401);
+    }
+
+    let response = app.post_verify_token(&serde_json::json!({"token": new_token})).await;
+    assert_eq!(response.status(), 200);
+
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "password123"})).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "newpassword123"})).await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
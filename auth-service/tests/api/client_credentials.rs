@@ -0,0 +1,163 @@
+use auth_service::routes::{CreateOAuthClientResponse, OAuthClientSummary, TokenResponse};
+
+use crate::helpers::TestApp;
+use crate::oauth::{oauth_error, register_client, CODE_CHALLENGE, REDIRECT_URI};
+use crate::oauth_tokens::introspect;
+
+// Registers a machine client as a new admin and returns its id and secret
+pub(crate) async fn register_machine_client(app: &TestApp, allowed_audiences: &[&str]) -> (String, String) {
+    app.login_as_admin().await;
+    let body = serde_json::json!({
+        "name": "Batch jobs",
+        "type": "machine",
+        "allowedScopes": ["reports:read", "reports:write"],
+        "allowedAudiences": allowed_audiences
+    });
+    let response = app.post_admin_oauth_client(&body).await;
+    assert_eq!(response.status(), 201);
+    let created = response.json::<CreateOAuthClientResponse>().await.expect("Could not deserialize response body");
+    (created.client.client_id, created.client_secret.expect("No secret for machine client"))
+}
+
+#[tokio::test]
+async fn should_show_machine_client_secret_once() {
+    let mut app = TestApp::new().await;
+    let (client_id, _) = register_machine_client(&app, &["app-service"]).await;
+
+    let response = app.get_admin_oauth_client(&client_id).await;
+    assert_eq!(response.status(), 200);
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert!(body.get("clientSecret").is_none());
+    let client = serde_json::from_value::<OAuthClientSummary>(body).unwrap();
+    assert_eq!((client.client_type.as_str(), client.allowed_audiences), ("machine", vec!["app-service".to_owned()]));
+    assert!(client.redirect_uris.is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_issue_audience_scoped_tokens() {
+    let mut app = TestApp::new().await;
+    let (client_id, secret) = register_machine_client(&app, &["app-service"]).await;
+
+    // Credentials may come through HTTP Basic or the form, and a sole audience may be left out
+    let response = app
+        .post_oauth_token_with_basic_auth(&client_id, &secret, &[("grant_type", "client_credentials"), ("scope", "reports:read")])
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let token = response.json::<TokenResponse>().await.expect("Could not deserialize response body");
+    assert_eq!((token.token_type.as_str(), token.scope.as_str()), ("Bearer", "reports:read"));
+    assert!(token.id_token.is_none());
+
+    let introspection = introspect(&app, &client_id, &secret, &token.access_token).await;
+    assert!(introspection.active);
+    assert_eq!(introspection.aud.as_deref(), Some("app-service"));
+    assert_eq!((introspection.sub, introspection.client_id), (Some(client_id.clone()), Some(client_id.clone())));
+
+    // Service tokens are not sessions
+    let response = app.post_verify_token(&serde_json::json!({"token": token.access_token})).await;
+    assert_eq!(response.status(), 401);
+
+    let form = [
+        ("grant_type", "client_credentials"),
+        ("client_id", client_id.as_str()),
+        ("client_secret", secret.as_str()),
+        ("audience", "app-service"),
+    ];
+    let response = app.post_oauth_token(&form).await;
+    assert_eq!(response.status(), 200);
+    let token = response.json::<TokenResponse>().await.unwrap();
+    assert_eq!(token.scope, "reports:read reports:write");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_allowed_audience() {
+    let mut app = TestApp::new().await;
+    let (client_id, secret) = register_machine_client(&app, &["app-service", "billing-service"]).await;
+
+    let cases = [
+        (vec![("grant_type", "client_credentials")], "invalid_target"),
+        (vec![("grant_type", "client_credentials"), ("audience", "admin-service")], "invalid_target"),
+        (vec![("grant_type", "client_credentials"), ("audience", "app-service"), ("scope", "users:delete")], "invalid_scope"),
+    ];
+    for (form, error) in cases {
+        let response = app.post_oauth_token_with_basic_auth(&client_id, &secret, &form).await;
+        assert_eq!(response.status(), 400, "Failed for input: {:?}", form);
+        assert_eq!(oauth_error(response).await, error);
+    }
+
+    let form = [("grant_type", "client_credentials"), ("audience", "billing-service")];
+    let response = app.post_oauth_token_with_basic_auth(&client_id, &secret, &form).await;
+    assert_eq!(response.status(), 200);
+    let token = response.json::<TokenResponse>().await.unwrap();
+    let introspection = introspect(&app, &client_id, &secret, &token.access_token).await;
+    assert_eq!(introspection.aud.as_deref(), Some("billing-service"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_invalid_client_credentials() {
+    let mut app = TestApp::new().await;
+    let (client_id, secret) = register_machine_client(&app, &["app-service"]).await;
+    let public_client_id = register_client(&app, &["profile"]).await;
+    let form = [("grant_type", "client_credentials")];
+
+    let wrong_secret = "A".repeat(secret.len());
+    for (id, secret) in [
+        (client_id.as_str(), wrong_secret.as_str()),
+        (client_id.as_str(), "not-a-secret"),
+        (public_client_id.as_str(), secret.as_str()),
+        ("00000000-0000-0000-0000-000000000000", secret.as_str()),
+    ] {
+        let response = app.post_oauth_token_with_basic_auth(id, secret, &form).await;
+        assert_eq!(response.status(), 401);
+        assert_eq!(response.headers()["www-authenticate"], "Basic realm=\"oauth\"");
+        assert_eq!(oauth_error(response).await, "invalid_client");
+    }
+
+    // Without any credentials at all
+    let response = app.post_oauth_token(&form).await;
+    assert_eq!(oauth_error(response).await, "invalid_client");
+
+    // Secrets stop working once the client is deleted
+    app.login_as_admin().await;
+    assert_eq!(app.delete_admin_oauth_client(&client_id).await.status(), 204);
+    let response = app.post_oauth_token_with_basic_auth(&client_id, &secret, &form).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_machine_clients_out_of_the_code_flow() {
+    let mut app = TestApp::new().await;
+    let (client_id, _) = register_machine_client(&app, &["app-service"]).await;
+
+    let query = [
+        ("response_type", "code"),
+        ("client_id", client_id.as_str()),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_challenge", CODE_CHALLENGE),
+        ("code_challenge_method", "S256"),
+    ];
+    let response = app.get_oauth_authorize(&query).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(oauth_error(response).await, "unauthorized_client");
+
+    let form = [
+        ("grant_type", "authorization_code"),
+        ("client_id", client_id.as_str()),
+        ("code", "code"),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", "verifier"),
+    ];
+    let response = app.post_oauth_token(&form).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(oauth_error(response).await, "unauthorized_client");
+
+    app.clean_up().await;
+}
@@ -0,0 +1,217 @@
+use auth_service::{
+    domain::{data_stores::{OutboxEmailId, OutboxStatus}, Email},
+    routes::{OutboxEmailListResponse, OutboxEmailSummary, TwoFactorAuthResponse},
+    utils::constants::{EMAIL_OUTBOX_MAX_ATTEMPTS, EMAIL_OUTBOX_RETRY_BASE_SECONDS, LOGIN_LOCKOUT_THRESHOLD, TWO_FA_CODE_TTL_SECONDS},
+};
+
+use crate::helpers::TestApp;
+
+// Logs a new 2FA user in and returns their email, which leaves a 2FA code email in the outbox
+async fn request_2fa_code(app: &TestApp) -> String {
+    let email = app.create_verified_user(true).await;
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let body = response.json::<TwoFactorAuthResponse>().await.expect("Could not deserialize response body");
+    assert_eq!(body.message, "2FA Required");
+    email
+}
+
+async fn list_outbox(app: &TestApp, status: &str) -> OutboxEmailListResponse {
+    let response = app.get_admin_email_outbox(&[("status", status), ("perPage", "100")]).await;
+    assert_eq!(response.status(), 200);
+    response.json::<OutboxEmailListResponse>().await.expect("Could not deserialize response body")
+}
+
+// Locks a new user out and returns their email, which leaves an account locked email in the outbox. Unlike
+// codes and links that email never expires, so its delivery is retried until the attempts run out.
+async fn request_lock_email(app: &TestApp) -> String {
+    let email = app.create_verified_user(false).await;
+    let wrong_login = serde_json::json!({"email": email, "password": "wrong-password"});
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        assert_eq!(app.post_login(&wrong_login).await.status(), 401);
+    }
+    assert_eq!(app.post_login(&wrong_login).await.status(), 423);
+    email
+}
+
+fn find_email<'a>(list: &'a OutboxEmailListResponse, recipient: &str, subject: &str) -> Option<&'a OutboxEmailSummary> {
+    list.emails.iter().find(|email| email.recipient == recipient && email.subject == subject)
+}
+
+fn find_2fa_email<'a>(list: &'a OutboxEmailListResponse, recipient: &str) -> Option<&'a OutboxEmailSummary> {
+    find_email(list, recipient, "Your login code")
+}
+
+// Fails delivery of the queued email with `subject` until it is dead-lettered, skipping the backoff instead of
+// waiting it out
+async fn fail_until_dead(app: &TestApp, recipient: &str, subject: &str) {
+    let recipient = Email::parse(recipient.to_owned()).unwrap();
+    let (queued, _) = app.email_outbox_store.read().await
+        .list(OutboxStatus::Pending, 0, 100)
+        .await
+        .unwrap();
+    let id = queued
+        .iter()
+        .find(|queued| queued.recipient == recipient && queued.content.subject == subject)
+        .expect("Email should be queued")
+        .id
+        .clone();
+
+    for _ in 0..EMAIL_OUTBOX_MAX_ATTEMPTS {
+        app.deliver_emails().await;
+        let (dead, _) = app.email_outbox_store.read().await.list(OutboxStatus::Dead, 0, 100).await.unwrap();
+        if dead.iter().any(|email| email.id == id) {
+            return;
+        }
+        app.email_outbox_store.write().await.mark_failed(&id, "fast forward", Some(0)).await.unwrap();
+    }
+    panic!("Email should be dead-lettered after {} attempts", EMAIL_OUTBOX_MAX_ATTEMPTS);
+}
+
+#[tokio::test]
+async fn should_return_403_if_not_admin() {
+    let mut app = TestApp::new().await;
+    app.login_verified_user().await;
+
+    assert_eq!(app.get_admin_email_outbox(&[]).await.status(), 403);
+    let response = app.post_admin_replay_outbox_email(OutboxEmailId::default().as_ref()).await;
+    assert_eq!(response.status(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_deliver_queued_emails_and_remove_them() {
+    let mut app = TestApp::new().await;
+    let email = request_2fa_code(&app).await;
+    let parsed_email = Email::parse(email.clone()).unwrap();
+
+    // Nothing goes out until the worker runs
+    assert!(app.email_client.last_email_to(&parsed_email).await.is_none());
+    app.deliver_emails().await;
+    let content = app.email_client.last_email_to(&parsed_email).await.expect("2FA code should be emailed");
+    assert_eq!(content.subject, "Your login code");
+
+    app.login_as_admin().await;
+    assert!(find_2fa_email(&list_outbox(&app, "pending").await, &email).is_none());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_login_working_and_retry_when_delivery_fails() {
+    let mut app = TestApp::new().await;
+    app.email_client.set_failing(true);
+    let email = request_2fa_code(&app).await;
+
+    app.deliver_emails().await;
+    assert!(app.email_client.last_email_to(&Email::parse(email.clone()).unwrap()).await.is_none());
+
+    app.login_as_admin().await;
+    let pending = list_outbox(&app, "pending").await;
+    let queued = find_2fa_email(&pending, &email).expect("Failed email should stay queued");
+    assert_eq!(queued.status, "pending");
+    assert_eq!(queued.attempts, 1);
+    assert!(queued.last_error.is_some());
+    assert!(queued.next_attempt_at >= queued.created_at + EMAIL_OUTBOX_RETRY_BASE_SECONDS as i64 - 1);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_dead_letter_after_max_attempts_and_replay() {
+    let mut app = TestApp::new().await;
+    app.email_client.set_failing(true);
+    let email = request_lock_email(&app).await;
+    let parsed_email = Email::parse(email.clone()).unwrap();
+    fail_until_dead(&app, &email, "Account locked").await;
+
+    app.login_as_admin().await;
+    assert!(find_email(&list_outbox(&app, "pending").await, &email, "Account locked").is_none());
+    // Dead letters are what the admin listing shows by default
+    let response = app.get_admin_email_outbox(&[("perPage", "100")]).await;
+    assert_eq!(response.status(), 200);
+    let dead = response.json::<OutboxEmailListResponse>().await.expect("Could not deserialize response body");
+    let dead_email = find_email(&dead, &email, "Account locked").expect("Email should be dead-lettered");
+    assert_eq!((dead_email.status.as_str(), dead_email.attempts), ("dead", EMAIL_OUTBOX_MAX_ATTEMPTS));
+    assert_eq!(dead_email.last_error.as_deref(), Some("Mock email client is failing"));
+    assert_eq!(dead_email.expires_at, None);
+
+    let response = app.post_admin_replay_outbox_email(&dead_email.id).await;
+    assert_eq!(response.status(), 200);
+    let replayed = response.json::<OutboxEmailSummary>().await.expect("Could not deserialize response body");
+    assert_eq!((replayed.status.as_str(), replayed.attempts), ("pending", 0));
+
+    app.email_client.set_failing(false);
+    app.deliver_emails().await;
+    let content = app.email_client.last_email_to(&parsed_email).await.expect("Replayed email should be delivered");
+    assert_eq!(content.subject, "Account locked");
+    assert!(find_email(&list_outbox(&app, "dead").await, &email, "Account locked").is_none());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_stop_retrying_2fa_code_before_it_expires() {
+    let mut app = TestApp::new().await;
+    app.email_client.set_failing(true);
+    let email = request_2fa_code(&app).await;
+    fail_until_dead(&app, &email, "Your login code").await;
+
+    app.login_as_admin().await;
+    let dead = list_outbox(&app, "dead").await;
+    let dead_email = find_2fa_email(&dead, &email).expect("Email should be dead-lettered");
+    let lifetime = dead_email.expires_at.expect("2FA code emails expire") - dead_email.created_at;
+    assert!((lifetime - TWO_FA_CODE_TTL_SECONDS as i64).abs() <= 1);
+    // The next retry would have come after the code stopped working
+    assert!(dead_email.attempts < EMAIL_OUTBOX_MAX_ATTEMPTS);
+    assert!(app.email_client.last_email_to(&Email::parse(email).unwrap()).await.is_none());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_purge_emails_of_deleted_accounts() {
+    let mut app = TestApp::new().await;
+    app.email_client.set_failing(true);
+    let deleted_by_admin = request_lock_email(&app).await;
+    fail_until_dead(&app, &deleted_by_admin, "Account locked").await;
+
+    // The reset email stays pending while delivery keeps failing
+    let deleted_by_user = app.create_verified_user(false).await;
+    let response = app.post_password_reset_request(&serde_json::json!({"email": deleted_by_user})).await;
+    assert_eq!(response.status(), 200);
+    let (pending, _) = app.email_outbox_store.read().await.list(OutboxStatus::Pending, 0, 100).await.unwrap();
+    assert!(pending.iter().any(|email| email.recipient.as_ref() == deleted_by_user));
+    let response = app.post_login(&serde_json::json!({"email": deleted_by_user, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let response = app.delete_account(&serde_json::json!({"password": "password123"})).await;
+    assert_eq!(response.status(), 204);
+
+    app.login_as_admin().await;
+    assert_eq!(app.delete_admin_user(&deleted_by_admin).await.status(), 204);
+
+    for status in [OutboxStatus::Pending, OutboxStatus::Dead] {
+        let (emails, _) = app.email_outbox_store.read().await.list(status, 0, 100).await.unwrap();
+        let recipients: Vec<&str> = emails.iter().map(|email| email.recipient.as_ref()).collect();
+        assert!(!recipients.contains(&deleted_by_admin.as_str()), "Failed for status: {:?}", status);
+        assert!(!recipients.contains(&deleted_by_user.as_str()), "Failed for status: {:?}", status);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_unknown_emails_and_statuses() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+
+    let response = app.post_admin_replay_outbox_email(OutboxEmailId::default().as_ref()).await;
+    assert_eq!(response.status(), 404);
+    let response = app.post_admin_replay_outbox_email("not-an-id").await;
+    assert_eq!(response.status(), 404);
+    let response = app.get_admin_email_outbox(&[("status", "sent")]).await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await;
+}
@@ -1,23 +1,39 @@
 use sqlx::{Connection, Executor, PgConnection, PgPool, postgres::{PgConnectOptions, PgPoolOptions}};
 use uuid::Uuid;
 use auth_service::{
-    Application, app_state::{
+    Application, domain::{Email, Role, SigningKeyStore}, app_state::{
         AppState,
-        BannedTokenStoreType, TwoFACodeStoreType
+        BannedTokenStoreType, EmailOutboxStoreType, EmailVerificationTokenStoreType, LoginAttemptStoreType, PasswordResetTokenStoreType, RefreshTokenStoreType, SigningKeyStoreType, TotpSecretStoreType, TwoFACodeStoreType, UserStoreType, WebauthnCredentialStoreType
     }, get_postgres_pool, get_redis_client, services::{
-        RedisTwoFACodeStore, RedisBannedTokenStore, data_stores::PostgresUserStore, mock_email_client::MockEmailClient
-    }, utils::constants::{DATABASE_URL, REDIS_HOST_NAME, test}
+        HashmapRateLimitStore, RedisAuthorizationGrantStore, RedisPhoneVerificationCodeStore, RedisTwoFACodeStore, RedisBannedTokenStore, RedisEmailVerificationTokenStore, RedisLoginAttemptStore, RedisMagicLinkStore, RedisPasswordResetTokenStore, RedisRefreshTokenStore, RedisWebauthnChallengeStore, data_stores::{PostgresApiKeyStore, PostgresEmailOutboxStore, PostgresOAuthClientStore, PostgresRecoveryCodeStore, PostgresSigningKeyStore, PostgresTotpSecretStore, PostgresUserStore, PostgresWebauthnCredentialStore}, email_outbox_worker::EmailOutboxWorker, email_templates::EmailTemplates, mock_email_client::MockEmailClient, mock_sms_client::MockSmsClient, signing_key_refresher::refresh_key_ring
+    }, utils::constants::{DATABASE_URL, JWT_COOKIE_NAME, JWT_SIGNING_KEYS, REDIS_HOST_NAME, test}
 };
 use std::{str::FromStr, sync::Arc};
-use tokio::sync::RwLock;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, net::{TcpListener, TcpStream}, sync::RwLock};
 use reqwest::cookie::Jar;
+use axum::{http::{header, HeaderMap, StatusCode}, routing::post, Json, Router};
 
 pub struct TestApp {
     pub address: String,
     pub http_client: reqwest::Client,
+    pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub cookie_jar: Arc<Jar>,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub totp_secret_store: TotpSecretStoreType,
+    pub webauthn_credential_store: WebauthnCredentialStoreType,
+    pub login_attempt_store: LoginAttemptStoreType,
+    pub email_outbox_store: EmailOutboxStoreType,
+    pub signing_key_store: SigningKeyStoreType,
+    pub email_client: MockEmailClient,
+    pub sms_client: MockSmsClient,
+    // Not spawned, tests call `deliver_emails` so they know when the outbox has been worked through
+    pub email_outbox_worker: EmailOutboxWorker,
+    pub pg_pool: PgPool,
     pub db_name: String,
     pub cleaned_up: bool,
 }
@@ -27,14 +43,54 @@ impl TestApp {
         let (pg_pool, db_name) = configure_postgresql().await;
         let redis_conn = configure_redis();
         let arc_redis_conn = Arc::new(RwLock::new(redis_conn));
-        let user_store = PostgresUserStore::new(pg_pool);
+        let user_store = PostgresUserStore::new(pg_pool.clone());
+        let totp_secret_store = PostgresTotpSecretStore::new(pg_pool.clone());
+        let webauthn_credential_store = PostgresWebauthnCredentialStore::new(pg_pool.clone());
+        let recovery_code_store = PostgresRecoveryCodeStore::new(pg_pool.clone());
+        let oauth_client_store = PostgresOAuthClientStore::new(pg_pool.clone());
+        let api_key_store = PostgresApiKeyStore::new(pg_pool.clone());
+        let email_outbox_store = PostgresEmailOutboxStore::new(pg_pool.clone());
+        let mut signing_key_store = PostgresSigningKeyStore::new(pg_pool.clone());
+        // Only seeds the store, the process wide key ring is shared by every test and left alone
+        signing_key_store
+            .add_initial_keys(JWT_SIGNING_KEYS.clone())
+            .await
+            .expect("Failed to seed signing keys");
         let banned_token_store = RedisBannedTokenStore::new(arc_redis_conn.clone());
-        let two_fa_code_store = RedisTwoFACodeStore::new(arc_redis_conn);
+        let two_fa_code_store = RedisTwoFACodeStore::new(arc_redis_conn.clone());
+        let password_reset_token_store = RedisPasswordResetTokenStore::new(arc_redis_conn.clone());
+        let email_verification_token_store = RedisEmailVerificationTokenStore::new(arc_redis_conn.clone());
+        let refresh_token_store = RedisRefreshTokenStore::new(arc_redis_conn.clone());
+        let webauthn_challenge_store = RedisWebauthnChallengeStore::new(arc_redis_conn.clone());
+        let magic_link_store = RedisMagicLinkStore::new(arc_redis_conn.clone());
+        let login_attempt_store = RedisLoginAttemptStore::new(arc_redis_conn.clone());
+        let phone_verification_code_store = RedisPhoneVerificationCodeStore::new(arc_redis_conn.clone());
+        let authorization_grant_store = RedisAuthorizationGrantStore::new(arc_redis_conn);
         let arc_user_store = Arc::new(RwLock::new(user_store));
         let arc_banned_token_store = Arc::new(RwLock::new(banned_token_store));
         let arc_two_fa_code_store = Arc::new(RwLock::new(two_fa_code_store));
+        let arc_password_reset_token_store = Arc::new(RwLock::new(password_reset_token_store));
+        let arc_email_verification_token_store = Arc::new(RwLock::new(email_verification_token_store));
+        let arc_refresh_token_store = Arc::new(RwLock::new(refresh_token_store));
+        let arc_totp_secret_store = Arc::new(RwLock::new(totp_secret_store));
+        let arc_webauthn_credential_store = Arc::new(RwLock::new(webauthn_credential_store));
+        let arc_webauthn_challenge_store = Arc::new(RwLock::new(webauthn_challenge_store));
+        let arc_recovery_code_store = Arc::new(RwLock::new(recovery_code_store));
+        let arc_magic_link_store = Arc::new(RwLock::new(magic_link_store));
+        let arc_login_attempt_store = Arc::new(RwLock::new(login_attempt_store));
+        // Every test app talks from 127.0.0.1, so a shared Redis bucket would make parallel tests throttle each other
+        let arc_rate_limit_store = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
+        let arc_email_outbox_store: EmailOutboxStoreType = Arc::new(RwLock::new(email_outbox_store));
         let email_client = MockEmailClient::default();
-        let app_state = Arc::new(AppState::new(arc_user_store, arc_banned_token_store.clone(), arc_two_fa_code_store.clone(), email_client));
+        let email_outbox_worker = EmailOutboxWorker::new(arc_email_outbox_store.clone(), Arc::new(email_client.clone()));
+        let email_templates = EmailTemplates::load("email_templates").expect("Failed to load email templates");
+        let arc_phone_verification_code_store = Arc::new(RwLock::new(phone_verification_code_store));
+        let sms_client = MockSmsClient::default();
+        let arc_oauth_client_store = Arc::new(RwLock::new(oauth_client_store));
+        let arc_api_key_store = Arc::new(RwLock::new(api_key_store));
+        let arc_authorization_grant_store = Arc::new(RwLock::new(authorization_grant_store));
+        let arc_signing_key_store: SigningKeyStoreType = Arc::new(RwLock::new(signing_key_store));
+        let app_state = Arc::new(AppState::new(arc_user_store.clone(), arc_banned_token_store.clone(), arc_two_fa_code_store.clone(), arc_password_reset_token_store.clone(), arc_email_verification_token_store.clone(), arc_refresh_token_store.clone(), arc_totp_secret_store.clone(), arc_webauthn_credential_store.clone(), arc_webauthn_challenge_store, arc_recovery_code_store, arc_magic_link_store, arc_login_attempt_store.clone(), arc_rate_limit_store, arc_email_outbox_store.clone(), Arc::new(email_templates), arc_phone_verification_code_store, Arc::new(sms_client.clone()), arc_oauth_client_store, arc_authorization_grant_store, arc_api_key_store, arc_signing_key_store.clone()));
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
             .expect("Failed to build app");
@@ -50,7 +106,7 @@ impl TestApp {
             .build()
             .unwrap();
 
-        Self { address, http_client, banned_token_store: arc_banned_token_store, cookie_jar, two_fa_code_store: arc_two_fa_code_store, db_name, cleaned_up: false }
+        Self { address, http_client, user_store: arc_user_store, banned_token_store: arc_banned_token_store, cookie_jar, two_fa_code_store: arc_two_fa_code_store, password_reset_token_store: arc_password_reset_token_store, email_verification_token_store: arc_email_verification_token_store, refresh_token_store: arc_refresh_token_store, totp_secret_store: arc_totp_secret_store, webauthn_credential_store: arc_webauthn_credential_store, login_attempt_store: arc_login_attempt_store, email_outbox_store: arc_email_outbox_store, signing_key_store: arc_signing_key_store, email_client, sms_client, email_outbox_worker, pg_pool, db_name, cleaned_up: false }
     }
 
     pub async fn clean_up(&mut self) {
@@ -59,6 +115,31 @@ impl TestApp {
         self.cleaned_up = true;
     }
 
+    // Sends everything that is due in the outbox through the mock email client
+    pub async fn deliver_emails(&self) {
+        self.email_outbox_worker.deliver_due().await.expect("Failed to deliver outbox emails");
+    }
+
+    // Brings forward the activation of rotated keys and reloads the process wide key ring from this app's store,
+    // instead of waiting out JWT_KEY_ACTIVATION_DELAY_SECONDS. Keys that already signed are moved further back,
+    // so the rotated key is the newest even when the store was seeded within the last second.
+    pub async fn activate_signing_keys(&self) {
+        sqlx::query("UPDATE jwt_signing_keys SET activates_at = CASE WHEN activates_at > now() THEN now() - INTERVAL '1 second' ELSE activates_at - INTERVAL '1 minute' END")
+            .execute(&self.pg_pool)
+            .await
+            .expect("Failed to activate signing keys");
+        refresh_key_ring(&self.signing_key_store).await.expect("Failed to refresh key ring");
+    }
+
+    // Adds a role to the roles catalog, which only knows the admin role out of the box
+    pub async fn add_role(&self, name: &str) {
+        sqlx::query("INSERT INTO roles (name) VALUES ($1)")
+            .bind(name)
+            .execute(&self.pg_pool)
+            .await
+            .expect("Failed to add role");
+    }
+
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
             .get(&format!("{}/", &self.address))
@@ -67,6 +148,27 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_rotate_signing_key<Body>(&self, body: &Body, admin_token: &str) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/admin/signing-keys/rotate", &self.address))
+            .bearer_auth(admin_token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response 
     where
         Body: serde::Serialize
@@ -89,6 +191,27 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_magic_link<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/login/magic-link", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_magic_link_callback(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login/magic-link/callback", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
             .post(&format!("{}/logout", &self.address))
@@ -118,6 +241,500 @@ impl TestApp {
             .await
             .expect("Failed to execute request.")
     }
+
+    pub async fn post_password_reset_request<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/password-reset/request", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password_reset_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/password-reset/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/verify-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_verification_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/verify-email/resend", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_enroll(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/2fa/totp/enroll", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/2fa/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_regenerate_recovery_codes<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/2fa/recovery-codes", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_register_start(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/webauthn/register/start", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_register_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/webauthn/register/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_login_start<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/webauthn/login/start", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_login_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/webauthn/login/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_account<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .delete(format!("{}/account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_account_export(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/account/export", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_account_phone<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/account/phone", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_account_phone_verify<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/account/phone/verify", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_account_phone(&self) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/account/phone", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_account_two_fa_channel<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .post(format!("{}/account/2fa-channel", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_users(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn patch_admin_user<Body>(&self, email: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize
+    {
+        self.http_client
+            .patch(format!("{}/admin/users/{}", &self.address, email))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_force_logout(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin/users/{}/logout", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_email_outbox(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/email-outbox", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_replay_outbox_email(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin/email-outbox/{}/replay", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin/users/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_api_key<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/account/api-keys", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_keys(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/account/api-keys", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_api_key(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/account/api-keys/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_oauth_client<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/oauth/clients", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_oauth_clients(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/oauth/clients", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_oauth_client(&self, client_id: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/oauth/clients/{}", &self.address, client_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_admin_oauth_client(&self, client_id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin/oauth/clients/{}", &self.address, client_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // The authorization endpoints answer with redirects meant for the browser, so they aren't followed here
+    pub async fn get_oauth_authorize(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.redirectless_client()
+            .get(format!("{}/oauth/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_consent(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        self.redirectless_client()
+            .post(format!("{}/oauth/authorize/consent", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_token(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/oauth/token", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_token_with_basic_auth(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        form: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/oauth/token", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_introspect(&self, client_id: &str, client_secret: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/oauth/introspect", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_revoke(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/oauth/revoke", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/openid-configuration", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_userinfo(&self, access_token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/userinfo", &self.address))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_userinfo(&self, access_token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/userinfo", &self.address))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    fn redirectless_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .cookie_provider(self.cookie_jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+    }
+
+    // Signs up and verifies a user with a random email and `password123`, returning the email
+    pub async fn create_verified_user(&self, requires_2fa: bool) -> String {
+        let email = get_random_email();
+        let body = serde_json::json!({"email": email, "password": "password123", "requires2FA": requires_2fa});
+        let response = self.post_signup(&body).await;
+        assert_eq!(response.status(), 201);
+        self.verify_email(&email).await;
+        email
+    }
+
+    // Logs a new user without 2FA in, returning their email and JWT, which is also left in the cookie jar
+    pub async fn login_verified_user(&self) -> (String, String) {
+        let email = self.create_verified_user(false).await;
+        let response = self.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+        assert_eq!(response.status(), 200);
+        (email, get_cookie(&response, JWT_COOKIE_NAME))
+    }
+
+    // Signs up a verified user without 2FA and logs them in, leaving their JWT and refresh token in the cookie jar
+    pub async fn signup_and_login(&self, email: &str) -> reqwest::Response {
+        let signup_body = serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        });
+        let response = self.post_signup(&signup_body).await;
+        assert_eq!(response.status(), 201);
+        self.verify_email(email).await;
+
+        let response = self.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+        assert_eq!(response.status(), 200);
+        response
+    }
+
+    // Logs a new admin in, leaving their JWT in the cookie jar
+    pub async fn login_as_admin(&self) -> String {
+        let email = self.create_verified_user(false).await;
+        self.user_store
+            .write()
+            .await
+            .set_roles(&email, vec![Role::admin()])
+            .await
+            .expect("Failed to grant admin role");
+
+        let response = self.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+        assert_eq!(response.status(), 200);
+        email
+    }
+
+    // Confirms a freshly signed up user's email with the token that was "sent" to them
+    pub async fn verify_email(&self, email: &str) {
+        let email = Email::parse(email.to_owned()).expect("Failed to parse email");
+        let (token, _) = self.email_verification_token_store
+            .read()
+            .await
+            .get_token(&email)
+            .await
+            .expect("Email verification token should be stored");
+
+        let body = serde_json::json!({"email": email.as_ref(), "token": token.as_ref()});
+        let response = self.post_verify_email(&body).await;
+        assert_eq!(response.status(), 200);
+    }
 }
 
 impl Drop for TestApp {
@@ -128,10 +745,145 @@ impl Drop for TestApp {
     }
 }
 
+// Bare-bones SMTP server that accepts every message, so the SMTP client can be tested without a real relay
+pub struct SmtpSink {
+    pub port: u16,
+    messages: Arc<RwLock<Vec<SmtpMessage>>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SmtpMessage {
+    // Decoded AUTH PLAIN payload, `\0username\0password`
+    pub auth: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    pub data: String,
+}
+
+impl SmtpSink {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind SMTP sink");
+        let port = listener.local_addr().unwrap().port();
+        let messages = Arc::new(RwLock::new(Vec::new()));
+
+        let sink_messages = messages.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_smtp_session(stream, sink_messages.clone()));
+            }
+        });
+
+        Self { port, messages }
+    }
+
+    pub async fn messages(&self) -> Vec<SmtpMessage> {
+        self.messages.read().await.clone()
+    }
+}
+
+async fn handle_smtp_session(stream: TcpStream, messages: Arc<RwLock<Vec<SmtpMessage>>>) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut auth = None;
+    let mut from = String::new();
+    let mut to = Vec::new();
+
+    writer.write_all(b"220 sink ESMTP\r\n").await?;
+    while let Some(line) = lines.next_line().await? {
+        let command = line.to_ascii_uppercase();
+        let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+            b"250-sink\r\n250-AUTH PLAIN\r\n250 8BITMIME\r\n"
+        } else if command.starts_with("AUTH PLAIN ") {
+            let credentials = STANDARD.decode(&line["AUTH PLAIN ".len()..]).expect("Invalid AUTH PLAIN payload");
+            auth = Some(String::from_utf8(credentials).expect("Invalid AUTH PLAIN payload"));
+            b"235 2.7.0 Authentication successful\r\n"
+        } else if command.starts_with("MAIL FROM:") {
+            from = line["MAIL FROM:".len()..].trim().to_owned();
+            b"250 OK\r\n"
+        } else if command.starts_with("RCPT TO:") {
+            to.push(line["RCPT TO:".len()..].trim().to_owned());
+            b"250 OK\r\n"
+        } else if command == "DATA" {
+            writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await?;
+            let mut data = Vec::new();
+            while let Some(line) = lines.next_line().await? {
+                if line == "." {
+                    break;
+                }
+                // Undo the dot-stuffing of lines that start with a dot
+                data.push(line.strip_prefix('.').map(str::to_owned).unwrap_or(line));
+            }
+            messages.write().await.push(SmtpMessage {
+                auth: auth.clone(),
+                from: std::mem::take(&mut from),
+                to: std::mem::take(&mut to),
+                data: data.join("\r\n"),
+            });
+            b"250 OK: queued\r\n"
+        } else if command == "QUIT" {
+            writer.write_all(b"221 Bye\r\n").await?;
+            return Ok(());
+        } else {
+            b"250 OK\r\n"
+        };
+        writer.write_all(reply).await?;
+    }
+    Ok(())
+}
+
+// Stands in for an SMS provider's HTTP API, recording every message and answering with a configurable status
+pub struct SmsProviderStub {
+    pub url: String,
+    requests: Arc<RwLock<Vec<SmsProviderRequest>>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SmsProviderRequest {
+    pub authorization: Option<String>,
+    pub body: serde_json::Value,
+}
+
+impl SmsProviderStub {
+    pub async fn start(status: u16) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind SMS provider stub");
+        let url = format!("http://{}/v1/messages", listener.local_addr().unwrap());
+        let requests = Arc::new(RwLock::new(Vec::new()));
+
+        let stub_requests = requests.clone();
+        let status = StatusCode::from_u16(status).expect("Invalid status code");
+        let router = Router::new().route(
+            "/v1/messages",
+            post(move |headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                let authorization = headers
+                    .get(header::AUTHORIZATION)
+                    .map(|value| value.to_str().unwrap().to_owned());
+                stub_requests.write().await.push(SmsProviderRequest { authorization, body });
+                status
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        Self { url, requests }
+    }
+
+    pub async fn requests(&self) -> Vec<SmsProviderRequest> {
+        self.requests.read().await.clone()
+    }
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
+pub fn get_cookie(response: &reqwest::Response, name: &str) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == name)
+        .unwrap_or_else(|| panic!("No {} cookie found", name))
+        .value()
+        .to_owned()
+}
+
 fn configure_redis() -> redis::Connection {
     println!("Configuring Redis... {:?}", REDIS_HOST_NAME.to_owned());
     get_redis_client(REDIS_HOST_NAME.to_owned())
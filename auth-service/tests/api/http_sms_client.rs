@@ -0,0 +1,67 @@
+use crate::helpers::SmsProviderStub;
+use auth_service::{
+    domain::{PhoneNumber, SmsClient},
+    services::http_sms_client::{HttpSmsClient, HttpSmsConfig},
+};
+
+fn stub_config(url: &str, api_key: &str) -> HttpSmsConfig {
+    HttpSmsConfig {
+        url: url.to_owned(),
+        api_key: api_key.to_owned(),
+        sender: "AuthService".to_owned(),
+    }
+}
+
+fn recipient() -> PhoneNumber {
+    PhoneNumber::parse("+14155552671".to_owned()).unwrap()
+}
+
+#[tokio::test]
+async fn should_post_message_to_sms_provider() {
+    let stub = SmsProviderStub::start(200).await;
+    let sms_client = HttpSmsClient::new(stub_config(&stub.url, "sms-api-key")).unwrap();
+
+    sms_client
+        .send_sms(&recipient(), "Your login code is 123456")
+        .await
+        .expect("Message should be accepted by the SMS provider");
+
+    let requests = stub.requests().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].authorization.as_deref(), Some("Bearer sms-api-key"));
+    assert_eq!(
+        requests[0].body,
+        serde_json::json!({"from": "AuthService", "to": "+14155552671", "body": "Your login code is 123456"})
+    );
+}
+
+#[tokio::test]
+async fn should_not_authenticate_without_api_key() {
+    let stub = SmsProviderStub::start(201).await;
+    let sms_client = HttpSmsClient::new(stub_config(&stub.url, "")).unwrap();
+
+    sms_client.send_sms(&recipient(), "Message").await.unwrap();
+
+    let requests = stub.requests().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].authorization, None);
+}
+
+#[tokio::test]
+async fn should_return_error_if_sms_provider_rejects_message() {
+    let stub = SmsProviderStub::start(500).await;
+    let sms_client = HttpSmsClient::new(stub_config(&stub.url, "sms-api-key")).unwrap();
+
+    let error = sms_client.send_sms(&recipient(), "Message").await.unwrap_err();
+    assert!(error.contains("500"), "Unexpected error: {}", error);
+    assert_eq!(stub.requests().await.len(), 1);
+}
+
+#[tokio::test]
+async fn should_return_error_if_sms_provider_is_unreachable() {
+    // Bind and drop a listener to get a port nothing listens on
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let sms_client = HttpSmsClient::new(stub_config(&format!("http://127.0.0.1:{}/v1/messages", port), "")).unwrap();
+
+    assert!(sms_client.send_sms(&recipient(), "Message").await.is_err());
+}
@@ -0,0 +1,68 @@
+use auth_service::{
+    domain::{AuthMethod, Authentication, Email},
+    utils::auth::{generate_auth_token, AUTH_TOKEN_AUDIENCE},
+};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[derive(Deserialize)]
+struct TestClaims {
+    sub: String,
+}
+
+#[tokio::test]
+async fn should_return_200_with_cacheable_key_set() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_jwks().await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get("cache-control").and_then(|v| v.to_str().ok()),
+        Some("public, max-age=300")
+    );
+
+    let jwks = response.json::<JwkSet>().await.expect("Could not deserialize response body to JwkSet");
+    for key in jwks.keys {
+        assert!(key.common.key_id.is_some());
+        // Shared secrets must never be published
+        assert!(!matches!(key.algorithm, AlgorithmParameters::OctetKey(_)));
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_verify_issued_tokens_with_published_key() {
+    let mut app = TestApp::new().await;
+
+    // Issue the token first, a concurrent rotation keeps publishing the key that signed it
+    let email = Email::parse(get_random_email()).unwrap();
+    let token = generate_auth_token(&email, &[], &Authentication::now(vec![AuthMethod::Password])).unwrap();
+    let header = decode_header(&token).unwrap();
+    let kid = header.kid.expect("Issued tokens should name their signing key");
+
+    let jwks = app.get_jwks().await.json::<JwkSet>().await.expect("Could not deserialize response body to JwkSet");
+    if header.alg == Algorithm::HS256 {
+        // Tokens signed with JWT_SECRET can only be checked through /verify-token
+        assert!(jwks.find(&kid).is_none());
+        app.clean_up().await;
+        return;
+    }
+
+    let jwk = jwks.find(&kid).expect("Signing key should be published");
+    let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[AUTH_TOKEN_AUDIENCE]);
+    let claims = decode::<TestClaims>(&token, &decoding_key, &validation)
+        .expect("Token should verify against the published key")
+        .claims;
+    assert_eq!(claims.sub, email.as_ref());
+
+    app.clean_up().await;
+}
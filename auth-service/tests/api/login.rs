@@ -2,7 +2,7 @@ use crate::helpers::TestApp;
 use auth_service::{
     domain::{Email},
     routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    utils::constants::{JWT_COOKIE_NAME, LOGIN_LOCKOUT_BASE_SECONDS, LOGIN_LOCKOUT_THRESHOLD},
     ErrorResponse,
 };
 
@@ -72,6 +72,8 @@ async fn should_return_401_if_incorrect_credentials() {
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
 
+    app.verify_email(&random_email).await;
+
     let wrong_credentials = serde_json::json!({
         "email": random_email,
         "password": "password124"
@@ -103,6 +105,8 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({"email": random_email, "password": "password123"});
 
     let response = app.post_login(&login_body).await;
@@ -130,6 +134,8 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
+
+    app.verify_email(&random_email).await;
     
     let login_body = serde_json::json!({"email": random_email, "password": "password123"});
 
@@ -146,8 +152,150 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     // assert that `json_body.login_attempt_id` is stored inside `app.two_fa_code_store`
     let two_fa_store = app.two_fa_code_store.read().await;
-    let (stored_login_attempt_id, _) = two_fa_store.get_code(&Email::parse(random_email).unwrap()).await.expect("2FA code should be stored");
+    let (stored_login_attempt_id, _, _) = two_fa_store.get_code(&Email::parse(random_email).unwrap()).await.expect("2FA code should be stored");
     assert_eq!(stored_login_attempt_id.as_ref(), json_body.loging_attempt_id);
     drop(two_fa_store);
     app.clean_up().await;
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn should_send_2fa_code_in_requested_language() {
+    let mut app = TestApp::new().await;
+    let email = app.create_verified_user(true).await;
+    let parsed_email = Email::parse(email.clone()).unwrap();
+
+    let response = app.http_client
+        .post(format!("{}/login", &app.address))
+        .header("Accept-Language", "fr-CA, fr;q=0.9, en;q=0.5")
+        .json(&serde_json::json!({"email": email, "password": "password123"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), 200);
+
+    let (_, code, _) = app.two_fa_code_store.read().await.get_code(&parsed_email).await.expect("2FA code should be stored");
+    app.deliver_emails().await;
+    let content = app.email_client.last_email_to(&parsed_email).await.expect("2FA code should be emailed");
+    assert_eq!(content.subject, "Votre code de connexion");
+    assert!(content.text.contains(code.as_ref()));
+    assert!(content.text.contains("127.0.0.1"));
+    assert!(content.html.contains(code.as_ref()));
+    assert!(content.html.starts_with("<!DOCTYPE html>"));
+
+    // Languages without templates fall back to English
+    let response = app.http_client
+        .post(format!("{}/login", &app.address))
+        .header("Accept-Language", "de")
+        .json(&serde_json::json!({"email": email, "password": "password123"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), 200);
+    app.deliver_emails().await;
+    let content = app.email_client.last_email_to(&parsed_email).await.unwrap();
+    assert_eq!(content.subject, "Your login code");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_423_and_lock_account_after_repeated_failures() {
+    let mut app = TestApp::new().await;
+    let email = app.create_verified_user(false).await;
+    let wrong_login = serde_json::json!({"email": email, "password": "wrong-password"});
+
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_login(&wrong_login).await;
+        assert_eq!(response.status(), 401);
+    }
+
+    let response = app.post_login(&wrong_login).await;
+    assert_eq!(response.status(), 423);
+    assert_eq!(
+        response.headers().get("retry-after").and_then(|value| value.to_str().ok()),
+        Some(LOGIN_LOCKOUT_BASE_SECONDS.to_string().as_str())
+    );
+    assert_eq!(
+        response.json::<ErrorResponse>().await.expect("Could not deserialize response body to ErrorResponse").error,
+        "Account locked".to_owned()
+    );
+
+    app.deliver_emails().await;
+    let content = app.email_client
+        .last_email_to(&Email::parse(email.clone()).unwrap())
+        .await
+        .expect("Owner should be told about the lock");
+    assert_eq!(content.subject, "Account locked");
+    assert!(content.text.contains("127.0.0.1"));
+
+    // Even the right password is refused while the lock lasts
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 423);
+    assert!(response.headers().get("retry-after").is_some());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_double_lock_duration_for_repeat_lockouts() {
+    let mut app = TestApp::new().await;
+    let email = app.create_verified_user(false).await;
+    let parsed_email = Email::parse(email.clone()).unwrap();
+
+    // Simulate an earlier lock that has already run out
+    app.login_attempt_store.write().await.lock(&parsed_email, 1).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+
+    let wrong_login = serde_json::json!({"email": email, "password": "wrong-password"});
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        assert_eq!(app.post_login(&wrong_login).await.status(), 401);
+    }
+
+    let response = app.post_login(&wrong_login).await;
+    assert_eq!(response.status(), 423);
+    assert_eq!(
+        response.headers().get("retry-after").and_then(|value| value.to_str().ok()),
+        Some((LOGIN_LOCKOUT_BASE_SECONDS * 2).to_string().as_str())
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_failed_attempts_after_successful_login() {
+    let mut app = TestApp::new().await;
+    let email = app.create_verified_user(false).await;
+    let wrong_login = serde_json::json!({"email": email, "password": "wrong-password"});
+
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        assert_eq!(app.post_login(&wrong_login).await.status(), 401);
+    }
+
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        assert_eq!(app.post_login(&wrong_login).await.status(), 401);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_once_an_email_uses_up_its_login_budget() {
+    let mut app = TestApp::new().await;
+    let targeted_email = crate::helpers::get_random_email();
+    // Malformed passwords are rejected before they count towards the account lockout
+    let login_body = serde_json::json!({"email": targeted_email, "password": "short"});
+
+    for _ in 0..10 {
+        assert_eq!(app.post_login(&login_body).await.status(), 400);
+    }
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 429);
+    assert!(response.headers().get("retry-after").is_some());
+
+    // Other accounts still have their own budget from the same address
+    let other_body = serde_json::json!({"email": crate::helpers::get_random_email(), "password": "short"});
+    let response = app.post_login(&other_body).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(response.headers().get("x-ratelimit-remaining").unwrap(), "9");
+    app.clean_up().await;
+}
@@ -43,6 +43,8 @@ async fn should_return_200_if_valid_jwt_cookie(){
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({"email": random_email, "password": "password123"});
 
     let response = app.post_login(&login_body).await;
@@ -86,6 +88,8 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({"email": random_email, "password": "password123"});
 
     let response = app.post_login(&login_body).await;
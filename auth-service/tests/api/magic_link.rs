@@ -0,0 +1,170 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{data_stores::{MagicLinkId, MagicLinkNonce}, Email},
+    routes::{MagicLinkResponse, TwoFactorAuthResponse},
+    utils::{
+        auth::generate_magic_link_token,
+        constants::{JWT_COOKIE_NAME, MAGIC_LINK_NONCE_COOKIE_NAME},
+    },
+};
+use reqwest::{cookie::CookieStore, Url};
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    app.verify_email(email).await;
+}
+
+// Requests a magic link and returns the token from the emailed link
+async fn request_link(app: &TestApp, email: &str) -> String {
+    let response = app.post_magic_link(&serde_json::json!({"email": email})).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.cookies().any(|cookie| cookie.name() == MAGIC_LINK_NONCE_COOKIE_NAME));
+
+    app.deliver_emails().await;
+    let content = app
+        .email_client
+        .last_email_to(&Email::parse(email.to_owned()).unwrap())
+        .await
+        .expect("No magic link email sent");
+    let after_token = content.text.split("token=").nth(1).expect("No token in magic link");
+    after_token.split_whitespace().next().unwrap_or_default().to_owned()
+}
+
+fn set_nonce_cookie(app: &TestApp, nonce: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; HttpOnly; SameSite=Lax; Path=/", MAGIC_LINK_NONCE_COOKIE_NAME, nonce),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_email() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_magic_link(&serde_json::json!({"email": "invalid"})).await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_reveal_unknown_accounts() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let response = app.post_magic_link(&serde_json::json!({"email": random_email})).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.json::<MagicLinkResponse>().await.expect("Could not deserialize response body"),
+        MagicLinkResponse { message: "If the account exists, a login link has been sent".to_owned() }
+    );
+
+    let email = Email::parse(random_email).unwrap();
+    app.deliver_emails().await;
+    assert!(app.email_client.last_email_to(&email).await.is_none());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_log_in_once_with_magic_link() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    let token = request_link(&app, &random_email).await;
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    let response = app.post_verify_token(&serde_json::json!({"token": auth_cookie.value()})).await;
+    assert_eq!(response.status(), 200);
+
+    // Even from the same browser the link can't be used a second time
+    request_link(&app, &random_email).await;
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_nonce_cookie_missing() {
+    let mut app = TestApp::new().await;
+    let email = Email::parse(get_random_email()).unwrap();
+
+    let token = generate_magic_link_token(&email, &MagicLinkId::default()).unwrap();
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_link_opened_in_another_browser() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    let token = request_link(&app, &random_email).await;
+    let requesting_browser_nonce = app
+        .cookie_jar
+        .cookies(&Url::parse(&app.address).unwrap())
+        .and_then(|cookies| {
+            cookies
+                .to_str()
+                .unwrap()
+                .split("; ")
+                .find_map(|cookie| cookie.strip_prefix(&format!("{}=", MAGIC_LINK_NONCE_COOKIE_NAME)))
+                .map(str::to_owned)
+        })
+        .expect("No nonce cookie stored");
+
+    set_nonce_cookie(&app, MagicLinkNonce::default().as_ref());
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status(), 401);
+
+    // The failed attempt burnt the link
+    set_nonce_cookie(&app, &requesting_browser_nonce);
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_token_is_tampered_with() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    let token = request_link(&app, &random_email).await;
+    let tampered = format!("{}x", token);
+    let response = app.get_magic_link_callback(&tampered).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_still_require_second_factor() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    signup(&app, &random_email, true).await;
+
+    let token = request_link(&app, &random_email).await;
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status(), 200);
+    assert!(!response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(body.message, "2FA Required");
+    assert!(!body.loging_attempt_id.is_empty());
+    app.clean_up().await;
+}
@@ -1,8 +1,29 @@
 mod helpers;
 mod routes;
+mod account;
+mod admin_users;
+mod api_keys;
+mod change_password;
+mod client_credentials;
+mod email_outbox;
+mod http_sms_client;
+mod jwks;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oauth_tokens;
+mod oidc;
+mod password_reset;
+mod phone;
+mod recovery_codes;
+mod refresh;
 mod root;
+mod signing_keys;
 mod signup;
+mod smtp_email_client;
+mod totp;
 mod verify_2fa;
-mod verify_token;
\ No newline at end of file
+mod verify_email;
+mod verify_token;
+mod webauthn;
\ No newline at end of file
@@ -0,0 +1,324 @@
+use auth_service::{
+    routes::{OAuthClientListResponse, OAuthClientSummary, TokenResponse},
+    utils::{auth::AccessTokenClaims, constants::AUTH_SERVICE_URL},
+    OAuthErrorResponse,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use reqwest::Url;
+
+use crate::helpers::TestApp;
+
+pub(crate) const REDIRECT_URI: &str = "https://app.example.com/callback";
+// Example pair from RFC 7636 appendix B
+pub(crate) const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+pub(crate) const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+// Registers a client as a new admin and returns its id
+pub(crate) async fn register_client(app: &TestApp, allowed_scopes: &[&str]) -> String {
+    app.login_as_admin().await;
+    let body = serde_json::json!({
+        "name": "Example <App>",
+        "redirectUris": [REDIRECT_URI],
+        "allowedScopes": allowed_scopes
+    });
+    let response = app.post_admin_oauth_client(&body).await;
+    assert_eq!(response.status(), 201);
+    response.json::<OAuthClientSummary>().await.expect("Could not deserialize response body").client_id
+}
+
+// Registers a client, then logs a regular user in and returns the client id and the user's email
+async fn setup_client_and_user(app: &TestApp) -> (String, String) {
+    let client_id = register_client(app, &["profile", "email"]).await;
+
+    let (email, _) = app.login_verified_user().await;
+    (client_id, email)
+}
+
+fn authorize_query(client_id: &str) -> Vec<(&str, &str)> {
+    vec![
+        ("response_type", "code"),
+        ("client_id", client_id),
+        ("redirect_uri", REDIRECT_URI),
+        ("scope", "profile"),
+        ("state", "xyz"),
+        ("code_challenge", CODE_CHALLENGE),
+        ("code_challenge_method", "S256"),
+    ]
+}
+
+// Opens the consent page and returns the consent id from its form
+pub(crate) async fn open_consent_page(app: &TestApp, query: &[(&str, &str)]) -> String {
+    let response = app.get_oauth_authorize(query).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-frame-options"], "DENY");
+    let page = response.text().await.unwrap();
+    assert!(page.contains("Example &lt;App&gt;"));
+    let after = page.split("name=\"consent_id\" value=\"").nth(1).expect("No consent id in page");
+    after.split('"').next().unwrap().to_owned()
+}
+
+pub(crate) fn location(response: &reqwest::Response) -> Url {
+    assert_eq!(response.status(), 303);
+    let location = response.headers()["location"].to_str().unwrap();
+    Url::parse(location).expect("Location should be an absolute URL")
+}
+
+pub(crate) fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned())
+}
+
+// Runs the consent step and returns the code sent to the redirect URI
+async fn authorize(app: &TestApp, client_id: &str) -> String {
+    let consent_id = open_consent_page(app, &authorize_query(client_id)).await;
+    let response = app.post_oauth_consent(&[("consent_id", &consent_id), ("decision", "allow")]).await;
+    let redirect = location(&response);
+    assert!(redirect.as_str().starts_with(REDIRECT_URI));
+    assert_eq!(query_param(&redirect, "state").as_deref(), Some("xyz"));
+    query_param(&redirect, "code").expect("No code in redirect")
+}
+
+pub(crate) async fn exchange_code(app: &TestApp, client_id: &str, code: &str, code_verifier: &str) -> reqwest::Response {
+    app.post_oauth_token(&[
+        ("grant_type", "authorization_code"),
+        ("client_id", client_id),
+        ("code", code),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", code_verifier),
+    ])
+    .await
+}
+
+pub(crate) async fn oauth_error(response: reqwest::Response) -> String {
+    response.json::<OAuthErrorResponse>().await.expect("Could not deserialize response body").error
+}
+
+#[tokio::test]
+async fn should_manage_clients_as_admin() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+
+    let body = serde_json::json!({"name": "Mobile app", "redirectUris": ["com.example.app:/callback"], "allowedScopes": ["profile"]});
+    let response = app.post_admin_oauth_client(&body).await;
+    assert_eq!(response.status(), 201);
+    let client = response.json::<OAuthClientSummary>().await.unwrap();
+    assert_eq!(client.redirect_uris, vec!["com.example.app:/callback"]);
+
+    let response = app.get_admin_oauth_clients().await;
+    let list = response.json::<OAuthClientListResponse>().await.unwrap();
+    assert_eq!(list.clients, vec![client]);
+    let client_id = list.clients[0].client_id.clone();
+    assert_eq!(app.get_admin_oauth_client(&client_id).await.status(), 200);
+
+    assert_eq!(app.delete_admin_oauth_client(&client_id).await.status(), 204);
+    assert_eq!(app.get_admin_oauth_client(&client_id).await.status(), 404);
+    assert_eq!(app.delete_admin_oauth_client(&client_id).await.status(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_for_invalid_client_registration() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+
+    let bodies = [
+        serde_json::json!({"name": "App", "redirectUris": ["http://app.example.com/callback"]}),
+        serde_json::json!({"name": "App", "redirectUris": ["https://app.example.com/callback#token"]}),
+        serde_json::json!({"name": "App", "redirectUris": []}),
+        serde_json::json!({"name": " ", "redirectUris": [REDIRECT_URI]}),
+        serde_json::json!({"name": "App", "redirectUris": [REDIRECT_URI], "allowedScopes": ["bad scope"]}),
+        serde_json::json!({"name": "App", "redirectUris": [REDIRECT_URI], "allowedAudiences": ["app-service"]}),
+        serde_json::json!({"name": "App", "type": "robot", "allowedAudiences": ["app-service"]}),
+        serde_json::json!({"name": "Jobs", "type": "machine"}),
+        serde_json::json!({"name": "Jobs", "type": "machine", "allowedAudiences": ["app service"]}),
+        serde_json::json!({"name": "Jobs", "type": "machine", "allowedAudiences": ["session"]}),
+        serde_json::json!({"name": "Jobs", "type": "machine", "redirectUris": [REDIRECT_URI], "allowedAudiences": ["app-service"]}),
+    ];
+    for body in bodies {
+        let response = app.post_admin_oauth_client(&body).await;
+        assert_eq!(response.status(), 400, "Failed for input: {:?}", body);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_not_admin() {
+    let mut app = TestApp::new().await;
+    setup_client_and_user(&app).await;
+
+    let body = serde_json::json!({"name": "App", "redirectUris": [REDIRECT_URI]});
+    assert_eq!(app.post_admin_oauth_client(&body).await.status(), 403);
+    assert_eq!(app.get_admin_oauth_clients().await.status(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_issue_access_token_for_code_and_verifier() {
+    let mut app = TestApp::new().await;
+    let (client_id, email) = setup_client_and_user(&app).await;
+
+    let code = authorize(&app, &client_id).await;
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let token = response.json::<TokenResponse>().await.expect("Could not deserialize response body");
+    assert_eq!((token.token_type.as_str(), token.scope.as_str()), ("Bearer", "profile"));
+    // Only OpenID Connect requests get an ID token
+    assert!(token.id_token.is_none());
+
+    let payload = token.access_token.split('.').nth(1).unwrap();
+    let claims: AccessTokenClaims = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+    assert_eq!((claims.sub, claims.aud, claims.client_id.clone()), (email, client_id.clone(), client_id.clone()));
+    assert_eq!(claims.iss, AUTH_SERVICE_URL.as_str());
+
+    // Access tokens are meant for the client, not for the session endpoints
+    let response = app.post_verify_token(&serde_json::json!({"token": token.access_token})).await;
+    assert_eq!(response.status(), 401);
+
+    // Codes are single use
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_wrong_code_verifier_and_spend_the_code() {
+    let mut app = TestApp::new().await;
+    let (client_id, _) = setup_client_and_user(&app).await;
+
+    let code = authorize(&app, &client_id).await;
+    let wrong_verifier = "a".repeat(43);
+    let response = exchange_code(&app, &client_id, &code, &wrong_verifier).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_malformed_token_requests() {
+    let mut app = TestApp::new().await;
+    let (client_id, _) = setup_client_and_user(&app).await;
+    let code = authorize(&app, &client_id).await;
+
+    let response = app.post_oauth_token(&[("grant_type", "password"), ("client_id", &client_id)]).await;
+    assert_eq!(oauth_error(response).await, "unsupported_grant_type");
+
+    let response = exchange_code(&app, "8f14e45f-ceea-467f-a0e6-4b2a1e3f5c6d", &code, CODE_VERIFIER).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(oauth_error(response).await, "invalid_client");
+
+    let response = app.post_oauth_token(&[("grant_type", "authorization_code"), ("client_id", &client_id), ("code", &code)]).await;
+    assert_eq!(oauth_error(response).await, "invalid_request");
+
+    let response = app
+        .post_oauth_token(&[
+            ("grant_type", "authorization_code"),
+            ("client_id", &client_id),
+            ("code", &code),
+            ("redirect_uri", "https://app.example.com/other"),
+            ("code_verifier", CODE_VERIFIER),
+        ])
+        .await;
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_redirect_to_login_without_session() {
+    let mut app = TestApp::new().await;
+    let (client_id, _) = setup_client_and_user(&app).await;
+    assert_eq!(app.post_logout().await.status(), 200);
+
+    let response = app.get_oauth_authorize(&authorize_query(&client_id)).await;
+    let redirect = location(&response);
+    assert_eq!(redirect.path(), "/");
+    let return_to = query_param(&redirect, "return_to").expect("No return_to in redirect");
+    assert!(return_to.starts_with("/oauth/authorize?"));
+    assert!(return_to.contains(&client_id));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_redirect_for_unknown_client_or_redirect_uri() {
+    let mut app = TestApp::new().await;
+    let (client_id, _) = setup_client_and_user(&app).await;
+
+    let mut query = authorize_query("8f14e45f-ceea-467f-a0e6-4b2a1e3f5c6d");
+    let response = app.get_oauth_authorize(&query).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+
+    query = authorize_query(&client_id);
+    query[2] = ("redirect_uri", "https://attacker.example.com/callback");
+    let response = app.get_oauth_authorize(&query).await;
+    assert_eq!(response.status(), 400);
+    assert!(response.headers().get("location").is_none());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_redirect_invalid_requests_back_to_client() {
+    let mut app = TestApp::new().await;
+    let (client_id, _) = setup_client_and_user(&app).await;
+
+    let test_cases = [
+        (("response_type", "token"), "unsupported_response_type"),
+        (("code_challenge_method", "plain"), "invalid_request"),
+        (("code_challenge", "too-short"), "invalid_request"),
+        (("scope", "profile admin"), "invalid_scope"),
+    ];
+    for ((name, value), expected) in test_cases {
+        let mut query = authorize_query(&client_id);
+        query.iter_mut().find(|(key, _)| *key == name).unwrap().1 = value;
+        let redirect = location(&app.get_oauth_authorize(&query).await);
+        assert!(redirect.as_str().starts_with(REDIRECT_URI));
+        assert_eq!(query_param(&redirect, "error").as_deref(), Some(expected), "Failed for input: {}", value);
+        assert_eq!(query_param(&redirect, "state").as_deref(), Some("xyz"));
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_redirect_access_denied_when_user_denies() {
+    let mut app = TestApp::new().await;
+    let (client_id, _) = setup_client_and_user(&app).await;
+
+    let consent_id = open_consent_page(&app, &authorize_query(&client_id)).await;
+    let response = app.post_oauth_consent(&[("consent_id", &consent_id), ("decision", "deny")]).await;
+    let redirect = location(&response);
+    assert_eq!(query_param(&redirect, "error").as_deref(), Some("access_denied"));
+    assert!(query_param(&redirect, "code").is_none());
+
+    // The consent was answered, so it can't be replayed to get a code after all
+    let response = app.post_oauth_consent(&[("consent_id", &consent_id), ("decision", "allow")]).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_consent_from_another_user() {
+    let mut app = TestApp::new().await;
+    let (client_id, _) = setup_client_and_user(&app).await;
+    let consent_id = open_consent_page(&app, &authorize_query(&client_id)).await;
+
+    // Someone else signs in on the same browser before the page is submitted
+    app.login_verified_user().await;
+
+    let response = app.post_oauth_consent(&[("consent_id", &consent_id), ("decision", "allow")]).await;
+    assert_eq!(response.status(), 403);
+
+    app.clean_up().await;
+}
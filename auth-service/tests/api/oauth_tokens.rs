@@ -0,0 +1,126 @@
+use auth_service::routes::{CreateApiKeyResponse, IntrospectionResponse, TokenResponse};
+
+use crate::client_credentials::register_machine_client;
+use crate::helpers::TestApp;
+use crate::oauth::{oauth_error, register_client};
+
+// Gets a token for the client's only audience
+async fn service_token(app: &TestApp, client_id: &str, client_secret: &str) -> String {
+    let response = app
+        .post_oauth_token_with_basic_auth(client_id, client_secret, &[("grant_type", "client_credentials")])
+        .await;
+    assert_eq!(response.status(), 200);
+    response.json::<TokenResponse>().await.expect("Could not deserialize response body").access_token
+}
+
+pub(crate) async fn introspect(app: &TestApp, client_id: &str, client_secret: &str, token: &str) -> IntrospectionResponse {
+    let response = app.post_oauth_introspect(client_id, client_secret, token).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    response.json::<IntrospectionResponse>().await.expect("Could not deserialize response body")
+}
+
+#[tokio::test]
+async fn should_introspect_tokens() {
+    let mut app = TestApp::new().await;
+    let (client_id, secret) = register_machine_client(&app, &["app-service"]).await;
+
+    let token = service_token(&app, &client_id, &secret).await;
+    let introspection = introspect(&app, &client_id, &secret, &token).await;
+    assert!(introspection.active);
+    assert_eq!(introspection.client_id.as_deref(), Some(client_id.as_str()));
+    assert_eq!(introspection.sub.as_deref(), Some(client_id.as_str()));
+    assert_eq!(introspection.aud.as_deref(), Some("app-service"));
+    assert_eq!(introspection.scope.as_deref(), Some("reports:read reports:write"));
+    assert!(introspection.iat.unwrap() < introspection.exp.unwrap());
+
+    let (email, token) = app.login_verified_user().await;
+    let introspection = introspect(&app, &client_id, &secret, &token).await;
+    assert!(introspection.active);
+    assert_eq!((introspection.sub.as_deref(), introspection.aud.as_deref()), (Some(email.as_str()), Some("session")));
+    assert!(introspection.client_id.is_none());
+
+    // API keys belong to the session's user but not to any client
+    let response = app.post_api_key(&serde_json::json!({"name": "CI", "scopes": ["read"], "expiresInDays": 30})).await;
+    assert_eq!(response.status(), 201);
+    let key = response.json::<CreateApiKeyResponse>().await.expect("Could not deserialize response body");
+    let introspection = introspect(&app, &client_id, &secret, &key.api_key).await;
+    assert!(introspection.active);
+    assert_eq!((introspection.sub.as_deref(), introspection.scope.as_deref()), (Some(email.as_str()), Some("read")));
+    assert_eq!(introspection.exp.map(|exp| exp as i64), key.key.expires_at);
+    assert!(introspection.client_id.is_none());
+
+    let response = app.delete_api_key(&key.key.id).await;
+    assert_eq!(response.status(), 204);
+    let introspection = introspect(&app, &client_id, &secret, &key.api_key).await;
+    assert!(!introspection.active);
+
+    // Inactive tokens tell nothing else about themselves
+    let response = app.post_oauth_introspect(&client_id, &secret, "invalid").await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.json::<serde_json::Value>().await.unwrap(), serde_json::json!({"active": false}));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_let_machine_clients_introspect() {
+    let mut app = TestApp::new().await;
+    let (client_id, secret) = register_machine_client(&app, &["app-service"]).await;
+    let public_client_id = register_client(&app, &["profile"]).await;
+    let (_, token) = app.login_verified_user().await;
+
+    let response = app.post_oauth_introspect(&client_id, &"A".repeat(secret.len()), &token).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(oauth_error(response).await, "invalid_client");
+
+    // Public clients can name themselves, which is not enough to look into tokens
+    let response = app
+        .http_client
+        .post(format!("{}/oauth/introspect", &app.address))
+        .form(&[("client_id", public_client_id.as_str()), ("token", token.as_str())])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), 400);
+    assert_eq!(oauth_error(response).await, "unauthorized_client");
+
+    let response = app.post_oauth_revoke(&[("client_id", &client_id), ("client_secret", &secret)]).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_tokens() {
+    let mut app = TestApp::new().await;
+    let (client_id, secret) = register_machine_client(&app, &["app-service"]).await;
+    let (other_client_id, other_secret) = register_machine_client(&app, &["app-service"]).await;
+    let public_client_id = register_client(&app, &["profile"]).await;
+
+    // Tokens of another client are left alone, without telling the caller
+    let token = service_token(&app, &client_id, &secret).await;
+    let form = [("token", token.as_str()), ("client_id", &other_client_id), ("client_secret", &other_secret)];
+    assert_eq!(app.post_oauth_revoke(&form).await.status(), 200);
+    assert!(introspect(&app, &client_id, &secret, &token).await.active);
+
+    let form = [("token", token.as_str()), ("token_type_hint", "access_token"), ("client_id", &client_id), ("client_secret", &secret)];
+    assert_eq!(app.post_oauth_revoke(&form).await.status(), 200);
+    assert!(!introspect(&app, &client_id, &secret, &token).await.active);
+    // Revoking twice, or revoking garbage, is fine too
+    assert_eq!(app.post_oauth_revoke(&form).await.status(), 200);
+    let form = [("token", "invalid"), ("client_id", client_id.as_str()), ("client_secret", secret.as_str())];
+    assert_eq!(app.post_oauth_revoke(&form).await.status(), 200);
+
+    // Session tokens can be revoked by any client holding them, public ones included
+    let (_, token) = app.login_verified_user().await;
+    let form = [("token", token.as_str()), ("client_id", public_client_id.as_str())];
+    assert_eq!(app.post_oauth_revoke(&form).await.status(), 200);
+    let response = app.post_verify_token(&serde_json::json!({"token": token})).await;
+    assert_eq!(response.status(), 401);
+    // Nor can the session be picked up again through its refresh token
+    assert_eq!(app.post_refresh().await.status(), 401);
+
+    app.clean_up().await;
+}
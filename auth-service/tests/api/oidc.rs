@@ -0,0 +1,191 @@
+use auth_service::{
+    domain::{Email, PhoneNumber, TwoFAChannel},
+    routes::{OpenIdConfiguration, TokenResponse, UserInfoResponse},
+    utils::{auth::IdTokenClaims, constants::AUTH_SERVICE_URL},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode_header, jwk::JwkSet};
+
+use crate::helpers::TestApp;
+use crate::oauth::{
+    exchange_code, location, oauth_error, open_consent_page, query_param, register_client, CODE_CHALLENGE,
+    CODE_VERIFIER,
+};
+
+const NONCE: &str = "n-0S6_WzA2Mj";
+
+// Runs an OpenID Connect authorization for the logged in user and returns the token response
+async fn authorize_openid(app: &TestApp, client_id: &str, scope: &str) -> TokenResponse {
+    let query = [
+        ("response_type", "code"),
+        ("client_id", client_id),
+        ("redirect_uri", crate::oauth::REDIRECT_URI),
+        ("scope", scope),
+        ("nonce", NONCE),
+        ("code_challenge", CODE_CHALLENGE),
+        ("code_challenge_method", "S256"),
+    ];
+    let consent_id = open_consent_page(app, &query).await;
+    let response = app.post_oauth_consent(&[("consent_id", &consent_id), ("decision", "allow")]).await;
+    let code = query_param(&location(&response), "code").expect("No code in redirect");
+
+    let response = exchange_code(app, client_id, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status(), 200);
+    response.json::<TokenResponse>().await.expect("Could not deserialize response body")
+}
+
+fn id_token_claims(token: &TokenResponse) -> IdTokenClaims {
+    let id_token = token.id_token.as_deref().expect("No ID token in response");
+    let payload = id_token.split('.').nth(1).unwrap();
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap()
+}
+
+// Logs a user with 2FA in through /login and /verify-2fa
+async fn login_with_2fa(app: &TestApp, email: &str) {
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let parsed_email = Email::parse(email.to_owned()).unwrap();
+    let (login_attempt_id, code, _) = app.two_fa_code_store
+        .read()
+        .await
+        .get_code(&parsed_email)
+        .await
+        .expect("2FA code should be stored");
+    let body = serde_json::json!({"email": email, "loginAttemptId": login_attempt_id.as_ref(), "2FACode": code.as_ref()});
+    assert_eq!(app.post_verify_2fa(&body).await.status(), 200);
+}
+
+#[tokio::test]
+async fn should_publish_openid_configuration() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_openid_configuration().await;
+    assert_eq!(response.status(), 200);
+    let configuration = response.json::<OpenIdConfiguration>().await.expect("Could not deserialize response body");
+    assert_eq!(configuration.issuer, AUTH_SERVICE_URL.as_str());
+    assert_eq!(configuration.token_endpoint, format!("{}/oauth/token", AUTH_SERVICE_URL.as_str()));
+    assert_eq!(configuration.userinfo_endpoint, format!("{}/userinfo", AUTH_SERVICE_URL.as_str()));
+    assert_eq!(configuration.code_challenge_methods_supported, vec!["S256"]);
+    assert!(configuration.scopes_supported.contains(&"openid".to_owned()));
+    assert!(configuration.grant_types_supported.contains(&"client_credentials".to_owned()));
+
+    // ID tokens are signed with the key that is active right now
+    let (_, token) = app.login_verified_user().await;
+    assert_eq!(configuration.id_token_signing_alg_values_supported, vec![decode_header(&token).unwrap().alg]);
+    assert!(app.get_jwks().await.json::<JwkSet>().await.is_ok());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_issue_id_token_for_openid_scope() {
+    let mut app = TestApp::new().await;
+    let client_id = register_client(&app, &["openid", "email"]).await;
+    let (email, _) = app.login_verified_user().await;
+
+    let token = authorize_openid(&app, &client_id, "openid email").await;
+    let claims = id_token_claims(&token);
+    assert_eq!((claims.sub, claims.aud, claims.iss), (email, client_id, AUTH_SERVICE_URL.to_owned()));
+    assert_eq!(claims.nonce.as_deref(), Some(NONCE));
+    assert_eq!(claims.amr, vec!["pwd"]);
+    assert!(claims.auth_time <= claims.iat && claims.iat < claims.exp);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_report_second_factor_in_amr() {
+    let mut app = TestApp::new().await;
+    let client_id = register_client(&app, &["openid"]).await;
+
+    let email = app.create_verified_user(true).await;
+    login_with_2fa(&app, &email).await;
+    let token = authorize_openid(&app, &client_id, "openid").await;
+    assert_eq!(id_token_claims(&token).amr, vec!["pwd", "otp", "mfa"]);
+
+    // Codes delivered by SMS are reported as such
+    let email = app.create_verified_user(true).await;
+    {
+        let mut user_store = app.user_store.write().await;
+        user_store.set_phone_number(&email, Some(PhoneNumber::parse("+15555550123".to_owned()).unwrap())).await.unwrap();
+        user_store.mark_phone_verified(&email).await.unwrap();
+        user_store.set_two_fa_channel(&email, TwoFAChannel::Sms).await.unwrap();
+    }
+    login_with_2fa(&app, &email).await;
+    let token = authorize_openid(&app, &client_id, "openid").await;
+    assert_eq!(id_token_claims(&token).amr, vec!["pwd", "sms", "mfa"]);
+
+    // A refreshed session still reports the original login
+    assert_eq!(app.post_refresh().await.status(), 200);
+    let token = authorize_openid(&app, &client_id, "openid").await;
+    assert_eq!(id_token_claims(&token).amr, vec!["pwd", "sms", "mfa"]);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_userinfo_for_granted_scopes() {
+    let mut app = TestApp::new().await;
+    let client_id = register_client(&app, &["openid", "email", "phone"]).await;
+    let (email, _) = app.login_verified_user().await;
+
+    let token = authorize_openid(&app, &client_id, "openid email").await;
+    let response = app.get_userinfo(&token.access_token).await;
+    assert_eq!(response.status(), 200);
+    let userinfo = response.json::<UserInfoResponse>().await.expect("Could not deserialize response body");
+    assert_eq!(
+        userinfo,
+        UserInfoResponse { sub: email.clone(), email: Some(email.clone()), email_verified: Some(true), ..Default::default() }
+    );
+
+    app.user_store
+        .write()
+        .await
+        .set_phone_number(&email, Some(PhoneNumber::parse("+15555550123".to_owned()).unwrap()))
+        .await
+        .unwrap();
+    let token = authorize_openid(&app, &client_id, "openid phone").await;
+    let response = app.post_userinfo(&token.access_token).await;
+    assert_eq!(response.status(), 200);
+    let userinfo = response.json::<UserInfoResponse>().await.expect("Could not deserialize response body");
+    assert_eq!(
+        userinfo,
+        UserInfoResponse {
+            sub: email,
+            phone_number: Some("+15555550123".to_owned()),
+            phone_number_verified: Some(false),
+            ..Default::default()
+        }
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_userinfo_without_valid_openid_token() {
+    let mut app = TestApp::new().await;
+    let client_id = register_client(&app, &["openid", "email"]).await;
+    let (email, session_token) = app.login_verified_user().await;
+
+    // Plain OAuth tokens get no ID token and can't read the user's claims
+    let token = authorize_openid(&app, &client_id, "email").await;
+    assert!(token.id_token.is_none());
+    let response = app.get_userinfo(&token.access_token).await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer error=\"insufficient_scope\"");
+
+    // Neither do session tokens, ID tokens or garbage
+    let token = authorize_openid(&app, &client_id, "openid").await;
+    for invalid in [session_token.as_str(), token.id_token.as_deref().unwrap(), "invalid"] {
+        let response = app.get_userinfo(invalid).await;
+        assert_eq!(response.status(), 401);
+        assert_eq!(response.headers()["www-authenticate"], "Bearer error=\"invalid_token\"");
+        assert_eq!(oauth_error(response).await, "invalid_token");
+    }
+
+    // Disabled users lose access through their outstanding tokens
+    app.user_store.write().await.set_disabled(&email, true).await.unwrap();
+    assert_eq!(app.get_userinfo(&token.access_token).await.status(), 401);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,180 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::Email,
+    routes::PasswordResetResponse,
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let response = app.post_password_reset_request(&serde_json::json!({})).await;
+    assert_eq!(response.status(), 422);
+
+    let test_cases = [
+        serde_json::json!({"email": random_email, "token": "some_token"}), // missing newPassword
+        serde_json::json!({"email": random_email, "newPassword": "password123"}), // missing token
+        serde_json::json!({"token": "some_token", "newPassword": "password123"}), // missing email
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_password_reset_confirm(&test_case).await;
+        assert_eq!(
+            response.status(),
+            422,
+            "The API did not fail with 422 when payload was {}",
+            test_case
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_password_reset_request(&serde_json::json!({"email": "not-an-email"})).await;
+    assert_eq!(response.status(), 400);
+
+    let test_cases = [
+        serde_json::json!({"email": "not-an-email", "token": "550e8400-e29b-41d4-a716-446655440000", "newPassword": "password123"}),
+        serde_json::json!({"email": "test@test.com", "token": "not-a-uuid", "newPassword": "password123"}),
+        serde_json::json!({"email": "test@test.com", "token": "550e8400-e29b-41d4-a716-446655440000", "newPassword": "short"}),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_password_reset_confirm(&test_case).await;
+        assert_eq!(
+            response.status(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload was {}",
+            test_case
+        );
+
+        assert_eq!(
+            response.json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid credentials".to_owned()
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_without_sending_token_if_user_does_not_exist() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let response = app.post_password_reset_request(&serde_json::json!({"email": random_email})).await;
+    assert_eq!(response.status(), 200);
+    response
+        .json::<PasswordResetResponse>()
+        .await
+        .expect("Could not deserialize response body to PasswordResetResponse");
+
+    let token_store = app.password_reset_token_store.read().await;
+    assert!(token_store.get_token(&Email::parse(random_email).unwrap()).await.is_err());
+    drop(token_store);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_token() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    app.verify_email(&random_email).await;
+
+    let response = app.post_password_reset_request(&serde_json::json!({"email": random_email})).await;
+    assert_eq!(response.status(), 200);
+
+    let confirm_body = serde_json::json!({
+        "email": random_email,
+        "token": "550e8400-e29b-41d4-a716-446655440000",
+        "newPassword": "newpassword123"
+    });
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_password_and_revoke_existing_sessions() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    app.verify_email(&random_email).await;
+
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let old_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    // Tokens carry second-resolution issue times, so make sure the reset happens in a later second
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    let response = app.post_password_reset_request(&serde_json::json!({"email": random_email})).await;
+    assert_eq!(response.status(), 200);
+
+    let token_store = app.password_reset_token_store.read().await;
+    let token = token_store
+        .get_token(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .expect("Password reset token should be stored");
+    drop(token_store);
+
+    let confirm_body = serde_json::json!({
+        "email": random_email,
+        "token": token.as_ref(),
+        "newPassword": "newpassword123"
+    });
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+    assert_eq!(response.status(), 200);
+
+    // The token is single-use
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+    assert_eq!(response.status(), 401);
+
+    // Sessions created before the reset no longer validate
+    let response = app.post_verify_token(&serde_json::json!({"token": old_token})).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "password123"})).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "newpassword123"})).await;
+    assert_eq!(response.status(), 200);
+    let new_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app.post_verify_token(&serde_json::json!({"token": new_token})).await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
@@ -0,0 +1,207 @@
+use crate::helpers::TestApp;
+use auth_service::{
+    domain::{Email, PhoneNumber},
+    routes::{AccountExport, TwoFAChannelResponse, TwoFactorAuthResponse},
+    utils::constants::{JWT_COOKIE_NAME, MAX_TWO_FA_ATTEMPTS},
+    ErrorResponse,
+};
+
+const PHONE_NUMBER: &str = "+14155552671";
+
+fn phone_number() -> PhoneNumber {
+    PhoneNumber::parse(PHONE_NUMBER.to_owned()).unwrap()
+}
+
+// Pulls the six digit code out of a text message
+fn code_from(message: &str) -> String {
+    message
+        .split_whitespace()
+        .map(|word| word.trim_end_matches('.'))
+        .find(|word| word.len() == 6 && word.chars().all(|c| c.is_ascii_digit()))
+        .expect("Text message should contain a code")
+        .to_owned()
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await
+}
+
+// Logs a new user in and verifies `PHONE_NUMBER` for them, returning their email
+async fn login_with_verified_phone(app: &TestApp) -> String {
+    let (email, _) = app.login_verified_user().await;
+
+    let response = app.post_account_phone(&serde_json::json!({"phoneNumber": PHONE_NUMBER})).await;
+    assert_eq!(response.status(), 200);
+    let message = app.sms_client.last_sms_to(&phone_number()).await.expect("Verification code should be texted");
+
+    let response = app.post_account_phone_verify(&serde_json::json!({"code": code_from(&message)})).await;
+    assert_eq!(response.status(), 200);
+    email
+}
+
+async fn export(app: &TestApp) -> AccountExport {
+    let response = app.get_account_export().await;
+    assert_eq!(response.status(), 200);
+    response.json::<AccountExport>().await.expect("Could not deserialize response body to AccountExport")
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_account_phone(&serde_json::json!({"phoneNumber": PHONE_NUMBER})).await;
+    assert_eq!(response.status(), 400);
+
+    let response = app.post_account_phone_verify(&serde_json::json!({"code": "123456"})).await;
+    assert_eq!(response.status(), 400);
+
+    let response = app.post_account_two_fa_channel(&serde_json::json!({"channel": "sms"})).await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+    app.login_verified_user().await;
+
+    for phone_number in ["", "4155552671", "+1 415 CALL ME"] {
+        let response = app.post_account_phone(&serde_json::json!({"phoneNumber": phone_number})).await;
+        assert_eq!(response.status(), 400, "The API did not fail with 400 for {:?}", phone_number);
+    }
+
+    let response = app.post_account_phone_verify(&serde_json::json!({"code": "12345"})).await;
+    assert_eq!(response.status(), 400);
+
+    let response = app.post_account_two_fa_channel(&serde_json::json!({"channel": "pigeon"})).await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_verify_phone_number_with_texted_code() {
+    let mut app = TestApp::new().await;
+    let email = login_with_verified_phone(&app).await;
+
+    let export = export(&app).await;
+    assert_eq!(export.email, email);
+    assert_eq!(export.phone_number.as_deref(), Some(PHONE_NUMBER));
+    assert!(export.phone_verified);
+    assert_eq!(export.two_fa_channel, "email");
+
+    // The code is single use
+    let message = app.sms_client.last_sms_to(&phone_number()).await.unwrap();
+    let response = app.post_account_phone_verify(&serde_json::json!({"code": code_from(&message)})).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_normalize_formatted_phone_number() {
+    let mut app = TestApp::new().await;
+    app.login_verified_user().await;
+
+    let response = app.post_account_phone(&serde_json::json!({"phoneNumber": "+1 (415) 555-2671"})).await;
+    assert_eq!(response.status(), 200);
+    assert!(app.sms_client.last_sms_to(&phone_number()).await.is_some());
+    assert_eq!(export(&app).await.phone_number.as_deref(), Some(PHONE_NUMBER));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_verification_code_after_too_many_wrong_guesses() {
+    let mut app = TestApp::new().await;
+    app.login_verified_user().await;
+
+    let response = app.post_account_phone(&serde_json::json!({"phoneNumber": PHONE_NUMBER})).await;
+    assert_eq!(response.status(), 200);
+    let code = code_from(&app.sms_client.last_sms_to(&phone_number()).await.unwrap());
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    for _ in 0..MAX_TWO_FA_ATTEMPTS {
+        let response = app.post_account_phone_verify(&serde_json::json!({"code": wrong_code})).await;
+        assert_eq!(response.status(), 401);
+    }
+    let response = app.post_account_phone_verify(&serde_json::json!({"code": code})).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Too many incorrect codes, request a new one"
+    );
+
+    // The right code doesn't help once it has been invalidated
+    let response = app.post_account_phone_verify(&serde_json::json!({"code": code})).await;
+    assert_eq!(response.status(), 401);
+    assert!(!export(&app).await.phone_verified);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_choosing_sms_without_verified_phone() {
+    let mut app = TestApp::new().await;
+    app.login_verified_user().await;
+
+    let response = app.post_account_two_fa_channel(&serde_json::json!({"channel": "sms"})).await;
+    assert_eq!(response.status(), 409);
+
+    // An unverified number isn't enough either
+    let response = app.post_account_phone(&serde_json::json!({"phoneNumber": PHONE_NUMBER})).await;
+    assert_eq!(response.status(), 200);
+    let response = app.post_account_two_fa_channel(&serde_json::json!({"channel": "sms"})).await;
+    assert_eq!(response.status(), 409);
+    assert_eq!(response.json::<ErrorResponse>().await.unwrap().error, "Phone number not verified");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_login_code_by_sms_when_chosen() {
+    let mut app = TestApp::new().await;
+    let email = login_with_verified_phone(&app).await;
+
+    let response = app.post_account_two_fa_channel(&serde_json::json!({"channel": "sms"})).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.json::<TwoFAChannelResponse>().await.unwrap().channel, "sms");
+    app.user_store.write().await.set_requires_2fa(&email, true).await.unwrap();
+    app.deliver_emails().await;
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), 200);
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().loging_attempt_id;
+
+    let message = app.sms_client.last_sms_to(&phone_number()).await.unwrap();
+    assert!(message.starts_with("Your login code is"), "Unexpected message: {}", message);
+
+    // Nothing went to the inbox
+    app.deliver_emails().await;
+    let email_address = Email::parse(email.clone()).unwrap();
+    let last_email = app.email_client.last_email_to(&email_address).await.unwrap();
+    assert_ne!(last_email.subject, "Your login code");
+
+    let body = serde_json::json!({"email": email, "loginAttemptId": login_attempt_id, "2FACode": code_from(&message)});
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_login_code_by_email_after_phone_is_removed() {
+    let mut app = TestApp::new().await;
+    let email = login_with_verified_phone(&app).await;
+
+    let response = app.post_account_two_fa_channel(&serde_json::json!({"channel": "sms"})).await;
+    assert_eq!(response.status(), 200);
+    app.user_store.write().await.set_requires_2fa(&email, true).await.unwrap();
+
+    let response = app.delete_account_phone().await;
+    assert_eq!(response.status(), 204);
+    let export = export(&app).await;
+    assert_eq!((export.phone_number, export.phone_verified, export.two_fa_channel.as_str()), (None, false, "email"));
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), 200);
+    app.deliver_emails().await;
+    let last_email = app.email_client.last_email_to(&Email::parse(email).unwrap()).await.unwrap();
+    assert_eq!(last_email.subject, "Your login code");
+    app.clean_up().await;
+}
@@ -0,0 +1,135 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    routes::{RecoveryCodesResponse, SignupResponse, TwoFactorAuthResponse},
+    utils::constants::LOGIN_LOCKOUT_THRESHOLD,
+};
+
+async fn signup_with_2fa(app: &TestApp, email: &str) -> Vec<String> {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    let recovery_codes = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes
+        .expect("No recovery codes returned");
+    app.verify_email(email).await;
+    recovery_codes
+}
+
+async fn verify_with_recovery_code(app: &TestApp, email: &str, recovery_code: &str) -> reqwest::Response {
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .loging_attempt_id;
+
+    let verify_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": recovery_code
+    });
+    app.post_verify_2fa(&verify_body).await
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_regenerate_recovery_codes(&serde_json::json!({"password": "password123"})).await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_each_recovery_code_once() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let recovery_codes = signup_with_2fa(&app, &random_email).await;
+    assert_eq!(recovery_codes.len(), 10);
+
+    let response = verify_with_recovery_code(&app, &random_email, &recovery_codes[0]).await;
+    assert_eq!(response.status(), 200);
+
+    let response = verify_with_recovery_code(&app, &random_email, &recovery_codes[0]).await;
+    assert_eq!(response.status(), 401);
+
+    // Codes are typed in by hand, so case and the dash don't matter
+    let typed_code = recovery_codes[1].to_uppercase().replace('-', "");
+    let response = verify_with_recovery_code(&app, &random_email, &typed_code).await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_recovery_code_belongs_to_another_user() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let other_email = get_random_email();
+    signup_with_2fa(&app, &random_email).await;
+    let other_recovery_codes = signup_with_2fa(&app, &other_email).await;
+
+    let response = verify_with_recovery_code(&app, &random_email, &other_recovery_codes[0]).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_previous_codes_when_regenerated() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let recovery_codes = signup_with_2fa(&app, &random_email).await;
+
+    let response = verify_with_recovery_code(&app, &random_email, &recovery_codes[0]).await;
+    assert_eq!(response.status(), 200);
+
+    // The current password has to be re-entered
+    let response = app.post_regenerate_recovery_codes(&serde_json::json!({"password": "wrongpassword"})).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.post_regenerate_recovery_codes(&serde_json::json!({"password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let new_recovery_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+    assert_eq!(new_recovery_codes.len(), 10);
+
+    let response = verify_with_recovery_code(&app, &random_email, &recovery_codes[1]).await;
+    assert_eq!(response.status(), 401);
+
+    let response = verify_with_recovery_code(&app, &random_email, &new_recovery_codes[0]).await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_account_after_repeated_wrong_passwords() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let recovery_codes = signup_with_2fa(&app, &random_email).await;
+
+    let response = verify_with_recovery_code(&app, &random_email, &recovery_codes[0]).await;
+    assert_eq!(response.status(), 200);
+
+    let wrong_body = serde_json::json!({"password": "wrongpassword"});
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_regenerate_recovery_codes(&wrong_body).await;
+        assert_eq!(response.status(), 401);
+    }
+    let response = app.post_regenerate_recovery_codes(&wrong_body).await;
+    assert_eq!(response.status(), 423);
+
+    // Even the right password is refused while the lock lasts
+    let response = app.post_regenerate_recovery_codes(&serde_json::json!({"password": "password123"})).await;
+    assert_eq!(response.status(), 423);
+    app.clean_up().await;
+}
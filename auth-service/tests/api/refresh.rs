@@ -0,0 +1,163 @@
+use crate::helpers::{get_cookie, get_random_email, TestApp};
+use auth_service::{
+    domain::data_stores::RefreshToken,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; HttpOnly; SameSite=Lax; Path=/", REFRESH_COOKIE_NAME, token),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_refresh_token() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        "invalid".to_owned(),
+        format!("{}.{}", uuid::Uuid::new_v4(), uuid::Uuid::new_v4()),
+    ];
+
+    for test_case in test_cases {
+        set_refresh_cookie(&app, &test_case);
+        let response = app.post_refresh().await;
+        assert_eq!(
+            response.status(),
+            401,
+            "The API did not fail with 401 for refresh token {}",
+            test_case
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_rotate_refresh_token() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let first = get_cookie(&app.signup_and_login(&random_email).await, REFRESH_COOKIE_NAME);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    let second = get_cookie(&response, REFRESH_COOKIE_NAME);
+    assert_ne!(first, second);
+
+    let second = RefreshToken::parse(second).expect("Invalid refresh token");
+    let (_, stored, _) = app
+        .refresh_token_store
+        .read()
+        .await
+        .get_token(second.family_id())
+        .await
+        .expect("Refresh token family not found");
+    assert_eq!(stored, second);
+
+    // The rotated token keeps working
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 200);
+
+    let response = app.post_verify_token(&serde_json::json!({"token": auth_cookie.value()})).await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_family_when_rotated_token_is_reused() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let first = get_cookie(&app.signup_and_login(&random_email).await, REFRESH_COOKIE_NAME);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 200);
+    let second = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    // Replaying the already rotated token is rejected...
+    set_refresh_cookie(&app, &first);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 401);
+
+    // ...and takes the legitimate current token of the family down with it
+    set_refresh_cookie(&app, &second);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_after_logout() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let refresh_token = get_cookie(&app.signup_and_login(&random_email).await, REFRESH_COOKIE_NAME);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_after_password_change() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let refresh_token = get_cookie(&app.signup_and_login(&random_email).await, REFRESH_COOKIE_NAME);
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    let body = serde_json::json!({"currentPassword": "password123", "newPassword": "newpassword123"});
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), 200);
+
+    // The caller gets a fresh family, the old one is gone
+    let new_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 401);
+    assert_ne!(refresh_token, new_refresh_token);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_and_end_session_if_account_disabled() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let refresh_token = get_cookie(&app.signup_and_login(&random_email).await, REFRESH_COOKIE_NAME);
+
+    app.user_store.write().await.set_disabled(&random_email, true).await.unwrap();
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(response.json::<ErrorResponse>().await.unwrap().error, "Account disabled".to_owned());
+
+    // Re-enabling the account doesn't bring the session back
+    app.user_store.write().await.set_disabled(&random_email, false).await.unwrap();
+    set_refresh_cookie(&app, &refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
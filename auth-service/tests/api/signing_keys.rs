@@ -0,0 +1,88 @@
+use auth_service::{
+    domain::{AuthMethod, Authentication, Email},
+    routes::SigningKeyRotationResponse,
+    utils::{auth::generate_auth_token, constants::ADMIN_API_TOKEN},
+    ErrorResponse,
+};
+use jsonwebtoken::{decode_header, jwk::JwkSet};
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_401_if_admin_token_is_wrong() {
+    let mut app = TestApp::new().await;
+
+    let body = serde_json::json!({"algorithm": "EdDSA"});
+    let response = app.post_rotate_signing_key(&body, "not-the-admin-token").await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_algorithm_is_invalid() {
+    let mut app = TestApp::new().await;
+    if ADMIN_API_TOKEN.is_empty() {
+        // Key rotation is disabled without an admin token
+        app.clean_up().await;
+        return;
+    }
+
+    let test_cases = [
+        serde_json::json!({"algorithm": "HS256"}),
+        serde_json::json!({"algorithm": "none"}),
+        serde_json::json!({"algorithm": "not an algorithm"}),
+    ];
+
+    for body in test_cases {
+        let response = app.post_rotate_signing_key(&body, &ADMIN_API_TOKEN).await;
+        assert_eq!(response.status(), 400, "Failed for input: {:?}", body);
+        assert_eq!(
+            response.json::<ErrorResponse>().await.expect("Could not deserialize response body to ErrorResponse").error,
+            "Unsupported signing algorithm, use EdDSA or RS256".to_owned()
+        );
+    }
+
+    app.clean_up().await;
+}
+
+// The only test that rotates the process-wide key ring, other tests never depend on which key is active
+#[tokio::test]
+async fn should_rotate_key_and_keep_old_tokens_valid() {
+    let mut app = TestApp::new().await;
+    if ADMIN_API_TOKEN.is_empty() {
+        app.clean_up().await;
+        return;
+    }
+
+    let email = Email::parse(get_random_email()).unwrap();
+    let old_token = generate_auth_token(&email, &[], &Authentication::now(vec![AuthMethod::Password])).unwrap();
+
+    let body = serde_json::json!({"algorithm": "EdDSA"});
+    let response = app.post_rotate_signing_key(&body, &ADMIN_API_TOKEN).await;
+    assert_eq!(response.status(), 200);
+    let rotation = response
+        .json::<SigningKeyRotationResponse>()
+        .await
+        .expect("Could not deserialize response body to SigningKeyRotationResponse");
+    assert_eq!(Some(rotation.previous_kid.clone()), decode_header(&old_token).unwrap().kid);
+    assert!(rotation.activates_at < rotation.previous_key_retires_at);
+
+    // The new key is published straight away but doesn't sign until every replica could have loaded it
+    let jwks = app.get_jwks().await.json::<JwkSet>().await.unwrap();
+    assert!(jwks.find(&rotation.kid).is_some());
+    assert!(jwks.find(&rotation.previous_kid).is_some());
+    let token = generate_auth_token(&email, &[], &Authentication::now(vec![AuthMethod::Password])).unwrap();
+    assert_eq!(decode_header(&token).unwrap().kid, Some(rotation.previous_kid.clone()));
+
+    app.activate_signing_keys().await;
+    let new_token = generate_auth_token(&email, &[], &Authentication::now(vec![AuthMethod::Password])).unwrap();
+    assert_eq!(decode_header(&new_token).unwrap().kid, Some(rotation.kid.clone()));
+
+    for token in [&old_token, &new_token] {
+        let response = app.post_verify_token(&serde_json::json!({"token": token})).await;
+        assert_eq!(response.status(), 200);
+    }
+
+    app.clean_up().await;
+}
@@ -97,8 +97,32 @@ async fn should_return_201_if_valid_input(){
     let response = app.post_signup(&body).await;
     assert_eq!(response.status(), 201);
 
+    let body = response.json::<SignupResponse>().await.expect("Could not deserialize response body");
+    assert_eq!(body.message, "User created successfully".to_owned());
+
+    // 2FA users get a set of recovery codes in case they lose their second factor
+    let recovery_codes = body.recovery_codes.expect("No recovery codes returned");
+    assert_eq!(recovery_codes.len(), 10);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_return_recovery_codes_without_2fa(){
+    let mut app = TestApp::new().await;
+    let random_email = crate::helpers::get_random_email();
+
+    let body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), 201);
+
     let expected_response = SignupResponse {
-        message: "User created successfully".to_owned()
+        message: "User created successfully".to_owned(),
+        recovery_codes: None
     };
 
     assert_eq!(
@@ -106,4 +130,28 @@ async fn should_return_201_if_valid_input(){
         expected_response
     );
     app.clean_up().await;
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn should_return_429_once_signup_budget_is_spent() {
+    let mut app = TestApp::new().await;
+    // Rejected signups still count, and skip the password hashing that would let the bucket refill mid-test
+    let body = serde_json::json!({"email": "not-an-email", "password": "password123", "requires2FA": false});
+
+    for remaining in (0..10).rev() {
+        let response = app.post_signup(&body).await;
+        assert_eq!(response.status(), 400);
+        assert_eq!(response.headers().get("x-ratelimit-limit").unwrap(), "10");
+        assert_eq!(response.headers().get("x-ratelimit-remaining").unwrap(), &remaining.to_string());
+    }
+
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), 429);
+    let retry_after: u64 = response.headers().get("retry-after").unwrap().to_str().unwrap().parse().unwrap();
+    assert!((1..=6).contains(&retry_after));
+    assert_eq!(response.headers().get("x-ratelimit-remaining").unwrap(), "0");
+    assert_eq!(
+        response.json::<ErrorResponse>().await.expect("Could not deserialize response body to ErrorResponse").error,
+        "Too many requests".to_owned()
+    );
+    app.clean_up().await;
+}
@@ -0,0 +1,80 @@
+use crate::helpers::SmtpSink;
+use auth_service::{
+    domain::{Email, EmailClient, EmailContent},
+    services::smtp_email_client::{SmtpConfig, SmtpEmailClient, SmtpTls},
+};
+
+fn content(subject: &str, text: &str) -> EmailContent {
+    EmailContent {
+        subject: subject.to_owned(),
+        text: text.to_owned(),
+        html: format!("<p>{}</p>", text),
+    }
+}
+
+fn sink_config(port: u16, username: &str) -> SmtpConfig {
+    SmtpConfig {
+        host: "127.0.0.1".to_owned(),
+        port,
+        tls: SmtpTls::None,
+        username: username.to_owned(),
+        password: "smtp-password".to_owned(),
+        sender: "Auth Service <no-reply@example.com>".to_owned(),
+    }
+}
+
+#[tokio::test]
+async fn should_deliver_email_to_smtp_server() {
+    let sink = SmtpSink::start().await;
+    let email_client = SmtpEmailClient::new(sink_config(sink.port, "smtp-user")).unwrap();
+    let recipient = Email::parse(crate::helpers::get_random_email()).unwrap();
+
+    email_client
+        .send_email(&recipient, &content("2FA Code", "Your code is 123456"))
+        .await
+        .expect("Email should be accepted by the SMTP server");
+
+    let messages = sink.messages().await;
+    assert_eq!(messages.len(), 1);
+    let message = &messages[0];
+    assert_eq!(message.auth.as_deref(), Some("\0smtp-user\0smtp-password"));
+    assert!(message.from.contains("<no-reply@example.com>"));
+    assert_eq!(message.to, vec![format!("<{}>", recipient.as_ref())]);
+    assert!(message.data.contains("Subject: 2FA Code"));
+    assert!(message.data.contains(&format!("To: {}", recipient.as_ref())));
+    assert!(message.data.contains("multipart/alternative"));
+    assert!(message.data.contains("Content-Type: text/plain"));
+    assert!(message.data.contains("Content-Type: text/html"));
+    assert!(message.data.contains("Your code is 123456"));
+    assert!(message.data.contains("<p>Your code is 123456</p>"));
+}
+
+#[tokio::test]
+async fn should_not_authenticate_without_username() {
+    let sink = SmtpSink::start().await;
+    let email_client = SmtpEmailClient::new(sink_config(sink.port, "")).unwrap();
+    let recipient = Email::parse(crate::helpers::get_random_email()).unwrap();
+
+    email_client.send_email(&recipient, &content("Subject", "Content")).await.unwrap();
+
+    let messages = sink.messages().await;
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].auth, None);
+}
+
+#[tokio::test]
+async fn should_return_error_if_smtp_server_is_unreachable() {
+    // Bind and drop a listener to get a port nothing listens on
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let email_client = SmtpEmailClient::new(sink_config(port, "")).unwrap();
+    let recipient = Email::parse(crate::helpers::get_random_email()).unwrap();
+
+    assert!(email_client.send_email(&recipient, &content("Subject", "Content")).await.is_err());
+}
+
+#[tokio::test]
+async fn should_reject_invalid_sender() {
+    let mut config = sink_config(25, "");
+    config.sender = "not an address".to_owned();
+    assert!(SmtpEmailClient::new(config).is_err());
+}
@@ -0,0 +1,176 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{data_stores::TotpSecret, Email},
+    routes::{ConfirmTotpResponse, TotpEnrollmentResponse, TwoFactorAuthResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+async fn enroll(app: &TestApp) -> TotpSecret {
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status(), 200);
+    let body = response
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollmentResponse");
+    TotpSecret::parse(body.secret).expect("Invalid TOTP secret")
+}
+
+fn current_time_step() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("Failed to read system time").as_secs() / 30
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status(), 400);
+
+    let response = app.post_totp_confirm(&serde_json::json!({"code": "123456"})).await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_otpauth_uri_and_qr_code_on_enroll() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status(), 200);
+    let body = response
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollmentResponse");
+
+    assert!(body.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(body.otpauth_uri.contains(&format!("secret={}", body.secret)));
+    assert!(body.qr_code_svg.contains("<svg"));
+
+    // The secret is stored, but unconfirmed until the user proves their app works
+    let email = Email::parse(random_email).unwrap();
+    let (stored, confirmed) = app.totp_secret_store.read().await.get_secret(&email).await.unwrap();
+    assert_eq!(stored.as_ref(), body.secret);
+    assert!(!confirmed);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_code() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+    enroll(&app).await;
+
+    for code in ["12345", "abcdef", ""] {
+        let response = app.post_totp_confirm(&serde_json::json!({"code": code})).await;
+        assert_eq!(response.status(), 400, "The API did not fail with 400 for code {:?}", code);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_code_or_not_enrolled() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let email = Email::parse(random_email.clone()).unwrap();
+    app.signup_and_login(&random_email).await;
+
+    let response = app.post_totp_confirm(&serde_json::json!({"code": "123456"})).await;
+    assert_eq!(response.status(), 401);
+
+    let secret = enroll(&app).await;
+    let wrong_code = TotpSecret::default().generate_current(&email).unwrap();
+    if secret.verify(&email, &wrong_code).unwrap().is_some() {
+        // Astronomically unlikely, but a collision would make the assertion below meaningless
+        return app.clean_up().await;
+    }
+    let response = app.post_totp_confirm(&serde_json::json!({"code": wrong_code})).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_enable_totp_and_require_it_at_login() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let email = Email::parse(random_email.clone()).unwrap();
+    app.signup_and_login(&random_email).await;
+
+    let secret = enroll(&app).await;
+    // Confirm with the previous code, as the current one can only be used once
+    let code = secret.generate_for_time_step(&email, current_time_step() - 1).unwrap();
+    let response = app.post_totp_confirm(&serde_json::json!({"code": code})).await;
+    assert_eq!(response.status(), 200);
+    let body = response.json::<ConfirmTotpResponse>().await.expect("Could not deserialize response body");
+    assert_eq!(body.message, "TOTP enabled".to_string());
+    assert_eq!(body.recovery_codes.len(), 10);
+
+    // A confirmed authenticator can't be replaced by enrolling again
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status(), 409);
+
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .loging_attempt_id;
+    assert!(!login_attempt_id.is_empty());
+
+    // The code kept in the 2FA store is not emailed and not accepted for TOTP users
+    let (_, stored_code, _) = app.two_fa_code_store.read().await.get_code(&email).await.unwrap();
+    if secret.verify(&email, stored_code.as_ref()).unwrap().is_none() {
+        let response = app.post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": stored_code.as_ref()
+        })).await;
+        assert_eq!(response.status(), 401);
+    }
+
+    let response = app.post_verify_2fa(&serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": secret.generate_current(&email).unwrap()
+    })).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_a_totp_code_that_was_already_used() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let email = Email::parse(random_email.clone()).unwrap();
+    app.signup_and_login(&random_email).await;
+
+    let secret = enroll(&app).await;
+    let time_step = current_time_step();
+    let code = secret.generate_for_time_step(&email, time_step).unwrap();
+    let response = app.post_totp_confirm(&serde_json::json!({"code": code})).await;
+    assert_eq!(response.status(), 200);
+
+    // Neither the code used to confirm nor any earlier one works at login
+    for time_step in [time_step, time_step - 1] {
+        let response = app.post_login(&serde_json::json!({"email": random_email, "password": "password123"})).await;
+        assert_eq!(response.status(), 200);
+        let login_attempt_id = response
+            .json::<TwoFactorAuthResponse>()
+            .await
+            .expect("Could not deserialize response body to TwoFactorAuthResponse")
+            .loging_attempt_id;
+
+        let response = app.post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": secret.generate_for_time_step(&email, time_step).unwrap()
+        })).await;
+        assert_eq!(response.status(), 401);
+    }
+    app.clean_up().await;
+}
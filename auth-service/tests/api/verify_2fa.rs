@@ -1,6 +1,9 @@
 use crate::helpers::TestApp;
 use serde_json;
 use auth_service::{
+    domain::Email,
+    routes::TwoFactorAuthResponse,
+    utils::constants::{LOGIN_LOCKOUT_THRESHOLD, MAX_TWO_FA_ATTEMPTS},
     ErrorResponse,
 };
 
@@ -66,6 +69,7 @@ async fn should_return_401_if_incorrect_credentials() {
     });
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({"email": random_email, "password": "password123"});
     let response = app.post_login(&login_body).await;
@@ -86,8 +90,8 @@ async fn should_return_401_if_incorrect_credentials() {
     let response = app.post_verify_2fa(&wrong_verify_body).await;
     assert_eq!(
         response.status(),
-        400,
-        "The API did not fail with 400 when the 2FA code was incorrect"
+        401,
+        "The API did not fail with 401 when the 2FA code was incorrect"
     );
     app.clean_up().await;
 }
@@ -105,6 +109,7 @@ async fn should_return_401_if_old_code() {
     });
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
+    app.verify_email(&random_email).await;
 
     // First login to get the initial 2FA code
     let login_body = serde_json::json!({"email": random_email, "password": "password123"});
@@ -150,6 +155,7 @@ async fn should_return_200_if_correct_code() {
     });
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({"email": random_email, "password": "password123"});
     let response = app.post_login(&login_body).await;
@@ -158,7 +164,7 @@ async fn should_return_200_if_correct_code() {
     // Get the correct 2FA code from the store
     let two_fa_store = app.two_fa_code_store.read().await;
     let email = auth_service::domain::Email::parse(random_email.clone()).unwrap();
-    let (stored_login_attempt_id, stored_code) = two_fa_store.get_code(&email).await.expect("2FA code should be stored");
+    let (stored_login_attempt_id, stored_code, _) = two_fa_store.get_code(&email).await.expect("2FA code should be stored");
     drop(two_fa_store);
 
     // Verify with correct code
@@ -195,6 +201,7 @@ async fn should_return_401_if_same_code_twice() {
     });
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({"email": random_email, "password": "password123"});
     let response = app.post_login(&login_body).await;
@@ -203,7 +210,7 @@ async fn should_return_401_if_same_code_twice() {
     // Get the correct 2FA code from the store
     let two_fa_store = app.two_fa_code_store.read().await;
     let email = auth_service::domain::Email::parse(random_email.clone()).unwrap();
-    let (stored_login_attempt_id, stored_code) = two_fa_store.get_code(&email).await.expect("2FA code should be stored");
+    let (stored_login_attempt_id, stored_code, _) = two_fa_store.get_code(&email).await.expect("2FA code should be stored");
     drop(two_fa_store); // Release the read lock
 
     // Verify with correct code first time - should succeed
@@ -224,4 +231,131 @@ async fn should_return_401_if_same_code_twice() {
         "The API did not fail with 401 when using the same 2FA code twice"
     );
     app.clean_up().await;
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn should_invalidate_code_after_too_many_wrong_guesses() {
+    let mut app = TestApp::new().await;
+    let email = app.create_verified_user(true).await;
+
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .loging_attempt_id;
+
+    let (_, code, _) = app.two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(email.clone()).unwrap())
+        .await
+        .expect("2FA code should be stored");
+    let wrong_code = if code.as_ref() == "111111" { "222222" } else { "111111" };
+    let wrong_body = serde_json::json!({"email": email, "loginAttemptId": login_attempt_id, "2FACode": wrong_code});
+
+    for _ in 1..MAX_TWO_FA_ATTEMPTS {
+        assert_eq!(app.post_verify_2fa(&wrong_body).await.status(), 401);
+    }
+    // Keeps the account lockout, which wrong codes count towards too, out of the way of the per-login limit
+    app.login_attempt_store.write().await.clear_failures(&Email::parse(email.clone()).unwrap()).await.unwrap();
+
+    let response = app.post_verify_2fa(&wrong_body).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.expect("Could not deserialize response body to ErrorResponse").error,
+        "Too many incorrect 2FA codes, log in again".to_owned()
+    );
+
+    // The real code no longer works either, the user has to start over
+    let correct_body = serde_json::json!({"email": email, "loginAttemptId": login_attempt_id, "2FACode": code.as_ref()});
+    assert_eq!(app.post_verify_2fa(&correct_body).await.status(), 401);
+
+    let response = app.post_login(&serde_json::json!({"email": email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .loging_attempt_id;
+    let (_, code, _) = app.two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(email.clone()).unwrap())
+        .await
+        .expect("2FA code should be stored");
+    let body = serde_json::json!({"email": email, "loginAttemptId": login_attempt_id, "2FACode": code.as_ref()});
+    assert_eq!(app.post_verify_2fa(&body).await.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_account_disabled_after_login() {
+    let mut app = TestApp::new().await;
+    let random_email = crate::helpers::get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({"email": random_email, "password": "password123"});
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let email = Email::parse(random_email.clone()).unwrap();
+    let (login_attempt_id, code, _) = app.two_fa_code_store.read().await.get_code(&email).await.unwrap();
+    app.user_store.write().await.set_disabled(&random_email, true).await.unwrap();
+
+    let verify_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": code.as_ref()
+    });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(response.json::<ErrorResponse>().await.unwrap().error, "Account disabled".to_owned());
+
+    // The pending login is gone, so re-enabling the account doesn't let the code through
+    app.user_store.write().await.set_disabled(&random_email, false).await.unwrap();
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_account_after_repeated_wrong_codes() {
+    let mut app = TestApp::new().await;
+    let email = app.create_verified_user(true).await;
+    let parsed_email = Email::parse(email.clone()).unwrap();
+    let login_body = serde_json::json!({"email": email, "password": "password123"});
+
+    // Logging in again with the right password doesn't wipe out the wrong codes
+    for attempt in 1..=*LOGIN_LOCKOUT_THRESHOLD {
+        assert_eq!(app.post_login(&login_body).await.status(), 200);
+        let (login_attempt_id, code, _) = app.two_fa_code_store.read().await.get_code(&parsed_email).await.unwrap();
+        let wrong_code = if code.as_ref() == "111111" { "222222" } else { "111111" };
+        let wrong_body = serde_json::json!({"email": email, "loginAttemptId": login_attempt_id.as_ref(), "2FACode": wrong_code});
+
+        let response = app.post_verify_2fa(&wrong_body).await;
+        if attempt < *LOGIN_LOCKOUT_THRESHOLD {
+            assert_eq!(response.status(), 401);
+            continue;
+        }
+        assert_eq!(response.status(), 423);
+        assert_eq!(response.json::<ErrorResponse>().await.unwrap().error, "Account locked".to_owned());
+
+        // The lock ends the pending login, the right code is refused as well
+        let correct_body = serde_json::json!({"email": email, "loginAttemptId": login_attempt_id.as_ref(), "2FACode": code.as_ref()});
+        assert_eq!(app.post_verify_2fa(&correct_body).await.status(), 423);
+        assert!(app.two_fa_code_store.read().await.get_code(&parsed_email).await.is_err());
+    }
+
+    assert_eq!(app.post_login(&login_body).await.status(), 423);
+    app.clean_up().await;
+}
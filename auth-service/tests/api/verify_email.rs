@@ -0,0 +1,150 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{domain::Email, routes::VerifyEmailResponse, ErrorResponse};
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let test_cases = [
+        serde_json::json!({"email": random_email}), // missing token
+        serde_json::json!({"token": "550e8400-e29b-41d4-a716-446655440000"}), // missing email
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_verify_email(&test_case).await;
+        assert_eq!(
+            response.status(),
+            422,
+            "The API did not fail with 422 when payload was {}",
+            test_case
+        );
+    }
+
+    let response = app.post_resend_verification_email(&serde_json::json!({})).await;
+    assert_eq!(response.status(), 422);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({"email": "not-an-email", "token": "550e8400-e29b-41d4-a716-446655440000"}),
+        serde_json::json!({"email": "test@test.com", "token": "not-a-uuid"}),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_verify_email(&test_case).await;
+        assert_eq!(
+            response.status(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload was {}",
+            test_case
+        );
+    }
+
+    let response = app.post_resend_verification_email(&serde_json::json!({"email": "not-an-email"})).await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_token() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+
+    let body = serde_json::json!({"email": random_email, "token": "550e8400-e29b-41d4-a716-446655440000"});
+    let response = app.post_verify_email(&body).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_allow_login_after_email_is_verified() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+
+    let login_body = serde_json::json!({"email": random_email, "password": "password123"});
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(
+        response.json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email not verified".to_owned()
+    );
+
+    let (token, _) = app.email_verification_token_store
+        .read()
+        .await
+        .get_token(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .expect("Email verification token should be stored");
+
+    let body = serde_json::json!({"email": random_email, "token": token.as_ref()});
+    let response = app.post_verify_email(&body).await;
+    assert_eq!(response.status(), 200);
+    response
+        .json::<VerifyEmailResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyEmailResponse");
+
+    // The token can't be reused once the email is verified
+    let response = app.post_verify_email(&body).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_resent_during_cooldown() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+
+    let response = app.post_resend_verification_email(&serde_json::json!({"email": random_email})).await;
+    assert_eq!(response.status(), 429);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_on_resend_if_user_does_not_exist() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let response = app.post_resend_verification_email(&serde_json::json!({"email": random_email})).await;
+    assert_eq!(response.status(), 200);
+
+    let token_store = app.email_verification_token_store.read().await;
+    assert!(token_store.get_token(&Email::parse(random_email).unwrap()).await.is_err());
+    drop(token_store);
+    app.clean_up().await;
+}
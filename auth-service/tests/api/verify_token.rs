@@ -1,4 +1,10 @@
-use auth_service::{domain::Email, utils::{auth::generate_auth_cookie, constants::JWT_COOKIE_NAME}};
+use auth_service::{
+    domain::{AuthMethod, Authentication, Email, Role, UserStoreError},
+    utils::{
+        auth::{generate_auth_token, Claims, AUTH_TOKEN_AUDIENCE},
+        constants::{AUTH_SERVICE_URL, JWT_COOKIE_NAME},
+    },
+};
 use reqwest::Url;
 use crate::helpers::{get_random_email, TestApp};
 
@@ -19,12 +25,18 @@ async fn should_return_200_valid_token() {
     let mut app = TestApp::new().await;
     let random_email = get_random_email();
     let email = Email::parse(random_email).expect("Failed to parse random email");
-    let jwt = generate_auth_cookie(&email).expect("Failed to generate auth cookie");
+    let jwt = generate_auth_token(&email, &[], &Authentication::now(vec![AuthMethod::Password])).expect("Failed to generate auth token");
     let body = serde_json::json!({
-        "token": jwt.value()
+        "token": jwt
     });
     let response = app.post_verify_token(&body).await;
     assert_eq!(response.status(), 200);
+
+    let claims = response.json::<Claims>().await.expect("Could not deserialize response body to Claims");
+    assert_eq!(claims.sub, email.as_ref());
+    assert_eq!(claims.iss, AUTH_SERVICE_URL.as_str());
+    assert_eq!(claims.aud, AUTH_TOKEN_AUDIENCE);
+    assert!(claims.roles.is_empty());
     app.clean_up().await;
 }
 
@@ -55,6 +67,8 @@ async fn should_return_401_if_banned_token() {
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status(), 201);
 
+    app.verify_email(&random_email).await;
+
     let login_body = serde_json::json!({"email": random_email, "password": "password123"});
 
     let response = app.post_login(&login_body).await;
@@ -84,4 +98,41 @@ async fn should_return_401_if_banned_token() {
     let response = app.post_verify_token(&body).await;
     assert_eq!(response.status(), 401);
     app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_roles_of_logged_in_user() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    app.verify_email(&random_email).await;
+
+    // Roles have to be in the catalog before they can be granted
+    let roles = vec![Role::admin(), Role::parse("support".to_owned()).unwrap()];
+    let result = app.user_store.write().await.set_roles(&random_email, roles.clone()).await;
+    assert_eq!(result, Err(UserStoreError::UnknownRole));
+    app.add_role("support").await;
+    app.user_store.write().await.set_roles(&random_email, roles).await.unwrap();
+
+    let login_body = serde_json::json!({"email": random_email, "password": "password123"});
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 200);
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let response = app.post_verify_token(&serde_json::json!({"token": auth_cookie.value()})).await;
+    assert_eq!(response.status(), 200);
+    let claims = response.json::<Claims>().await.expect("Could not deserialize response body to Claims");
+    assert_eq!(claims.sub, random_email);
+    assert_eq!(claims.roles, vec!["admin".to_owned(), "support".to_owned()]);
+    app.clean_up().await;
 }
\ No newline at end of file
@@ -0,0 +1,379 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::Email,
+    routes::{CreationOptions, RequestOptions, TwoFactorAuthResponse, WebauthnRegistrationResponse},
+    utils::{
+        constants::{JWT_COOKIE_NAME, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID},
+        webauthn::encode_base64url,
+    },
+};
+use ciborium::Value;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use sha2::{Digest, Sha256};
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// Plays the part of a security key or platform authenticator with a single ES256 credential
+struct SoftwareAuthenticator {
+    signing_key: SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+    origin: String,
+}
+
+impl SoftwareAuthenticator {
+    fn new() -> Self {
+        Self {
+            signing_key: SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng),
+            credential_id: uuid::Uuid::new_v4().as_bytes().to_vec(),
+            sign_count: 0,
+            origin: WEBAUTHN_ORIGIN.to_owned(),
+        }
+    }
+
+    fn credential_id(&self) -> String {
+        encode_base64url(&self.credential_id)
+    }
+
+    fn client_data(&self, ceremony_type: &str, challenge: &str) -> Vec<u8> {
+        serde_json::json!({"type": ceremony_type, "challenge": challenge, "origin": self.origin})
+            .to_string()
+            .into_bytes()
+    }
+
+    fn authenticator_data(&mut self, flags: u8) -> Vec<u8> {
+        self.sign_count += 1;
+        let mut data = Sha256::digest(WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend(self.sign_count.to_be_bytes());
+        data
+    }
+
+    fn cose_key(&self) -> Vec<u8> {
+        let point = self.signing_key.verifying_key().to_encoded_point(false);
+        let key = Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (Value::Integer(3.into()), Value::Integer((-7).into())),
+            (Value::Integer((-1).into()), Value::Integer(1.into())),
+            (Value::Integer((-2).into()), Value::Bytes(point.x().unwrap().to_vec())),
+            (Value::Integer((-3).into()), Value::Bytes(point.y().unwrap().to_vec())),
+        ]);
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&key, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn register(&mut self, options: &CreationOptions) -> serde_json::Value {
+        let mut auth_data = self.authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA);
+        auth_data.extend([0u8; 16]); // AAGUID
+        auth_data.extend((self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend(self.credential_id.clone());
+        auth_data.extend(self.cose_key());
+
+        let attestation = Value::Map(vec![
+            (Value::Text("fmt".to_owned()), Value::Text("none".to_owned())),
+            (Value::Text("attStmt".to_owned()), Value::Map(vec![])),
+            (Value::Text("authData".to_owned()), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        serde_json::json!({
+            "id": self.credential_id(),
+            "name": "Software key",
+            "response": {
+                "clientDataJSON": encode_base64url(&self.client_data("webauthn.create", &options.challenge)),
+                "attestationObject": encode_base64url(&attestation_object),
+            }
+        })
+    }
+
+    fn assert(&mut self, options: &RequestOptions, flags: u8) -> serde_json::Value {
+        let auth_data = self.authenticator_data(flags);
+        let client_data = self.client_data("webauthn.get", &options.challenge);
+
+        let mut message = auth_data.clone();
+        message.extend(Sha256::digest(&client_data));
+        let signature: Signature = self.signing_key.sign(&message);
+
+        serde_json::json!({
+            "id": self.credential_id(),
+            "response": {
+                "clientDataJSON": encode_base64url(&client_data),
+                "authenticatorData": encode_base64url(&auth_data),
+                "signature": encode_base64url(signature.to_der().as_bytes()),
+            }
+        })
+    }
+}
+
+async fn register(app: &TestApp, authenticator: &mut SoftwareAuthenticator) {
+    let response = app.post_webauthn_register_start().await;
+    assert_eq!(response.status(), 200);
+    let options = response
+        .json::<CreationOptions>()
+        .await
+        .expect("Could not deserialize response body to CreationOptions");
+
+    let response = app.post_webauthn_register_finish(&authenticator.register(&options)).await;
+    assert_eq!(response.status(), 201);
+}
+
+async fn login_options(app: &TestApp, body: &serde_json::Value) -> RequestOptions {
+    let response = app.post_webauthn_login_start(body).await;
+    assert_eq!(response.status(), 200);
+    response
+        .json::<RequestOptions>()
+        .await
+        .expect("Could not deserialize response body to RequestOptions")
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_webauthn_register_start().await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_register_passkey() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+    let mut authenticator = SoftwareAuthenticator::new();
+
+    let response = app.post_webauthn_register_start().await;
+    assert_eq!(response.status(), 200);
+    let options = response
+        .json::<CreationOptions>()
+        .await
+        .expect("Could not deserialize response body to CreationOptions");
+    assert_eq!(options.rp.id, *WEBAUTHN_RP_ID);
+    assert_eq!(options.pub_key_cred_params[0].alg, -7);
+
+    let response = app.post_webauthn_register_finish(&authenticator.register(&options)).await;
+    assert_eq!(response.status(), 201);
+    let body = response
+        .json::<WebauthnRegistrationResponse>()
+        .await
+        .expect("Could not deserialize response body to WebauthnRegistrationResponse");
+    assert_eq!(body.credential_id, authenticator.credential_id());
+
+    let email = Email::parse(random_email).unwrap();
+    let credentials = app.webauthn_credential_store.read().await.get_credentials(&email).await.unwrap();
+    assert_eq!(credentials.len(), 1);
+    assert_eq!(credentials[0].name, "Software key");
+
+    // The challenge was consumed, so the same registration can't be replayed
+    let response = app.post_webauthn_register_finish(&authenticator.register(&options)).await;
+    assert_eq!(response.status(), 401);
+
+    // The new credential is excluded from further registrations
+    let response = app.post_webauthn_register_start().await;
+    let options = response.json::<CreationOptions>().await.unwrap();
+    assert_eq!(options.exclude_credentials[0].id, authenticator.credential_id());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_wrong_origin() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+    let mut authenticator = SoftwareAuthenticator::new();
+    authenticator.origin = "https://phishing.example.com".to_owned();
+
+    let response = app.post_webauthn_register_start().await;
+    let options = response.json::<CreationOptions>().await.unwrap();
+    let response = app.post_webauthn_register_finish(&authenticator.register(&options)).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_passwordless_with_passkey() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(&app, &mut authenticator).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status(), 200);
+
+    // Tokens only differ by their second-resolution timestamps, make sure the new one isn't
+    // identical to the one logout just banned
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    // Discoverable login: the user doesn't even type their email
+    let options = login_options(&app, &serde_json::json!({})).await;
+    assert_eq!(options.user_verification, "required");
+    assert!(options.allow_credentials.is_empty());
+
+    let assertion = authenticator.assert(&options, FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+    let response = app.post_webauthn_login_finish(&assertion).await;
+    assert_eq!(response.status(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    let response = app.post_verify_token(&serde_json::json!({"token": auth_cookie.value()})).await;
+    assert_eq!(response.status(), 200);
+
+    // Replaying the same assertion fails, its challenge is gone
+    let response = app.post_webauthn_login_finish(&assertion).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_passwordless_without_user_verification_or_cloned_key() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email).await;
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(&app, &mut authenticator).await;
+
+    let body = serde_json::json!({"email": random_email});
+    let options = login_options(&app, &body).await;
+    assert_eq!(options.allow_credentials[0].id, authenticator.credential_id());
+    let response = app.post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT)).await;
+    assert_eq!(response.status(), 401);
+
+    let options = login_options(&app, &body).await;
+    let response = app.post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT | FLAG_USER_VERIFIED)).await;
+    assert_eq!(response.status(), 200);
+
+    // A copy of the key replaying an old counter value is rejected
+    authenticator.sign_count -= 2;
+    let options = login_options(&app, &body).await;
+    let response = app.post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT | FLAG_USER_VERIFIED)).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+// Signs up a 2FA user who then registers a passkey, and signs them out again
+async fn signup_with_second_factor_passkey(app: &TestApp, random_email: &str) -> SoftwareAuthenticator {
+    let email = Email::parse(random_email.to_owned()).unwrap();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    app.verify_email(random_email).await;
+
+    // Sign in once with the emailed code to register the passkey
+    let login_body = serde_json::json!({"email": random_email, "password": "password123"});
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 200);
+    let (login_attempt_id, code, _) = app.two_fa_code_store.read().await.get_code(&email).await.unwrap();
+    let response = app.post_verify_2fa(&serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": code.as_ref()
+    })).await;
+    assert_eq!(response.status(), 200);
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(app, &mut authenticator).await;
+    let response = app.post_logout().await;
+    assert_eq!(response.status(), 200);
+    authenticator
+}
+
+// Starts a password login and the passkey challenge that is to finish it
+async fn start_second_factor_login(app: &TestApp, random_email: &str) -> RequestOptions {
+    let response = app.post_login(&serde_json::json!({"email": random_email, "password": "password123"})).await;
+    assert_eq!(response.status(), 200);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .loging_attempt_id;
+    login_options(app, &serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id
+    })).await
+}
+
+#[tokio::test]
+async fn should_accept_passkey_as_second_factor() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let email = Email::parse(random_email.clone()).unwrap();
+    let mut authenticator = signup_with_second_factor_passkey(&app, &random_email).await;
+
+    let login_body = serde_json::json!({"email": random_email, "password": "password123"});
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 200);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .loging_attempt_id;
+
+    let response = app.post_webauthn_login_start(&serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": uuid::Uuid::new_v4().to_string()
+    })).await;
+    assert_eq!(response.status(), 401);
+
+    // A challenge started for a login that was since replaced can't finish the newer one
+    let stale_options = login_options(&app, &serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id
+    })).await;
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 200);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .loging_attempt_id;
+    let response = app.post_webauthn_login_finish(&authenticator.assert(&stale_options, FLAG_USER_PRESENT)).await;
+    assert_eq!(response.status(), 401);
+
+    let options = login_options(&app, &serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id
+    })).await;
+    let response = app.post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT)).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    // The login attempt is finished, its emailed code can't be used anymore
+    assert!(app.two_fa_code_store.read().await.get_code(&email).await.is_err());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_passkey_as_second_factor_if_account_disabled_or_locked() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let email = Email::parse(random_email.clone()).unwrap();
+    let mut authenticator = signup_with_second_factor_passkey(&app, &random_email).await;
+
+    // The account may be disabled between the password and the passkey
+    let options = start_second_factor_login(&app, &random_email).await;
+    app.user_store.write().await.set_disabled(&random_email, true).await.unwrap();
+    let response = app.post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT)).await;
+    assert_eq!(response.status(), 403);
+    assert!(!response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+    assert!(app.two_fa_code_store.read().await.get_code(&email).await.is_err());
+    app.user_store.write().await.set_disabled(&random_email, false).await.unwrap();
+
+    // A lock holds off the passkey just like an emailed code
+    let options = start_second_factor_login(&app, &random_email).await;
+    app.login_attempt_store.write().await.lock(&email, 300).await.unwrap();
+    let response = app.post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT)).await;
+    assert_eq!(response.status(), 423);
+    assert!(!response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+    app.clean_up().await;
+}